@@ -1,31 +1,86 @@
 use std::sync::{atomic::AtomicBool, Mutex};
-use lib_gb::{ppu::gfx_device::GfxDevice, keypad::joypad_provider::JoypadProvider};
+use lib_gb::{ppu::{gfx_device::GfxDevice, colors::DmgPalettesPreset, cgb_compatibility_palettes::PaletteKeyCombination, post_processing::PostProcessingFilter}, keypad::joypad_provider::JoypadProvider};
 
 use crate::joypad_menu::{MenuOption, MenuJoypadProvider, joypad_gfx_menu, JoypadMenu};
+use crate::mpmc_gfx_device::FrameBuffers;
 
 enum EmulatorMenuOption{
     Resume,
+    Palettes,
+    Layers,
+    Filters,
+    SpriteLimit,
+    DumpVram,
+    Screenshot,
+    Recording,
+    Replay,
     Restart,
     Shutdown
 }
 
-const GAME_MENU_OPTIONS:[MenuOption<EmulatorMenuOption, &str>;3] = [
+const GAME_MENU_OPTIONS:[MenuOption<EmulatorMenuOption, &str>;11] = [
     MenuOption{prompt:"Resume", value:EmulatorMenuOption::Resume},
+    MenuOption{prompt:"Palettes", value:EmulatorMenuOption::Palettes},
+    MenuOption{prompt:"Layers", value:EmulatorMenuOption::Layers},
+    MenuOption{prompt:"Filters", value:EmulatorMenuOption::Filters},
+    MenuOption{prompt:"Toggle sprite limit", value:EmulatorMenuOption::SpriteLimit},
+    MenuOption{prompt:"Dump VRAM", value:EmulatorMenuOption::DumpVram},
+    MenuOption{prompt:"Screenshot", value:EmulatorMenuOption::Screenshot},
+    MenuOption{prompt:"Start/stop recording", value:EmulatorMenuOption::Recording},
+    MenuOption{prompt:"Save replay GIF", value:EmulatorMenuOption::Replay},
     MenuOption{prompt:"Restart", value:EmulatorMenuOption::Restart}, 
     MenuOption{prompt:"Shutdown", value:EmulatorMenuOption::Shutdown}
 ];
 
+// The dmg palettes the user can pick while the game is running
+#[derive(Clone, Copy)]
+pub enum PalettesSelection{
+    Preset(DmgPalettesPreset),
+    // The palettes the cgb boot rom chooses for the game
+    Colorize,
+    KeyCombination(PaletteKeyCombination)
+}
+
+// The rendering layers the user can show or hide while the game is running
+#[derive(Clone, Copy)]
+pub enum LayerToggleSelection{
+    Background,
+    Window,
+    Sprites
+}
+
+const LAYERS_MENU_OPTIONS:[MenuOption<LayerToggleSelection, &str>;3] = [
+    MenuOption{prompt:"Toggle background", value:LayerToggleSelection::Background},
+    MenuOption{prompt:"Toggle window", value:LayerToggleSelection::Window},
+    MenuOption{prompt:"Toggle sprites", value:LayerToggleSelection::Sprites}
+];
+
 pub struct MagenBoyState{
     // Use atomic bool, normal bool doesnt works on arm (probably cause of the memory model)
     pub running:AtomicBool,
     pub pause:AtomicBool,
     pub exit:AtomicBool,
-    pub state_mutex:Mutex<()>
+    pub state_mutex:Mutex<()>,
+    // Applied and cleared by the emulation thread
+    pub palettes_request:Mutex<Option<PalettesSelection>>,
+    pub layer_toggle_request:Mutex<Option<LayerToggleSelection>>,
+    // Adds or removes the filter from the post processing chain
+    pub filter_toggle_request:Mutex<Option<PostProcessingFilter>>,
+    // Toggles the 10 sprites per line limit of the current game
+    pub sprite_limit_toggle_request:AtomicBool,
+    // Dumps the vram debug views next to the rom after the current frame
+    pub vram_dump_request:AtomicBool,
+    // Saves a screenshot of the next frame to the screenshots directory
+    pub screenshot_request:AtomicBool,
+    // Starts or stops the audio and video recording
+    pub recording_toggle_request:AtomicBool,
+    // Saves the last seconds of the game as a gif
+    pub replay_save_request:AtomicBool
 }
 
 impl MagenBoyState{
     pub const fn new() -> Self {
-        Self { running: AtomicBool::new(true), pause: AtomicBool::new(false), exit: AtomicBool::new(false), state_mutex: Mutex::new(()) }
+        Self { running: AtomicBool::new(true), pause: AtomicBool::new(false), exit: AtomicBool::new(false), state_mutex: Mutex::new(()), palettes_request: Mutex::new(None), layer_toggle_request: Mutex::new(None), filter_toggle_request: Mutex::new(None), sprite_limit_toggle_request: AtomicBool::new(false), vram_dump_request: AtomicBool::new(false), screenshot_request: AtomicBool::new(false), recording_toggle_request: AtomicBool::new(false), replay_save_request: AtomicBool::new(false) }
     }
 }
 
@@ -38,9 +93,28 @@ impl<JP:JoypadProvider + MenuJoypadProvider> MagenBoyMenu<JP> {
         Self { provider }
     }
 
-    pub fn pop_game_menu<GFX:GfxDevice>(&mut self, state:&MagenBoyState, gfx_device:&mut GFX, receiver:crossbeam_channel::Receiver<usize>){
-        match self.get_game_menu_selection(state, gfx_device, receiver){
+    pub fn pop_game_menu<GFX:GfxDevice>(&mut self, state:&MagenBoyState, gfx_device:&mut GFX, receiver:crossbeam_channel::Receiver<FrameBuffers>){
+        match self.get_menu_selection(&GAME_MENU_OPTIONS, state, gfx_device, receiver.clone()){
             EmulatorMenuOption::Resume => {},
+            EmulatorMenuOption::Palettes => {
+                let palettes_options = Self::get_palettes_menu_options();
+                let selection = self.get_menu_selection(&palettes_options, state, gfx_device, receiver);
+                *state.palettes_request.lock().unwrap() = Some(*selection);
+            },
+            EmulatorMenuOption::Layers => {
+                let selection = self.get_menu_selection(&LAYERS_MENU_OPTIONS, state, gfx_device, receiver);
+                *state.layer_toggle_request.lock().unwrap() = Some(*selection);
+            },
+            EmulatorMenuOption::Filters => {
+                let filters_options = Self::get_filters_menu_options();
+                let selection = self.get_menu_selection(&filters_options, state, gfx_device, receiver);
+                *state.filter_toggle_request.lock().unwrap() = Some(*selection);
+            },
+            EmulatorMenuOption::SpriteLimit => state.sprite_limit_toggle_request.store(true, std::sync::atomic::Ordering::Relaxed),
+            EmulatorMenuOption::DumpVram => state.vram_dump_request.store(true, std::sync::atomic::Ordering::Relaxed),
+            EmulatorMenuOption::Screenshot => state.screenshot_request.store(true, std::sync::atomic::Ordering::Relaxed),
+            EmulatorMenuOption::Recording => state.recording_toggle_request.store(true, std::sync::atomic::Ordering::Relaxed),
+            EmulatorMenuOption::Replay => state.replay_save_request.store(true, std::sync::atomic::Ordering::Relaxed),
             EmulatorMenuOption::Restart => state.running.store(false, std::sync::atomic::Ordering::Relaxed),
             EmulatorMenuOption::Shutdown => {
                 state.running.store(false, std::sync::atomic::Ordering::Relaxed);
@@ -49,10 +123,27 @@ impl<JP:JoypadProvider + MenuJoypadProvider> MagenBoyMenu<JP> {
         }
     }
 
-    fn get_game_menu_selection<GFX:GfxDevice>(&mut self, state:&MagenBoyState,gfx_device:&mut GFX, emulation_framebuffer_channel:crossbeam_channel::Receiver<usize>)->&EmulatorMenuOption{
+    fn get_palettes_menu_options()->Vec<MenuOption<PalettesSelection, &'static str>>{
+        let mut options = Vec::new();
+        for preset in DmgPalettesPreset::ALL{
+            options.push(MenuOption{prompt:preset.get_name(), value:PalettesSelection::Preset(preset)});
+        }
+        options.push(MenuOption{prompt:"Colorize", value:PalettesSelection::Colorize});
+        for combination in PaletteKeyCombination::ALL{
+            options.push(MenuOption{prompt:combination.get_name(), value:PalettesSelection::KeyCombination(combination)});
+        }
+
+        return options;
+    }
+
+    fn get_filters_menu_options()->Vec<MenuOption<PostProcessingFilter, &'static str>>{
+        return PostProcessingFilter::ALL.iter().map(|filter|MenuOption{prompt:filter.get_name(), value:*filter}).collect();
+    }
+
+    fn get_menu_selection<'a, T, GFX:GfxDevice>(&mut self, options:&'a [MenuOption<T, &'static str>], state:&MagenBoyState,gfx_device:&mut GFX, emulation_framebuffer_channel:crossbeam_channel::Receiver<FrameBuffers>)->&'a T{
         let menu_renderer = joypad_gfx_menu::GfxDeviceMenuRenderer::new(gfx_device);
     
-        let mut menu = JoypadMenu::new(&GAME_MENU_OPTIONS, menu_renderer);  
+        let mut menu = JoypadMenu::new(options, menu_renderer);  
     
         // lock the mutex here to sync the 2 threads
         state.pause.store(true, std::sync::atomic::Ordering::SeqCst);
@@ -0,0 +1,67 @@
+use std::{fs, path::Path};
+use lib_gb::ppu::{hd_pack::{HdPack, HdTileKey}, debug_views::TILE_SIZE};
+use crate::vram_dump::write_png_data;
+
+const PNG_EXTENSION:&str = "png";
+
+// Loads every png in the directory named after a HdTileKey, the scale is taken from the first tile
+pub fn load_hd_pack(path:&str)->HdPack{
+    let entries = match fs::read_dir(path){
+        Result::Ok(entries)=>entries,
+        Result::Err(error)=>std::panic!("Error reading the hd pack directory {}: {}", path, error)
+    };
+    let mut pack:Option<HdPack> = Option::None;
+    for entry in entries.filter_map(|entry|entry.ok()){
+        let file_path = entry.path();
+        if file_path.extension().and_then(|extension|extension.to_str()) != Some(PNG_EXTENSION){
+            continue;
+        }
+        let key = match file_path.file_stem().and_then(|stem|stem.to_str()).and_then(HdTileKey::from_file_name){
+            Some(key)=>key,
+            Option::None=>{
+                log::warn!("skipping the hd tile {:?}, the name is not a tile key", file_path);
+                continue;
+            }
+        };
+        let (width, rgba) = match read_rgba_png(&file_path){
+            Result::Ok(image)=>image,
+            Result::Err(error)=>{
+                log::warn!("skipping the hd tile {:?}: {}", file_path, error);
+                continue;
+            }
+        };
+        let pack = pack.get_or_insert_with(||HdPack::new((width / TILE_SIZE).max(1)));
+        if let Result::Err(error) = pack.add_tile(key, rgba){
+            log::warn!("skipping the hd tile {:?}: {}", file_path, error);
+        }
+    }
+
+    return pack.unwrap_or_else(||std::panic!("Error! the hd pack directory {} has no valid tiles", path));
+}
+
+// Writes the tiles as 8x8 pngs that can be edited and loaded as a pack
+pub fn dump_hd_tiles(path:&str, tiles:Vec<(HdTileKey, Vec<u8>)>){
+    for (key, rgba) in tiles{
+        let file_path = Path::new(path).join(format!("{}.{}", key.get_file_name(), PNG_EXTENSION));
+        if let Result::Err(error) = write_png_data(&file_path.to_string_lossy(), TILE_SIZE, TILE_SIZE, png::ColorType::Rgba, &rgba){
+            log::error!("error writing the hd tile to {:?}: {}", file_path, error);
+        }
+    }
+}
+
+// Returns the image width and its pixels as RGBA8
+pub fn read_rgba_png(path:&Path)->Result<(usize, Vec<u8>), png::DecodingError>{
+    let mut decoder = png::Decoder::new(fs::File::open(path)?);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0;reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+    let rgba = match info.color_type{
+        png::ColorType::Rgba=>buffer,
+        png::ColorType::Rgb=>buffer.chunks_exact(3).flat_map(|pixel|[pixel[0], pixel[1], pixel[2], 0xFF]).collect(),
+        png::ColorType::GrayscaleAlpha=>buffer.chunks_exact(2).flat_map(|pixel|[pixel[0], pixel[0], pixel[0], pixel[1]]).collect(),
+        _=>buffer.iter().flat_map(|gray|[*gray, *gray, *gray, 0xFF]).collect()
+    };
+    return Ok((info.width as usize, rgba));
+}
\ No newline at end of file
@@ -2,6 +2,10 @@ mod mbc_handler;
 mod mpmc_gfx_device;
 mod joypad_menu;
 mod emulation_menu;
+mod vram_dump;
+mod hd_pack_loader;
+mod screenshot;
+mod rom_settings;
 
 #[cfg(feature = "rpi")]
 mod rpi_gpio;
@@ -13,6 +17,15 @@ mod audio{
     #[cfg(not(feature = "sdl-resample"))]
     pub mod manual_audio_resampler;
 }
+mod recording{
+    pub mod av_recorder;
+    pub mod last_frame_gfx_device;
+    pub mod replay_buffer;
+    pub mod gif_encoder;
+    pub mod avi_writer;
+    pub mod y4m_writer;
+    pub mod wav_writer;
+}
 #[cfg(feature = "sdl")]
 mod sdl{
     pub mod utils;
@@ -45,10 +58,11 @@ cfg_if::cfg_if!{
     }
 }
 
-use crate::{audio::multi_device_audio::*, mbc_handler::*, mpmc_gfx_device::MpmcGfxDevice, emulation_menu::MagenBoyMenu};
-use emulation_menu::MagenBoyState;
+use crate::{audio::multi_device_audio::*, mbc_handler::*, mpmc_gfx_device::MpmcGfxDevice, emulation_menu::MagenBoyMenu, recording::{av_recorder::*, last_frame_gfx_device::LastFrameGfxDevice, replay_buffer::*, gif_encoder::GifOptions}};
+use emulation_menu::{MagenBoyState, PalettesSelection, LayerToggleSelection};
 use joypad_menu::{JoypadMenu, MenuOption, MenuRenderer};
-use lib_gb::{keypad::button::Button, apu::audio_device::*, machine::gameboy::GameBoy, mmu::gb_mmu::BOOT_ROM_SIZE, ppu::{gb_ppu::{BUFFERS_NUMBER, SCREEN_HEIGHT, SCREEN_WIDTH}, gfx_device::{GfxDevice, Pixel}}};
+use lib_gb::{keypad::button::Button, apu::audio_device::*, machine::{gameboy::GameBoy, mode::Mode, profiler::{Profiler, SymbolTable}, event_tracer::EventTracer}, mmu::gb_mmu::BOOT_ROM_SIZE, ppu::{gb_ppu::{BUFFERS_NUMBER, SCREEN_HEIGHT, SCREEN_WIDTH}, gfx_device::{GfxDevice, Pixel}, colors::DmgPalettesPreset, cgb_compatibility_palettes::get_compatibility_palettes, layer_toggles::LayerToggles, debug_views::SPRITES_COUNT, hd_pack::HdTilesDumper, post_processing::{PostProcessing, PostProcessingFilter, DEFAULT_SCALE}}};
+use image_inter::PixelArtScaler;
 use std::{fs, env, result::Result, vec::Vec, path::PathBuf};
 use log::info;
 cfg_if::cfg_if! {if #[cfg(feature = "apu")]{
@@ -57,8 +71,13 @@ cfg_if::cfg_if! {if #[cfg(feature = "apu")]{
 }}
 #[cfg(feature = "sdl")]
 use sdl2::sys::*;
+#[cfg(all(feature = "sdl", not(feature = "rpi")))]
+use sdl::sdl_gfx_device::ScalingMode;
 
 const TURBO_MUL:u8 = 1;
+const DEFAULT_TRACE_LAST_FRAME:u32 = 60;
+#[cfg(feature = "sdl")]
+const DEFAULT_SCREENSHOT_KEY:&str = "F12";
 
 cfg_if::cfg_if!{ if #[cfg(feature = "rpi")] {
     const RESET_PIN_BCM:u8 = 14;
@@ -122,6 +141,108 @@ fn get_terminal_feature_flag_value(args:&Vec<String>, flag:&str, error_message:&
     return args.get(index + 1).expect(error_message).clone();
 }
 
+// Parses an inclusive frames range in the format first-last
+fn parse_frames_range(value:&str)->(u32, u32){
+    let error_message = "Error! the --trace-frames parameter must be in the format first-last";
+    let mut parts = value.split('-').map(|part|part.trim().parse::<u32>().expect(error_message));
+    let first = parts.next().expect(error_message);
+    let last = parts.next().expect(error_message);
+    if parts.next().is_some() || first > last{
+        std::panic!("{}", error_message);
+    }
+
+    return (first, last);
+}
+
+// Matches the preset names case insensitive with dashes instead of spaces (dmg-green for example)
+fn parse_palettes_preset(value:&str)->DmgPalettesPreset{
+    let value = value.to_lowercase();
+    return *DmgPalettesPreset::ALL.iter()
+        .find(|preset|preset.get_name().to_lowercase().replace(' ', "-") == value)
+        .unwrap_or_else(||std::panic!("Error! unknown palette: {}", value));
+}
+
+fn parse_upscaler(value:&str)->PixelArtScaler{
+    let value = value.to_lowercase();
+    return *PixelArtScaler::ALL.iter()
+        .find(|scaler|scaler.get_name().to_lowercase() == value)
+        .unwrap_or_else(||std::panic!("Error! unknown upscaler: {}", value));
+}
+
+// A comma separated list of filters names applied in order (lcd-ghosting,cgb-color-correction for example)
+fn parse_post_processing_filters(value:&str)->Vec<PostProcessingFilter>{
+    return value.split(',').map(|name|{
+        let name = name.trim().to_lowercase();
+        *PostProcessingFilter::ALL.iter()
+            .find(|filter|filter.get_name().to_lowercase().replace(' ', "-") == name)
+            .unwrap_or_else(||std::panic!("Error! unknown filter: {}", name))
+    }).collect();
+}
+
+// Either all for all the sprites or a comma separated list of oam indexes
+fn parse_hidden_sprites(value:&str, toggles:&mut LayerToggles){
+    if value == "all"{
+        toggles.set_all_sprites_hidden(true);
+        return;
+    }
+    for index in value.split(','){
+        let index = index.trim().parse::<u8>().ok().filter(|index|(*index as usize) < SPRITES_COUNT)
+            .unwrap_or_else(||std::panic!("Error! the --hide-sprites parameter must be all or a comma separated list of oam indexes (0-{})", SPRITES_COUNT - 1));
+        toggles.set_sprite_hidden(index, true);
+    }
+}
+
+fn parse_recording_format(args:&Vec<String>)->RecordingFormat{
+    if check_for_terminal_feature_flag(args, "--record-pipe"){
+        return RecordingFormat::Pipe(get_terminal_feature_flag_value(args, "--record-pipe", "Error! you must specify a value for the --record-pipe parameter"));
+    }
+    if !check_for_terminal_feature_flag(args, "--record-format"){
+        return RecordingFormat::Avi;
+    }
+    let value = get_terminal_feature_flag_value(args, "--record-format", "Error! you must specify a value for the --record-format parameter");
+    return match value.to_lowercase().as_str(){
+        "avi"=>RecordingFormat::Avi,
+        "y4m"=>RecordingFormat::Y4m,
+        _=>std::panic!("Error! the --record-format parameter must be avi or y4m")
+    };
+}
+
+#[cfg(all(feature = "sdl", not(feature = "rpi")))]
+fn parse_scaling_mode(value:&str)->ScalingMode{
+    let value = value.to_lowercase();
+    return *ScalingMode::ALL.iter()
+        .find(|mode|mode.get_name().to_lowercase() == value)
+        .unwrap_or_else(||std::panic!("Error! unknown scaling mode: {}", value));
+}
+
+// Pauses the emulation until the window regains the focus, a quit is pushed back for the main loop to handle
+#[cfg(feature = "sdl")]
+unsafe fn wait_for_focus(state:&MagenBoyState){
+    state.pause.store(true, std::sync::atomic::Ordering::SeqCst);
+    let mut event:SDL_Event = std::mem::zeroed();
+    while SDL_WaitEvent(&mut event) != 0{
+        if event.type_ == SDL_EventType::SDL_QUIT as u32{
+            SDL_PushEvent(&mut event);
+            break;
+        }
+        if event.type_ == SDL_EventType::SDL_WINDOWEVENT as u32 && event.window.event == SDL_WindowEventID::SDL_WINDOWEVENT_FOCUS_GAINED as u8{
+            break;
+        }
+    }
+    state.pause.store(false, std::sync::atomic::Ordering::SeqCst);
+}
+
+// Accepts the sdl key names (F12, Print Screen, P for example)
+#[cfg(feature = "sdl")]
+fn parse_scancode(value:&str)->SDL_Scancode{
+    let name = std::ffi::CString::new(value).unwrap_or_else(|_|std::panic!("Error! invalid key name: {}", value));
+    let scancode = unsafe{SDL_GetScancodeFromName(name.as_ptr())};
+    if scancode == SDL_Scancode::SDL_SCANCODE_UNKNOWN{
+        std::panic!("Error! unknown key: {}", value);
+    }
+    return scancode;
+}
+
 fn get_rom_selection<MR:MenuRenderer<PathBuf, String>>(roms_path:&str, menu_renderer:MR)->String{
     let mut menu_options = Vec::new();
     let dir_entries = std::fs::read_dir(roms_path).expect(std::format!("Error openning the roms directory: {}",roms_path).as_str());
@@ -129,7 +250,7 @@ fn get_rom_selection<MR:MenuRenderer<PathBuf, String>>(roms_path:&str, menu_rend
         let entry = entry.unwrap();
         let path = entry.path();
         if let Some(extension) = path.as_path().extension(){
-            if extension == "gb"{
+            if PROGRAM_SUFFIXES.iter().any(|suffix|extension == &suffix[1..]){
                 let filename = String::from(path.file_name().expect("Error should be a file").to_str().unwrap());
                 let option = MenuOption{value: path, prompt: filename};
                 menu_options.push(option);
@@ -162,13 +283,43 @@ fn main() {
         Result::Err(error)=>std::panic!("error initing logger: {}", error)
     }
 
+    let upscaler = if check_for_terminal_feature_flag(&args, "--upscaler"){
+        let name = get_terminal_feature_flag_value(&args, "--upscaler", "Error! you must specify a value for the --upscaler parameter");
+        let scaler = parse_upscaler(&name);
+        info!("upscaling with {}", scaler.get_name());
+        Some(scaler)
+    }else{
+        Option::None
+    };
+
     // Initialize the gfx first cause it initialize both the screen and the sdl context for the joypad
     cfg_if::cfg_if!{ if #[cfg(feature = "rpi")]{
-        let mut gfx_device:rpi_gpio::ili9341_controller::Ili9341GfxDevice<rpi_gpio::SpiType> = rpi_gpio::ili9341_controller::Ili9341GfxDevice::new(RESET_PIN_BCM, DC_PIN_BCM, LED_PIN_BCM, TURBO_MUL, 0);
+        let mut gfx_device:rpi_gpio::ili9341_controller::Ili9341GfxDevice<rpi_gpio::SpiType> = rpi_gpio::ili9341_controller::Ili9341GfxDevice::new(RESET_PIN_BCM, DC_PIN_BCM, LED_PIN_BCM, TURBO_MUL, 0, upscaler);
     }else{
-        let mut gfx_device = sdl::sdl_gfx_device::SdlGfxDevice::new("MagenBoy", SCREEN_SCALE, TURBO_MUL,
-        check_for_terminal_feature_flag(&args, "--no-vsync"), check_for_terminal_feature_flag(&args, "--full-screen"));
+        let screen_scale = if check_for_terminal_feature_flag(&args, "--window-scale"){
+            get_terminal_feature_flag_value(&args, "--window-scale", "Error! you must specify a value for the --window-scale parameter")
+                .parse::<usize>().ok().filter(|scale|*scale > 0).expect("Error! the --window-scale parameter must be a positive number")
+        }else{
+            SCREEN_SCALE
+        };
+        let scaling_mode = if check_for_terminal_feature_flag(&args, "--scaling"){
+            let name = get_terminal_feature_flag_value(&args, "--scaling", "Error! you must specify a value for the --scaling parameter");
+            parse_scaling_mode(&name)
+        }else{
+            ScalingMode::AspectFit
+        };
+        let mut gfx_device = sdl::sdl_gfx_device::SdlGfxDevice::new("MagenBoy", screen_scale, TURBO_MUL,
+        check_for_terminal_feature_flag(&args, "--no-vsync"), check_for_terminal_feature_flag(&args, "--full-screen"), scaling_mode, upscaler);
+        if check_for_terminal_feature_flag(&args, "--border"){
+            let path = get_terminal_feature_flag_value(&args, "--border", "Error! you must specify a value for the --border parameter");
+            match hd_pack_loader::read_rgba_png(std::path::Path::new(&path)){
+                Result::Ok((width, rgba))=>gfx_device.set_border(width, &rgba),
+                Result::Err(error)=>std::panic!("Error reading the border {}: {}", path, error)
+            }
+        }
     }}
+    #[cfg(feature = "sdl")]
+    let pause_on_focus_loss = !check_for_terminal_feature_flag(&args, "--no-focus-pause");
 
     cfg_if::cfg_if!{if #[cfg(feature = "rpi")]{
         let provider = GpioJoypadProvider::new(buttons_mapper);
@@ -178,6 +329,14 @@ fn main() {
     }} 
     let mut emulation_menu = MagenBoyMenu::new(provider);
 
+    #[cfg(feature = "sdl")]
+    let screenshot_key = if check_for_terminal_feature_flag(&args, "--screenshot-key"){
+        let name = get_terminal_feature_flag_value(&args, "--screenshot-key", "Error! you must specify a value for the --screenshot-key parameter");
+        parse_scancode(&name)
+    }else{
+        parse_scancode(DEFAULT_SCREENSHOT_KEY)
+    };
+
     while !(EMULATOR_STATE.exit.load(std::sync::atomic::Ordering::Relaxed)){
         let program_name = if check_for_terminal_feature_flag(&args, "--rom-menu"){
             let roms_path = get_terminal_feature_flag_value(&args, "--rom-menu", "Error! no roms folder specified");
@@ -221,6 +380,27 @@ fn main() {
                         else if event.type_ == SDL_EventType::SDL_KEYDOWN as u32 && event.key.keysym.scancode == SDL_Scancode::SDL_SCANCODE_ESCAPE{
                             emulation_menu.pop_game_menu(&EMULATOR_STATE, &mut gfx_device, r.clone());
                         }
+                        else if event.type_ == SDL_EventType::SDL_KEYDOWN as u32 && event.key.keysym.scancode == SDL_Scancode::SDL_SCANCODE_F2{
+                            EMULATOR_STATE.vram_dump_request.store(true, std::sync::atomic::Ordering::Relaxed);
+                        }
+                        else if event.type_ == SDL_EventType::SDL_KEYDOWN as u32 && (event.key.keysym.scancode == SDL_Scancode::SDL_SCANCODE_F11 ||
+                            (event.key.keysym.scancode == SDL_Scancode::SDL_SCANCODE_RETURN && event.key.keysym.mod_ & SDL_Keymod::KMOD_ALT as u16 != 0)){
+                            // The ili9341 has no window to toggle
+                            #[cfg(not(feature = "rpi"))]
+                            gfx_device.toggle_full_screen();
+                        }
+                        else if event.type_ == SDL_EventType::SDL_WINDOWEVENT as u32 && event.window.event == SDL_WindowEventID::SDL_WINDOWEVENT_FOCUS_LOST as u8 && pause_on_focus_loss{
+                            wait_for_focus(&EMULATOR_STATE);
+                        }
+                        else if event.type_ == SDL_EventType::SDL_KEYDOWN as u32 && event.key.keysym.scancode == screenshot_key{
+                            EMULATOR_STATE.screenshot_request.store(true, std::sync::atomic::Ordering::Relaxed);
+                        }
+                        else if event.type_ == SDL_EventType::SDL_KEYDOWN as u32 && event.key.keysym.scancode == SDL_Scancode::SDL_SCANCODE_F10{
+                            EMULATOR_STATE.recording_toggle_request.store(true, std::sync::atomic::Ordering::Relaxed);
+                        }
+                        else if event.type_ == SDL_EventType::SDL_KEYDOWN as u32 && event.key.keysym.scancode == SDL_Scancode::SDL_SCANCODE_F9{
+                            EMULATOR_STATE.replay_save_request.store(true, std::sync::atomic::Ordering::Relaxed);
+                        }
                     }
                 } else if #[cfg(feature = "rpi")]{
                     if menu_pin.is_low(){
@@ -229,7 +409,12 @@ fn main() {
                 }}
 
                 match r.recv() {
-                    Result::Ok(buffer) => gfx_device.swap_buffer(&*(buffer as *const [Pixel; SCREEN_WIDTH * SCREEN_HEIGHT])),
+                    Result::Ok(frame) => {
+                        if let Some((hd_buffer, scale)) = frame.hd_buffer{
+                            gfx_device.swap_hd_buffer(std::slice::from_raw_parts(hd_buffer as *const Pixel, SCREEN_WIDTH * SCREEN_HEIGHT * scale * scale), scale);
+                        }
+                        gfx_device.swap_buffer(&*(frame.buffer as *const [Pixel; SCREEN_WIDTH * SCREEN_HEIGHT]));
+                    }
                     Result::Err(_) => break,
                 }
             }
@@ -260,11 +445,13 @@ extern "C" fn sigint_handler(_:std::os::raw::c_int){
 
 // Receiving usize and not raw ptr cause in rust you cant pass a raw ptr to another thread
 fn emulation_thread_main(args: Vec<String>, program_name: String, spsc_gfx_device: MpmcGfxDevice) {
+    let recorder = RecorderHandle::default();
     cfg_if::cfg_if!{ 
         if #[cfg(feature = "apu")]{
             let mut devices: Vec::<Box::<dyn AudioDevice>> = Vec::new();
             let audio_device = sdl::ChosenAudioDevice::<ChosenResampler>::new(44100, TURBO_MUL);
             devices.push(Box::new(audio_device));
+            devices.push(Box::new(RecordingAudioDevice::<ChosenResampler>::new(GB_FREQUENCY, recorder.clone())));
             
             if check_for_terminal_feature_flag(&args, "--file-audio"){
                 let wav_ad = audio::wav_file_audio_device::WavfileAudioDevice::<ChosenResampler>::new(44100, GB_FREQUENCY, "output.wav");
@@ -277,6 +464,7 @@ fn emulation_thread_main(args: Vec<String>, program_name: String, spsc_gfx_devic
         }
     }
     let audio_devices = MultiAudioDevice::new(devices);
+    let (gfx_device, last_frame) = LastFrameGfxDevice::new(spsc_gfx_device);
     let mut mbc = initialize_mbc(&program_name);
     cfg_if::cfg_if!{
         if #[cfg(feature = "rpi")]{
@@ -286,14 +474,30 @@ fn emulation_thread_main(args: Vec<String>, program_name: String, spsc_gfx_devic
             let joypad_provider = sdl::sdl_joypad_provider::SdlJoypadProvider::new(buttons_mapper);
         }
     }
+    let cdl_path = if check_for_terminal_feature_flag(&args, "--cdl"){
+        Some(get_terminal_feature_flag_value(&args, "--cdl", "Error! you must specify a value for the --cdl parameter"))
+    }else{
+        Option::None
+    };
+    let code_data_logger = cdl_path.as_ref().map(|path|initialize_code_data_logger(path, &mbc));
     let bootrom_path = if check_for_terminal_feature_flag(&args, "--bootrom"){
         get_terminal_feature_flag_value(&args, "--bootrom", "Error! you must specify a value for the --bootrom parameter")
     }else{
         String::from("dmg_boot.bin")
     };
 
-    let mut gameboy = match fs::read(bootrom_path){
-        Result::Ok(file)=>{
+    // Computed before the gameboy borrows the mbc
+    let compatibility_palettes = get_compatibility_palettes(&*mbc);
+
+    // The dmg bootrom cant boot the cartridge in cgb mode
+    let bootrom = if Mode::from_cartridge(&*mbc) == Mode::CGB{
+        info!("cgb cartridge detected, skipping the bootrom");
+        Option::None
+    }else{
+        fs::read(bootrom_path).ok()
+    };
+    let mut gameboy = match bootrom{
+        Some(file)=>{
             info!("found bootrom!");
     
             let mut bootrom:[u8;BOOT_ROM_SIZE] = [0;BOOT_ROM_SIZE];
@@ -301,23 +505,254 @@ fn emulation_thread_main(args: Vec<String>, program_name: String, spsc_gfx_devic
                 bootrom[i] = file[i];
             }
         
-            GameBoy::new_with_bootrom(&mut mbc, joypad_provider,audio_devices, spsc_gfx_device, bootrom)
+            GameBoy::new_with_bootrom(&mut mbc, joypad_provider,audio_devices, gfx_device, bootrom)
         }
-        Result::Err(_)=>{
+        Option::None=>{
             info!("could not find bootrom... booting directly to rom");
     
-            GameBoy::new(&mut mbc, joypad_provider, audio_devices, spsc_gfx_device)
+            GameBoy::new(&mut mbc, joypad_provider, audio_devices, gfx_device)
         }
     };
     info!("initialized gameboy successfully!");
 
+    if let Some(logger) = code_data_logger{
+        gameboy.set_code_data_logger(logger);
+    }
+
+    // Both replace the dmg palettes so only one of them can be used
+    if check_for_terminal_feature_flag(&args, "--palette") && check_for_terminal_feature_flag(&args, "--colorize"){
+        std::panic!("Error! the --palette and --colorize parameters cannot be used together");
+    }
+    if check_for_terminal_feature_flag(&args, "--palette"){
+        let name = get_terminal_feature_flag_value(&args, "--palette", "Error! you must specify a value for the --palette parameter");
+        let preset = parse_palettes_preset(&name);
+        gameboy.set_dmg_palettes(preset.get_palettes());
+        info!("using the {} palette", preset.get_name());
+    }
+    if check_for_terminal_feature_flag(&args, "--colorize"){
+        gameboy.set_dmg_palettes(compatibility_palettes);
+        info!("colorizing with the cgb compatibility palettes");
+    }
+
+    let mut layer_toggles = LayerToggles::default();
+    layer_toggles.hide_background = check_for_terminal_feature_flag(&args, "--hide-background");
+    layer_toggles.hide_window = check_for_terminal_feature_flag(&args, "--hide-window");
+    if check_for_terminal_feature_flag(&args, "--hide-sprites"){
+        let value = get_terminal_feature_flag_value(&args, "--hide-sprites", "Error! you must specify a value for the --hide-sprites parameter");
+        parse_hidden_sprites(&value, &mut layer_toggles);
+    }
+    if layer_toggles != LayerToggles::default(){
+        gameboy.set_layer_toggles(layer_toggles);
+        info!("hiding rendering layers: {:?}", layer_toggles);
+    }
+
+    // The flag is remembered for the next runs of the rom
+    let mut rom_settings = rom_settings::RomSettings::load(&program_name);
+    if check_for_terminal_feature_flag(&args, "--no-sprite-limit") && !rom_settings.no_sprite_limit{
+        rom_settings.no_sprite_limit = true;
+        rom_settings.save(&program_name);
+    }
+    if rom_settings.no_sprite_limit{
+        gameboy.set_unlimited_sprites_per_line(true);
+        info!("drawing all the sprites on every line");
+    }
+
+    if check_for_terminal_feature_flag(&args, "--filters"){
+        let value = get_terminal_feature_flag_value(&args, "--filters", "Error! you must specify a value for the --filters parameter");
+        let scale = if check_for_terminal_feature_flag(&args, "--filters-scale"){
+            get_terminal_feature_flag_value(&args, "--filters-scale", "Error! you must specify a value for the --filters-scale parameter")
+                .parse::<usize>().ok().filter(|scale|*scale > 0).unwrap_or_else(||std::panic!("Error! the --filters-scale parameter must be a positive number"))
+        }else{
+            DEFAULT_SCALE
+        };
+        let filters = parse_post_processing_filters(&value);
+        info!("post processing filters: {:?}", filters);
+        *gameboy.get_post_processing() = PostProcessing::new(filters, scale);
+    }
+
+    if check_for_terminal_feature_flag(&args, "--hd-pack"){
+        let pack_path = get_terminal_feature_flag_value(&args, "--hd-pack", "Error! you must specify a value for the --hd-pack parameter");
+        let pack = hd_pack_loader::load_hd_pack(&pack_path);
+        info!("loaded {} hd tiles at {}x from: {}", pack.len(), pack.get_scale(), pack_path);
+        gameboy.set_hd_pack(Some(pack));
+    }
+    let hd_dump_path = if check_for_terminal_feature_flag(&args, "--hd-dump"){
+        let path = get_terminal_feature_flag_value(&args, "--hd-dump", "Error! you must specify a value for the --hd-dump parameter");
+        if let Result::Err(error) = fs::create_dir_all(&path){
+            std::panic!("Error creating the hd dump directory {}: {}", path, error);
+        }
+        gameboy.set_hd_tiles_dumper(Some(HdTilesDumper::new()));
+        info!("dumping the hd tiles to: {}", path);
+        Some(path)
+    }else{
+        Option::None
+    };
+
+    let screenshots_directory = if check_for_terminal_feature_flag(&args, "--screenshots-dir"){
+        get_terminal_feature_flag_value(&args, "--screenshots-dir", "Error! you must specify a value for the --screenshots-dir parameter")
+    }else{
+        String::from(screenshot::DEFAULT_SCREENSHOTS_DIRECTORY)
+    };
+    let save_processed_screenshot = check_for_terminal_feature_flag(&args, "--screenshot-processed");
+    let screenshot_frame = if check_for_terminal_feature_flag(&args, "--screenshot-at-frame"){
+        let frame = get_terminal_feature_flag_value(&args, "--screenshot-at-frame", "Error! you must specify a value for the --screenshot-at-frame parameter");
+        Some(frame.parse::<u32>().unwrap_or_else(|_|std::panic!("Error! the --screenshot-at-frame parameter must be a frame number")))
+    }else{
+        Option::None
+    };
+
+    let recordings_directory = if check_for_terminal_feature_flag(&args, "--recordings-dir"){
+        get_terminal_feature_flag_value(&args, "--recordings-dir", "Error! you must specify a value for the --recordings-dir parameter")
+    }else{
+        String::from(DEFAULT_RECORDINGS_DIRECTORY)
+    };
+    let recording_format = parse_recording_format(&args);
+    if check_for_terminal_feature_flag(&args, "--record"){
+        EMULATOR_STATE.recording_toggle_request.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    let replay_seconds = if check_for_terminal_feature_flag(&args, "--replay-seconds"){
+        get_terminal_feature_flag_value(&args, "--replay-seconds", "Error! you must specify a value for the --replay-seconds parameter")
+            .parse::<u32>().unwrap_or_else(|_|std::panic!("Error! the --replay-seconds parameter must be a number of seconds"))
+    }else{
+        DEFAULT_REPLAY_SECONDS
+    };
+    let mut replay_buffer = ReplayBuffer::new(replay_seconds);
+    let mut gif_options = GifOptions::default();
+    if check_for_terminal_feature_flag(&args, "--replay-scale"){
+        gif_options.scale = get_terminal_feature_flag_value(&args, "--replay-scale", "Error! you must specify a value for the --replay-scale parameter")
+            .parse::<usize>().ok().filter(|scale|*scale > 0).unwrap_or_else(||std::panic!("Error! the --replay-scale parameter must be a positive number"));
+    }
+    if check_for_terminal_feature_flag(&args, "--replay-frame-skip"){
+        gif_options.frame_skip = get_terminal_feature_flag_value(&args, "--replay-frame-skip", "Error! you must specify a value for the --replay-frame-skip parameter")
+            .parse::<usize>().unwrap_or_else(|_|std::panic!("Error! the --replay-frame-skip parameter must be a number"));
+    }
+    let replays_directory = if check_for_terminal_feature_flag(&args, "--replays-dir"){
+        get_terminal_feature_flag_value(&args, "--replays-dir", "Error! you must specify a value for the --replays-dir parameter")
+    }else{
+        String::from(DEFAULT_REPLAYS_DIRECTORY)
+    };
+
+    if check_for_terminal_feature_flag(&args, "--profile"){
+        let symbols_path = if check_for_terminal_feature_flag(&args, "--symbols"){
+            get_terminal_feature_flag_value(&args, "--symbols", "Error! you must specify a value for the --symbols parameter")
+        }else{
+            format!("{}.sym", program_name)
+        };
+        let symbols = match fs::read_to_string(&symbols_path){
+            Result::Ok(content)=>SymbolTable::parse(&content),
+            Result::Err(_)=>{
+                log::warn!("could not find symbols file: {}, profiling by address", symbols_path);
+                SymbolTable::empty()
+            }
+        };
+        gameboy.set_profiler(Profiler::new(symbols));
+        info!("profiling is enabled");
+    }
+
+    if check_for_terminal_feature_flag(&args, "--trace"){
+        let (first_frame, last_frame) = if check_for_terminal_feature_flag(&args, "--trace-frames"){
+            let frames = get_terminal_feature_flag_value(&args, "--trace-frames", "Error! you must specify a value for the --trace-frames parameter");
+            parse_frames_range(&frames)
+        }else{
+            (0, DEFAULT_TRACE_LAST_FRAME)
+        };
+        gameboy.set_event_tracer(EventTracer::new(first_frame, last_frame));
+        info!("tracing frames {} to {}", first_frame, last_frame);
+    }
+
+    let mut frames_counter:u32 = 0;
     EMULATOR_STATE.running.store(true, std::sync::atomic::Ordering::Relaxed);
     while EMULATOR_STATE.running.load(std::sync::atomic::Ordering::Relaxed){
         if !EMULATOR_STATE.pause.load(std::sync::atomic::Ordering::SeqCst){
             let state = &EMULATOR_STATE;
             let _mutex_ctx = state.state_mutex.lock().unwrap();
+            if let Some(selection) = state.palettes_request.lock().unwrap().take(){
+                gameboy.set_dmg_palettes(match selection{
+                    PalettesSelection::Preset(preset)=>preset.get_palettes(),
+                    PalettesSelection::Colorize=>compatibility_palettes,
+                    PalettesSelection::KeyCombination(combination)=>combination.get_palettes()
+                });
+            }
+            if let Some(selection) = state.layer_toggle_request.lock().unwrap().take(){
+                let mut toggles = gameboy.get_layer_toggles();
+                match selection{
+                    LayerToggleSelection::Background=>toggles.hide_background = !toggles.hide_background,
+                    LayerToggleSelection::Window=>toggles.hide_window = !toggles.hide_window,
+                    LayerToggleSelection::Sprites=>toggles.set_all_sprites_hidden(!toggles.are_all_sprites_hidden())
+                }
+                gameboy.set_layer_toggles(toggles);
+            }
+            if let Some(filter) = state.filter_toggle_request.lock().unwrap().take(){
+                let post_processing = gameboy.get_post_processing();
+                post_processing.toggle_filter(filter);
+                info!("post processing filters: {:?}", post_processing.get_filters());
+            }
+            if state.sprite_limit_toggle_request.swap(false, std::sync::atomic::Ordering::Relaxed){
+                let unlimited = !gameboy.is_unlimited_sprites_per_line();
+                gameboy.set_unlimited_sprites_per_line(unlimited);
+                info!("sprites per line limit: {}", if unlimited {"off"} else {"on"});
+                rom_settings.no_sprite_limit = unlimited;
+                rom_settings.save(&program_name);
+            }
+            if state.recording_toggle_request.swap(false, std::sync::atomic::Ordering::Relaxed){
+                if recorder.is_recording(){
+                    recorder.stop();
+                }
+                else{
+                    match AvRecorder::new(&recording_format, &recordings_directory, &program_name){
+                        Result::Ok(av_recorder)=>recorder.start(av_recorder),
+                        Result::Err(error)=>log::error!("error starting the recording: {}", error)
+                    }
+                }
+            }
+            if state.screenshot_request.swap(false, std::sync::atomic::Ordering::Relaxed) || screenshot_frame == Some(frames_counter){
+                gameboy.request_screenshot();
+            }
             gameboy.cycle_frame();
+            {
+                let last_frame = last_frame.borrow();
+                recorder.write_frame(&last_frame);
+                replay_buffer.push_frame(&last_frame);
+            }
+            if state.replay_save_request.swap(false, std::sync::atomic::Ordering::Relaxed){
+                replay_buffer.save_gif(&replays_directory, &program_name, gif_options);
+            }
+            frames_counter = frames_counter.wrapping_add(1);
+            if let Some(screenshot) = gameboy.take_screenshot(){
+                screenshot::save_screenshot(&screenshot, &screenshots_directory, &program_name, save_processed_screenshot);
+            }
+            if state.vram_dump_request.swap(false, std::sync::atomic::Ordering::Relaxed){
+                vram_dump::dump_vram_views(gameboy.get_ppu(), &program_name);
+            }
+            if let Some(path) = &hd_dump_path{
+                hd_pack_loader::dump_hd_tiles(path, gameboy.take_hd_dumped_tiles());
+            }
         }
+        else{
+            // Not burning a core while the menu is open or the window is out of focus
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+    recorder.stop();
+    if let Some(profiler) = gameboy.take_profiler(){
+        let profile_path = get_terminal_feature_flag_value(&args, "--profile", "Error! you must specify a value for the --profile parameter");
+        let result = fs::File::create(&profile_path).and_then(|file|profiler.write_folded_stacks(&mut std::io::BufWriter::new(file)));
+        match result{
+            Result::Ok(())=>info!("wrote profile to: {}", profile_path),
+            Result::Err(error)=>log::error!("error writing the profile to {}: {}", profile_path, error)
+        }
+    }
+    if let Some(tracer) = gameboy.take_event_tracer(){
+        let trace_path = get_terminal_feature_flag_value(&args, "--trace", "Error! you must specify a value for the --trace parameter");
+        let result = fs::File::create(&trace_path).and_then(|file|tracer.write_chrome_trace(&mut std::io::BufWriter::new(file)));
+        match result{
+            Result::Ok(())=>info!("wrote trace to: {}", trace_path),
+            Result::Err(error)=>log::error!("error writing the trace to {}: {}", trace_path, error)
+        }
+    }
+    if let (Some(cdl_path), Some(logger)) = (&cdl_path, gameboy.take_code_data_logger()){
+        release_code_data_logger(cdl_path, logger);
     }
     drop(gameboy);
     release_mbc(&program_name, mbc);
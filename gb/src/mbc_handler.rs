@@ -1,16 +1,15 @@
-use lib_gb::mmu::carts::*;
+use lib_gb::mmu::{carts::*, code_data_logger::CodeDataLogger};
 use std::boxed::Box;
 use std::fs;
 use log::info;
 
-const PROGRAM_SUFFIX:&str = ".gb";
+pub const PROGRAM_SUFFIXES:[&str;2] = [".gb", ".gbc"];
 pub const SAVE_SUFFIX:&str = ".sav";
 
 pub fn initialize_mbc(program_name:&String)->Box<dyn Mbc>{
-
-    let program_path = format!("{}{}",program_name,PROGRAM_SUFFIX);
-    let error_message = format!("No program found, notice that the file must have a `.gb` suffix - {}\n", program_name);
-    let program = fs::read(program_path).expect(error_message.as_str());
+    let program = PROGRAM_SUFFIXES.iter().find_map(|suffix|fs::read(format!("{}{}", program_name, suffix)).ok());
+    let error_message = format!("No program found, notice that the file must have a `.gb` or `.gbc` suffix - {}\n", program_name);
+    let program = program.expect(error_message.as_str());
     let save_data = try_get_save_data(program_name);
     
     return lib_gb::machine::mbc_initializer::initialize_mbc(program, save_data);
@@ -33,4 +32,29 @@ pub fn release_mbc(program_name:&String, mbc: Box<dyn Mbc>){
     else{
         info!("No battery detected, no save data created");
     }
-}
\ No newline at end of file
+}
+
+// Continues the log from previous sessions when it matches the cartridge
+pub fn initialize_code_data_logger(cdl_path:&str, mbc:&Box<dyn Mbc>)->CodeDataLogger{
+    let rom_size = mbc.get_rom().len();
+    let ram_size = mbc.get_ram().len();
+    if let Ok(data) = fs::read(cdl_path){
+        match CodeDataLogger::from_bytes(&data){
+            Ok(logger) if logger.get_rom_size() == rom_size && logger.get_ram_size() == ram_size=>{
+                info!("continuing the code data log from: {}", cdl_path);
+                return logger;
+            }
+            Ok(_)=>log::warn!("the code data log at {} does not match the cartridge, starting a new one", cdl_path),
+            Err(error)=>log::warn!("error loading the code data log at {}: {}, starting a new one", cdl_path, error)
+        }
+    }
+
+    return CodeDataLogger::new(rom_size, ram_size);
+}
+
+pub fn release_code_data_logger(cdl_path:&str, logger:CodeDataLogger){
+    match fs::write(cdl_path, logger.to_bytes()){
+        Ok(())=>info!("saved the code data log to: {}", cdl_path),
+        Err(error)=>log::error!("error saving the code data log to {}: {}", cdl_path, error)
+    }
+}
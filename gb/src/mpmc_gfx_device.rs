@@ -1,19 +1,31 @@
 use lib_gb::ppu::{gb_ppu::{SCREEN_HEIGHT, SCREEN_WIDTH}, gfx_device::{GfxDevice, Pixel}};
 
+// Pointers to the ppu buffers, the hd buffer is sent with its scale when an hd pack is loaded
+pub struct FrameBuffers{
+    pub buffer:usize,
+    pub hd_buffer:Option<(usize, usize)>
+}
+
 pub struct MpmcGfxDevice{
-    sender: crossbeam_channel::Sender<usize>
+    sender: crossbeam_channel::Sender<FrameBuffers>,
+    hd_buffer:Option<(usize, usize)>
 }
 
 impl MpmcGfxDevice{
-    pub fn new(sender:crossbeam_channel::Sender<usize>)->Self{
-        Self{sender}
+    pub fn new(sender:crossbeam_channel::Sender<FrameBuffers>)->Self{
+        Self{sender, hd_buffer:Option::None}
     }
 }
 
 impl GfxDevice for MpmcGfxDevice{
     fn swap_buffer(&mut self, buffer:&[Pixel; SCREEN_HEIGHT * SCREEN_WIDTH]) {
-        if self.sender.send(buffer.as_ptr() as usize).is_err(){
+        let frame = FrameBuffers{buffer:buffer.as_ptr() as usize, hd_buffer:self.hd_buffer.take()};
+        if self.sender.send(frame).is_err(){
             log::debug!("The receiver endpoint has been closed");
         }
     }
+
+    fn swap_hd_buffer(&mut self, buffer:&[Pixel], scale:usize){
+        self.hd_buffer = Some((buffer.as_ptr() as usize, scale));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,174 @@
+use std::{cell::RefCell, fs::{File, OpenOptions}, io::{BufWriter, Write, Result}, path::Path, rc::Rc};
+use lib_gb::{GB_FREQUENCY, machine::gameboy::CYCLES_PER_FRAME, apu::audio_device::*, ppu::{gb_ppu::{SCREEN_HEIGHT, SCREEN_WIDTH}, gfx_device::Pixel, color::Color}};
+use crate::screenshot::get_timestamped_name;
+#[cfg(feature = "apu")]
+use crate::audio::audio_resampler::AudioResampler;
+use super::{avi_writer::AviWriter, y4m_writer::Y4mWriter, wav_writer::WavWriter};
+
+pub const DEFAULT_RECORDINGS_DIRECTORY:&str = "recordings";
+const AUDIO_FREQUENCY:u32 = 44100;
+// The same volume as the sdl audio devices
+#[cfg(feature = "apu")]
+const VOLUME:Sample = 10;
+const RGB_CHANNELS:usize = 3;
+
+#[derive(Clone)]
+pub enum RecordingFormat{
+    Avi,
+    // A y4m video file and a wav audio file
+    Y4m,
+    // Raw RGB24 frames written to a pipe (created with mkfifo for example) and a wav audio file
+    Pipe(String)
+}
+
+enum RecordingOutput{
+    Avi(AviWriter<BufWriter<File>>),
+    Y4m(Y4mWriter<BufWriter<File>>, WavWriter<BufWriter<File>>),
+    Pipe(BufWriter<File>, WavWriter<BufWriter<File>>)
+}
+
+// Records a frame every emulated frame (the frame rate is exactly GB_FREQUENCY / CYCLES_PER_FRAME) with the audio of that frame,
+// without the apu feature there are no samples and the audio stays empty
+pub struct AvRecorder{
+    output:RecordingOutput,
+    // The RGB24 conversion buffer
+    frame:Vec<u8>,
+    samples:Vec<StereoSample>,
+    frames:u32
+}
+
+impl AvRecorder{
+    pub fn new(format:&RecordingFormat, directory:&str, program_name:&str)->Result<Self>{
+        std::fs::create_dir_all(directory)?;
+        let name = get_timestamped_name(program_name);
+        let create_file = |extension:&str|->Result<BufWriter<File>>{
+            let path = Path::new(directory).join(format!("{}.{}", name, extension));
+            log::info!("recording to: {}", path.display());
+            return Ok(BufWriter::new(File::create(path)?));
+        };
+        let output = match format{
+            RecordingFormat::Avi=>RecordingOutput::Avi(AviWriter::new(create_file("avi")?, SCREEN_WIDTH, SCREEN_HEIGHT, GB_FREQUENCY, CYCLES_PER_FRAME, AUDIO_FREQUENCY)?),
+            RecordingFormat::Y4m=>RecordingOutput::Y4m(
+                Y4mWriter::new(create_file("y4m")?, SCREEN_WIDTH, SCREEN_HEIGHT, GB_FREQUENCY, CYCLES_PER_FRAME)?,
+                WavWriter::new(create_file("wav")?, AUDIO_FREQUENCY)?
+            ),
+            RecordingFormat::Pipe(path)=>{
+                log::info!("waiting for a reader on the pipe: {}", path);
+                let pipe = BufWriter::new(OpenOptions::new().write(true).open(path)?);
+                log::info!("streaming rgb24 {}x{} frames at {}/{} fps to: {}", SCREEN_WIDTH, SCREEN_HEIGHT, GB_FREQUENCY, CYCLES_PER_FRAME, path);
+                RecordingOutput::Pipe(pipe, WavWriter::new(create_file("wav")?, AUDIO_FREQUENCY)?)
+            }
+        };
+        return Ok(Self{output, frame:vec![0;SCREEN_WIDTH * SCREEN_HEIGHT * RGB_CHANNELS], samples:Vec::new(), frames:0});
+    }
+
+    #[cfg(feature = "apu")]
+    pub fn push_samples(&mut self, samples:&[StereoSample]){
+        self.samples.extend(samples.iter().map(|sample|StereoSample{left_sample:sample.left_sample * VOLUME, right_sample:sample.right_sample * VOLUME}));
+    }
+
+    // Writes the frame and the audio since the previous call
+    pub fn write_frame(&mut self, buffer:&[Pixel])->Result<()>{
+        for (rgb, pixel) in self.frame.chunks_exact_mut(RGB_CHANNELS).zip(buffer.iter()){
+            let color = Color::from(*pixel);
+            rgb.copy_from_slice(&[color.r, color.g, color.b]);
+        }
+        match &mut self.output{
+            RecordingOutput::Avi(writer)=>{
+                writer.write_frame(&self.frame)?;
+                writer.write_samples(&self.samples)?;
+            }
+            RecordingOutput::Y4m(writer, wav_writer)=>{
+                writer.write_frame(&self.frame)?;
+                wav_writer.write_samples(&self.samples)?;
+            }
+            RecordingOutput::Pipe(pipe, wav_writer)=>{
+                pipe.write_all(&self.frame)?;
+                wav_writer.write_samples(&self.samples)?;
+            }
+        }
+        self.samples.clear();
+        self.frames += 1;
+        return Ok(());
+    }
+
+    pub fn finish(self)->Result<u32>{
+        match self.output{
+            RecordingOutput::Avi(writer)=>writer.finish()?,
+            RecordingOutput::Y4m(writer, wav_writer)=>{
+                writer.finish()?;
+                wav_writer.finish()?;
+            }
+            RecordingOutput::Pipe(mut pipe, wav_writer)=>{
+                pipe.flush()?;
+                wav_writer.finish()?;
+            }
+        }
+        return Ok(self.frames);
+    }
+}
+
+// Shares the recorder between the emulation loop and the devices, which are all owned by the emulation thread
+#[derive(Clone, Default)]
+pub struct RecorderHandle{
+    recorder:Rc<RefCell<Option<AvRecorder>>>
+}
+
+impl RecorderHandle{
+    pub fn is_recording(&self)->bool{
+        self.recorder.borrow().is_some()
+    }
+
+    pub fn start(&self, recorder:AvRecorder){
+        self.stop();
+        *self.recorder.borrow_mut() = Some(recorder);
+    }
+
+    pub fn stop(&self){
+        if let Some(recorder) = self.recorder.borrow_mut().take(){
+            match recorder.finish(){
+                Result::Ok(frames)=>log::info!("stopped recording after {} frames", frames),
+                Result::Err(error)=>log::error!("error finishing the recording: {}", error)
+            }
+        }
+    }
+
+    // Called once every emulated frame, stops the recording on errors (a closed pipe for example)
+    pub fn write_frame(&self, buffer:&[Pixel]){
+        let result = self.recorder.borrow_mut().as_mut().map_or(Ok(()), |recorder|recorder.write_frame(buffer));
+        if let Result::Err(error) = result{
+            log::error!("error recording the frame: {}", error);
+            self.stop();
+        }
+    }
+
+    #[cfg(feature = "apu")]
+    fn push_samples(&self, samples:&[StereoSample]){
+        if let Some(recorder) = self.recorder.borrow_mut().as_mut(){
+            recorder.push_samples(samples);
+        }
+    }
+}
+
+// Resamples the apu audio to the recorder while recording
+#[cfg(feature = "apu")]
+pub struct RecordingAudioDevice<AR:AudioResampler>{
+    resampler:AR,
+    recorder:RecorderHandle
+}
+
+#[cfg(feature = "apu")]
+impl<AR:AudioResampler> RecordingAudioDevice<AR>{
+    pub fn new(original_frequency:u32, recorder:RecorderHandle)->Self{
+        Self{resampler:AR::new(original_frequency, AUDIO_FREQUENCY), recorder}
+    }
+}
+
+#[cfg(feature = "apu")]
+impl<AR:AudioResampler> AudioDevice for RecordingAudioDevice<AR>{
+    fn push_buffer(&mut self, buffer:&[StereoSample; BUFFER_SIZE]){
+        if self.recorder.is_recording(){
+            self.recorder.push_samples(&self.resampler.resample(buffer));
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use std::io::{Write, Seek, SeekFrom, Result, Error};
+use lib_gb::apu::audio_device::StereoSample;
+use super::wav_writer::{write_pcm_format, samples_to_bytes, BLOCK_ALIGN};
+
+const RGB_CHANNELS:usize = 3;
+const AVIF_HASINDEX:u32 = 0x10;
+const AVIIF_KEYFRAME:u32 = 0x10;
+const VIDEO_CHUNK_ID:&[u8;4] = b"00db";
+const AUDIO_CHUNK_ID:&[u8;4] = b"01wb";
+const CHUNK_HEADER_SIZE:u64 = 8;
+const INDEX_ENTRY_SIZE:u64 = 16;
+// Avi 1.0 files (without the OpenDML extension) are not read correctly by some players above 2GB
+const MAX_FILE_SIZE:u64 = 1 << 31;
+
+struct IndexEntry{
+    chunk_id:&'static [u8;4],
+    offset:u32,
+    size:u32
+}
+
+// The positions of the header fields that are known only when the recording is finished
+struct HeaderOffsets{
+    total_frames:u64,
+    video_length:u64,
+    audio_length:u64,
+    movi_size:u64
+}
+
+// Writes uncompressed 24 bit RGB frames and 16 bit stereo pcm audio to an avi 1.0 file
+pub struct AviWriter<W:Write + Seek>{
+    writer:W,
+    width:usize,
+    height:usize,
+    header_offsets:HeaderOffsets,
+    // The position of the movi list type, the index offsets are relative to it
+    movi_position:u64,
+    position:u64,
+    index:Vec<IndexEntry>,
+    frames:u32,
+    audio_samples:u32
+}
+
+impl<W:Write + Seek> AviWriter<W>{
+    // The frame rate is rate / scale frames per second
+    pub fn new(mut writer:W, width:usize, height:usize, rate:u32, scale:u32, audio_frequency:u32)->Result<Self>{
+        let frame_size = (width * height * RGB_CHANNELS) as u32;
+        let mut header = Vec::new();
+        let write_u32 = |header:&mut Vec<u8>, value:u32|header.extend_from_slice(&value.to_le_bytes());
+
+        header.extend_from_slice(b"RIFF");
+        write_u32(&mut header, 0);
+        header.extend_from_slice(b"AVI LIST");
+        let hdrl_size_position = header.len();
+        write_u32(&mut header, 0);
+        header.extend_from_slice(b"hdrlavih");
+        write_u32(&mut header, 56);
+        write_u32(&mut header, (1_000_000u64 * scale as u64 / rate as u64) as u32);
+        write_u32(&mut header, ((frame_size as u64 * rate as u64 / scale as u64) + (audio_frequency * BLOCK_ALIGN as u32) as u64) as u32);
+        write_u32(&mut header, 0);
+        write_u32(&mut header, AVIF_HASINDEX);
+        let total_frames = header.len() as u64;
+        write_u32(&mut header, 0);
+        write_u32(&mut header, 0);
+        write_u32(&mut header, 2);
+        write_u32(&mut header, frame_size);
+        write_u32(&mut header, width as u32);
+        write_u32(&mut header, height as u32);
+        header.extend_from_slice(&[0;16]);
+
+        // The video stream
+        header.extend_from_slice(b"LIST");
+        write_u32(&mut header, 4 + 64 + 48);
+        header.extend_from_slice(b"strlstrh");
+        write_u32(&mut header, 56);
+        header.extend_from_slice(b"vidsDIB ");
+        write_u32(&mut header, 0);
+        write_u32(&mut header, 0);
+        write_u32(&mut header, 0);
+        write_u32(&mut header, scale);
+        write_u32(&mut header, rate);
+        write_u32(&mut header, 0);
+        let video_length = header.len() as u64;
+        write_u32(&mut header, 0);
+        write_u32(&mut header, frame_size);
+        write_u32(&mut header, u32::MAX);
+        write_u32(&mut header, 0);
+        header.extend_from_slice(&[0, 0, 0, 0]);
+        header.extend_from_slice(&(width as u16).to_le_bytes());
+        header.extend_from_slice(&(height as u16).to_le_bytes());
+        // BITMAPINFOHEADER, positive height for bottom up rows
+        header.extend_from_slice(b"strf");
+        write_u32(&mut header, 40);
+        write_u32(&mut header, 40);
+        write_u32(&mut header, width as u32);
+        write_u32(&mut header, height as u32);
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&((RGB_CHANNELS * 8) as u16).to_le_bytes());
+        write_u32(&mut header, 0);
+        write_u32(&mut header, frame_size);
+        header.extend_from_slice(&[0;16]);
+
+        // The audio stream, every sample is a block
+        header.extend_from_slice(b"LIST");
+        write_u32(&mut header, 4 + 64 + 26);
+        header.extend_from_slice(b"strlstrh");
+        write_u32(&mut header, 56);
+        header.extend_from_slice(b"auds");
+        write_u32(&mut header, 0);
+        write_u32(&mut header, 0);
+        write_u32(&mut header, 0);
+        write_u32(&mut header, 0);
+        write_u32(&mut header, 1);
+        write_u32(&mut header, audio_frequency);
+        write_u32(&mut header, 0);
+        let audio_length = header.len() as u64;
+        write_u32(&mut header, 0);
+        write_u32(&mut header, audio_frequency * BLOCK_ALIGN as u32);
+        write_u32(&mut header, u32::MAX);
+        write_u32(&mut header, BLOCK_ALIGN as u32);
+        header.extend_from_slice(&[0;8]);
+        // WAVEFORMATEX
+        header.extend_from_slice(b"strf");
+        write_u32(&mut header, 18);
+        write_pcm_format(&mut header, audio_frequency)?;
+        header.extend_from_slice(&0u16.to_le_bytes());
+
+        let hdrl_size = (header.len() - hdrl_size_position - 4) as u32;
+        header[hdrl_size_position..hdrl_size_position + 4].copy_from_slice(&hdrl_size.to_le_bytes());
+        header.extend_from_slice(b"LIST");
+        let movi_size = header.len() as u64;
+        write_u32(&mut header, 0);
+        let movi_position = header.len() as u64;
+        header.extend_from_slice(b"movi");
+
+        writer.write_all(&header)?;
+        return Ok(Self{
+            writer, width, height,
+            header_offsets:HeaderOffsets{total_frames, video_length, audio_length, movi_size},
+            movi_position, position:header.len() as u64, index:Vec::new(), frames:0, audio_samples:0
+        });
+    }
+
+    // The frame is rows of RGB pixels from the top
+    pub fn write_frame(&mut self, rgb_frame:&[u8])->Result<()>{
+        let row_size = self.width * RGB_CHANNELS;
+        let mut data = Vec::with_capacity(rgb_frame.len());
+        for row in rgb_frame.chunks_exact(row_size).rev().take(self.height){
+            for rgb in row.chunks_exact(RGB_CHANNELS){
+                data.extend_from_slice(&[rgb[2], rgb[1], rgb[0]]);
+            }
+        }
+        self.write_chunk(VIDEO_CHUNK_ID, &data)?;
+        self.frames += 1;
+        return Ok(());
+    }
+
+    pub fn write_samples(&mut self, samples:&[StereoSample])->Result<()>{
+        if samples.is_empty(){
+            return Ok(());
+        }
+        self.write_chunk(AUDIO_CHUNK_ID, &samples_to_bytes(samples))?;
+        self.audio_samples += samples.len() as u32;
+        return Ok(());
+    }
+
+    // Writes the index and the header sizes
+    pub fn finish(mut self)->Result<()>{
+        self.writer.write_all(b"idx1")?;
+        self.writer.write_all(&((self.index.len() as u64 * INDEX_ENTRY_SIZE) as u32).to_le_bytes())?;
+        for entry in self.index.iter(){
+            self.writer.write_all(entry.chunk_id)?;
+            self.writer.write_all(&AVIIF_KEYFRAME.to_le_bytes())?;
+            self.writer.write_all(&entry.offset.to_le_bytes())?;
+            self.writer.write_all(&entry.size.to_le_bytes())?;
+        }
+        let file_size = self.position + CHUNK_HEADER_SIZE + (self.index.len() as u64 * INDEX_ENTRY_SIZE);
+
+        let fields = [
+            (4, (file_size - CHUNK_HEADER_SIZE) as u32),
+            (self.header_offsets.total_frames, self.frames),
+            (self.header_offsets.video_length, self.frames),
+            (self.header_offsets.audio_length, self.audio_samples),
+            (self.header_offsets.movi_size, (self.position - self.movi_position) as u32)
+        ];
+        for (offset, value) in fields{
+            self.writer.seek(SeekFrom::Start(offset))?;
+            self.writer.write_all(&value.to_le_bytes())?;
+        }
+        return self.writer.flush();
+    }
+
+    fn write_chunk(&mut self, chunk_id:&'static [u8;4], data:&[u8])->Result<()>{
+        let chunk_size = CHUNK_HEADER_SIZE + data.len() as u64;
+        let index_size = (self.index.len() as u64 + 1) * INDEX_ENTRY_SIZE;
+        if self.position + chunk_size + CHUNK_HEADER_SIZE + index_size > MAX_FILE_SIZE{
+            return Err(Error::other("the avi file reached its maximum size"));
+        }
+        self.writer.write_all(chunk_id)?;
+        self.writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.writer.write_all(data)?;
+        self.index.push(IndexEntry{chunk_id, offset:(self.position - self.movi_position) as u32, size:data.len() as u32});
+        self.position += chunk_size;
+        return Ok(());
+    }
+}
@@ -0,0 +1,271 @@
+use std::{collections::HashMap, io::{Write, Result}};
+use lib_gb::ppu::{gb_ppu::{SCREEN_HEIGHT, SCREEN_WIDTH}, color::Color};
+use super::replay_buffer::IndexedFrame;
+
+const MAX_TABLE_SIZE:usize = 256;
+const MAX_CODE_SIZE:u32 = 12;
+const MAX_CODES:u16 = 1 << MAX_CODE_SIZE;
+const MAX_SUB_BLOCK_SIZE:usize = 255;
+const CENTISECONDS_PER_SECOND:u64 = 100;
+// Leaves the frame in place, the next frame is drawn over it
+const DISPOSAL_DO_NOT_DISPOSE:u8 = 1;
+
+#[derive(Clone, Copy)]
+pub struct GifOptions{
+    // Nearest neighbour integer scale
+    pub scale:usize,
+    // The frames skipped after every encoded frame, most viewers do not respect delays shorter than 2 centiseconds
+    pub frame_skip:usize
+}
+
+impl Default for GifOptions{
+    fn default()->Self{
+        Self{scale:1, frame_skip:1}
+    }
+}
+
+// An image drawn over the previous frames, the unchanged pixels inside its rectangle are transparent
+struct GifImage{
+    left:usize,
+    top:usize,
+    width:usize,
+    height:usize,
+    indexes:Vec<u8>,
+    local_table:Option<Vec<Color>>,
+    // The log2 of the (global or local) table size
+    table_bits:u8,
+    transparent_index:Option<u8>,
+    delay:u16
+}
+
+// Encodes the frames (frame_rate is rate / scale frames per second) as a looping animated gif,
+// only the changed part of every frame is encoded and identical frames are merged to a longer one
+pub fn write_gif<W:Write>(writer:&mut W, frames:&[IndexedFrame], frame_rate:(u32, u32), options:GifOptions)->Result<()>{
+    let frames:Vec<&IndexedFrame> = frames.iter().step_by(options.frame_skip + 1).collect();
+    // A shared table when all the colors fit in it with the transparent color
+    let mut global_table:Vec<Color> = Vec::new();
+    for color in frames.iter().flat_map(|frame|frame.get_palette()){
+        if !global_table.contains(color){
+            global_table.push(*color);
+        }
+    }
+    let global_table = if global_table.len() < MAX_TABLE_SIZE {Some(global_table)} else {None};
+
+    let (rate, frame_scale) = frame_rate;
+    // Rounding the time of every frame so the delays add up to the real duration
+    let get_time = |frame:usize|((frame * (options.frame_skip + 1)) as u64 * frame_scale as u64 * CENTISECONDS_PER_SECOND + (rate as u64 / 2)) / rate as u64;
+
+    writer.write_all(b"GIF89a")?;
+    writer.write_all(&((SCREEN_WIDTH * options.scale) as u16).to_le_bytes())?;
+    writer.write_all(&((SCREEN_HEIGHT * options.scale) as u16).to_le_bytes())?;
+    match &global_table{
+        Some(table)=>{
+            let table_bits = get_table_bits(table.len() + 1);
+            writer.write_all(&[0x80 | 0x70 | (table_bits - 1), 0, 0])?;
+            write_color_table(writer, table, table_bits)?;
+        }
+        None=>writer.write_all(&[0x70, 0, 0])?
+    }
+    // The netscape extension with 0 repetitions (loops forever)
+    writer.write_all(&[0x21, 0xFF, 11])?;
+    writer.write_all(b"NETSCAPE2.0")?;
+    writer.write_all(&[3, 1, 0, 0, 0])?;
+
+    let mut canvas:Option<Vec<Color>> = None;
+    let mut pending_image:Option<GifImage> = None;
+    for (frame_index, frame) in frames.iter().enumerate(){
+        let delay = (get_time(frame_index + 1) - get_time(frame_index)) as u16;
+        let palette = frame.get_palette();
+        let colors:Vec<Color> = frame.get_indexes().iter().map(|index|palette[*index as usize]).collect();
+        let changed_rect = match &canvas{
+            Some(canvas)=>get_changed_rect(canvas, &colors),
+            None=>Some((0, 0, SCREEN_WIDTH, SCREEN_HEIGHT))
+        };
+        let (left, top, width, height) = match changed_rect{
+            Some(rect)=>rect,
+            None=>{
+                if let Some(image) = pending_image.as_mut(){
+                    image.delay = image.delay.saturating_add(delay);
+                }
+                continue;
+            }
+        };
+
+        let table = global_table.clone().unwrap_or_else(||palette.to_vec());
+        let transparent_index = if canvas.is_some() && table.len() < MAX_TABLE_SIZE {Some(table.len() as u8)} else {None};
+        let mut indexes = Vec::with_capacity(width * height);
+        for y in top..top + height{
+            for x in left..left + width{
+                let index = (y * SCREEN_WIDTH) + x;
+                let color = colors[index];
+                let unchanged = canvas.as_ref().is_some_and(|canvas|canvas[index] == color);
+                indexes.push(match transparent_index{
+                    Some(transparent_index) if unchanged=>transparent_index,
+                    _=>table.iter().position(|c|*c == color).unwrap() as u8
+                });
+            }
+        }
+
+        if let Some(image) = pending_image.take(){
+            write_image(writer, &image, options.scale)?;
+        }
+        pending_image = Some(GifImage{
+            left, top, width, height, indexes, transparent_index, delay,
+            table_bits:get_table_bits(table.len() + (global_table.is_some() || transparent_index.is_some()) as usize),
+            local_table:if global_table.is_some() {None} else {Some(table)}
+        });
+        canvas = Some(colors);
+    }
+    if let Some(image) = pending_image{
+        write_image(writer, &image, options.scale)?;
+    }
+
+    writer.write_all(&[0x3B])?;
+    return writer.flush();
+}
+
+// The bounding rectangle of the changed pixels (left, top, width, height)
+fn get_changed_rect(canvas:&[Color], frame:&[Color])->Option<(usize, usize, usize, usize)>{
+    let (mut left, mut top, mut right, mut bottom) = (SCREEN_WIDTH, SCREEN_HEIGHT, 0, 0);
+    for (index, (old, new)) in canvas.iter().zip(frame.iter()).enumerate(){
+        if old != new{
+            let (x, y) = (index % SCREEN_WIDTH, index / SCREEN_WIDTH);
+            left = left.min(x);
+            right = right.max(x);
+            top = top.min(y);
+            bottom = bottom.max(y);
+        }
+    }
+    if left > right{
+        return None;
+    }
+    return Some((left, top, right - left + 1, bottom - top + 1));
+}
+
+fn write_image<W:Write>(writer:&mut W, image:&GifImage, scale:usize)->Result<()>{
+    // Graphic control extension
+    let transparent_flag = image.transparent_index.is_some() as u8;
+    writer.write_all(&[0x21, 0xF9, 4, (DISPOSAL_DO_NOT_DISPOSE << 2) | transparent_flag])?;
+    writer.write_all(&image.delay.to_le_bytes())?;
+    writer.write_all(&[image.transparent_index.unwrap_or(0), 0])?;
+
+    writer.write_all(&[0x2C])?;
+    for value in [image.left, image.top, image.width, image.height]{
+        writer.write_all(&((value * scale) as u16).to_le_bytes())?;
+    }
+    match &image.local_table{
+        Some(table)=>{
+            writer.write_all(&[0x80 | (image.table_bits - 1)])?;
+            write_color_table(writer, table, image.table_bits)?;
+        }
+        None=>writer.write_all(&[0])?
+    }
+
+    let mut scaled_indexes = Vec::with_capacity(image.indexes.len() * scale * scale);
+    for row in image.indexes.chunks_exact(image.width){
+        for _ in 0..scale{
+            for index in row{
+                scaled_indexes.extend(std::iter::repeat_n(*index, scale));
+            }
+        }
+    }
+    let min_code_size = (image.table_bits as u32).max(2);
+    writer.write_all(&[min_code_size as u8])?;
+    for block in lzw_encode(&scaled_indexes, min_code_size).chunks(MAX_SUB_BLOCK_SIZE){
+        writer.write_all(&[block.len() as u8])?;
+        writer.write_all(block)?;
+    }
+    return writer.write_all(&[0]);
+}
+
+// The log2 of the table size, gif tables have 2 to 256 colors
+fn get_table_bits(colors_count:usize)->u8{
+    let mut bits = 1;
+    while (1 << bits) < colors_count{
+        bits += 1;
+    }
+    return bits;
+}
+
+fn write_color_table<W:Write>(writer:&mut W, table:&[Color], table_bits:u8)->Result<()>{
+    for index in 0..1usize << table_bits{
+        let color = table.get(index).copied().unwrap_or_default();
+        writer.write_all(&[color.r, color.g, color.b])?;
+    }
+    return Ok(());
+}
+
+#[derive(Default)]
+struct BitWriter{
+    bytes:Vec<u8>,
+    buffer:u32,
+    bits_count:u32
+}
+
+impl BitWriter{
+    fn write(&mut self, code:u16, size:u32){
+        self.buffer |= (code as u32) << self.bits_count;
+        self.bits_count += size;
+        while self.bits_count >= 8{
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits_count -= 8;
+        }
+    }
+
+    fn finish(mut self)->Vec<u8>{
+        if self.bits_count > 0{
+            self.bytes.push(self.buffer as u8);
+        }
+        return self.bytes;
+    }
+}
+
+// The gif variant of lzw, the codes are written from the least significant bit and the code size grows up to 12 bits
+fn lzw_encode(indexes:&[u8], min_code_size:u32)->Vec<u8>{
+    let clear_code:u16 = 1 << min_code_size;
+    let end_code = clear_code + 1;
+    let mut writer = BitWriter::default();
+    let mut dictionary:HashMap<(u16, u8), u16> = HashMap::new();
+    let mut code_size = min_code_size + 1;
+    let mut next_code = end_code + 1;
+    writer.write(clear_code, code_size);
+
+    let mut indexes = indexes.iter();
+    let mut prefix = match indexes.next(){
+        Some(index)=>*index as u16,
+        None=>{
+            writer.write(end_code, code_size);
+            return writer.finish();
+        }
+    };
+    for index in indexes{
+        if let Some(code) = dictionary.get(&(prefix, *index)){
+            prefix = *code;
+            continue;
+        }
+        writer.write(prefix, code_size);
+        if next_code == MAX_CODES{
+            writer.write(clear_code, code_size);
+            dictionary.clear();
+            code_size = min_code_size + 1;
+            next_code = end_code + 1;
+        }
+        else{
+            dictionary.insert((prefix, *index), next_code);
+            next_code += 1;
+            // The decoder adds every code one code later, so it grows when the code after this one is read
+            if next_code > (1 << code_size) && code_size < MAX_CODE_SIZE{
+                code_size += 1;
+            }
+        }
+        prefix = *index as u16;
+    }
+    writer.write(prefix, code_size);
+    // The decoder adds a code for the last prefix too before reading the end code
+    if next_code == (1 << code_size) && code_size < MAX_CODE_SIZE{
+        code_size += 1;
+    }
+    writer.write(end_code, code_size);
+    return writer.finish();
+}
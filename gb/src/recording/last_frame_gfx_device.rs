@@ -0,0 +1,29 @@
+use std::{cell::RefCell, rc::Rc};
+use lib_gb::ppu::{gb_ppu::{SCREEN_HEIGHT, SCREEN_WIDTH}, gfx_device::{GfxDevice, Pixel}};
+
+// The last frame the ppu has drawn, read by the emulation loop after every emulated frame (so it repeats while the lcd is off)
+pub type LastFrame = Rc<RefCell<Vec<Pixel>>>;
+
+// Copies the frames for the recorders and passes them to the device
+pub struct LastFrameGfxDevice<GFX:GfxDevice>{
+    device:GFX,
+    last_frame:LastFrame
+}
+
+impl<GFX:GfxDevice> LastFrameGfxDevice<GFX>{
+    pub fn new(device:GFX)->(Self, LastFrame){
+        let last_frame = Rc::new(RefCell::new(vec![Pixel::MAX;SCREEN_WIDTH * SCREEN_HEIGHT]));
+        return (Self{device, last_frame:last_frame.clone()}, last_frame);
+    }
+}
+
+impl<GFX:GfxDevice> GfxDevice for LastFrameGfxDevice<GFX>{
+    fn swap_buffer(&mut self, buffer:&[Pixel; SCREEN_HEIGHT * SCREEN_WIDTH]) {
+        self.last_frame.borrow_mut().copy_from_slice(buffer);
+        self.device.swap_buffer(buffer);
+    }
+
+    fn swap_hd_buffer(&mut self, buffer:&[Pixel], scale:usize){
+        self.device.swap_hd_buffer(buffer, scale);
+    }
+}
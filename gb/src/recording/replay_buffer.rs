@@ -0,0 +1,120 @@
+use std::{collections::{HashMap, VecDeque}, fs::File, io::BufWriter, path::Path};
+use lib_gb::{GB_FREQUENCY, machine::gameboy::CYCLES_PER_FRAME, ppu::{gfx_device::Pixel, color::Color}};
+use crate::screenshot::get_timestamped_name;
+use super::gif_encoder::{write_gif, GifOptions};
+
+pub const DEFAULT_REPLAYS_DIRECTORY:&str = "replays";
+// The replay is opt in since it keeps the frames in memory and indexes every frame
+pub const DEFAULT_REPLAY_SECONDS:u32 = 0;
+
+const MAX_PALETTE_SIZE:usize = 256;
+
+// A frame as indexes to its colors packed with the minimal bits per pixel, most of the dmg frames take 2 bits per pixel
+#[derive(Clone)]
+pub struct IndexedFrame{
+    palette:Vec<Color>,
+    bits_per_pixel:usize,
+    data:Vec<u8>,
+    pixels_count:usize
+}
+
+impl IndexedFrame{
+    pub fn new(buffer:&[Pixel])->Self{
+        Self::try_index(buffer.iter().map(|pixel|Color::from(*pixel)))
+            // Frames with more colors (cgb frames with mid frame palette changes) are reduced to RGB332
+            .unwrap_or_else(||Self::try_index(buffer.iter().map(|pixel|to_rgb332(Color::from(*pixel)))).unwrap())
+    }
+
+    pub fn get_palette(&self)->&[Color]{
+        &self.palette
+    }
+
+    pub fn get_indexes(&self)->Vec<u8>{
+        let pixels_per_byte = 8 / self.bits_per_pixel;
+        let mask = ((1u16 << self.bits_per_pixel) - 1) as u8;
+        return (0..self.pixels_count)
+            .map(|index|(self.data[index / pixels_per_byte] >> ((index % pixels_per_byte) * self.bits_per_pixel)) & mask)
+            .collect();
+    }
+
+    fn try_index(colors:impl Iterator<Item = Color>)->Option<Self>{
+        let mut palette = Vec::new();
+        let mut palette_indexes = HashMap::new();
+        let mut indexes = Vec::new();
+        for color in colors{
+            let index = *palette_indexes.entry([color.r, color.g, color.b]).or_insert_with(||{
+                palette.push(color);
+                palette.len() - 1
+            });
+            if index >= MAX_PALETTE_SIZE{
+                return None;
+            }
+            indexes.push(index as u8);
+        }
+
+        let bits_per_pixel = match palette.len(){
+            0..=2=>1,
+            3..=4=>2,
+            5..=16=>4,
+            _=>8
+        };
+        let pixels_per_byte = 8 / bits_per_pixel;
+        let mut data = vec![0;indexes.len().div_ceil(pixels_per_byte)];
+        for (pixel, index) in indexes.iter().enumerate(){
+            data[pixel / pixels_per_byte] |= index << ((pixel % pixels_per_byte) * bits_per_pixel);
+        }
+        return Some(Self{palette, bits_per_pixel, data, pixels_count:indexes.len()});
+    }
+}
+
+fn to_rgb332(color:Color)->Color{
+    let expand = |value:u8, bits:u32|((value >> (8 - bits)) as u16 * 0xFF / ((1 << bits) - 1)) as u8;
+    return Color{r:expand(color.r, 3), g:expand(color.g, 3), b:expand(color.b, 2)};
+}
+
+// Keeps the frames of the last seconds for the instant replay
+pub struct ReplayBuffer{
+    frames:VecDeque<IndexedFrame>,
+    capacity:usize
+}
+
+impl ReplayBuffer{
+    pub fn new(seconds:u32)->Self{
+        let capacity = (seconds as u64 * GB_FREQUENCY as u64 / CYCLES_PER_FRAME as u64) as usize;
+        Self{frames:VecDeque::with_capacity(capacity), capacity}
+    }
+
+    pub fn push_frame(&mut self, buffer:&[Pixel]){
+        if self.capacity == 0{
+            return;
+        }
+        if self.frames.len() == self.capacity{
+            self.frames.pop_front();
+        }
+        self.frames.push_back(IndexedFrame::new(buffer));
+    }
+
+    // Encodes the frames in the background to <rom>_<timestamp>.gif in the directory
+    pub fn save_gif(&self, directory:&str, program_name:&str, options:GifOptions){
+        if self.capacity == 0{
+            log::warn!("the instant replay is disabled, enable it with --replay-seconds");
+            return;
+        }
+        if self.frames.is_empty(){
+            log::warn!("there are no frames to save in the replay");
+            return;
+        }
+        let frames:Vec<IndexedFrame> = self.frames.iter().cloned().collect();
+        let directory = directory.to_string();
+        let path = Path::new(&directory).join(format!("{}.gif", get_timestamped_name(program_name)));
+        std::thread::Builder::new().name("Replay Encoder Thread".to_string()).spawn(move ||{
+            let result = std::fs::create_dir_all(&directory)
+                .and_then(|_|File::create(&path))
+                .and_then(|file|write_gif(&mut BufWriter::new(file), &frames, (GB_FREQUENCY, CYCLES_PER_FRAME), options));
+            match result{
+                Result::Ok(())=>log::info!("wrote replay of {} frames to: {}", frames.len(), path.display()),
+                Result::Err(error)=>log::error!("error writing the replay to {}: {}", path.display(), error)
+            }
+        }).unwrap();
+    }
+}
@@ -0,0 +1,63 @@
+use std::io::{Write, Seek, SeekFrom, Result};
+use lib_gb::apu::audio_device::StereoSample;
+
+pub const CHANNELS:u16 = 2;
+pub const BITS_PER_SAMPLE:u16 = 16;
+pub const BLOCK_ALIGN:u16 = CHANNELS * (BITS_PER_SAMPLE / 8);
+const WAVE_FORMAT_PCM:u16 = 1;
+const HEADER_SIZE:u32 = 44;
+const RIFF_SIZE_OFFSET:u64 = 4;
+const DATA_SIZE_OFFSET:u64 = 40;
+
+// Streams 16 bit stereo pcm samples to a wav file, the sizes in the header are written when finished
+pub struct WavWriter<W:Write + Seek>{
+    writer:W,
+    data_size:u32
+}
+
+impl<W:Write + Seek> WavWriter<W>{
+    pub fn new(mut writer:W, frequency:u32)->Result<Self>{
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        write_pcm_format(&mut writer, frequency)?;
+        writer.write_all(b"data")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        return Ok(Self{writer, data_size:0});
+    }
+
+    pub fn write_samples(&mut self, samples:&[StereoSample])->Result<()>{
+        self.writer.write_all(&samples_to_bytes(samples))?;
+        self.data_size += (samples.len() * BLOCK_ALIGN as usize) as u32;
+        return Ok(());
+    }
+
+    pub fn finish(mut self)->Result<()>{
+        self.writer.seek(SeekFrom::Start(RIFF_SIZE_OFFSET))?;
+        self.writer.write_all(&(HEADER_SIZE - 8 + self.data_size).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(DATA_SIZE_OFFSET))?;
+        self.writer.write_all(&self.data_size.to_le_bytes())?;
+        return self.writer.flush();
+    }
+}
+
+// The fields of the WAVEFORMAT struct (without cbSize), shared with the avi audio stream
+pub fn write_pcm_format<W:Write>(writer:&mut W, frequency:u32)->Result<()>{
+    writer.write_all(&WAVE_FORMAT_PCM.to_le_bytes())?;
+    writer.write_all(&CHANNELS.to_le_bytes())?;
+    writer.write_all(&frequency.to_le_bytes())?;
+    writer.write_all(&(frequency * BLOCK_ALIGN as u32).to_le_bytes())?;
+    writer.write_all(&BLOCK_ALIGN.to_le_bytes())?;
+    return writer.write_all(&BITS_PER_SAMPLE.to_le_bytes());
+}
+
+pub fn samples_to_bytes(samples:&[StereoSample])->Vec<u8>{
+    let mut bytes = Vec::with_capacity(samples.len() * BLOCK_ALIGN as usize);
+    for sample in samples{
+        bytes.extend_from_slice(&sample.left_sample.to_le_bytes());
+        bytes.extend_from_slice(&sample.right_sample.to_le_bytes());
+    }
+    return bytes;
+}
@@ -0,0 +1,43 @@
+use std::io::{Write, Result};
+
+const RGB_CHANNELS:usize = 3;
+
+// Writes the frames as full range 4:4:4 BT.601 YCbCr (the y4m format has no RGB colorspace)
+pub struct Y4mWriter<W:Write>{
+    writer:W,
+    planes:[Vec<u8>;3]
+}
+
+impl<W:Write> Y4mWriter<W>{
+    // The frame rate is rate / scale frames per second
+    pub fn new(mut writer:W, width:usize, height:usize, rate:u32, scale:u32)->Result<Self>{
+        writeln!(writer, "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C444 XCOLORRANGE=FULL", width, height, rate, scale)?;
+        let plane_size = width * height;
+        return Ok(Self{writer, planes:[vec![0;plane_size], vec![0;plane_size], vec![0;plane_size]]});
+    }
+
+    // The frame is rows of RGB pixels from the top
+    pub fn write_frame(&mut self, rgb_frame:&[u8])->Result<()>{
+        let [y_plane, cb_plane, cr_plane] = &mut self.planes;
+        for (index, rgb) in rgb_frame.chunks_exact(RGB_CHANNELS).enumerate(){
+            let (r, g, b) = (rgb[0] as i32, rgb[1] as i32, rgb[2] as i32);
+            y_plane[index] = to_channel((77 * r) + (150 * g) + (29 * b), 0);
+            cb_plane[index] = to_channel((-43 * r) - (85 * g) + (128 * b), 128);
+            cr_plane[index] = to_channel((128 * r) - (107 * g) - (21 * b), 128);
+        }
+        self.writer.write_all(b"FRAME\n")?;
+        for plane in self.planes.iter(){
+            self.writer.write_all(plane)?;
+        }
+        return Ok(());
+    }
+
+    pub fn finish(mut self)->Result<()>{
+        self.writer.flush()
+    }
+}
+
+// The coefficients are 8 bit fixed point fractions
+fn to_channel(value:i32, offset:i32)->u8{
+    (((value + 128) >> 8) + offset).clamp(0, u8::MAX as i32) as u8
+}
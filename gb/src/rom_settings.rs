@@ -0,0 +1,37 @@
+use std::fs;
+use log::info;
+
+pub const SETTINGS_SUFFIX:&str = ".settings";
+const NO_SPRITE_LIMIT_KEY:&str = "no-sprite-limit";
+
+// The enhancements chosen for a specific rom, saved next to it like the save data
+#[derive(Default)]
+pub struct RomSettings{
+    pub no_sprite_limit:bool
+}
+
+impl RomSettings{
+    pub fn load(program_name:&str)->Self{
+        let mut settings = Self::default();
+        let settings_path = format!("{}{}", program_name, SETTINGS_SUFFIX);
+        if let Ok(text) = fs::read_to_string(&settings_path){
+            for line in text.lines(){
+                match line.split_once('='){
+                    Some((NO_SPRITE_LIMIT_KEY, value))=>settings.no_sprite_limit = value.trim() == "true",
+                    _=>log::warn!("ignoring an unknown setting in {}: {}", settings_path, line)
+                }
+            }
+            info!("loaded the rom settings from: {}", settings_path);
+        }
+
+        return settings;
+    }
+
+    pub fn save(&self, program_name:&str){
+        let settings_path = format!("{}{}", program_name, SETTINGS_SUFFIX);
+        match fs::write(&settings_path, format!("{}={}\n", NO_SPRITE_LIMIT_KEY, self.no_sprite_limit)){
+            Ok(())=>info!("saved the rom settings to: {}", settings_path),
+            Err(error)=>log::error!("error saving the rom settings to {}: {}", settings_path, error)
+        }
+    }
+}
@@ -2,6 +2,9 @@ use std::ops::Add;
 
 use lib_gb::ppu::{gb_ppu::{SCREEN_WIDTH, SCREEN_HEIGHT}, gfx_device::{GfxDevice, Pixel}};
 use rppal::gpio::OutputPin;
+use image_inter::PixelArtScaler;
+#[cfg(not(target_arch = "arm"))]
+use image_inter::{Resizer, ResizeAlgorithm, OutputFormat, ByteOrder};
 
 pub enum Ili9341Commands{
     SoftwareReset = 0x01,
@@ -140,10 +143,7 @@ impl<SC:SpiController> Ili9341Contoller<SC>{
     }
 
 
-    pub fn write_frame_buffer(&mut self, buffer:&[u16;SCREEN_HEIGHT*SCREEN_WIDTH]){
-        let mut scaled_buffer: [u8;TARGET_SCREEN_HEIGHT * TARGET_SCREEN_WIDTH * 2] = [0;TARGET_SCREEN_HEIGHT * TARGET_SCREEN_WIDTH * 2];
-        unsafe{image_inter::scale_biliniear_c::<SCREEN_WIDTH, SCREEN_HEIGHT, TARGET_SCREEN_WIDTH, TARGET_SCREEN_HEIGHT>(buffer.as_ptr(), scaled_buffer.as_mut_ptr())};
-
+    pub fn write_frame_buffer(&mut self, scaled_buffer:&[u8;SPI_BUFFER_SIZE]){
         let end_x_index = TARGET_SCREEN_WIDTH + FRAME_BUFFER_X_OFFSET - 1;
         self.spi.write(Ili9341Commands::ColumnAddressSet, &[
             (FRAME_BUFFER_X_OFFSET >> 8) as u8,
@@ -157,7 +157,7 @@ impl<SC:SpiController> Ili9341Contoller<SC>{
             ((TARGET_SCREEN_HEIGHT - 1) & 0xFF) as u8 
         ]);
 
-        self.spi.write_buffer(Ili9341Commands::MemoryWrite, &scaled_buffer);
+        self.spi.write_buffer(Ili9341Commands::MemoryWrite, scaled_buffer);
     }
 
     fn sleep_ms(milliseconds_to_sleep:u64){
@@ -179,6 +179,9 @@ pub struct Ili9341GfxDevice<SC:SpiController>{
     ili9341_controller:Ili9341Contoller<SC>,
     turbo_mul:u8,
     turbo_frame_counter:u8,
+    // The frames are upscaled to this buffer and then resized to the screen
+    upscaler:Option<(PixelArtScaler, Vec<u16>)>,
+    resizer:FrameResizer,
 
     frame_limiter:u32,
     frames_counter: u32,
@@ -187,17 +190,78 @@ pub struct Ili9341GfxDevice<SC:SpiController>{
 }
 
 impl<SC:SpiController> Ili9341GfxDevice<SC>{
-    pub fn new(reset_pin_bcm:u8, dc_pin_bcm:u8, led_pin_bcm:u8, turbo_mul:u8, frame_limiter:u32)->Self{
+    pub fn new(reset_pin_bcm:u8, dc_pin_bcm:u8, led_pin_bcm:u8, turbo_mul:u8, frame_limiter:u32, upscaler:Option<PixelArtScaler>)->Self{
         #[cfg(not(feature = "u16pixel"))]
         std::compile_error("ili9341 gfx device must have Pixel type = u16");
 
         let ili9341_controller = Ili9341Contoller::new(reset_pin_bcm, dc_pin_bcm, led_pin_bcm);
+        let factor = upscaler.map_or(1, |scaler|scaler.get_factor());
+        let resizer = FrameResizer::new(factor);
 
         Ili9341GfxDevice {
             ili9341_controller,frames_counter:0,
             time_counter: std::time::Duration::ZERO, last_time:std::time::Instant::now(),
-            turbo_mul, turbo_frame_counter:0, frame_limiter
+            turbo_mul, turbo_frame_counter:0, frame_limiter,
+            upscaler:upscaler.map(|scaler|(scaler, vec![0;SCREEN_WIDTH * SCREEN_HEIGHT * factor * factor])),
+            resizer
+        }
+    }
+
+    fn scale_frame_buffer(&mut self, buffer:&[Pixel; SCREEN_HEIGHT * SCREEN_WIDTH])->[u8;SPI_BUFFER_SIZE]{
+        let mut scaled_buffer = [0;SPI_BUFFER_SIZE];
+        match &mut self.upscaler{
+            Some((scaler, upscaled_buffer))=>{
+                scaler.scale::<Pixel, SCREEN_WIDTH, SCREEN_HEIGHT>(buffer, upscaled_buffer);
+                self.resizer.resize(upscaled_buffer, &mut scaled_buffer)
+            }
+            None=>self.resizer.resize(buffer, &mut scaled_buffer)
+        }
+        return scaled_buffer;
+    }
+}
+
+// Resizes the (upscaled) frames to the screen size.
+// The resizer has no simd kernels for 32 bit arm and its scalar kernel was not measured there,
+// so the c bilinear scaler that was used before is kept on those targets.
+struct FrameResizer{
+    #[cfg(not(target_arch = "arm"))]
+    resizer:Resizer,
+    #[cfg(target_arch = "arm")]
+    factor:usize
+}
+
+impl FrameResizer{
+    #[cfg(not(target_arch = "arm"))]
+    fn new(factor:usize)->Self{
+        // The ili9341 expects big endian pixels
+        let resizer = Resizer::new(SCREEN_WIDTH * factor, SCREEN_HEIGHT * factor, TARGET_SCREEN_WIDTH, TARGET_SCREEN_HEIGHT,
+            ResizeAlgorithm::Bilinear, OutputFormat::Rgb565(ByteOrder::BigEndian)).unwrap();
+        log::info!("Resizing the frames using {:?}", resizer.get_simd_level());
+        return FrameResizer{resizer};
+    }
+
+    #[cfg(target_arch = "arm")]
+    fn new(factor:usize)->Self{
+        if factor > 3{
+            std::panic!("Error! unsupported upscaler factor: {}", factor);
         }
+        log::info!("Resizing the frames using the c bilinear scaler");
+        return FrameResizer{factor};
+    }
+
+    #[cfg(not(target_arch = "arm"))]
+    fn resize(&mut self, input:&[Pixel], output:&mut [u8;SPI_BUFFER_SIZE]){
+        self.resizer.resize(input, output).unwrap();
+    }
+
+    #[cfg(target_arch = "arm")]
+    fn resize(&mut self, input:&[Pixel], output:&mut [u8;SPI_BUFFER_SIZE]){
+        unsafe{match self.factor{
+            1=>image_inter::scale_biliniear_c::<SCREEN_WIDTH, SCREEN_HEIGHT, TARGET_SCREEN_WIDTH, TARGET_SCREEN_HEIGHT>(input.as_ptr(), output.as_mut_ptr()),
+            2=>image_inter::scale_biliniear_c::<{SCREEN_WIDTH * 2}, {SCREEN_HEIGHT * 2}, TARGET_SCREEN_WIDTH, TARGET_SCREEN_HEIGHT>(input.as_ptr(), output.as_mut_ptr()),
+            3=>image_inter::scale_biliniear_c::<{SCREEN_WIDTH * 3}, {SCREEN_HEIGHT * 3}, TARGET_SCREEN_WIDTH, TARGET_SCREEN_HEIGHT>(input.as_ptr(), output.as_mut_ptr()),
+            factor=>std::panic!("Error! unsupported upscaler factor: {}", factor)
+        }}
     }
 }
 
@@ -209,7 +273,8 @@ impl<SC:SpiController> GfxDevice for Ili9341GfxDevice<SC>{
         }
 
         if self.frames_counter & self.frame_limiter == 0{
-            self.ili9341_controller.write_frame_buffer(&buffer);
+            let scaled_buffer = self.scale_frame_buffer(buffer);
+            self.ili9341_controller.write_frame_buffer(&scaled_buffer);
         }
 
         // measure fps
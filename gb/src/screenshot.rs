@@ -0,0 +1,36 @@
+use std::path::Path;
+use lib_gb::ppu::screenshot::Screenshot;
+use crate::vram_dump::write_png;
+
+pub const DEFAULT_SCREENSHOTS_DIRECTORY:&str = "screenshots";
+
+// Writes the screenshot to the directory as <rom name>_<timestamp>.png, the processed frame gets a _processed suffix
+pub fn save_screenshot(screenshot:&Screenshot, directory:&str, program_name:&str, save_processed_frame:bool){
+    if let Result::Err(error) = std::fs::create_dir_all(directory){
+        log::error!("error creating the screenshots directory {}: {}", directory, error);
+        return;
+    }
+    let file_prefix = get_timestamped_name(program_name);
+
+    let mut images = vec![(file_prefix.clone(), &screenshot.frame)];
+    if save_processed_frame{
+        match &screenshot.processed_frame{
+            Some(image)=>images.push((format!("{}_processed", file_prefix), image)),
+            Option::None=>log::info!("the frame has no post processing, saving only the raw frame")
+        }
+    }
+    for (name, image) in images{
+        let path = Path::new(directory).join(format!("{}.png", name));
+        let path = path.to_string_lossy();
+        match write_png(&path, image){
+            Result::Ok(())=>log::info!("wrote screenshot to: {}", path),
+            Result::Err(error)=>log::error!("error writing the screenshot to {}: {}", path, error)
+        }
+    }
+}
+
+// <rom name>_<timestamp>, the name of the files captured from the game
+pub fn get_timestamped_name(program_name:&str)->String{
+    let rom_name = Path::new(program_name).file_name().and_then(|name|name.to_str()).unwrap_or(program_name);
+    return format!("{}_{}", rom_name, chrono::Local::now().format("%Y-%m-%d_%H-%M-%S%.3f"));
+}
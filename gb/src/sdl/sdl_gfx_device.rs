@@ -1,47 +1,97 @@
 use std::ffi::{CString, c_void};
 use sdl2::sys::*;
 use lib_gb::ppu::{gb_ppu::{SCREEN_HEIGHT, SCREEN_WIDTH}, gfx_device::{GfxDevice, Pixel}};
+use image_inter::PixelArtScaler;
+#[cfg(feature = "static-scale")]
+use image_inter::{Resizer, ResizeAlgorithm, OutputFormat, ByteOrder};
 use crate::sdl::utils::get_sdl_error_message;
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum ScalingMode{
+    IntegerOnly,
+    AspectFit,
+    Stretch
+}
+
+impl ScalingMode{
+    pub const ALL:[ScalingMode;3] = [Self::IntegerOnly, Self::AspectFit, Self::Stretch];
+
+    pub fn get_name(self)->&'static str{
+        match self{
+            Self::IntegerOnly=>"Integer",
+            Self::AspectFit=>"Aspect-Fit",
+            Self::Stretch=>"Stretch"
+        }
+    }
+
+    // The size of the content scaled to the output, integer scaling never goes below 1x and crops instead
+    fn scale(self, content:(i32, i32), output:(i32, i32))->(i32, i32){
+        let (content_width, content_height) = content;
+        let (output_width, output_height) = output;
+        return match self{
+            Self::IntegerOnly=>{
+                let factor = (output_width / content_width).min(output_height / content_height).max(1);
+                (content_width * factor, content_height * factor)
+            }
+            Self::AspectFit=>{
+                if output_width * content_height < output_height * content_width{
+                    (output_width, output_width * content_height / content_width)
+                }
+                else{
+                    (output_height * content_width / content_height, output_height)
+                }
+            }
+            Self::Stretch=>output
+        };
+    }
+}
+
 pub struct SdlGfxDevice{
     _window_name: CString,
+    window: *mut SDL_Window,
     renderer: *mut SDL_Renderer,
     texture: *mut SDL_Texture,
+    scaling_mode: ScalingMode,
+    // The border texture with its size, the frame is drawn at its center
+    border: Option<(*mut SDL_Texture, i32, i32)>,
+    // Created on the first hd frame with the pack scale
+    hd_texture: Option<(*mut SDL_Texture, usize)>,
+    hd_frame_ready:bool,
+    // The frames are upscaled to a texture of the scaler factor size and stretched by sdl
+    upscaler: Option<(PixelArtScaler, *mut SDL_Texture)>,
+    upscaled_buffer: Vec<Pixel>,
     discard:u8,
     turbo_mul:u8,
+    // The frames are scaled on the cpu to the texture, recreated whenever the drawn size changes
     #[cfg(feature = "static-scale")]
-    screen_scale:usize,
+    static_scaler:Option<(Resizer, Vec<u8>, (usize, usize))>,
 }
 
 impl SdlGfxDevice{
-    pub fn new(window_name:&str, screen_scale: usize, turbo_mul:u8, disable_vsync:bool, full_screen:bool)->Self{
+    pub fn new(window_name:&str, screen_scale: usize, turbo_mul:u8, disable_vsync:bool, full_screen:bool, scaling_mode:ScalingMode, upscaler:Option<PixelArtScaler>)->Self{
         #[cfg(feature = "u16pixel")]
         std::compile_error("Sdl gfx device must have Pixel type = u32");
 
         let cs_wnd_name = CString::new(window_name).unwrap();
 
-        let (_window, renderer, texture): (*mut SDL_Window, *mut SDL_Renderer, *mut SDL_Texture) = unsafe{
+        let (window, renderer, texture): (*mut SDL_Window, *mut SDL_Renderer, *mut SDL_Texture) = unsafe{
             if SDL_Init(SDL_INIT_VIDEO) != 0{
                 std::panic!("Init error: {}", get_sdl_error_message());
             }
 
-            let window_flags = if full_screen{
-                #[cfg(feature = "static-scale")]
-                log::warn!("Please notice that this binary have been compiled with the static-scale feature and you are running with the full screen option.\nThe rendering window might be in wrong scale.");
-                
+            let mut window_flags = SDL_WindowFlags::SDL_WINDOW_RESIZABLE as u32;
+            if full_screen{
                 // Hide cursor
                 SDL_ShowCursor(0);
-                SDL_WindowFlags::SDL_WINDOW_FULLSCREEN_DESKTOP as u32
+                window_flags |= SDL_WindowFlags::SDL_WINDOW_FULLSCREEN_DESKTOP as u32;
             }
-            else{
-                0
-            };
 
             let wind:*mut SDL_Window = SDL_CreateWindow(
                 cs_wnd_name.as_ptr(),
                 SDL_WINDOWPOS_UNDEFINED_MASK as i32, SDL_WINDOWPOS_UNDEFINED_MASK as i32,
                 SCREEN_WIDTH as i32 * screen_scale as i32, SCREEN_HEIGHT as i32 * screen_scale as i32,
                  window_flags);
+            SDL_SetWindowMinimumSize(wind, SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32);
 
             let mut render_flags = SDL_RendererFlags::SDL_RENDERER_ACCELERATED as u32;
             if !disable_vsync{
@@ -50,56 +100,122 @@ impl SdlGfxDevice{
 
             let rend: *mut SDL_Renderer = SDL_CreateRenderer(wind, -1, render_flags);
             
-            let texture_width:i32;
-            let texture_height:i32;
-
-            cfg_if::cfg_if!{
-                if #[cfg(feature = "static-scale")]{
-                    texture_height = SCREEN_HEIGHT as i32 * screen_scale as i32;
-                    texture_width = SCREEN_WIDTH as i32 * screen_scale as i32;
-                }
-                else{
-                    if SDL_RenderSetLogicalSize(rend, (SCREEN_WIDTH as u32) as i32, (SCREEN_HEIGHT as u32) as i32) != 0{
-                        std::panic!("Error while setting logical rendering\nError:{}", get_sdl_error_message());
-                    }
-                    texture_height = SCREEN_HEIGHT as i32;
-                    texture_width = SCREEN_WIDTH as i32;
-                }
-            }
-            
             let tex: *mut SDL_Texture = SDL_CreateTexture(rend,
                 SDL_PixelFormatEnum::SDL_PIXELFORMAT_RGB888 as u32, SDL_TextureAccess::SDL_TEXTUREACCESS_STREAMING as i32,
-                    texture_width, texture_height);
+                    SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32);
             
             (wind, rend, tex)
         };
+
+        let upscaler = upscaler.map(|scaler|{
+            let factor = scaler.get_factor() as i32;
+            let texture = unsafe{SDL_CreateTexture(renderer,
+                SDL_PixelFormatEnum::SDL_PIXELFORMAT_RGB888 as u32, SDL_TextureAccess::SDL_TEXTUREACCESS_STREAMING as i32,
+                SCREEN_WIDTH as i32 * factor, SCREEN_HEIGHT as i32 * factor)};
+            if texture.is_null(){
+                std::panic!("Error while creating the upscaled texture\nError:{}", get_sdl_error_message());
+            }
+            (scaler, texture)
+        });
         
         Self{
             _window_name: cs_wnd_name,
+            window,
             renderer,
             texture,
+            scaling_mode,
+            border: Option::None,
+            hd_texture: Option::None,
+            hd_frame_ready: false,
+            upscaled_buffer: upscaler.map_or(Vec::new(), |(scaler, _)|vec![0;SCREEN_WIDTH * SCREEN_HEIGHT * scaler.get_factor() * scaler.get_factor()]),
+            upscaler,
             discard:0,
             turbo_mul, 
             #[cfg(feature = "static-scale")]
-            screen_scale
+            static_scaler:Option::None
         }
     }
 
+    // The border is an RGBA image at least the size of the screen, scaled together with the frame
+    pub fn set_border(&mut self, width:usize, rgba:&[u8]){
+        let height = rgba.len() / 4 / width;
+        if width < SCREEN_WIDTH || height < SCREEN_HEIGHT{
+            std::panic!("Error! the border must be at least {}x{}, got {}x{}", SCREEN_WIDTH, SCREEN_HEIGHT, width, height);
+        }
+        let pixels:Vec<u32> = rgba.chunks_exact(4).map(|pixel|u32::from_be_bytes([pixel[3], pixel[0], pixel[1], pixel[2]])).collect();
+        unsafe{
+            let texture = SDL_CreateTexture(self.renderer,
+                SDL_PixelFormatEnum::SDL_PIXELFORMAT_ARGB8888 as u32, SDL_TextureAccess::SDL_TEXTUREACCESS_STATIC as i32,
+                width as i32, height as i32);
+            if texture.is_null(){
+                std::panic!("Error while creating the border texture\nError:{}", get_sdl_error_message());
+            }
+            SDL_SetTextureBlendMode(texture, SDL_BlendMode::SDL_BLENDMODE_BLEND);
+            SDL_UpdateTexture(texture, std::ptr::null(), pixels.as_ptr() as *const c_void, (width * 4) as i32);
+            if let Some((old_texture, _, _)) = self.border.replace((texture, width as i32, height as i32)){
+                SDL_DestroyTexture(old_texture);
+            }
+        }
+    }
+
+    pub fn toggle_full_screen(&mut self){
+        unsafe{
+            let full_screen = SDL_GetWindowFlags(self.window) & SDL_WindowFlags::SDL_WINDOW_FULLSCREEN as u32 == 0;
+            let flags = if full_screen {SDL_WindowFlags::SDL_WINDOW_FULLSCREEN_DESKTOP as u32} else {0};
+            if SDL_SetWindowFullscreen(self.window, flags) != 0{
+                log::error!("Error while toggling full screen\nError:{}", get_sdl_error_message());
+                return;
+            }
+            SDL_ShowCursor(!full_screen as i32);
+        }
+    }
+
+    // Returns the rect of the border (or the frame without a border) and the rect of the frame in the window
+    fn get_destination_rects(&self)->(SDL_Rect, SDL_Rect){
+        let (mut output_width, mut output_height) = (0, 0);
+        unsafe{SDL_GetRendererOutputSize(self.renderer, &mut output_width, &mut output_height)};
+        let (content_width, content_height) = self.border.map_or((SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32), |(_, width, height)|(width, height));
+        let (width, height) = self.scaling_mode.scale((content_width, content_height), (output_width, output_height));
+        let content_rect = SDL_Rect{x: (output_width - width) / 2, y: (output_height - height) / 2, w: width, h: height};
+
+        let frame_width = SCREEN_WIDTH as i32 * width / content_width;
+        let frame_height = SCREEN_HEIGHT as i32 * height / content_height;
+        let frame_rect = SDL_Rect{
+            x: content_rect.x + (width - frame_width) / 2,
+            y: content_rect.y + (height - frame_height) / 2,
+            w: frame_width, h: frame_height
+        };
+        return (content_rect, frame_rect);
+    }
+
+    unsafe fn update_texture<T>(texture:*mut SDL_Texture, buffer:&[T]){
+        let mut pixels: *mut c_void = std::ptr::null_mut();
+        let mut length: std::os::raw::c_int = 0;
+        SDL_LockTexture(texture, std::ptr::null(), &mut pixels, &mut length);
+        std::ptr::copy_nonoverlapping(buffer.as_ptr() as *const u8, pixels as *mut u8, std::mem::size_of_val(buffer));
+        SDL_UnlockTexture(texture);
+    }
+
     #[cfg(feature = "static-scale")]
-    fn extend_vec(vec:&[u32], scale:usize, w:usize, h:usize)->Vec<u32>{
-        let mut new_vec = vec![0;vec.len()*scale*scale];
-        for y in 0..h{
-            let sy = y*scale;
-            for x in 0..w{
-                let sx = x*scale;
-                for i in 0..scale{
-                    for j in 0..scale{
-                        new_vec[(sy+i)*(w*scale)+sx+j] = vec[y*w+x];
-                    }
+    fn scale_on_cpu(&mut self, buffer:&[Pixel], width:usize, height:usize){
+        if self.static_scaler.as_ref().map(|(_, _, size)|*size) != Some((width, height)){
+            let resizer = Resizer::new(SCREEN_WIDTH, SCREEN_HEIGHT, width, height,
+                ResizeAlgorithm::Nearest, OutputFormat::Xrgb8888(ByteOrder::NATIVE)).unwrap();
+            let scaled_buffer = vec![0;resizer.get_output_size()];
+            unsafe{
+                SDL_DestroyTexture(self.texture);
+                self.texture = SDL_CreateTexture(self.renderer,
+                    SDL_PixelFormatEnum::SDL_PIXELFORMAT_RGB888 as u32, SDL_TextureAccess::SDL_TEXTUREACCESS_STREAMING as i32,
+                    width as i32, height as i32);
+                if self.texture.is_null(){
+                    std::panic!("Error while creating the scaled texture\nError:{}", get_sdl_error_message());
                 }
-            } 
+            }
+            self.static_scaler = Some((resizer, scaled_buffer, (width, height)));
         }
-        return new_vec;
+        let (resizer, scaled_buffer, _) = self.static_scaler.as_mut().unwrap();
+        resizer.resize(buffer, scaled_buffer).unwrap();
+        unsafe{Self::update_texture(self.texture, scaled_buffer)};
     }
 }
 
@@ -110,19 +226,67 @@ impl GfxDevice for SdlGfxDevice{
             return;
         }
 
-        #[cfg(feature = "static-scale")]
-        let buffer = Self::extend_vec(buffer, self.screen_scale, SCREEN_WIDTH, SCREEN_HEIGHT);
+        let (content_rect, frame_rect) = self.get_destination_rects();
+        // The window is minimized
+        if frame_rect.w <= 0 || frame_rect.h <= 0{
+            return;
+        }
+
+        // The hd frame is drawn instead of the original one, stretched to the frame rect
+        let texture = if self.hd_frame_ready{
+            self.hd_frame_ready = false;
+            self.hd_texture.unwrap().0
+        }
+        else if let Some((scaler, texture)) = self.upscaler{
+            scaler.scale::<Pixel, SCREEN_WIDTH, SCREEN_HEIGHT>(buffer, &mut self.upscaled_buffer);
+            unsafe{Self::update_texture(texture, &self.upscaled_buffer)};
+            texture
+        }
+        else{
+            cfg_if::cfg_if!{
+                if #[cfg(feature = "static-scale")]{
+                    self.scale_on_cpu(buffer, frame_rect.w as usize, frame_rect.h as usize);
+                }
+                else{
+                    unsafe{Self::update_texture(self.texture, buffer)};
+                }
+            }
+            self.texture
+        };
 
         unsafe{
-            let mut pixels: *mut c_void = std::ptr::null_mut();
-            let mut length: std::os::raw::c_int = 0;
-            SDL_LockTexture(self.texture, std::ptr::null(), &mut pixels, &mut length);
-            std::ptr::copy_nonoverlapping(buffer.as_ptr(),pixels as *mut u32,  buffer.len());
-            SDL_UnlockTexture(self.texture);
-            
-            //There is no need to call SDL_RenderClear since im replacing the whole buffer 
-            SDL_RenderCopy(self.renderer, self.texture, std::ptr::null(), std::ptr::null());
+            // Clearing the bars around the frame
+            SDL_RenderClear(self.renderer);
+            if let Some((border, _, _)) = self.border{
+                SDL_RenderCopy(self.renderer, border, std::ptr::null(), &content_rect);
+            }
+            SDL_RenderCopy(self.renderer, texture, std::ptr::null(), &frame_rect);
             SDL_RenderPresent(self.renderer);
         }
     }
+
+    fn swap_hd_buffer(&mut self, buffer:&[Pixel], scale:usize){
+        // Skipping the upload of the frames swap_buffer will discard
+        if (self.discard + 1) % self.turbo_mul != 0{
+            return;
+        }
+        let texture = match self.hd_texture{
+            Some((texture, texture_scale)) if texture_scale == scale=>texture,
+            _=>unsafe{
+                if let Some((texture, _)) = self.hd_texture{
+                    SDL_DestroyTexture(texture);
+                }
+                let texture = SDL_CreateTexture(self.renderer,
+                    SDL_PixelFormatEnum::SDL_PIXELFORMAT_RGB888 as u32, SDL_TextureAccess::SDL_TEXTUREACCESS_STREAMING as i32,
+                    (SCREEN_WIDTH * scale) as i32, (SCREEN_HEIGHT * scale) as i32);
+                if texture.is_null(){
+                    std::panic!("Error while creating the hd texture\nError:{}", get_sdl_error_message());
+                }
+                self.hd_texture = Some((texture, scale));
+                texture
+            }
+        };
+        unsafe{Self::update_texture(texture, buffer)};
+        self.hd_frame_ready = true;
+    }
 }
\ No newline at end of file
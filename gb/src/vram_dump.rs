@@ -0,0 +1,49 @@
+use std::{fs, io::Write};
+use lib_gb::ppu::{gb_ppu::GbPpu, gfx_device::GfxDevice, debug_views::{RgbImage, TileMap}};
+
+// Writes the vram debug views next to the rom
+pub fn dump_vram_views<GFX:GfxDevice>(ppu:&GbPpu<GFX>, program_name:&str){
+    let images = [
+        ("tiles", ppu.render_tiles()),
+        ("tile_map_9800", ppu.render_tile_map(TileMap::Map9800)),
+        ("tile_map_9c00", ppu.render_tile_map(TileMap::Map9C00)),
+        ("sprites", ppu.render_sprites())
+    ];
+    for (name, image) in images.iter(){
+        let path = format!("{}_{}.png", program_name, name);
+        match write_png(&path, image){
+            Result::Ok(())=>log::info!("wrote vram view to: {}", path),
+            Result::Err(error)=>log::error!("error writing the vram view to {}: {}", path, error)
+        }
+    }
+
+    let path = format!("{}_oam.txt", program_name);
+    match fs::File::create(&path).and_then(|file|write_sprites_attributes(ppu, &mut std::io::BufWriter::new(file))){
+        Result::Ok(())=>log::info!("wrote the oam sprites to: {}", path),
+        Result::Err(error)=>log::error!("error writing the oam sprites to {}: {}", path, error)
+    }
+}
+
+pub fn write_png(path:&str, image:&RgbImage)->Result<(), png::EncodingError>{
+    write_png_data(path, image.width, image.height, png::ColorType::Rgb, &image.buffer)
+}
+
+pub fn write_png_data(path:&str, width:usize, height:usize, color_type:png::ColorType, data:&[u8])->Result<(), png::EncodingError>{
+    let file = fs::File::create(path)?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(color_type);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(data)?;
+    return Ok(());
+}
+
+fn write_sprites_attributes<GFX:GfxDevice, W:Write>(ppu:&GbPpu<GFX>, writer:&mut W)->std::io::Result<()>{
+    writeln!(writer, "index  y    x    tile  palette  cgb_palette  bank  flip_x  flip_y  bg_priority")?;
+    for sprite in ppu.get_sprites_attributes(){
+        writeln!(writer, "{:<6} {:<4} {:<4} {:#04X}  {:<8} {:<12} {:<5} {:<7} {:<7} {}",
+            sprite.oam_index, sprite.y, sprite.x, sprite.tile_number, sprite.palette_number as u8, sprite.cgb_palette_number,
+            sprite.vram_bank, sprite.flip_x, sprite.flip_y, sprite.is_bg_priority)?;
+    }
+    return writer.flush();
+}
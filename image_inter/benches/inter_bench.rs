@@ -1,5 +1,5 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use image_inter::{scale_bilinear, scale_biliniear_c, scale_nearest};
+use image_inter::{scale_bilinear, scale_biliniear_c, scale_nearest, Resizer, ResizeAlgorithm, OutputFormat, ByteOrder, SimdLevel};
 
 
 pub fn interpolation_rust_bench(c: &mut Criterion){
@@ -51,5 +51,35 @@ pub fn interpolation_fir_bench(c: &mut Criterion){
     }));
 }
 
-criterion_group!(benches, interpolation_fir_bench, interpolation_rust_bench, interpolation_c_bench, neighbor_rust_inter);
+pub fn resizer_bench(c: &mut Criterion){
+    let input_buffer = [0_u16; 160*144];
+    let mut output_buffer = [0_u8; 240*266*2];
+    let mut resizer = Resizer::new(160, 144, 266, 240, ResizeAlgorithm::Bilinear, OutputFormat::Rgb565(ByteOrder::BigEndian)).unwrap();
+    for level in SimdLevel::ALL.iter().filter(|level|level.is_supported()){
+        resizer.set_simd_level(*level).unwrap();
+        c.bench_function(&format!("bench resizer {:?}", level), |b|b.iter(||{
+            resizer.resize(&input_buffer, &mut output_buffer).unwrap();
+        }));
+    }
+}
+
+// The ili9341 spi path resizes the pixel art upscaled frames, compares the c scaler with the resizer on those sizes
+fn ili9341_factor_bench<const INPUT_WIDTH:usize, const INPUT_HEIGHT:usize>(c: &mut Criterion){
+    let input_buffer = vec![0_u16; INPUT_WIDTH * INPUT_HEIGHT];
+    let mut output_buffer = [0_u8; 240*266*2];
+    c.bench_function(&format!("bench ili9341 c inter {}x{}", INPUT_WIDTH, INPUT_HEIGHT), |b|b.iter(||{
+        unsafe{scale_biliniear_c::<INPUT_WIDTH, INPUT_HEIGHT, 266, 240>(input_buffer.as_ptr(), output_buffer.as_mut_ptr())};
+    }));
+    let mut resizer = Resizer::new(INPUT_WIDTH, INPUT_HEIGHT, 266, 240, ResizeAlgorithm::Bilinear, OutputFormat::Rgb565(ByteOrder::BigEndian)).unwrap();
+    c.bench_function(&format!("bench ili9341 resizer {:?} {}x{}", resizer.get_simd_level(), INPUT_WIDTH, INPUT_HEIGHT), |b|b.iter(||{
+        resizer.resize(&input_buffer, &mut output_buffer).unwrap();
+    }));
+}
+
+pub fn ili9341_bench(c: &mut Criterion){
+    ili9341_factor_bench::<{160 * 2}, {144 * 2}>(c);
+    ili9341_factor_bench::<{160 * 3}, {144 * 3}>(c);
+}
+
+criterion_group!(benches, interpolation_fir_bench, interpolation_rust_bench, interpolation_c_bench, neighbor_rust_inter, resizer_bench, ili9341_bench);
 criterion_main!(benches);
\ No newline at end of file
@@ -0,0 +1,32 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use image_inter::{PixelArtScaler, RgbPixel};
+
+// A checkerboard of 8x8 blocks so the edge detection paths are exercised
+fn create_input<P:RgbPixel>()->Vec<P>{
+    (0..160*144).map(|i|{
+        let value = if ((i % 160) / 8 + (i / 160) / 8) % 2 == 0 {0xFF} else {0x20};
+        P::from_rgb([value, value, value])
+    }).collect()
+}
+
+fn bench_scaler<P:RgbPixel>(c: &mut Criterion, pixel_format:&str){
+    let input_buffer = create_input::<P>();
+    for scaler in PixelArtScaler::ALL{
+        let factor = scaler.get_factor();
+        let mut output_buffer = vec![P::from_rgb([0, 0, 0]); 160*144*factor*factor];
+        c.bench_function(&format!("bench {} {}", scaler.get_name(), pixel_format), |b|b.iter(||{
+            scaler.scale::<P, 160, 144>(&input_buffer, &mut output_buffer);
+        }));
+    }
+}
+
+pub fn pixel_art_rgb565_bench(c: &mut Criterion){
+    bench_scaler::<u16>(c, "rgb565");
+}
+
+pub fn pixel_art_rgb888_bench(c: &mut Criterion){
+    bench_scaler::<u32>(c, "rgb888");
+}
+
+criterion_group!(benches, pixel_art_rgb565_bench, pixel_art_rgb888_bench);
+criterion_main!(benches);
\ No newline at end of file
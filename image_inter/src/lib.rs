@@ -1,4 +1,11 @@
+mod pixel_art;
+mod resize;
+mod simd;
+
 use libc::c_int;
+pub use pixel_art::{PixelArtScaler, RgbPixel};
+pub use resize::{Resizer, ResizeAlgorithm, ResizeError, OutputFormat, ByteOrder};
+pub use simd::SimdLevel;
 
 extern "C" {
     fn scale_buffer(
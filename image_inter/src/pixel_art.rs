@@ -0,0 +1,443 @@
+// Upscalers for pixel art, they scale by an integer factor and keep the edges sharp instead of blurring them like the bilinear scaling
+
+// A pixel format the upscalers can read and blend
+pub trait RgbPixel: Copy + PartialEq{
+    fn to_rgb(self)->[u8;3];
+    fn from_rgb(rgb:[u8;3])->Self;
+}
+
+// RGB565
+impl RgbPixel for u16{
+    fn to_rgb(self)->[u8;3]{
+        let r = ((self >> 11) & 0x1F) as u8;
+        let g = ((self >> 5) & 0x3F) as u8;
+        let b = (self & 0x1F) as u8;
+        [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2)]
+    }
+
+    fn from_rgb(rgb:[u8;3])->Self{
+        (((rgb[0] >> 3) as u16) << 11) | (((rgb[1] >> 2) as u16) << 5) | ((rgb[2] >> 3) as u16)
+    }
+}
+
+// RGB888 (0x00RRGGBB)
+impl RgbPixel for u32{
+    fn to_rgb(self)->[u8;3]{
+        [(self >> 16) as u8, (self >> 8) as u8, self as u8]
+    }
+
+    fn from_rgb(rgb:[u8;3])->Self{
+        ((rgb[0] as u32) << 16) | ((rgb[1] as u32) << 8) | rgb[2] as u32
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PixelArtScaler{
+    // Also known as EPX and AdvMAME2x
+    Scale2x,
+    Scale3x,
+    // The hqx scalers by Maxim Stepin
+    Hq2x,
+    Hq3x,
+    // The first level of the xBR algorithm by Hyllian, blends the corners of the pixels along the detected edges
+    Xbr2x
+}
+
+impl PixelArtScaler{
+    pub const ALL:[PixelArtScaler;5] = [Self::Scale2x, Self::Scale3x, Self::Hq2x, Self::Hq3x, Self::Xbr2x];
+
+    pub fn get_name(self)->&'static str{
+        match self{
+            Self::Scale2x=>"Scale2x",
+            Self::Scale3x=>"Scale3x",
+            Self::Hq2x=>"HQ2x",
+            Self::Hq3x=>"HQ3x",
+            Self::Xbr2x=>"xBR2x"
+        }
+    }
+
+    pub fn get_factor(self)->usize{
+        match self{
+            Self::Scale2x | Self::Hq2x | Self::Xbr2x=>2,
+            Self::Scale3x | Self::Hq3x=>3
+        }
+    }
+
+    // The output is INPUT_WIDTH * factor by INPUT_HEIGHT * factor pixels
+    pub fn scale<P:RgbPixel, const INPUT_WIDTH:usize, const INPUT_HEIGHT:usize>(self, input:&[P], output:&mut [P]){
+        let factor = self.get_factor();
+        assert_eq!(input.len(), INPUT_WIDTH * INPUT_HEIGHT);
+        assert_eq!(output.len(), INPUT_WIDTH * INPUT_HEIGHT * factor * factor);
+
+        let image = Image{buffer:input, width:INPUT_WIDTH, height:INPUT_HEIGHT};
+        let output_width = INPUT_WIDTH * factor;
+        for y in 0..INPUT_HEIGHT{
+            for x in 0..INPUT_WIDTH{
+                let neighborhood = Neighborhood{image:&image, x, y};
+                let mut block = [[neighborhood.center();3];3];
+                match self{
+                    Self::Scale2x=>scale2x(&neighborhood, &mut block),
+                    Self::Scale3x=>scale3x(&neighborhood, &mut block),
+                    Self::Hq2x=>hq2x(&neighborhood, &mut block),
+                    Self::Hq3x=>hq3x(&neighborhood, &mut block),
+                    Self::Xbr2x=>xbr2x(&neighborhood, &mut block)
+                }
+                for (sub_y, row) in block.iter().take(factor).enumerate(){
+                    let output_index = ((y * factor) + sub_y) * output_width + (x * factor);
+                    output[output_index..output_index + factor].copy_from_slice(&row[..factor]);
+                }
+            }
+        }
+    }
+}
+
+struct Image<'a, P>{
+    buffer:&'a [P],
+    width:usize,
+    height:usize
+}
+
+// The pixels around a pixel, the edges of the image are extended
+struct Neighborhood<'a, P>{
+    image:&'a Image<'a, P>,
+    x:usize,
+    y:usize
+}
+
+impl<'a, P:RgbPixel> Neighborhood<'a, P>{
+    fn get(&self, dx:isize, dy:isize)->P{
+        let x = (self.x as isize + dx).clamp(0, self.image.width as isize - 1) as usize;
+        let y = (self.y as isize + dy).clamp(0, self.image.height as isize - 1) as usize;
+        self.image.buffer[(y * self.image.width) + x]
+    }
+
+    fn center(&self)->P{
+        self.get(0, 0)
+    }
+}
+
+// The corner the neighbors are read relative to, every corner rule is written for the bottom right corner
+// and mirrored for the others
+#[derive(Clone, Copy)]
+struct Corner{
+    mirror_x:isize,
+    mirror_y:isize
+}
+
+const CORNERS:[Corner;4] = [
+    Corner{mirror_x:-1, mirror_y:-1}, Corner{mirror_x:1, mirror_y:-1},
+    Corner{mirror_x:-1, mirror_y:1}, Corner{mirror_x:1, mirror_y:1}
+];
+
+impl Corner{
+    fn get<P:RgbPixel>(&self, neighborhood:&Neighborhood<P>, dx:isize, dy:isize)->P{
+        neighborhood.get(dx * self.mirror_x, dy * self.mirror_y)
+    }
+
+    // The index of the corner sub pixel in a block of the factor size
+    fn get_block_index(&self, factor:usize)->(usize, usize){
+        let to_index = |mirror:isize|if mirror < 0 {0} else {factor - 1};
+        (to_index(self.mirror_x), to_index(self.mirror_y))
+    }
+}
+
+// The pixels around the center named like the scale2x article
+//  A B C
+//  D E F
+//  G H I
+struct Pixels<P>{
+    a:P, b:P, c:P,
+    d:P, e:P, f:P,
+    g:P, h:P, i:P
+}
+
+impl<P:RgbPixel> Pixels<P>{
+    fn new(neighborhood:&Neighborhood<P>)->Self{
+        let get = |dx, dy|neighborhood.get(dx, dy);
+        Self{
+            a:get(-1, -1), b:get(0, -1), c:get(1, -1),
+            d:get(-1, 0), e:get(0, 0), f:get(1, 0),
+            g:get(-1, 1), h:get(0, 1), i:get(1, 1)
+        }
+    }
+}
+
+// Based on https://www.scale2x.it/algorithm
+fn scale2x<P:RgbPixel>(neighborhood:&Neighborhood<P>, block:&mut [[P;3];3]){
+    let Pixels{b, d, e, f, h, ..} = Pixels::new(neighborhood);
+    if b == h || d == f{
+        return;
+    }
+    block[0][0] = if d == b {d} else {e};
+    block[0][1] = if b == f {f} else {e};
+    block[1][0] = if d == h {d} else {e};
+    block[1][1] = if h == f {f} else {e};
+}
+
+fn scale3x<P:RgbPixel>(neighborhood:&Neighborhood<P>, block:&mut [[P;3];3]){
+    let Pixels{a, b, c, d, e, f, g, h, i} = Pixels::new(neighborhood);
+    if b == h || d == f{
+        return;
+    }
+    block[0][0] = if d == b {d} else {e};
+    block[0][1] = if (d == b && e != c) || (b == f && e != a) {b} else {e};
+    block[0][2] = if b == f {f} else {e};
+    block[1][0] = if (d == b && e != g) || (d == h && e != a) {d} else {e};
+    block[1][2] = if (b == f && e != i) || (h == f && e != c) {f} else {e};
+    block[2][0] = if d == h {d} else {e};
+    block[2][1] = if (d == h && e != i) || (h == f && e != g) {h} else {e};
+    block[2][2] = if h == f {f} else {e};
+}
+
+// The hqx thresholds for the YUV channels
+const YUV_Y_THRESHOLD:i32 = 48;
+const YUV_U_THRESHOLD:i32 = 7;
+const YUV_V_THRESHOLD:i32 = 6;
+
+fn to_yuv<P:RgbPixel>(pixel:P)->[i32;3]{
+    let [r, g, b] = pixel.to_rgb().map(|channel|channel as i32);
+    let y = (r + g + b) >> 2;
+    let u = 128 + ((r - b) >> 2);
+    let v = 128 + ((2 * g - r - b) >> 3);
+    [y, u, v]
+}
+
+fn is_different<P:RgbPixel>(first:P, second:P)->bool{
+    if first == second{
+        return false;
+    }
+    let (first, second) = (to_yuv(first), to_yuv(second));
+    return (first[0] - second[0]).abs() > YUV_Y_THRESHOLD ||
+        (first[1] - second[1]).abs() > YUV_U_THRESHOLD ||
+        (first[2] - second[2]).abs() > YUV_V_THRESHOLD;
+}
+
+// Weighted average of the pixels
+fn interpolate<P:RgbPixel>(pixels:&[(P, u32)])->P{
+    let total_weight:u32 = pixels.iter().map(|(_, weight)|weight).sum();
+    let mut rgb = [0_u32;3];
+    for (pixel, weight) in pixels{
+        for (channel, value) in rgb.iter_mut().zip(pixel.to_rgb()){
+            *channel += value as u32 * weight;
+        }
+    }
+    return P::from_rgb(rgb.map(|channel|(channel / total_weight) as u8));
+}
+
+// The 3x3 pixels of the hqx rules, the neighbors are ordered so the rules are written for the top left corner
+//  0 1 2
+//  3 4 5
+//  6 7 8
+struct HqxWindow<P>{
+    w:[P;9],
+    // Bit n is set when neighbor n is different from the center, the center has no bit so the neighbors after it use bit n - 1
+    pattern:u8
+}
+
+impl<P:RgbPixel> HqxWindow<P>{
+    fn new(pixels:&[P;9], order:&[usize;9])->Self{
+        let w = order.map(|index|pixels[index]);
+        let mut pattern = 0;
+        for (bit, neighbor) in w.iter().enumerate().filter(|(index, _)|*index != 4).map(|(_, neighbor)|neighbor).enumerate(){
+            if is_different(w[4], *neighbor){
+                pattern |= 1 << bit;
+            }
+        }
+        return Self{w, pattern};
+    }
+
+    // The pattern equals the expected bits of any of the (mask, expected bits) pairs
+    fn matches(&self, patterns:&[(u8, u8)])->bool{
+        patterns.iter().any(|(mask, expected)|self.pattern & mask == *expected)
+    }
+}
+
+fn get_hqx_pixels<P:RgbPixel>(neighborhood:&Neighborhood<P>)->[P;9]{
+    let get = |dx, dy|neighborhood.get(dx, dy);
+    [
+        get(-1, -1), get(0, -1), get(1, -1),
+        get(-1, 0), get(0, 0), get(1, 0),
+        get(-1, 1), get(0, 1), get(1, 1)
+    ]
+}
+
+// The x and y of a sub pixel in the block
+type BlockPosition = (usize, usize);
+
+// The patterns are the compact form of the hqx tables from the ffmpeg hqx filter
+const HQX_EDGE_PATTERNS:[(u8, u8);3] = [(0x0B, 0x0B), (0xFE, 0x4A), (0xFE, 0x1A)];
+const HQX_CORNER_PATTERNS:[(u8, u8);13] = [
+    (0x6F, 0x2A), (0x5B, 0x0A), (0xBF, 0x3A), (0xDF, 0x5A), (0x9F, 0x8A), (0xCF, 0x8A), (0xEF, 0x4E),
+    (0x3F, 0x0E), (0xFB, 0x5A), (0xBB, 0x8A), (0x7F, 0x5A), (0xAF, 0x8A), (0xEB, 0x8A)
+];
+const HQX_TOP_PATTERNS:[(u8, u8);2] = [(0xBF, 0x37), (0xDB, 0x13)];
+const HQX_LEFT_PATTERNS:[(u8, u8);2] = [(0xDB, 0x49), (0xEF, 0x6D)];
+
+// The orders of the neighbors for every hq2x sub pixel, the top left rules are mirrored for the other corners
+const HQ2X_ORDERS:[([usize;9], BlockPosition);4] = [
+    ([0, 1, 2, 3, 4, 5, 6, 7, 8], (0, 0)),
+    ([2, 1, 0, 5, 4, 3, 8, 7, 6], (1, 0)),
+    ([6, 7, 8, 3, 4, 5, 0, 1, 2], (0, 1)),
+    ([8, 7, 6, 5, 4, 3, 2, 1, 0], (1, 1))
+];
+
+fn hq2x<P:RgbPixel>(neighborhood:&Neighborhood<P>, block:&mut [[P;3];3]){
+    let pixels = get_hqx_pixels(neighborhood);
+    for (order, (x, y)) in HQ2X_ORDERS.iter(){
+        block[*y][*x] = hq2x_corner(&HqxWindow::new(&pixels, order));
+    }
+}
+
+fn hq2x_corner<P:RgbPixel>(window:&HqxWindow<P>)->P{
+    let w = &window.w;
+    if window.matches(&HQX_TOP_PATTERNS) && is_different(w[1], w[5]){
+        return interpolate(&[(w[4], 3), (w[3], 1)]);
+    }
+    if window.matches(&HQX_LEFT_PATTERNS) && is_different(w[7], w[3]){
+        return interpolate(&[(w[4], 3), (w[1], 1)]);
+    }
+    if window.matches(&HQX_EDGE_PATTERNS) && is_different(w[3], w[1]){
+        return w[4];
+    }
+    if window.matches(&HQX_CORNER_PATTERNS) && is_different(w[3], w[1]){
+        return interpolate(&[(w[4], 3), (w[0], 1)]);
+    }
+    if window.matches(&[(0x0B, 0x08)]){
+        return interpolate(&[(w[4], 2), (w[0], 1), (w[1], 1)]);
+    }
+    if window.matches(&[(0x0B, 0x02)]){
+        return interpolate(&[(w[4], 2), (w[0], 1), (w[3], 1)]);
+    }
+    if window.matches(&[(0x2F, 0x2F)]){
+        return interpolate(&[(w[4], 14), (w[3], 1), (w[1], 1)]);
+    }
+    if window.matches(&HQX_TOP_PATTERNS){
+        return interpolate(&[(w[4], 5), (w[1], 2), (w[3], 1)]);
+    }
+    if window.matches(&HQX_LEFT_PATTERNS){
+        return interpolate(&[(w[4], 5), (w[3], 2), (w[1], 1)]);
+    }
+    if window.matches(&[(0x1B, 0x03), (0x4F, 0x43), (0x8B, 0x83), (0x6B, 0x43)]){
+        return interpolate(&[(w[4], 3), (w[3], 1)]);
+    }
+    if window.matches(&[(0x4B, 0x09), (0x8B, 0x89), (0x1F, 0x19), (0x3B, 0x19)]){
+        return interpolate(&[(w[4], 3), (w[1], 1)]);
+    }
+    if window.matches(&[(0x7E, 0x2A), (0xEF, 0xAB), (0xBF, 0x8F), (0x7E, 0x0E)]){
+        return interpolate(&[(w[4], 2), (w[3], 3), (w[1], 3)]);
+    }
+    if window.matches(&[(0xFB, 0x6A), (0x6F, 0x6E), (0x3F, 0x3E), (0xFB, 0xFA), (0xDF, 0xDE), (0xDF, 0x1E)]){
+        return interpolate(&[(w[4], 3), (w[0], 1)]);
+    }
+    if window.matches(&[(0x0A, 0x00), (0x4F, 0x4B), (0x9F, 0x1B), (0x2F, 0x0B), (0xBE, 0x0A), (0xEE, 0x0A), (0x7E, 0x0A), (0xEB, 0x4B), (0x3B, 0x1B)]){
+        return interpolate(&[(w[4], 2), (w[3], 1), (w[1], 1)]);
+    }
+    return interpolate(&[(w[4], 6), (w[3], 1), (w[1], 1)]);
+}
+
+// The orders of the neighbors for every hq3x corner and the side after it clockwise, the top left rules are rotated
+// for the other corners. The center sub pixel is the center pixel
+const HQ3X_ORDERS:[([usize;9], BlockPosition, BlockPosition);4] = [
+    ([0, 1, 2, 3, 4, 5, 6, 7, 8], (0, 0), (1, 0)),
+    ([2, 5, 8, 1, 4, 7, 0, 3, 6], (2, 0), (2, 1)),
+    ([8, 7, 6, 5, 4, 3, 2, 1, 0], (2, 2), (1, 2)),
+    ([6, 3, 0, 7, 4, 1, 8, 5, 2], (0, 2), (0, 1))
+];
+
+fn hq3x<P:RgbPixel>(neighborhood:&Neighborhood<P>, block:&mut [[P;3];3]){
+    let pixels = get_hqx_pixels(neighborhood);
+    for (order, (corner_x, corner_y), (side_x, side_y)) in HQ3X_ORDERS.iter(){
+        let window = HqxWindow::new(&pixels, order);
+        block[*corner_y][*corner_x] = hq3x_corner(&window);
+        block[*side_y][*side_x] = hq3x_side(&window);
+    }
+}
+
+fn hq3x_corner<P:RgbPixel>(window:&HqxWindow<P>)->P{
+    let w = &window.w;
+    if window.matches(&HQX_TOP_PATTERNS) && is_different(w[1], w[5]){
+        return interpolate(&[(w[4], 3), (w[3], 1)]);
+    }
+    if window.matches(&HQX_LEFT_PATTERNS) && is_different(w[7], w[3]){
+        return interpolate(&[(w[4], 3), (w[1], 1)]);
+    }
+    if window.matches(&HQX_EDGE_PATTERNS) && is_different(w[3], w[1]){
+        return w[4];
+    }
+    if window.matches(&HQX_CORNER_PATTERNS) && is_different(w[3], w[1]){
+        return interpolate(&[(w[4], 3), (w[0], 1)]);
+    }
+    if window.matches(&[(0x4B, 0x09), (0x8B, 0x89), (0x1F, 0x19), (0x3B, 0x19)]){
+        return interpolate(&[(w[4], 3), (w[1], 1)]);
+    }
+    if window.matches(&[(0x1B, 0x03), (0x4F, 0x43), (0x8B, 0x83), (0x6B, 0x43)]){
+        return interpolate(&[(w[4], 3), (w[3], 1)]);
+    }
+    if window.matches(&[(0x7E, 0x2A), (0xEF, 0xAB), (0xBF, 0x8F), (0x7E, 0x0E)]){
+        return interpolate(&[(w[4], 2), (w[3], 7), (w[1], 7)]);
+    }
+    if window.matches(&[(0x4F, 0x4B), (0x9F, 0x1B), (0x2F, 0x0B), (0xBE, 0x0A), (0xEE, 0x0A), (0x7E, 0x0A), (0xEB, 0x4B), (0x3B, 0x1B)]){
+        return interpolate(&[(w[4], 2), (w[3], 1), (w[1], 1)]);
+    }
+    if window.matches(&[
+        (0x0B, 0x08), (0xF9, 0x68), (0xF3, 0x62), (0x6D, 0x6C), (0x67, 0x66), (0x3D, 0x3C), (0x37, 0x36),
+        (0xF9, 0xF8), (0xDD, 0xDC), (0xF3, 0xF2), (0xD7, 0xD6), (0xDD, 0x1C), (0xD7, 0x16), (0x0B, 0x02)
+    ]){
+        return interpolate(&[(w[4], 3), (w[0], 1)]);
+    }
+    return interpolate(&[(w[4], 2), (w[3], 1), (w[1], 1)]);
+}
+
+// The top side sub pixel
+fn hq3x_side<P:RgbPixel>(window:&HqxWindow<P>)->P{
+    let w = &window.w;
+    if window.matches(&[(0xFE, 0xDE), (0x9E, 0x16), (0xDA, 0x12), (0x17, 0x16), (0x5B, 0x12), (0xBB, 0x12)]) && is_different(w[1], w[5]){
+        return w[4];
+    }
+    if window.matches(&[(0x0F, 0x0B), (0x5E, 0x0A), (0xFB, 0x7B), (0x3B, 0x0B), (0xBE, 0x0A), (0x7A, 0x0A)]) && is_different(w[3], w[1]){
+        return w[4];
+    }
+    if window.matches(&[(0xBF, 0x8F), (0x7E, 0x0E), (0xBF, 0x37), (0xDB, 0x13)]){
+        return interpolate(&[(w[1], 3), (w[4], 1)]);
+    }
+    if window.matches(&[(0x02, 0x00), (0x7C, 0x28), (0xED, 0xA9), (0xF5, 0xB4), (0xD9, 0x90)]){
+        return interpolate(&[(w[4], 3), (w[1], 1)]);
+    }
+    if window.matches(&[
+        (0x4F, 0x4B), (0xFB, 0x7B), (0xFE, 0x7E), (0x9F, 0x1B), (0x2F, 0x0B), (0xBE, 0x0A), (0x7E, 0x0A), (0xFB, 0x4B),
+        (0xFB, 0xDB), (0xFE, 0xDE), (0xFE, 0x56), (0x57, 0x56), (0x97, 0x16), (0x3F, 0x1E), (0xDB, 0x12), (0xBB, 0x12)
+    ]){
+        return interpolate(&[(w[4], 7), (w[1], 1)]);
+    }
+    return w[4];
+}
+
+fn xbr_distance<P:RgbPixel>(first:P, second:P)->u32{
+    let (first, second) = (to_yuv(first), to_yuv(second));
+    return (48 * (first[0] - second[0]).unsigned_abs()) + (7 * (first[1] - second[1]).unsigned_abs()) + (6 * (first[2] - second[2]).unsigned_abs());
+}
+
+// Based on the xBR level 1 rules, the names follow the 5x5 neighborhood from the xBR article
+//     A1 B1 C1
+//  A0 A  B  C  C4
+//  D0 D  E  F  F4
+//  G0 G  H  I  I4
+//     G5 H5 I5
+fn xbr2x<P:RgbPixel>(neighborhood:&Neighborhood<P>, block:&mut [[P;3];3]){
+    let e = neighborhood.center();
+    for corner in CORNERS{
+        let get = |dx, dy|corner.get(neighborhood, dx, dy);
+        let (b, c, d, f, g, h, i) = (get(0, -1), get(1, -1), get(-1, 0), get(1, 0), get(-1, 1), get(0, 1), get(1, 1));
+        let (f4, h5, i4, i5) = (get(2, 0), get(0, 2), get(2, 1), get(1, 2));
+        if e == f || e == h{
+            continue;
+        }
+        let edge_weight = xbr_distance(e, c) + xbr_distance(e, g) + xbr_distance(i, f4) + xbr_distance(i, h5) + (4 * xbr_distance(h, f));
+        let cross_weight = xbr_distance(h, d) + xbr_distance(h, i5) + xbr_distance(f, i4) + xbr_distance(f, b) + (4 * xbr_distance(e, i));
+        if edge_weight < cross_weight{
+            let new_color = if xbr_distance(e, f) <= xbr_distance(e, h) {f} else {h};
+            let (x, y) = corner.get_block_index(2);
+            block[y][x] = interpolate(&[(e, 1), (new_color, 1)]);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{RgbPixel, simd::{SimdLevel, Kernels}};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ByteOrder{
+    BigEndian,
+    LittleEndian
+}
+
+impl ByteOrder{
+    #[cfg(target_endian = "little")]
+    pub const NATIVE:ByteOrder = ByteOrder::LittleEndian;
+    #[cfg(target_endian = "big")]
+    pub const NATIVE:ByteOrder = ByteOrder::BigEndian;
+
+    fn is_native(self)->bool{
+        match self{
+            Self::BigEndian=>cfg!(target_endian = "big"),
+            Self::LittleEndian=>cfg!(target_endian = "little")
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OutputFormat{
+    // The format of the ili9341 lcd (big endian) and the u16pixel build
+    Rgb565(ByteOrder),
+    // 0x00RRGGBB, the format of the sdl RGB888 texture (little endian)
+    Xrgb8888(ByteOrder)
+}
+
+impl OutputFormat{
+    pub fn get_bytes_per_pixel(self)->usize{
+        match self{
+            Self::Rgb565(_)=>2,
+            Self::Xrgb8888(_)=>4
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ResizeAlgorithm{
+    Nearest,
+    Bilinear
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ResizeError{
+    EmptySize,
+    InputSizeMismatch{expected:usize, actual:usize},
+    OutputSizeMismatch{expected:usize, actual:usize},
+    UnsupportedSimdLevel(SimdLevel)
+}
+
+impl std::fmt::Display for ResizeError{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self{
+            Self::EmptySize=>write!(f, "the input and output sizes must not be empty"),
+            Self::InputSizeMismatch{expected, actual}=>write!(f, "expected an input of {} pixels, got {}", expected, actual),
+            Self::OutputSizeMismatch{expected, actual}=>write!(f, "expected an output of {} bytes, got {}", expected, actual),
+            Self::UnsupportedSimdLevel(level)=>write!(f, "the cpu does not support {:?}", level)
+        }
+    }
+}
+
+impl std::error::Error for ResizeError{}
+
+// The weights are 8 bit fixed point fractions, every blend is rounded down to 8 bits so all the kernels give the same results
+const WEIGHT_BITS:u32 = 8;
+const POSITION_FRACTION_BITS:u32 = 16;
+
+// The source of an output row or column
+#[derive(Clone, Copy)]
+struct Sample{
+    first:usize,
+    second:usize,
+    // The weight of the second pixel
+    weight:u16
+}
+
+// Resizes images of a fixed size, the sample positions and the scratch buffers are computed once
+pub struct Resizer{
+    input_width:usize,
+    input_height:usize,
+    output_width:usize,
+    output_height:usize,
+    algorithm:ResizeAlgorithm,
+    format:OutputFormat,
+    kernels:Kernels,
+    columns:Vec<Sample>,
+    rows:Vec<Sample>,
+    // Planar channels (r, g, b) of the 2 input rows and of the vertically blended row
+    input_rows:[[Vec<u16>;3];2],
+    blended_row:[Vec<u16>;3],
+    // The gathered horizontal samples and the output row channels
+    gathered:[[Vec<u16>;3];2],
+    weights:Vec<u16>,
+    output_row:[Vec<u16>;3],
+    packed_row:Vec<u16>
+}
+
+impl Resizer{
+    // Uses the fastest simd instructions the cpu supports
+    pub fn new(input_width:usize, input_height:usize, output_width:usize, output_height:usize, algorithm:ResizeAlgorithm, format:OutputFormat)->Result<Self, ResizeError>{
+        if input_width == 0 || input_height == 0 || output_width == 0 || output_height == 0{
+            return Err(ResizeError::EmptySize);
+        }
+        let columns = Self::create_samples(input_width, output_width, algorithm);
+        let rows = Self::create_samples(input_height, output_height, algorithm);
+        let weights = columns.iter().map(|sample|sample.weight).collect();
+        let channels = |size:usize|[vec![0;size], vec![0;size], vec![0;size]];
+        return Ok(Self{
+            input_width, input_height, output_width, output_height, algorithm, format,
+            kernels:Kernels::new(SimdLevel::detect()), columns, rows,
+            input_rows:[channels(input_width), channels(input_width)],
+            blended_row:channels(input_width),
+            gathered:[channels(output_width), channels(output_width)],
+            weights,
+            output_row:channels(output_width),
+            packed_row:vec![0;output_width]
+        });
+    }
+
+    // Forces the simd instructions, the scalar level is always supported
+    pub fn set_simd_level(&mut self, level:SimdLevel)->Result<(), ResizeError>{
+        if !level.is_supported(){
+            return Err(ResizeError::UnsupportedSimdLevel(level));
+        }
+        self.kernels = Kernels::new(level);
+        return Ok(());
+    }
+
+    pub fn get_simd_level(&self)->SimdLevel{
+        self.kernels.level
+    }
+
+    pub fn get_output_size(&self)->usize{
+        self.output_width * self.output_height * self.format.get_bytes_per_pixel()
+    }
+
+    pub fn resize<P:RgbPixel>(&mut self, input:&[P], output:&mut [u8])->Result<(), ResizeError>{
+        if input.len() != self.input_width * self.input_height{
+            return Err(ResizeError::InputSizeMismatch{expected:self.input_width * self.input_height, actual:input.len()});
+        }
+        if output.len() != self.get_output_size(){
+            return Err(ResizeError::OutputSizeMismatch{expected:self.get_output_size(), actual:output.len()});
+        }
+
+        let row_size = self.output_width * self.format.get_bytes_per_pixel();
+        // The input rows currently converted to planar channels
+        let mut loaded_rows = [usize::MAX;2];
+        for (y, output_row) in output.chunks_exact_mut(row_size).enumerate(){
+            let sample = self.rows[y];
+            for (index, input_y) in [sample.first, sample.second].into_iter().enumerate(){
+                if loaded_rows[index] != input_y{
+                    let row = &input[input_y * self.input_width..(input_y + 1) * self.input_width];
+                    split_channels(row, &mut self.input_rows[index]);
+                    loaded_rows[index] = input_y;
+                }
+            }
+
+            let [first_samples, second_samples] = &mut self.gathered;
+            for channel in 0..3{
+                let blended_row = &mut self.blended_row[channel];
+                (self.kernels.blend_rows)(&self.input_rows[0][channel], &self.input_rows[1][channel], sample.weight, blended_row);
+                if self.algorithm == ResizeAlgorithm::Nearest{
+                    for (output, column) in self.output_row[channel].iter_mut().zip(&self.columns){
+                        *output = blended_row[column.first];
+                    }
+                    continue;
+                }
+                for ((first, second), column) in first_samples[channel].iter_mut().zip(second_samples[channel].iter_mut()).zip(&self.columns){
+                    *first = blended_row[column.first];
+                    *second = blended_row[column.second];
+                }
+                (self.kernels.blend_lanes)(&first_samples[channel], &second_samples[channel], &self.weights, &mut self.output_row[channel]);
+            }
+            self.write_row(output_row);
+        }
+
+        return Ok(());
+    }
+
+    fn write_row(&mut self, output_row:&mut [u8]){
+        let [r, g, b] = &self.output_row;
+        match self.format{
+            OutputFormat::Rgb565(byte_order)=>{
+                (self.kernels.pack_rgb565)(r, g, b, !byte_order.is_native(), &mut self.packed_row);
+                for (bytes, pixel) in output_row.chunks_exact_mut(2).zip(self.packed_row.iter()){
+                    bytes.copy_from_slice(&pixel.to_ne_bytes());
+                }
+            }
+            OutputFormat::Xrgb8888(byte_order)=>{
+                for (x, bytes) in output_row.chunks_exact_mut(4).enumerate(){
+                    let pixel = ((r[x] as u32) << 16) | ((g[x] as u32) << 8) | b[x] as u32;
+                    bytes.copy_from_slice(&match byte_order{
+                        ByteOrder::BigEndian=>pixel.to_be_bytes(),
+                        ByteOrder::LittleEndian=>pixel.to_le_bytes()
+                    });
+                }
+            }
+        }
+    }
+
+    fn create_samples(input_size:usize, output_size:usize, algorithm:ResizeAlgorithm)->Vec<Sample>{
+        (0..output_size).map(|position|match algorithm{
+            ResizeAlgorithm::Nearest=>{
+                let first = position * input_size / output_size;
+                Sample{first, second:first, weight:0}
+            }
+            // The same mapping as scale_bilinear, the last output pixels are blended toward the last input pixel
+            ResizeAlgorithm::Bilinear=>{
+                let step = ((input_size as u64 - 1) << POSITION_FRACTION_BITS) / output_size as u64;
+                let source = position as u64 * step;
+                let first = (source >> POSITION_FRACTION_BITS) as usize;
+                let weight = ((source >> (POSITION_FRACTION_BITS - WEIGHT_BITS)) & ((1 << WEIGHT_BITS) - 1)) as u16;
+                Sample{first, second:(first + 1).min(input_size - 1), weight}
+            }
+        }).collect()
+    }
+}
+
+fn split_channels<P:RgbPixel>(row:&[P], channels:&mut [Vec<u16>;3]){
+    for (x, pixel) in row.iter().enumerate(){
+        let [r, g, b] = pixel.to_rgb();
+        channels[0][x] = r as u16;
+        channels[1][x] = g as u16;
+        channels[2][x] = b as u16;
+    }
+}
@@ -0,0 +1,288 @@
+// The resize kernels, every simd kernel must give the exact results of the scalar one
+
+const WEIGHT_ONE:u16 = 256;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SimdLevel{
+    Scalar,
+    Sse2,
+    Avx2,
+    Neon
+}
+
+impl SimdLevel{
+    pub const ALL:[SimdLevel;4] = [Self::Scalar, Self::Sse2, Self::Avx2, Self::Neon];
+
+    // The fastest level the cpu supports
+    pub fn detect()->SimdLevel{
+        return *Self::ALL.iter().rev().find(|level|level.is_supported()).unwrap_or(&Self::Scalar);
+    }
+
+    pub fn is_supported(self)->bool{
+        match self{
+            Self::Scalar=>true,
+            #[cfg(target_arch = "x86_64")]
+            Self::Sse2=>std::arch::is_x86_feature_detected!("sse2"),
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2=>std::arch::is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch = "aarch64")]
+            Self::Neon=>std::arch::is_aarch64_feature_detected!("neon"),
+            #[allow(unreachable_patterns)]
+            _=>false
+        }
+    }
+}
+
+// output[i] = (first[i] * (256 - weight) + second[i] * weight) >> 8
+type BlendRowsKernel = fn(&[u16], &[u16], u16, &mut [u16]);
+// Like BlendRowsKernel with a weight for every element
+type BlendLanesKernel = fn(&[u16], &[u16], &[u16], &mut [u16]);
+// Packs 8 bit channels to RGB565, optionally swapping the bytes
+type PackRgb565Kernel = fn(&[u16], &[u16], &[u16], bool, &mut [u16]);
+
+#[derive(Clone, Copy)]
+pub(crate) struct Kernels{
+    pub level:SimdLevel,
+    pub blend_rows:BlendRowsKernel,
+    pub blend_lanes:BlendLanesKernel,
+    pub pack_rgb565:PackRgb565Kernel
+}
+
+impl Kernels{
+    // The level must be supported
+    pub fn new(level:SimdLevel)->Self{
+        match level{
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Sse2=>Self{level, blend_rows:x86::blend_rows_sse2, blend_lanes:x86::blend_lanes_sse2, pack_rgb565:x86::pack_rgb565_sse2},
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Avx2=>Self{level, blend_rows:x86::blend_rows_avx2, blend_lanes:x86::blend_lanes_avx2, pack_rgb565:x86::pack_rgb565_avx2},
+            #[cfg(target_arch = "aarch64")]
+            SimdLevel::Neon=>Self{level, blend_rows:neon::blend_rows, blend_lanes:neon::blend_lanes, pack_rgb565:neon::pack_rgb565},
+            _=>Self{level:SimdLevel::Scalar, blend_rows:scalar::blend_rows, blend_lanes:scalar::blend_lanes, pack_rgb565:scalar::pack_rgb565}
+        }
+    }
+}
+
+mod scalar{
+    use super::WEIGHT_ONE;
+
+    pub fn blend_rows(first:&[u16], second:&[u16], weight:u16, output:&mut [u16]){
+        for ((output, first), second) in output.iter_mut().zip(first).zip(second){
+            *output = blend(*first, *second, weight);
+        }
+    }
+
+    pub fn blend_lanes(first:&[u16], second:&[u16], weights:&[u16], output:&mut [u16]){
+        for (((output, first), second), weight) in output.iter_mut().zip(first).zip(second).zip(weights){
+            *output = blend(*first, *second, *weight);
+        }
+    }
+
+    pub fn pack_rgb565(r:&[u16], g:&[u16], b:&[u16], swap_bytes:bool, output:&mut [u16]){
+        for (((output, r), g), b) in output.iter_mut().zip(r).zip(g).zip(b){
+            let pixel = ((r >> 3) << 11) | ((g >> 2) << 5) | (b >> 3);
+            *output = if swap_bytes {pixel.swap_bytes()} else {pixel};
+        }
+    }
+
+    fn blend(first:u16, second:u16, weight:u16)->u16{
+        ((first * (WEIGHT_ONE - weight)) + (second * weight)) >> 8
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86{
+    use std::arch::x86_64::*;
+    use super::{scalar, WEIGHT_ONE};
+
+    const SSE2_LANES:usize = 8;
+    const AVX2_LANES:usize = 16;
+
+    // The kernels are selected only after the feature detection so calling the target feature functions is safe
+    pub fn blend_rows_sse2(first:&[u16], second:&[u16], weight:u16, output:&mut [u16]){
+        let length = output.len().min(first.len()).min(second.len());
+        let simd_length = length - (length % SSE2_LANES);
+        unsafe{blend_rows_sse2_impl(&first[..simd_length], &second[..simd_length], weight, &mut output[..simd_length])};
+        scalar::blend_rows(&first[simd_length..length], &second[simd_length..length], weight, &mut output[simd_length..length]);
+    }
+
+    pub fn blend_lanes_sse2(first:&[u16], second:&[u16], weights:&[u16], output:&mut [u16]){
+        let length = output.len().min(first.len()).min(second.len()).min(weights.len());
+        let simd_length = length - (length % SSE2_LANES);
+        unsafe{blend_lanes_sse2_impl(&first[..simd_length], &second[..simd_length], &weights[..simd_length], &mut output[..simd_length])};
+        scalar::blend_lanes(&first[simd_length..length], &second[simd_length..length], &weights[simd_length..length], &mut output[simd_length..length]);
+    }
+
+    pub fn pack_rgb565_sse2(r:&[u16], g:&[u16], b:&[u16], swap_bytes:bool, output:&mut [u16]){
+        let length = output.len().min(r.len()).min(g.len()).min(b.len());
+        let simd_length = length - (length % SSE2_LANES);
+        unsafe{pack_rgb565_sse2_impl(&r[..simd_length], &g[..simd_length], &b[..simd_length], swap_bytes, &mut output[..simd_length])};
+        scalar::pack_rgb565(&r[simd_length..length], &g[simd_length..length], &b[simd_length..length], swap_bytes, &mut output[simd_length..length]);
+    }
+
+    pub fn blend_rows_avx2(first:&[u16], second:&[u16], weight:u16, output:&mut [u16]){
+        let length = output.len().min(first.len()).min(second.len());
+        let simd_length = length - (length % AVX2_LANES);
+        unsafe{blend_rows_avx2_impl(&first[..simd_length], &second[..simd_length], weight, &mut output[..simd_length])};
+        blend_rows_sse2(&first[simd_length..length], &second[simd_length..length], weight, &mut output[simd_length..length]);
+    }
+
+    pub fn blend_lanes_avx2(first:&[u16], second:&[u16], weights:&[u16], output:&mut [u16]){
+        let length = output.len().min(first.len()).min(second.len()).min(weights.len());
+        let simd_length = length - (length % AVX2_LANES);
+        unsafe{blend_lanes_avx2_impl(&first[..simd_length], &second[..simd_length], &weights[..simd_length], &mut output[..simd_length])};
+        blend_lanes_sse2(&first[simd_length..length], &second[simd_length..length], &weights[simd_length..length], &mut output[simd_length..length]);
+    }
+
+    pub fn pack_rgb565_avx2(r:&[u16], g:&[u16], b:&[u16], swap_bytes:bool, output:&mut [u16]){
+        let length = output.len().min(r.len()).min(g.len()).min(b.len());
+        let simd_length = length - (length % AVX2_LANES);
+        unsafe{pack_rgb565_avx2_impl(&r[..simd_length], &g[..simd_length], &b[..simd_length], swap_bytes, &mut output[..simd_length])};
+        pack_rgb565_sse2(&r[simd_length..length], &g[simd_length..length], &b[simd_length..length], swap_bytes, &mut output[simd_length..length]);
+    }
+
+    // The lengths of the slices are multiples of the lanes count.
+    // The products fit in 16 bits since the channels are 8 bits and the weights sum to 256
+    #[target_feature(enable = "sse2")]
+    unsafe fn blend_rows_sse2_impl(first:&[u16], second:&[u16], weight:u16, output:&mut [u16]){
+        let first_weight = _mm_set1_epi16((WEIGHT_ONE - weight) as i16);
+        let second_weight = _mm_set1_epi16(weight as i16);
+        for i in (0..output.len()).step_by(SSE2_LANES){
+            let first = _mm_loadu_si128(first.as_ptr().add(i) as *const __m128i);
+            let second = _mm_loadu_si128(second.as_ptr().add(i) as *const __m128i);
+            let sum = _mm_add_epi16(_mm_mullo_epi16(first, first_weight), _mm_mullo_epi16(second, second_weight));
+            _mm_storeu_si128(output.as_mut_ptr().add(i) as *mut __m128i, _mm_srli_epi16::<8>(sum));
+        }
+    }
+
+    #[target_feature(enable = "sse2")]
+    unsafe fn blend_lanes_sse2_impl(first:&[u16], second:&[u16], weights:&[u16], output:&mut [u16]){
+        let one = _mm_set1_epi16(WEIGHT_ONE as i16);
+        for i in (0..output.len()).step_by(SSE2_LANES){
+            let first = _mm_loadu_si128(first.as_ptr().add(i) as *const __m128i);
+            let second = _mm_loadu_si128(second.as_ptr().add(i) as *const __m128i);
+            let second_weight = _mm_loadu_si128(weights.as_ptr().add(i) as *const __m128i);
+            let first_weight = _mm_sub_epi16(one, second_weight);
+            let sum = _mm_add_epi16(_mm_mullo_epi16(first, first_weight), _mm_mullo_epi16(second, second_weight));
+            _mm_storeu_si128(output.as_mut_ptr().add(i) as *mut __m128i, _mm_srli_epi16::<8>(sum));
+        }
+    }
+
+    #[target_feature(enable = "sse2")]
+    unsafe fn pack_rgb565_sse2_impl(r:&[u16], g:&[u16], b:&[u16], swap_bytes:bool, output:&mut [u16]){
+        for i in (0..output.len()).step_by(SSE2_LANES){
+            let r = _mm_slli_epi16::<11>(_mm_srli_epi16::<3>(_mm_loadu_si128(r.as_ptr().add(i) as *const __m128i)));
+            let g = _mm_slli_epi16::<5>(_mm_srli_epi16::<2>(_mm_loadu_si128(g.as_ptr().add(i) as *const __m128i)));
+            let b = _mm_srli_epi16::<3>(_mm_loadu_si128(b.as_ptr().add(i) as *const __m128i));
+            let mut pixels = _mm_or_si128(_mm_or_si128(r, g), b);
+            if swap_bytes{
+                pixels = _mm_or_si128(_mm_slli_epi16::<8>(pixels), _mm_srli_epi16::<8>(pixels));
+            }
+            _mm_storeu_si128(output.as_mut_ptr().add(i) as *mut __m128i, pixels);
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn blend_rows_avx2_impl(first:&[u16], second:&[u16], weight:u16, output:&mut [u16]){
+        let first_weight = _mm256_set1_epi16((WEIGHT_ONE - weight) as i16);
+        let second_weight = _mm256_set1_epi16(weight as i16);
+        for i in (0..output.len()).step_by(AVX2_LANES){
+            let first = _mm256_loadu_si256(first.as_ptr().add(i) as *const __m256i);
+            let second = _mm256_loadu_si256(second.as_ptr().add(i) as *const __m256i);
+            let sum = _mm256_add_epi16(_mm256_mullo_epi16(first, first_weight), _mm256_mullo_epi16(second, second_weight));
+            _mm256_storeu_si256(output.as_mut_ptr().add(i) as *mut __m256i, _mm256_srli_epi16::<8>(sum));
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn blend_lanes_avx2_impl(first:&[u16], second:&[u16], weights:&[u16], output:&mut [u16]){
+        let one = _mm256_set1_epi16(WEIGHT_ONE as i16);
+        for i in (0..output.len()).step_by(AVX2_LANES){
+            let first = _mm256_loadu_si256(first.as_ptr().add(i) as *const __m256i);
+            let second = _mm256_loadu_si256(second.as_ptr().add(i) as *const __m256i);
+            let second_weight = _mm256_loadu_si256(weights.as_ptr().add(i) as *const __m256i);
+            let first_weight = _mm256_sub_epi16(one, second_weight);
+            let sum = _mm256_add_epi16(_mm256_mullo_epi16(first, first_weight), _mm256_mullo_epi16(second, second_weight));
+            _mm256_storeu_si256(output.as_mut_ptr().add(i) as *mut __m256i, _mm256_srli_epi16::<8>(sum));
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn pack_rgb565_avx2_impl(r:&[u16], g:&[u16], b:&[u16], swap_bytes:bool, output:&mut [u16]){
+        for i in (0..output.len()).step_by(AVX2_LANES){
+            let r = _mm256_slli_epi16::<11>(_mm256_srli_epi16::<3>(_mm256_loadu_si256(r.as_ptr().add(i) as *const __m256i)));
+            let g = _mm256_slli_epi16::<5>(_mm256_srli_epi16::<2>(_mm256_loadu_si256(g.as_ptr().add(i) as *const __m256i)));
+            let b = _mm256_srli_epi16::<3>(_mm256_loadu_si256(b.as_ptr().add(i) as *const __m256i));
+            let mut pixels = _mm256_or_si256(_mm256_or_si256(r, g), b);
+            if swap_bytes{
+                pixels = _mm256_or_si256(_mm256_slli_epi16::<8>(pixels), _mm256_srli_epi16::<8>(pixels));
+            }
+            _mm256_storeu_si256(output.as_mut_ptr().add(i) as *mut __m256i, pixels);
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon{
+    use std::arch::aarch64::*;
+    use super::{scalar, WEIGHT_ONE};
+
+    const LANES:usize = 8;
+
+    // The kernels are selected only after the feature detection so calling the target feature functions is safe
+    pub fn blend_rows(first:&[u16], second:&[u16], weight:u16, output:&mut [u16]){
+        let length = output.len().min(first.len()).min(second.len());
+        let simd_length = length - (length % LANES);
+        unsafe{blend_rows_impl(&first[..simd_length], &second[..simd_length], weight, &mut output[..simd_length])};
+        scalar::blend_rows(&first[simd_length..length], &second[simd_length..length], weight, &mut output[simd_length..length]);
+    }
+
+    pub fn blend_lanes(first:&[u16], second:&[u16], weights:&[u16], output:&mut [u16]){
+        let length = output.len().min(first.len()).min(second.len()).min(weights.len());
+        let simd_length = length - (length % LANES);
+        unsafe{blend_lanes_impl(&first[..simd_length], &second[..simd_length], &weights[..simd_length], &mut output[..simd_length])};
+        scalar::blend_lanes(&first[simd_length..length], &second[simd_length..length], &weights[simd_length..length], &mut output[simd_length..length]);
+    }
+
+    pub fn pack_rgb565(r:&[u16], g:&[u16], b:&[u16], swap_bytes:bool, output:&mut [u16]){
+        let length = output.len().min(r.len()).min(g.len()).min(b.len());
+        let simd_length = length - (length % LANES);
+        unsafe{pack_rgb565_impl(&r[..simd_length], &g[..simd_length], &b[..simd_length], swap_bytes, &mut output[..simd_length])};
+        scalar::pack_rgb565(&r[simd_length..length], &g[simd_length..length], &b[simd_length..length], swap_bytes, &mut output[simd_length..length]);
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn blend_rows_impl(first:&[u16], second:&[u16], weight:u16, output:&mut [u16]){
+        let first_weight = vdupq_n_u16(WEIGHT_ONE - weight);
+        let second_weight = vdupq_n_u16(weight);
+        for i in (0..output.len()).step_by(LANES){
+            let sum = vmlaq_u16(vmulq_u16(vld1q_u16(first.as_ptr().add(i)), first_weight), vld1q_u16(second.as_ptr().add(i)), second_weight);
+            vst1q_u16(output.as_mut_ptr().add(i), vshrq_n_u16::<8>(sum));
+        }
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn blend_lanes_impl(first:&[u16], second:&[u16], weights:&[u16], output:&mut [u16]){
+        let one = vdupq_n_u16(WEIGHT_ONE);
+        for i in (0..output.len()).step_by(LANES){
+            let second_weight = vld1q_u16(weights.as_ptr().add(i));
+            let first_weight = vsubq_u16(one, second_weight);
+            let sum = vmlaq_u16(vmulq_u16(vld1q_u16(first.as_ptr().add(i)), first_weight), vld1q_u16(second.as_ptr().add(i)), second_weight);
+            vst1q_u16(output.as_mut_ptr().add(i), vshrq_n_u16::<8>(sum));
+        }
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn pack_rgb565_impl(r:&[u16], g:&[u16], b:&[u16], swap_bytes:bool, output:&mut [u16]){
+        for i in (0..output.len()).step_by(LANES){
+            let r = vshlq_n_u16::<11>(vshrq_n_u16::<3>(vld1q_u16(r.as_ptr().add(i))));
+            let g = vshlq_n_u16::<5>(vshrq_n_u16::<2>(vld1q_u16(g.as_ptr().add(i))));
+            let b = vshrq_n_u16::<3>(vld1q_u16(b.as_ptr().add(i)));
+            let mut pixels = vorrq_u16(vorrq_u16(r, g), b);
+            if swap_bytes{
+                pixels = vorrq_u16(vshlq_n_u16::<8>(pixels), vshrq_n_u16::<8>(pixels));
+            }
+            vst1q_u16(output.as_mut_ptr().add(i), pixels);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use image_inter::{PixelArtScaler, RgbPixel};
+
+const WHITE:u32 = 0xFF_FFFF;
+const BLACK:u32 = 0;
+
+// A black diagonal line from the top left to the bottom right on white
+fn create_diagonal<const SIZE:usize>()->Vec<u32>{
+    (0..SIZE * SIZE).map(|i|if i % SIZE == i / SIZE {BLACK} else {WHITE}).collect()
+}
+
+fn scale<const SIZE:usize>(scaler:PixelArtScaler, input:&[u32])->Vec<u32>{
+    let factor = scaler.get_factor();
+    let mut output = vec![0;SIZE * SIZE * factor * factor];
+    scaler.scale::<u32, SIZE, SIZE>(input, &mut output);
+    return output;
+}
+
+#[test]
+fn test_flat_image_stays_flat(){
+    let input = vec![0x12_3456_u32;8 * 8];
+    for scaler in PixelArtScaler::ALL{
+        assert!(scale::<8>(scaler, &input).iter().all(|pixel|*pixel == 0x12_3456), "{}", scaler.get_name());
+    }
+}
+
+#[test]
+fn test_scale2x_smooths_diagonal(){
+    let output = scale::<4>(PixelArtScaler::Scale2x, &create_diagonal::<4>());
+    let width = 8;
+    // The diagonal pixels stay solid
+    assert!([(2 * width) + 2, (2 * width) + 3, (3 * width) + 2, (3 * width) + 3].iter().all(|i|output[*i] == BLACK));
+    // The white pixel at (1, 0) gets black only in its bottom left corner
+    assert_eq!(output[width + 2], BLACK);
+    assert_eq!(output[2], WHITE);
+    assert_eq!(output[width + 3], WHITE);
+}
+
+#[test]
+fn test_scale3x_matches_scale2x_on_edges(){
+    let output = scale::<4>(PixelArtScaler::Scale3x, &create_diagonal::<4>());
+    let width = 12;
+    // The white pixel at (1, 0) gets black in its bottom left corner
+    assert_eq!(output[(2 * width) + 3], BLACK);
+    assert_eq!(output[5], WHITE);
+}
+
+#[test]
+fn test_hqx_isolated_pixel(){
+    let mut input = vec![WHITE;5 * 5];
+    input[(2 * 5) + 2] = BLACK;
+
+    // Every hq2x sub pixel of a lone pixel is blended 14:1:1 with its white sides
+    let output = scale::<5>(PixelArtScaler::Hq2x, &input);
+    let width = 10;
+    assert!([(4 * width) + 4, (4 * width) + 5, (5 * width) + 4, (5 * width) + 5].iter().all(|i|output[*i] == 0x1F_1F1F));
+
+    // The hq3x corners are blended 2:1:1 with the white sides while the sides and the center stay black
+    let output = scale::<5>(PixelArtScaler::Hq3x, &input);
+    let width = 15;
+    for y in 0..3{
+        for x in 0..3{
+            let expected = if x != 1 && y != 1 {0x7F_7F7F} else {BLACK};
+            assert_eq!(output[((6 + y) * width) + 6 + x], expected, "({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn test_straight_edges_stay_sharp(){
+    // Black on the left half and white on the right half
+    let input:Vec<u32> = (0..8 * 8).map(|i|if i % 8 < 4 {BLACK} else {WHITE}).collect();
+    for scaler in PixelArtScaler::ALL{
+        let factor = scaler.get_factor();
+        let output = scale::<8>(scaler, &input);
+        for (i, pixel) in output.iter().enumerate(){
+            let x = (i % (8 * factor)) / factor;
+            let y = (i / (8 * factor)) / factor;
+            assert_eq!(*pixel, input[(y * 8) + x], "{}", scaler.get_name());
+        }
+    }
+}
+
+#[test]
+fn test_scalers_are_symmetric(){
+    const SIZE:usize = 32;
+    // A noisy image so most of the hqx patterns show up
+    let shades = [BLACK, WHITE, 0x80_4020, 0x20_C0A0];
+    let mut seed:u32 = 1;
+    let input:Vec<u32> = (0..SIZE * SIZE).map(|_|{
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        shades[(seed >> 16) as usize % shades.len()]
+    }).collect();
+    let transpose = |image:&[u32], size:usize|->Vec<u32>{
+        (0..size * size).map(|i|image[((i % size) * size) + (i / size)]).collect()
+    };
+    for scaler in PixelArtScaler::ALL{
+        let size = SIZE * scaler.get_factor();
+        // Scaling the transposed image is the same as transposing the scaled image
+        let output = scale::<SIZE>(scaler, &input);
+        let transposed_output = scale::<SIZE>(scaler, &transpose(&input, SIZE));
+        assert_eq!(transpose(&output, size), transposed_output, "{}", scaler.get_name());
+    }
+}
+
+#[test]
+fn test_rgb565_round_trip(){
+    for rgb in [[0, 0, 0], [0xFF, 0xFF, 0xFF], [0xF8, 0xFC, 0xF8], [0x10, 0x20, 0x40]]{
+        let pixel = u16::from_rgb(rgb);
+        assert_eq!(u16::from_rgb(pixel.to_rgb()), pixel);
+    }
+    assert_eq!(u16::from_rgb([0xFF, 0, 0]), 0xF800);
+}
\ No newline at end of file
@@ -0,0 +1,79 @@
+use image_inter::{Resizer, ResizeAlgorithm, ResizeError, OutputFormat, ByteOrder, SimdLevel, RgbPixel};
+
+// A deterministic noise image so every kernel lane sees different values
+fn create_input<P:RgbPixel>(width:usize, height:usize)->Vec<P>{
+    let mut state:u32 = 0x1234_5678;
+    (0..width * height).map(|_|{
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        let bytes = state.to_be_bytes();
+        P::from_rgb([bytes[0], bytes[1], bytes[2]])
+    }).collect()
+}
+
+fn resize<P:RgbPixel>(input:&[P], input_size:(usize, usize), output_size:(usize, usize), algorithm:ResizeAlgorithm, format:OutputFormat, level:SimdLevel)->Vec<u8>{
+    let mut resizer = Resizer::new(input_size.0, input_size.1, output_size.0, output_size.1, algorithm, format).unwrap();
+    resizer.set_simd_level(level).unwrap();
+    let mut output = vec![0;resizer.get_output_size()];
+    resizer.resize(input, &mut output).unwrap();
+    return output;
+}
+
+const FORMATS:[OutputFormat;4] = [
+    OutputFormat::Rgb565(ByteOrder::BigEndian), OutputFormat::Rgb565(ByteOrder::LittleEndian),
+    OutputFormat::Xrgb8888(ByteOrder::BigEndian), OutputFormat::Xrgb8888(ByteOrder::LittleEndian)
+];
+
+#[test]
+fn test_simd_matches_scalar(){
+    // Odd sizes leave remainders after the simd lanes
+    let sizes = [((160, 144), (266, 240)), ((37, 23), (101, 7)), ((320, 288), (266, 240))];
+    let levels:Vec<SimdLevel> = SimdLevel::ALL.iter().copied().filter(|level|level.is_supported()).collect();
+    for (input_size, output_size) in sizes{
+        let input_u16 = create_input::<u16>(input_size.0, input_size.1);
+        let input_u32 = create_input::<u32>(input_size.0, input_size.1);
+        for algorithm in [ResizeAlgorithm::Nearest, ResizeAlgorithm::Bilinear]{
+            for format in FORMATS{
+                let expected_u16 = resize(&input_u16, input_size, output_size, algorithm, format, SimdLevel::Scalar);
+                let expected_u32 = resize(&input_u32, input_size, output_size, algorithm, format, SimdLevel::Scalar);
+                for level in &levels{
+                    assert!(resize(&input_u16, input_size, output_size, algorithm, format, *level) == expected_u16, "{:?} {:?} {:?}", level, algorithm, format);
+                    assert!(resize(&input_u32, input_size, output_size, algorithm, format, *level) == expected_u32, "{:?} {:?} {:?}", level, algorithm, format);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_nearest_integer_scale(){
+    let input = create_input::<u32>(4, 3);
+    let output = resize(&input, (4, 3), (8, 6), ResizeAlgorithm::Nearest, OutputFormat::Xrgb8888(ByteOrder::LittleEndian), SimdLevel::detect());
+    for y in 0..6{
+        for x in 0..8{
+            let index = ((y * 8) + x) * 4;
+            let pixel = u32::from_le_bytes(output[index..index + 4].try_into().unwrap());
+            assert_eq!(pixel, input[((y / 2) * 4) + (x / 2)]);
+        }
+    }
+}
+
+#[test]
+fn test_rgb565_byte_order(){
+    let input = [0xF800_u16, 0x001F];
+    let big_endian = resize(&input, (2, 1), (2, 1), ResizeAlgorithm::Nearest, OutputFormat::Rgb565(ByteOrder::BigEndian), SimdLevel::detect());
+    assert_eq!(big_endian, [0xF8, 0x00, 0x00, 0x1F]);
+    let little_endian = resize(&input, (2, 1), (2, 1), ResizeAlgorithm::Nearest, OutputFormat::Rgb565(ByteOrder::LittleEndian), SimdLevel::detect());
+    assert_eq!(little_endian, [0x00, 0xF8, 0x1F, 0x00]);
+}
+
+#[test]
+fn test_size_errors(){
+    assert!(matches!(Resizer::new(0, 144, 266, 240, ResizeAlgorithm::Bilinear, FORMATS[0]), Err(ResizeError::EmptySize)));
+    let mut resizer = Resizer::new(160, 144, 266, 240, ResizeAlgorithm::Bilinear, FORMATS[0]).unwrap();
+    let mut output = vec![0;resizer.get_output_size()];
+    assert_eq!(resizer.resize(&[0_u16;10], &mut output), Err(ResizeError::InputSizeMismatch{expected:160 * 144, actual:10}));
+    assert_eq!(resizer.resize(&[0_u16;160 * 144], &mut output[1..]), Err(ResizeError::OutputSizeMismatch{expected:266 * 240 * 2, actual:(266 * 240 * 2) - 1}));
+    for level in SimdLevel::ALL{
+        assert_eq!(resizer.set_simd_level(level).is_ok(), level.is_supported());
+    }
+}
\ No newline at end of file
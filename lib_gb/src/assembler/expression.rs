@@ -0,0 +1,240 @@
+// A small recursive descent evaluator for the assembler numeric expressions.
+// Supports rgbds like number literals ($FF, %1010, 0xFF, 0b1010, 'A'), symbols and the common C operators.
+
+pub enum ExpressionError{
+    Undefined(String),
+    Invalid(String)
+}
+
+#[derive(Clone, PartialEq)]
+enum Token{
+    Number(i64),
+    Symbol(String),
+    Operator(&'static str),
+    OpenParen,
+    CloseParen
+}
+
+const OPERATORS:[&str;12] = ["<<", ">>", "+", "-", "*", "/", "%", "&", "|", "^", "~", "!"];
+
+pub fn evaluate(expression:&str, resolver:&dyn Fn(&str)->Option<i64>)->Result<i64, ExpressionError>{
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser{tokens, position:0, resolver};
+    let value = parser.parse_or()?;
+    if parser.position != parser.tokens.len(){
+        return Err(ExpressionError::Invalid(format!("unexpected trailing input in expression: {}", expression)));
+    }
+
+    return Ok(value);
+}
+
+pub fn is_symbol_char(c:char)->bool{
+    c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '#' || c == '@'
+}
+
+fn tokenize(expression:&str)->Result<Vec<Token>, ExpressionError>{
+    let chars:Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len(){
+        let c = chars[i];
+        // A prefix is allowed only when the previous token is not a value (number, symbol or a closing paren)
+        let expecting_value = !matches!(tokens.last(), Some(Token::Number(_)) | Some(Token::Symbol(_)) | Some(Token::CloseParen));
+
+        if c.is_whitespace(){
+            i += 1;
+        }
+        else if c == '('{
+            tokens.push(Token::OpenParen);
+            i += 1;
+        }
+        else if c == ')'{
+            tokens.push(Token::CloseParen);
+            i += 1;
+        }
+        else if c == '$' && i + 1 < chars.len() && chars[i + 1].is_ascii_hexdigit(){
+            let (value, next) = parse_digits(&chars, i + 1, 16)?;
+            tokens.push(Token::Number(value));
+            i = next;
+        }
+        else if c == '%' && expecting_value && i + 1 < chars.len() && (chars[i + 1] == '0' || chars[i + 1] == '1'){
+            let (value, next) = parse_digits(&chars, i + 1, 2)?;
+            tokens.push(Token::Number(value));
+            i = next;
+        }
+        else if c == '\''{
+            if i + 2 >= chars.len() || chars[i + 2] != '\''{
+                return Err(ExpressionError::Invalid(format!("bad character literal in: {}", expression)));
+            }
+            tokens.push(Token::Number(chars[i + 1] as i64));
+            i += 3;
+        }
+        else if c.is_ascii_digit(){
+            let (radix, start) = if c == '0' && i + 1 < chars.len() && (chars[i + 1] == 'x' || chars[i + 1] == 'X'){
+                (16, i + 2)
+            }
+            else if c == '0' && i + 2 < chars.len() && (chars[i + 1] == 'b' || chars[i + 1] == 'B') && chars[i + 2].is_digit(2){
+                (2, i + 2)
+            }
+            else{
+                (10, i)
+            };
+            let (value, next) = parse_digits(&chars, start, radix)?;
+            tokens.push(Token::Number(value));
+            i = next;
+        }
+        else if is_symbol_char(c){
+            let start = i;
+            while i < chars.len() && is_symbol_char(chars[i]){
+                i += 1;
+            }
+            tokens.push(Token::Symbol(chars[start..i].iter().collect()));
+        }
+        else{
+            let rest:String = chars[i..].iter().collect();
+            match OPERATORS.iter().find(|op|rest.starts_with(**op)){
+                Some(op)=>{
+                    tokens.push(Token::Operator(op));
+                    i += op.len();
+                }
+                None=>return Err(ExpressionError::Invalid(format!("unexpected character '{}' in expression: {}", c, expression)))
+            }
+        }
+    }
+
+    return Ok(tokens);
+}
+
+fn parse_digits(chars:&[char], start:usize, radix:u32)->Result<(i64, usize), ExpressionError>{
+    let mut i = start;
+    let mut value:i64 = 0;
+    while i < chars.len() && (chars[i].is_digit(radix) || chars[i] == '_'){
+        if chars[i] != '_'{
+            value = value.wrapping_mul(radix as i64) + chars[i].to_digit(radix).unwrap() as i64;
+        }
+        i += 1;
+    }
+    if i == start || (i < chars.len() && is_symbol_char(chars[i])){
+        return Err(ExpressionError::Invalid(format!("bad number literal: {}", chars[start..].iter().collect::<String>())));
+    }
+
+    return Ok((value, i));
+}
+
+struct Parser<'a>{
+    tokens:Vec<Token>,
+    position:usize,
+    resolver:&'a dyn Fn(&str)->Option<i64>
+}
+
+impl<'a> Parser<'a>{
+    fn peek_operator(&self, operators:&[&str])->Option<&'static str>{
+        if let Some(Token::Operator(op)) = self.tokens.get(self.position){
+            if operators.contains(op){
+                return Some(op);
+            }
+        }
+        return None;
+    }
+
+    fn parse_binary(&mut self, operators:&[&str], next:fn(&mut Self)->Result<i64, ExpressionError>)->Result<i64, ExpressionError>{
+        let mut value = next(self)?;
+        while let Some(op) = self.peek_operator(operators){
+            self.position += 1;
+            let rhs = next(self)?;
+            value = match op{
+                "|"=>value | rhs,
+                "^"=>value ^ rhs,
+                "&"=>value & rhs,
+                "<<"=>value.wrapping_shl(rhs as u32),
+                ">>"=>value.wrapping_shr(rhs as u32),
+                "+"=>value.wrapping_add(rhs),
+                "-"=>value.wrapping_sub(rhs),
+                "*"=>value.wrapping_mul(rhs),
+                "/" | "%"=>{
+                    if rhs == 0{
+                        return Err(ExpressionError::Invalid(String::from("division by zero")));
+                    }
+                    if op == "/" {value / rhs} else {value % rhs}
+                }
+                _=>std::panic!("unexpected operator: {}", op)
+            };
+        }
+
+        return Ok(value);
+    }
+
+    fn parse_or(&mut self)->Result<i64, ExpressionError>{
+        self.parse_binary(&["|"], Self::parse_xor)
+    }
+
+    fn parse_xor(&mut self)->Result<i64, ExpressionError>{
+        self.parse_binary(&["^"], Self::parse_and)
+    }
+
+    fn parse_and(&mut self)->Result<i64, ExpressionError>{
+        self.parse_binary(&["&"], Self::parse_shift)
+    }
+
+    fn parse_shift(&mut self)->Result<i64, ExpressionError>{
+        self.parse_binary(&["<<", ">>"], Self::parse_additive)
+    }
+
+    fn parse_additive(&mut self)->Result<i64, ExpressionError>{
+        self.parse_binary(&["+", "-"], Self::parse_multiplicative)
+    }
+
+    fn parse_multiplicative(&mut self)->Result<i64, ExpressionError>{
+        self.parse_binary(&["*", "/", "%"], Self::parse_unary)
+    }
+
+    fn parse_unary(&mut self)->Result<i64, ExpressionError>{
+        if let Some(op) = self.peek_operator(&["-", "+", "~", "!"]){
+            self.position += 1;
+            let value = self.parse_unary()?;
+            return Ok(match op{
+                "-"=>value.wrapping_neg(),
+                "~"=>!value,
+                "!"=>(value == 0) as i64,
+                _=>value
+            });
+        }
+
+        return self.parse_primary();
+    }
+
+    fn parse_primary(&mut self)->Result<i64, ExpressionError>{
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        return match token{
+            Some(Token::Number(value))=>Ok(value),
+            Some(Token::OpenParen)=>{
+                let value = self.parse_or()?;
+                self.expect_close_paren()?;
+                Ok(value)
+            }
+            Some(Token::Symbol(name))=>{
+                let upper = name.to_ascii_uppercase();
+                if (upper == "HIGH" || upper == "LOW") && self.tokens.get(self.position) == Some(&Token::OpenParen){
+                    self.position += 1;
+                    let value = self.parse_or()?;
+                    self.expect_close_paren()?;
+                    return Ok(if upper == "HIGH" {(value >> 8) & 0xFF} else {value & 0xFF});
+                }
+                match (self.resolver)(&name){
+                    Some(value)=>Ok(value),
+                    None=>Err(ExpressionError::Undefined(name))
+                }
+            }
+            _=>Err(ExpressionError::Invalid(String::from("expected a value in expression")))
+        };
+    }
+
+    fn expect_close_paren(&mut self)->Result<(), ExpressionError>{
+        if self.tokens.get(self.position) != Some(&Token::CloseParen){
+            return Err(ExpressionError::Invalid(String::from("missing closing parenthesis")));
+        }
+        self.position += 1;
+        return Ok(());
+    }
+}
@@ -0,0 +1,235 @@
+use super::operand::*;
+
+pub struct EncodeContext<'a>{
+    // The address of the first byte of the instruction
+    pub address:u16,
+    // On the first pass symbols might be unresolved so range checks are skipped
+    pub strict:bool,
+    pub evaluate:&'a dyn Fn(&str)->Result<i64, String>
+}
+
+const ALU_MNEMONICS:[&str;8] = ["add", "adc", "sub", "sbc", "and", "xor", "or", "cp"];
+const CB_SHIFT_MNEMONICS:[&str;8] = ["rlc", "rrc", "rl", "rr", "sla", "sra", "swap", "srl"];
+
+pub fn encode(mnemonic:&str, operands:&[Operand], ctx:&EncodeContext)->Result<Vec<u8>, String>{
+    if let Some(opcode) = get_no_operands_opcode(mnemonic){
+        if !operands.is_empty(){
+            return Err(format!("{} does not take operands", mnemonic));
+        }
+        return Ok(vec![opcode]);
+    }
+
+    if let Some(alu_index) = ALU_MNEMONICS.iter().position(|m|*m == mnemonic){
+        return encode_alu(mnemonic, alu_index as u8, operands, ctx);
+    }
+    if let Some(shift_index) = CB_SHIFT_MNEMONICS.iter().position(|m|*m == mnemonic){
+        return match operands{
+            [op] if r8(op).is_some()=>Ok(vec![0xCB, ((shift_index as u8) << 3) | r8(op).unwrap()]),
+            _=>Err(invalid_operands(mnemonic))
+        };
+    }
+
+    return match (mnemonic, operands){
+        ("stop", [])=>Ok(vec![0x10, 0x00]),
+        ("ld", [dst, src])=>encode_ld(dst, src, ctx),
+        ("ldi", [Operand::IndirectHl, Operand::Reg8(REG_A_INDEX)])=>Ok(vec![0x22]),
+        ("ldi", [Operand::Reg8(REG_A_INDEX), Operand::IndirectHl])=>Ok(vec![0x2A]),
+        ("ldd", [Operand::IndirectHl, Operand::Reg8(REG_A_INDEX)])=>Ok(vec![0x32]),
+        ("ldd", [Operand::Reg8(REG_A_INDEX), Operand::IndirectHl])=>Ok(vec![0x3A]),
+        ("ldh", [Operand::Indirect(address), Operand::Reg8(REG_A_INDEX)])=>Ok(vec![0xE0, high_page(address, ctx)?]),
+        ("ldh", [Operand::Reg8(REG_A_INDEX), Operand::Indirect(address)])=>Ok(vec![0xF0, high_page(address, ctx)?]),
+        ("ldh", [Operand::IndirectC, Operand::Reg8(REG_A_INDEX)])=>Ok(vec![0xE2]),
+        ("ldh", [Operand::Reg8(REG_A_INDEX), Operand::IndirectC])=>Ok(vec![0xF2]),
+        ("inc", [op]) | ("dec", [op])=>{
+            let is_inc = mnemonic == "inc";
+            if let Some(r) = r8(op){
+                Ok(vec![(if is_inc {0x04} else {0x05}) | (r << 3)])
+            }
+            else if let Some(rr) = rr_sp(op){
+                Ok(vec![(if is_inc {0x03} else {0x0B}) | (rr << 4)])
+            }
+            else{
+                Err(invalid_operands(mnemonic))
+            }
+        }
+        ("jp", [Operand::Reg16(Reg16::Hl)]) | ("jp", [Operand::IndirectHl])=>Ok(vec![0xE9]),
+        ("jp", [Operand::Immediate(target)])=>Ok(with_u16(0xC3, target, ctx)?),
+        ("jp", [cc, Operand::Immediate(target)]) if condition(cc).is_some()=>Ok(with_u16(0xC2 | (condition(cc).unwrap() << 3), target, ctx)?),
+        ("call", [Operand::Immediate(target)])=>Ok(with_u16(0xCD, target, ctx)?),
+        ("call", [cc, Operand::Immediate(target)]) if condition(cc).is_some()=>Ok(with_u16(0xC4 | (condition(cc).unwrap() << 3), target, ctx)?),
+        ("jr", [Operand::Immediate(target)])=>Ok(vec![0x18, relative_offset(target, ctx)?]),
+        ("jr", [cc, Operand::Immediate(target)]) if condition(cc).is_some()=>Ok(vec![0x20 | (condition(cc).unwrap() << 3), relative_offset(target, ctx)?]),
+        ("ret", [])=>Ok(vec![0xC9]),
+        ("ret", [cc]) if condition(cc).is_some()=>Ok(vec![0xC0 | (condition(cc).unwrap() << 3)]),
+        ("rst", [Operand::Immediate(vector)])=>{
+            let vector = (ctx.evaluate)(vector)?;
+            if vector & !0x38 != 0{
+                return Err(format!("invalid rst vector: {:#X}", vector));
+            }
+            Ok(vec![0xC7 | vector as u8])
+        }
+        ("push", [op]) if rr_stack(op).is_some()=>Ok(vec![0xC5 | (rr_stack(op).unwrap() << 4)]),
+        ("pop", [op]) if rr_stack(op).is_some()=>Ok(vec![0xC1 | (rr_stack(op).unwrap() << 4)]),
+        ("bit", [Operand::Immediate(bit), op]) | ("res", [Operand::Immediate(bit), op]) | ("set", [Operand::Immediate(bit), op]) if r8(op).is_some()=>{
+            let bit = (ctx.evaluate)(bit)?;
+            if !(0..8).contains(&bit){
+                return Err(format!("invalid bit number: {}", bit));
+            }
+            let base = match mnemonic{"bit"=>0x40, "res"=>0x80, _=>0xC0};
+            Ok(vec![0xCB, base | ((bit as u8) << 3) | r8(op).unwrap()])
+        }
+        _=>Err(invalid_operands(mnemonic))
+    };
+}
+
+fn get_no_operands_opcode(mnemonic:&str)->Option<u8>{
+    return match mnemonic{
+        "nop"=>Some(0x00),
+        "rlca"=>Some(0x07),
+        "rrca"=>Some(0x0F),
+        "rla"=>Some(0x17),
+        "rra"=>Some(0x1F),
+        "daa"=>Some(0x27),
+        "cpl"=>Some(0x2F),
+        "scf"=>Some(0x37),
+        "ccf"=>Some(0x3F),
+        "halt"=>Some(0x76),
+        "reti"=>Some(0xD9),
+        "di"=>Some(0xF3),
+        "ei"=>Some(0xFB),
+        _=>None
+    };
+}
+
+fn encode_alu(mnemonic:&str, alu_index:u8, operands:&[Operand], ctx:&EncodeContext)->Result<Vec<u8>, String>{
+    let source = match operands{
+        [Operand::Reg16(Reg16::Hl), op] if mnemonic == "add"=>{
+            return match rr_sp(op){
+                Some(rr)=>Ok(vec![0x09 | (rr << 4)]),
+                None=>Err(invalid_operands(mnemonic))
+            };
+        }
+        [Operand::Reg16(Reg16::Sp), Operand::Immediate(offset)] if mnemonic == "add"=>{
+            return Ok(vec![0xE8, signed_u8(offset, ctx)?]);
+        }
+        [Operand::Reg8(REG_A_INDEX), source]=>source,
+        [source]=>source,
+        _=>return Err(invalid_operands(mnemonic))
+    };
+
+    return match source{
+        Operand::Immediate(value)=>Ok(vec![0xC6 | (alu_index << 3), u8_value(value, ctx)?]),
+        _=>match r8(source){
+            Some(r)=>Ok(vec![0x80 | (alu_index << 3) | r]),
+            None=>Err(invalid_operands(mnemonic))
+        }
+    };
+}
+
+fn encode_ld(dst:&Operand, src:&Operand, ctx:&EncodeContext)->Result<Vec<u8>, String>{
+    return match (dst, src){
+        (Operand::IndirectHl, Operand::IndirectHl)=>Err(String::from("ld [hl], [hl] is not a valid instruction")),
+        (Operand::IndirectBc, Operand::Reg8(REG_A_INDEX))=>Ok(vec![0x02]),
+        (Operand::IndirectDe, Operand::Reg8(REG_A_INDEX))=>Ok(vec![0x12]),
+        (Operand::IndirectHlIncrement, Operand::Reg8(REG_A_INDEX))=>Ok(vec![0x22]),
+        (Operand::IndirectHlDecrement, Operand::Reg8(REG_A_INDEX))=>Ok(vec![0x32]),
+        (Operand::Reg8(REG_A_INDEX), Operand::IndirectBc)=>Ok(vec![0x0A]),
+        (Operand::Reg8(REG_A_INDEX), Operand::IndirectDe)=>Ok(vec![0x1A]),
+        (Operand::Reg8(REG_A_INDEX), Operand::IndirectHlIncrement)=>Ok(vec![0x2A]),
+        (Operand::Reg8(REG_A_INDEX), Operand::IndirectHlDecrement)=>Ok(vec![0x3A]),
+        (Operand::IndirectC, Operand::Reg8(REG_A_INDEX))=>Ok(vec![0xE2]),
+        (Operand::Reg8(REG_A_INDEX), Operand::IndirectC)=>Ok(vec![0xF2]),
+        (Operand::Indirect(address), Operand::Reg8(REG_A_INDEX))=>with_u16(0xEA, address, ctx),
+        (Operand::Reg8(REG_A_INDEX), Operand::Indirect(address))=>with_u16(0xFA, address, ctx),
+        (Operand::Indirect(address), Operand::Reg16(Reg16::Sp))=>with_u16(0x08, address, ctx),
+        (Operand::Reg16(Reg16::Sp), Operand::Reg16(Reg16::Hl))=>Ok(vec![0xF9]),
+        (Operand::Reg16(Reg16::Hl), Operand::SpOffset(offset))=>Ok(vec![0xF8, signed_u8(offset, ctx)?]),
+        (Operand::Reg16(_), Operand::Immediate(value)) if rr_sp(dst).is_some()=>with_u16(0x01 | (rr_sp(dst).unwrap() << 4), value, ctx),
+        (_, Operand::Immediate(value)) if r8(dst).is_some()=>Ok(vec![0x06 | (r8(dst).unwrap() << 3), u8_value(value, ctx)?]),
+        _=>match (r8(dst), r8(src)){
+            (Some(d), Some(s))=>Ok(vec![0x40 | (d << 3) | s]),
+            _=>Err(invalid_operands("ld"))
+        }
+    };
+}
+
+fn r8(operand:&Operand)->Option<u8>{
+    return match operand{
+        Operand::Reg8(r)=>Some(*r),
+        Operand::IndirectHl=>Some(REG_INDIRECT_HL_INDEX),
+        _=>None
+    };
+}
+
+fn rr_sp(operand:&Operand)->Option<u8>{
+    return match operand{
+        Operand::Reg16(Reg16::Bc)=>Some(0),
+        Operand::Reg16(Reg16::De)=>Some(1),
+        Operand::Reg16(Reg16::Hl)=>Some(2),
+        Operand::Reg16(Reg16::Sp)=>Some(3),
+        _=>None
+    };
+}
+
+fn rr_stack(operand:&Operand)->Option<u8>{
+    return match operand{
+        Operand::Reg16(Reg16::Af)=>Some(3),
+        Operand::Reg16(Reg16::Sp)=>None,
+        _=>rr_sp(operand)
+    };
+}
+
+fn condition(operand:&Operand)->Option<u8>{
+    return match operand{
+        Operand::Condition(cc)=>Some(*cc),
+        // C is both a register and a condition
+        Operand::Reg8(REG_C_INDEX)=>Some(3),
+        _=>None
+    };
+}
+
+fn invalid_operands(mnemonic:&str)->String{
+    format!("invalid operands for {}", mnemonic)
+}
+
+fn u8_value(expression:&str, ctx:&EncodeContext)->Result<u8, String>{
+    let value = (ctx.evaluate)(expression)?;
+    if ctx.strict && !(-128..=0xFF).contains(&value){
+        return Err(format!("value {} does not fit in 8 bits", value));
+    }
+    return Ok(value as u8);
+}
+
+fn signed_u8(expression:&str, ctx:&EncodeContext)->Result<u8, String>{
+    let value = (ctx.evaluate)(expression)?;
+    if ctx.strict && !(-128..=127).contains(&value){
+        return Err(format!("value {} does not fit in a signed 8 bit offset", value));
+    }
+    return Ok(value as i8 as u8);
+}
+
+fn with_u16(opcode:u8, expression:&str, ctx:&EncodeContext)->Result<Vec<u8>, String>{
+    let value = (ctx.evaluate)(expression)?;
+    if ctx.strict && !(-0x8000..=0xFFFF).contains(&value){
+        return Err(format!("value {} does not fit in 16 bits", value));
+    }
+    return Ok(vec![opcode, value as u8, (value >> 8) as u8]);
+}
+
+fn high_page(expression:&str, ctx:&EncodeContext)->Result<u8, String>{
+    let value = (ctx.evaluate)(expression)?;
+    if ctx.strict && !(0..=0xFF).contains(&value) && !(0xFF00..=0xFFFF).contains(&value){
+        return Err(format!("ldh address {:#X} is not in the high page", value));
+    }
+    return Ok(value as u8);
+}
+
+fn relative_offset(target:&str, ctx:&EncodeContext)->Result<u8, String>{
+    let target = (ctx.evaluate)(target)?;
+    // The offset is relative to the address after the 2 bytes of the instruction
+    let offset = target - (ctx.address as i64 + 2);
+    if ctx.strict && !(-128..=127).contains(&offset){
+        return Err(format!("jr target is out of range, offset: {}", offset));
+    }
+    return Ok(offset as i8 as u8);
+}
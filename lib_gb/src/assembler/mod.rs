@@ -0,0 +1,398 @@
+// A minimal SM83 assembler, mainly used for writing readable test programs and small patches.
+// The syntax follows rgbds: labels (and .local labels), EQU constants, db/dw/ds, SECTION and org.
+//
+// SECTION "name", ROM0[$0150]
+// Start:
+//     ld a, $10
+// .loop:
+//     dec a
+//     jr nz, .loop
+//     halt
+
+mod expression;
+mod operand;
+mod instructions;
+mod rom_header;
+
+use std::collections::HashMap;
+use self::{expression::*, operand::*, instructions::*};
+
+pub use rom_header::build_rom;
+
+const ROM_BANK_SIZE:u32 = 0x4000;
+
+#[derive(Debug)]
+pub struct AssemblerError{
+    pub line:usize,
+    pub message:String
+}
+
+impl std::fmt::Display for AssemblerError{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+// A continuous block of assembled bytes
+pub struct Chunk{
+    pub bank:u16,
+    pub address:u16,
+    pub bytes:Vec<u8>
+}
+
+impl Chunk{
+    // The offset of the chunk in a rom file, banked chunks are placed according to their bank
+    pub fn rom_offset(&self)->u32{
+        if self.address >= ROM_BANK_SIZE as u16 && self.address < 0x8000{
+            return self.bank as u32 * ROM_BANK_SIZE + (self.address as u32 - ROM_BANK_SIZE);
+        }
+        return self.address as u32;
+    }
+}
+
+pub struct Program{
+    pub chunks:Vec<Chunk>,
+    pub symbols:HashMap<String, i64>
+}
+
+impl Program{
+    // Flattens the chunks into a single buffer starting at the lowest offset, gaps are filled with zeros
+    pub fn to_bytes(&self)->Vec<u8>{
+        let start = self.chunks.iter().map(|c|c.rom_offset()).min().unwrap_or(0);
+        let end = self.chunks.iter().map(|c|c.rom_offset() + c.bytes.len() as u32).max().unwrap_or(0);
+        let mut buffer = vec![0; (end - start) as usize];
+        for chunk in &self.chunks{
+            let offset = (chunk.rom_offset() - start) as usize;
+            buffer[offset..offset + chunk.bytes.len()].copy_from_slice(&chunk.bytes);
+        }
+
+        return buffer;
+    }
+}
+
+// Assembles the source to a flat byte buffer (the first byte is at the first section address)
+pub fn assemble(source:&str)->Result<Vec<u8>, AssemblerError>{
+    return Ok(assemble_program(source)?.to_bytes());
+}
+
+// Assembles the source to a valid rom image with a correct cartridge header
+pub fn assemble_rom(source:&str, title:&str)->Result<Vec<u8>, AssemblerError>{
+    let program = assemble_program(source)?;
+    return build_rom(&program, title);
+}
+
+pub fn assemble_program(source:&str)->Result<Program, AssemblerError>{
+    let mut assembler = Assembler::new();
+    // The first pass resolves the labels addresses, the second one encodes with all the symbols known
+    assembler.run_pass(source, false)?;
+    assembler.run_pass(source, true)?;
+
+    return Ok(Program{chunks:assembler.chunks, symbols:assembler.symbols});
+}
+
+struct Assembler{
+    symbols:HashMap<String, i64>,
+    chunks:Vec<Chunk>,
+    current_global_label:String,
+    current_bank:u16,
+    address:u16,
+    // The type of the current section when it is not in rom, these sections only define labels
+    ram_section:Option<String>,
+    // The rom bank and address to continue from after the ram sections
+    rom_location:(u16, u16),
+    // The end of the last section of every ram type, floating sections are placed there
+    ram_addresses:HashMap<String, u16>,
+    strict:bool
+}
+
+impl Assembler{
+    fn new()->Self{
+        Self{symbols:HashMap::new(), chunks:Vec::new(), current_global_label:String::new(), current_bank:0, address:0,
+            ram_section:None, rom_location:(0, 0), ram_addresses:HashMap::new(), strict:false}
+    }
+
+    fn run_pass(&mut self, source:&str, strict:bool)->Result<(), AssemblerError>{
+        self.strict = strict;
+        self.chunks.clear();
+        self.current_global_label.clear();
+        self.current_bank = 0;
+        self.address = 0;
+        self.ram_section = None;
+        self.rom_location = (0, 0);
+        self.ram_addresses.clear();
+
+        for (index, line) in source.lines().enumerate(){
+            self.assemble_line(line).map_err(|message|AssemblerError{line:index + 1, message})?;
+        }
+
+        return Ok(());
+    }
+
+    fn assemble_line(&mut self, line:&str)->Result<(), String>{
+        let mut line = strip_comment(line).trim();
+
+        // Labels
+        if let Some(colon_index) = find_label_end(line){
+            let name = line[..colon_index].trim();
+            let full_name = self.define_label_name(name);
+            self.define_symbol(full_name, self.address as i64)?;
+            line = line[colon_index..].trim_start_matches(':').trim();
+        }
+        if line.is_empty(){
+            return Ok(());
+        }
+
+        let (first_word, rest) = split_first_word(line);
+        let first_word_lower = first_word.to_ascii_lowercase();
+
+        // Constants: "NAME EQU value" or "DEF NAME EQU value"
+        let (constant_name, constant_rest) = if first_word_lower == "def" {split_first_word(rest)} else {(first_word, rest)};
+        let (second_word, value) = split_first_word(constant_rest);
+        if second_word.eq_ignore_ascii_case("equ") || second_word == "="{
+            return self.define_constant(constant_name, value);
+        }
+
+        return match first_word_lower.as_str(){
+            "section"=>self.section(rest),
+            "org"=>{
+                let address = self.evaluate_now(rest)? as u16;
+                if self.ram_section.is_some(){
+                    self.address = address;
+                }
+                else{
+                    self.start_chunk(self.current_bank, address);
+                }
+                Ok(())
+            }
+            "db"=>self.data(rest, 1),
+            "dw"=>self.data(rest, 2),
+            "ds"=>{
+                let operands = split_operands(rest);
+                let count = self.evaluate_now(operands.first().ok_or("ds requires a size")?)?;
+                let fill = match operands.get(1){
+                    Some(fill)=>self.evaluate(fill)? as u8,
+                    // Ram sections only reserve the space
+                    None if self.ram_section.is_some()=>return self.reserve(count as usize),
+                    None=>0
+                };
+                self.emit(&vec![fill; count as usize])
+            }
+            _=>{
+                let operands:Vec<Operand> = split_operands(rest).iter().map(|o|Operand::parse(o)).collect();
+                let evaluate = |expression:&str|self.evaluate(expression);
+                let ctx = EncodeContext{address:self.address, strict:self.strict, evaluate:&evaluate};
+                let bytes = encode(&first_word_lower, &operands, &ctx)?;
+                self.emit(&bytes)
+            }
+        };
+    }
+
+    // SECTION "name", ROM0[$addr] | ROMX[$addr], BANK[n] | WRAM0[$addr] | HRAM[$addr]...
+    fn section(&mut self, arguments:&str)->Result<(), String>{
+        let arguments = split_operands(arguments);
+        if arguments.len() < 2{
+            return Err(String::from("SECTION requires a name and a type"));
+        }
+        let section_type = arguments[1].as_str();
+        let (type_name, address) = match section_type.find('['){
+            Some(index)=>{
+                let address = section_type[index + 1..].trim_end().trim_end_matches(']');
+                (section_type[..index].trim().to_ascii_uppercase(), Some(self.evaluate_now(address)? as u16))
+            }
+            None=>(section_type.trim().to_ascii_uppercase(), None)
+        };
+        let (mut bank, ram_start_address) = match type_name.as_str(){
+            "ROM0"=>(0, None),
+            "ROMX"=>(1, None),
+            "VRAM"=>(0, Some(0x8000)),
+            "SRAM"=>(0, Some(0xA000)),
+            "WRAM0"=>(0, Some(0xC000)),
+            "WRAMX"=>(0, Some(0xD000)),
+            "OAM"=>(0, Some(0xFE00)),
+            "HRAM"=>(0, Some(0xFF80)),
+            _=>return Err(format!("unknown section type: {}", type_name))
+        };
+        if let Some(bank_argument) = arguments.get(2){
+            let upper = bank_argument.to_ascii_uppercase();
+            if !upper.starts_with("BANK[") || !upper.ends_with(']'){
+                return Err(format!("bad section option: {}", bank_argument));
+            }
+            bank = self.evaluate_now(&bank_argument[5..bank_argument.len() - 1])? as u16;
+        }
+
+        // Leaving a ram section continues the rom layout from where it stopped
+        if let Some(ram_type) = self.ram_section.take(){
+            self.ram_addresses.insert(ram_type, self.address);
+            (self.current_bank, self.address) = self.rom_location;
+        }
+        match ram_start_address{
+            None=>{
+                let address = match address{
+                    Some(address)=>address,
+                    // Floating sections are placed right after the last section
+                    None=>{
+                        if type_name == "ROMX" && self.address < ROM_BANK_SIZE as u16 {ROM_BANK_SIZE as u16} else {self.address}
+                    }
+                };
+                self.start_chunk(bank, address);
+            }
+            Some(start_address)=>{
+                let address = match address{
+                    Some(address)=>address,
+                    None=>*self.ram_addresses.get(&type_name).unwrap_or(&start_address)
+                };
+                self.rom_location = (self.current_bank, self.address);
+                self.current_bank = bank;
+                self.address = address;
+                self.ram_section = Some(type_name);
+            }
+        }
+
+        return Ok(());
+    }
+
+    fn data(&mut self, arguments:&str, item_size:usize)->Result<(), String>{
+        let mut bytes = Vec::new();
+        for item in split_operands(arguments){
+            if item.starts_with('"') && item.ends_with('"') && item.len() >= 2{
+                for c in item[1..item.len() - 1].bytes(){
+                    bytes.push(c);
+                    if item_size == 2{
+                        bytes.push(0);
+                    }
+                }
+                continue;
+            }
+            let value = self.evaluate(&item)?;
+            let max_value = if item_size == 1 {0xFF} else {0xFFFF};
+            if self.strict && (value > max_value || value < -(max_value + 1) / 2){
+                return Err(format!("value {} does not fit in {} bytes", value, item_size));
+            }
+            bytes.push(value as u8);
+            if item_size == 2{
+                bytes.push((value >> 8) as u8);
+            }
+        }
+
+        return self.emit(&bytes);
+    }
+
+    fn emit(&mut self, bytes:&[u8])->Result<(), String>{
+        if let Some(section_type) = &self.ram_section{
+            return Err(format!("data is not allowed in a {} section", section_type));
+        }
+        if self.chunks.is_empty(){
+            self.start_chunk(self.current_bank, self.address);
+        }
+        self.reserve(bytes.len())?;
+        self.chunks.last_mut().unwrap().bytes.extend_from_slice(bytes);
+
+        return Ok(());
+    }
+
+    // Advances the address without emitting bytes
+    fn reserve(&mut self, size:usize)->Result<(), String>{
+        if self.address as usize + size > 0x10000{
+            return Err(String::from("address overflowed past $FFFF"));
+        }
+        self.address = self.address.wrapping_add(size as u16);
+
+        return Ok(());
+    }
+
+    fn start_chunk(&mut self, bank:u16, address:u16){
+        self.current_bank = bank;
+        self.address = address;
+        self.chunks.push(Chunk{bank, address, bytes:Vec::new()});
+    }
+
+    fn define_label_name(&mut self, name:&str)->String{
+        if name.starts_with('.'){
+            return format!("{}{}", self.current_global_label, name);
+        }
+        self.current_global_label = String::from(name);
+        return String::from(name);
+    }
+
+    fn define_constant(&mut self, name:&str, value:&str)->Result<(), String>{
+        match self.evaluate(value){
+            Ok(value)=>self.define_symbol(String::from(name), value),
+            // On the first pass the constant might reference a label that is defined later
+            Err(_) if !self.strict=>Ok(()),
+            Err(message)=>Err(message)
+        }
+    }
+
+    fn define_symbol(&mut self, name:String, value:i64)->Result<(), String>{
+        if name.is_empty() || !name.chars().all(is_symbol_char) || name.starts_with(|c:char|c.is_ascii_digit()){
+            return Err(format!("invalid symbol name: {}", name));
+        }
+        if let Some(old_value) = self.symbols.get(&name){
+            // Symbols are defined again in the second pass
+            if !self.strict || *old_value != value{
+                return Err(format!("symbol {} is already defined", name));
+            }
+        }
+        self.symbols.insert(name, value);
+
+        return Ok(());
+    }
+
+    fn resolve(&self, name:&str)->Option<i64>{
+        if name == "@"{
+            return Some(self.address as i64);
+        }
+        if name.starts_with('.'){
+            return self.symbols.get(&format!("{}{}", self.current_global_label, name)).copied();
+        }
+        return self.symbols.get(name).copied();
+    }
+
+    // Unresolved symbols evaluate to 0 on the first pass since only the layout matters
+    fn evaluate(&self, expression:&str)->Result<i64, String>{
+        return match evaluate(expression, &|name|self.resolve(name)){
+            Ok(value)=>Ok(value),
+            Err(ExpressionError::Undefined(name))=>{
+                if self.strict {Err(format!("undefined symbol: {}", name))} else {Ok(0)}
+            }
+            Err(ExpressionError::Invalid(message))=>Err(message)
+        };
+    }
+
+    // Values that affect the layout (org, ds, sections) must be known on the first pass
+    fn evaluate_now(&self, expression:&str)->Result<i64, String>{
+        return match evaluate(expression, &|name|self.resolve(name)){
+            Ok(value)=>Ok(value),
+            Err(ExpressionError::Undefined(name))=>Err(format!("symbol {} must be defined before it is used here", name)),
+            Err(ExpressionError::Invalid(message))=>Err(message)
+        };
+    }
+}
+
+fn strip_comment(line:&str)->&str{
+    let mut in_string = false;
+    for (i, c) in line.char_indices(){
+        match c{
+            '"'=>in_string = !in_string,
+            ';' if !in_string=>return &line[..i],
+            _=>{}
+        }
+    }
+    return line;
+}
+
+// A label is a symbol at the start of the line followed by a colon
+fn find_label_end(line:&str)->Option<usize>{
+    let end = line.find(|c:char|!is_symbol_char(c))?;
+    if end > 0 && line[end..].starts_with(':'){
+        return Some(end);
+    }
+    return None;
+}
+
+fn split_first_word(line:&str)->(&str, &str){
+    return match line.find(char::is_whitespace){
+        Some(index)=>(&line[..index], line[index..].trim()),
+        None=>(line, "")
+    };
+}
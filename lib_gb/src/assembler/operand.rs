@@ -0,0 +1,126 @@
+// Operands as they appear in the source, expressions are kept as strings and evaluated at encoding time
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Reg16{
+    Bc,
+    De,
+    Hl,
+    Sp,
+    Af
+}
+
+pub enum Operand{
+    // Register index as encoded in the opcodes: B C D E H L [HL] A
+    Reg8(u8),
+    Reg16(Reg16),
+    IndirectHl,
+    IndirectHlIncrement,
+    IndirectHlDecrement,
+    IndirectBc,
+    IndirectDe,
+    IndirectC,
+    Indirect(String),
+    // Condition index as encoded in the opcodes: NZ Z NC C (C is parsed as the register)
+    Condition(u8),
+    SpOffset(String),
+    Immediate(String)
+}
+
+pub const REG_C_INDEX:u8 = 1;
+pub const REG_INDIRECT_HL_INDEX:u8 = 6;
+pub const REG_A_INDEX:u8 = 7;
+
+impl Operand{
+    pub fn parse(operand:&str)->Operand{
+        let trimmed = operand.trim();
+        let lower = trimmed.to_ascii_lowercase();
+        let square_brackets = lower.starts_with('[') && lower.ends_with(']');
+        // Parentheses are accepted only for the register indirect forms, otherwise they are a part of an expression
+        let parentheses = lower.starts_with('(') && lower.ends_with(')') && is_single_group(&lower);
+        if square_brackets || parentheses{
+            let inner:String = lower[1..lower.len() - 1].chars().filter(|c|!c.is_whitespace()).collect();
+            match inner.as_str(){
+                "hl"=>return Operand::IndirectHl,
+                "hl+" | "hli"=>return Operand::IndirectHlIncrement,
+                "hl-" | "hld"=>return Operand::IndirectHlDecrement,
+                "bc"=>return Operand::IndirectBc,
+                "de"=>return Operand::IndirectDe,
+                "c" | "$ff00+c" | "0xff00+c"=>return Operand::IndirectC,
+                _=>if square_brackets {return Operand::Indirect(String::from(trimmed[1..trimmed.len() - 1].trim()))}
+            }
+        }
+
+        let compact:String = lower.chars().filter(|c|!c.is_whitespace()).collect();
+        return match compact.as_str(){
+            "b"=>Operand::Reg8(0),
+            "c"=>Operand::Reg8(REG_C_INDEX),
+            "d"=>Operand::Reg8(2),
+            "e"=>Operand::Reg8(3),
+            "h"=>Operand::Reg8(4),
+            "l"=>Operand::Reg8(5),
+            "a"=>Operand::Reg8(REG_A_INDEX),
+            "bc"=>Operand::Reg16(Reg16::Bc),
+            "de"=>Operand::Reg16(Reg16::De),
+            "hl"=>Operand::Reg16(Reg16::Hl),
+            "sp"=>Operand::Reg16(Reg16::Sp),
+            "af"=>Operand::Reg16(Reg16::Af),
+            "nz"=>Operand::Condition(0),
+            "z"=>Operand::Condition(1),
+            "nc"=>Operand::Condition(2),
+            _=>{
+                if compact.starts_with("sp+") || compact.starts_with("sp-"){
+                    // keep the sign as part of the expression
+                    Operand::SpOffset(String::from(&compact[2..]))
+                }
+                else{
+                    Operand::Immediate(String::from(trimmed))
+                }
+            }
+        };
+    }
+}
+
+// Checks that the parentheses wrapping the operand are a single group, "(1+2)*3" for example is not an indirect operand
+fn is_single_group(operand:&str)->bool{
+    let mut depth = 0;
+    for (i, c) in operand.char_indices(){
+        match c{
+            '('=>depth += 1,
+            ')'=>{
+                depth -= 1;
+                if depth == 0 && i != operand.len() - 1{
+                    return false;
+                }
+            }
+            _=>{}
+        }
+    }
+    return true;
+}
+
+// Split by commas that are not inside strings, brackets or parentheses
+pub fn split_operands(operands:&str)->Vec<String>{
+    let mut result = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    for c in operands.chars(){
+        match c{
+            '"'=>in_string = !in_string,
+            '(' | '[' if !in_string=>depth += 1,
+            ')' | ']' if !in_string=>depth -= 1,
+            ',' if !in_string && depth == 0=>{
+                result.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _=>{}
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() || !result.is_empty(){
+        result.push(current.trim().to_string());
+    }
+
+    return result;
+}
@@ -0,0 +1,84 @@
+use super::{AssemblerError, Program, ROM_BANK_SIZE};
+
+const ENTRY_POINT_ADDRESS:usize = 0x100;
+const LOGO_ADDRESS:usize = 0x104;
+const TITLE_ADDRESS:usize = 0x134;
+const TITLE_MAX_SIZE:usize = 16;
+const CARTRIDGE_TYPE_ADDRESS:usize = 0x147;
+const ROM_SIZE_ADDRESS:usize = 0x148;
+const HEADER_CHECKSUM_ADDRESS:usize = 0x14D;
+const GLOBAL_CHECKSUM_ADDRESS:usize = 0x14E;
+const HEADER_END_ADDRESS:usize = 0x150;
+
+const MBC1_CARTRIDGE_TYPE:u8 = 1;
+
+const NINTENDO_LOGO:[u8;48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E
+];
+
+// nop; jp $0150
+const DEFAULT_ENTRY_POINT:[u8;4] = [0x00, 0xC3, 0x50, 0x01];
+
+// Lays the program chunks in a rom image and fills the cartridge header (logo, title, type, size and checksums)
+pub fn build_rom(program:&Program, title:&str)->Result<Vec<u8>, AssemblerError>{
+    let rom_end = program.chunks.iter().map(|c|c.rom_offset() as usize + c.bytes.len()).max().unwrap_or(0);
+    let banks_count = std::cmp::max(2, rom_end.div_ceil(ROM_BANK_SIZE as usize)).next_power_of_two();
+    let mut rom = vec![0xFF; banks_count * ROM_BANK_SIZE as usize];
+
+    let mut has_entry_point = false;
+    for chunk in &program.chunks{
+        if chunk.address >= 0x8000{
+            return Err(AssemblerError{line:0, message:format!("section at {:#X} is not in rom", chunk.address)});
+        }
+        let start = chunk.rom_offset() as usize;
+        let end = start + chunk.bytes.len();
+        if start < HEADER_END_ADDRESS && end > LOGO_ADDRESS{
+            return Err(AssemblerError{line:0, message:format!("code at {:#X}-{:#X} overlaps the cartridge header", start, end)});
+        }
+        if start <= ENTRY_POINT_ADDRESS && end > ENTRY_POINT_ADDRESS{
+            has_entry_point = true;
+        }
+        rom[start..end].copy_from_slice(&chunk.bytes);
+    }
+
+    if !has_entry_point{
+        rom[ENTRY_POINT_ADDRESS..ENTRY_POINT_ADDRESS + DEFAULT_ENTRY_POINT.len()].copy_from_slice(&DEFAULT_ENTRY_POINT);
+    }
+
+    rom[LOGO_ADDRESS..LOGO_ADDRESS + NINTENDO_LOGO.len()].copy_from_slice(&NINTENDO_LOGO);
+    rom[TITLE_ADDRESS..HEADER_END_ADDRESS].fill(0);
+    let title = title.as_bytes();
+    let title_size = std::cmp::min(title.len(), TITLE_MAX_SIZE);
+    rom[TITLE_ADDRESS..TITLE_ADDRESS + title_size].copy_from_slice(&title[..title_size]);
+    rom[CARTRIDGE_TYPE_ADDRESS] = if banks_count > 2 {MBC1_CARTRIDGE_TYPE} else {0};
+    // 0 = 32KB, each step doubles the size
+    rom[ROM_SIZE_ADDRESS] = (banks_count / 2).trailing_zeros() as u8;
+
+    rom[HEADER_CHECKSUM_ADDRESS] = calc_header_checksum(&rom);
+    let global_checksum = calc_global_checksum(&rom);
+    rom[GLOBAL_CHECKSUM_ADDRESS] = (global_checksum >> 8) as u8;
+    rom[GLOBAL_CHECKSUM_ADDRESS + 1] = global_checksum as u8;
+
+    return Ok(rom);
+}
+
+pub fn calc_header_checksum(rom:&[u8])->u8{
+    let mut checksum:u8 = 0;
+    for byte in &rom[TITLE_ADDRESS..HEADER_CHECKSUM_ADDRESS]{
+        checksum = checksum.wrapping_sub(*byte).wrapping_sub(1);
+    }
+    return checksum;
+}
+
+// Sum of all the rom bytes except the global checksum bytes
+pub fn calc_global_checksum(rom:&[u8])->u16{
+    let mut checksum:u16 = 0;
+    for (i, byte) in rom.iter().enumerate(){
+        if i != GLOBAL_CHECKSUM_ADDRESS && i != GLOBAL_CHECKSUM_ADDRESS + 1{
+            checksum = checksum.wrapping_add(*byte as u16);
+        }
+    }
+    return checksum;
+}
@@ -57,8 +57,8 @@ impl GbCpu {
         //unhalting the CPU
         self.halt = false;
 
-        // 5 cycles - 2 pushing pc to memory, 3 internal operation
-        return 3;
+        // 5 cycles - 3 pushing pc to memory, 2 internal operation
+        return 2;
     }
 
     pub fn set_flag(&mut self, flag:Flag){
@@ -19,7 +19,7 @@ type U32MemoryOpcodeFunc<T> = fn(&mut GbCpu,&mut T,u32)->u8;
 
 impl GbCpu{
     pub fn run_opcode(&mut self, memory:&mut impl Memory)->u8{
-        let opcode = self.fetch_next_byte(memory);
+        let opcode = self.fetch_next_opcode(memory);
     
         match opcode{
             //Stop
@@ -51,11 +51,11 @@ impl GbCpu{
             0xFB=>ei(self),
     
             //cpu and opcode
-            0x03|0x13|0x23|0x33=>inc_rr(self, opcode),
+            0x03|0x13|0x23|0x33=>inc_rr(self, memory, opcode),
             0x04|0x14|0x24|0x0C|0x1C|0x2C|0x3C=>inc_r(self, opcode),
             0x05|0x15|0x25|0x0D|0x1D|0x2D|0x3D=>dec_r(self, opcode),
             0x09|0x19|0x29|0x39=>add_hl_rr(self, opcode),
-            0x0B|0x1B|0x2B|0x3B=>dec_rr(self, opcode),
+            0x0B|0x1B|0x2B|0x3B=>dec_rr(self, memory, opcode),
             0x40..=0x45 | 0x47..=0x4D | 0x4F..=0x55 | 0x57..=0x5D |
             0x5F..=0x65 | 0x67..=0x6D | 0x6F | 0x78..=0x7D | 0x7F=>ld_r_r(self, opcode),
             0x80..=0x85 | 0x87=>add_a_r(self, opcode),
@@ -133,7 +133,7 @@ impl GbCpu{
     
             //0xCB opcodes
             0xCB=>{
-                let next = self.fetch_next_byte(memory);
+                let next = self.fetch_next_opcode(memory);
                 let u16_opcode = (opcode as u16) << 8 | next as u16;
                 match next{
                     0x00..=0x05 | 0x07=>rlc_r(self, u16_opcode),
@@ -171,8 +171,14 @@ impl GbCpu{
     }
 
     
+    fn fetch_next_opcode(&mut self, memory: &mut impl Memory)->u8{
+        let byte:u8 = memory.read_opcode(self.program_counter, 1);
+        self.program_counter+=1;
+        return byte;
+    }
+
     fn fetch_next_byte(&mut self, memory: &mut impl Memory)->u8{
-        let byte:u8 = memory.read(self.program_counter, 1);
+        let byte:u8 = memory.read_operand(self.program_counter, 1);
         self.program_counter+=1;
         return byte;
     }
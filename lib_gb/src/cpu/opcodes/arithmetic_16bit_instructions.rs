@@ -1,5 +1,6 @@
 use crate::cpu::gb_cpu::GbCpu;
 use crate::cpu::flag::Flag;
+use crate::mmu::memory::Memory;
 use super::opcodes_utils::{
     get_arithmetic_16reg,
     check_for_half_carry_third_nible_add,
@@ -38,21 +39,25 @@ pub fn add_sp_dd(cpu:&mut GbCpu, opcode:u16)->u8{
     return 2;
 }
 
-pub fn inc_rr(cpu:&mut GbCpu, opcode:u8)->u8{
+pub fn inc_rr(cpu:&mut GbCpu, memory:&mut impl Memory, opcode:u8)->u8{
     let reg = (opcode & 0xF0)>>4;
     let reg = get_arithmetic_16reg(cpu, reg);
-    *reg = (*reg).wrapping_add(1);
+    let value = *reg;
+    *reg = value.wrapping_add(1);
+    memory.inc_dec_access(value, 1);
     
     // 2 cycles - 1 reading opcode, 1 internal operation
-    return 1;
+    return 0;
 }
 
 
-pub fn dec_rr(cpu:&mut GbCpu, opcode:u8)->u8{
+pub fn dec_rr(cpu:&mut GbCpu, memory:&mut impl Memory, opcode:u8)->u8{
     let reg = (opcode & 0xF0)>>4;
     let reg = get_arithmetic_16reg(cpu, reg);
-    *reg = (*reg).wrapping_sub(1);
+    let value = *reg;
+    *reg = value.wrapping_sub(1);
+    memory.inc_dec_access(value, 1);
     
     // 2 cycles - 1 reading opcode, 1 internal operation
-    return 1;
+    return 0;
 }
\ No newline at end of file
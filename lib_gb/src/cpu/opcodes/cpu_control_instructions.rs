@@ -1,4 +1,4 @@
-use crate::{cpu::gb_cpu::GbCpu, utils::memory_registers::{IE_REGISTER_ADDRESS, JOYP_REGISTER_ADDRESS}};
+use crate::{cpu::gb_cpu::GbCpu, utils::{memory_registers::{IE_REGISTER_ADDRESS, JOYP_REGISTER_ADDRESS, KEY1_REGISTER_ADDRESS}, bit_masks::BIT_0_MASK}};
 use crate::cpu::flag::Flag;
 use crate::mmu::memory::Memory;
 
@@ -29,6 +29,11 @@ pub fn halt(cpu:&mut GbCpu)->u8{
 }
 
 pub fn stop(cpu:&mut GbCpu, memory: &mut impl Memory)->u8{
+    // On the cgb a stop after preparing through KEY1 switches the cpu speed, the switch itself is done by the mmu
+    if cpu.cgb_mode && memory.read(KEY1_REGISTER_ADDRESS, 0) & BIT_0_MASK != 0{
+        cpu.double_speed = !cpu.double_speed;
+        return 0;
+    }
     if (memory.read(IE_REGISTER_ADDRESS, 0) & 0b11111 == 0) && (memory.read(JOYP_REGISTER_ADDRESS, 0) & 0b1111 == 0){
         cpu.stop = true;
     }
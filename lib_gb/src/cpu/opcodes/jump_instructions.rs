@@ -15,8 +15,8 @@ pub fn call(cpu:&mut GbCpu, memory:&mut impl Memory, opcode:u32)->u8{
     push_pc(cpu, memory);
     cpu.program_counter = address_to_jump;
     
-    // 6 cycles - 3 reading opcode, 2 writing pc to sp address, 1 internal operation
-    return 1;
+    // 6 cycles - 3 reading opcode, 1 internal operation, 2 writing pc to sp address
+    return 0;
 }
 
 fn call_if_true(cpu:&mut GbCpu, memory:&mut impl Memory, opcode:u32, flag:bool)->u8{
@@ -90,8 +90,8 @@ pub fn rst(cpu:&mut GbCpu, memory:&mut impl Memory, opcode:u8)->u8{
     push_pc(cpu, memory);
     cpu.program_counter = value as u16;
     
-    // 4 cycles - 1 reading opcode, 2 writing pc to sp address, 1 internal operation
-    return 1;
+    // 4 cycles - 1 reading opcode, 1 internal operation, 2 writing pc to sp address
+    return 0;
 }
 
 pub fn reti(cpu:&mut GbCpu, memory:&mut impl Memory)->u8{
@@ -61,8 +61,8 @@ pub fn push(cpu:&mut GbCpu, memory:&mut impl Memory, opcode:u8)->u8{
 
     opcodes_utils::push(cpu, memory, value);
 
-    // 4 cycles - 1 reading opcode, 2 writing to sp address and sp+1 address, 1 internal operation
-    return 1;
+    // 4 cycles - 1 reading opcode, 1 internal operation, 2 writing to sp address and sp+1 address
+    return 0;
 }
 
 //load into hl sp + rr
@@ -144,7 +144,7 @@ pub fn ldi_hl_a(cpu: &mut GbCpu, memory:&mut impl Memory)->u8{
 
 //load into register A the value in address HL and then increment register HL value
 pub fn ldi_a_hl(cpu: &mut GbCpu, memory:&mut impl Memory)->u8{
-    *cpu.af.high() = memory.read(*cpu.hl.value(), 1);
+    *cpu.af.high() = memory.read_with_inc_dec(*cpu.hl.value(), 1);
     cpu.inc_hl();
     
     // 2 cycles - 1 reading opcode, 1 reading hl address
@@ -162,7 +162,7 @@ pub fn ldd_hl_a(cpu: &mut GbCpu, memory:&mut impl Memory)->u8{
 
 //load into register A the value in address HL and then decrement register HL value
 pub fn ldd_a_hl(cpu: &mut GbCpu, memory:&mut impl Memory)->u8{
-    *cpu.af.high() = memory.read(*cpu.hl.value(), 1);
+    *cpu.af.high() = memory.read_with_inc_dec(*cpu.hl.value(), 1);
     cpu.dec_hl();
     
     // 2 cycles - 1 reading opcode, 1 reading hl address
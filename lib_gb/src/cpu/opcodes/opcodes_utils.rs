@@ -69,18 +69,20 @@ pub fn get_reg_two_rows(cpu: &mut GbCpu, mut reg:u8)->&mut u8{
     }
 }
 
+// Takes 3 m_cycles - 1 decrementing sp, 2 writing the value
 pub fn push(cpu:&mut GbCpu,memory:&mut impl Memory, value:u16){
     let high = ((value & 0xFF00) >> 8) as u8;
     let low = (value & 0xFF) as u8;
     
+    memory.inc_dec_access(cpu.stack_pointer, 1);
     memory.write(cpu.stack_pointer-1, high, 1);
     memory.write(cpu.stack_pointer-2, low, 1);
     cpu.stack_pointer-=2;
 }
 
 pub fn pop(cpu:&mut GbCpu,memory:&mut impl Memory)->u16{
-    let mut value:u16 = memory.read(cpu.stack_pointer, 1) as u16;
-    value |= (memory.read(cpu.stack_pointer+1, 1) as u16)<<8;
+    let mut value:u16 = memory.read_with_inc_dec(cpu.stack_pointer, 1) as u16;
+    value |= (memory.read_with_inc_dec(cpu.stack_pointer+1, 1) as u16)<<8;
     cpu.stack_pointer+=2;
     
     return value;
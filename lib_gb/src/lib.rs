@@ -6,5 +6,6 @@ pub mod keypad;
 pub mod apu;
 pub mod timer;
 pub mod utils;
+pub mod assembler;
 
 pub use utils::GB_FREQUENCY;
\ No newline at end of file
@@ -0,0 +1,202 @@
+// Records hardware events with their M-cycle timestamps and writes them in the Chrome Trace Event JSON format
+// (viewable in Perfetto or chrome://tracing), each hardware unit gets its own track.
+
+use std::io::Write;
+use crate::ppu::ppu_state::PpuState;
+
+// The trace timestamps are in microseconds
+const M_CYCLES_PER_MICROSECOND:f64 = 1.048576;
+const TIMER_INTERRUPT_BIT:u8 = 2;
+const INTERRUPT_NAMES:[&str;5] = ["VBlank", "STAT", "Timer", "Serial", "Joypad"];
+const TRACK_NAMES:[&str;8] = ["Frames", "PPU mode", "LY", "Interrupts", "OAM DMA", "Timer", "APU", "MBC"];
+
+#[derive(Clone, Copy)]
+enum Track{
+    Frames = 0,
+    PpuMode,
+    Ly,
+    Interrupts,
+    Dma,
+    Timer,
+    Apu,
+    Mbc
+}
+
+enum Phase{
+    Complete(u64),
+    Instant,
+    Counter(u32)
+}
+
+struct TraceEvent{
+    name:String,
+    track:Track,
+    phase:Phase,
+    m_cycle:u64
+}
+
+pub struct EventTracer{
+    first_frame:u32,
+    last_frame:u32,
+    current_frame:u32,
+    m_cycles:u64,
+    events:Vec<TraceEvent>,
+
+    frame_start:u64,
+    ppu_state:Option<(PpuState, u64)>,
+    ly:Option<u8>,
+    interrupt_flag:u8,
+    dma_start:Option<u64>
+}
+
+impl EventTracer{
+    // Records the frames in the inclusive range [first_frame, last_frame], counting from the tracer creation
+    pub fn new(first_frame:u32, last_frame:u32)->Self{
+        Self{
+            first_frame, last_frame, current_frame:0, m_cycles:0, events:Vec::new(),
+            frame_start:0, ppu_state:None, ly:None, interrupt_flag:0, dma_start:None
+        }
+    }
+
+    pub fn is_recording(&self)->bool{
+        self.current_frame >= self.first_frame && self.current_frame <= self.last_frame
+    }
+
+    pub fn is_finished(&self)->bool{
+        self.current_frame > self.last_frame
+    }
+
+    // Called after the hardware has been cycled with the state it ended in
+    pub fn observe(&mut self, m_cycles:u32, ppu_state:PpuState, ly:u8, interrupt_flag:u8, dma_active:bool){
+        self.m_cycles += m_cycles as u64;
+        if !self.is_recording(){
+            self.ppu_state = None;
+            self.ly = None;
+            self.dma_start = None;
+            self.interrupt_flag = interrupt_flag;
+            return;
+        }
+
+        match self.ppu_state{
+            Some((state, start)) if state != ppu_state=>{
+                self.push(Self::get_ppu_state_name(state), Track::PpuMode, Phase::Complete(self.m_cycles - start), start);
+                self.ppu_state = Some((ppu_state, self.m_cycles));
+            }
+            None=>self.ppu_state = Some((ppu_state, self.m_cycles)),
+            _=>{}
+        }
+
+        if self.ly != Some(ly){
+            self.ly = Some(ly);
+            self.push(String::from("LY"), Track::Ly, Phase::Counter(ly as u32), self.m_cycles);
+        }
+
+        let requested_interrupts = interrupt_flag & !self.interrupt_flag;
+        for (bit, name) in INTERRUPT_NAMES.iter().enumerate(){
+            if requested_interrupts & (1 << bit) != 0{
+                self.push(format!("{} requested", name), Track::Interrupts, Phase::Instant, self.m_cycles);
+                if bit as u8 == TIMER_INTERRUPT_BIT{
+                    self.push(String::from("TIMA overflow"), Track::Timer, Phase::Instant, self.m_cycles);
+                }
+            }
+        }
+        self.interrupt_flag = interrupt_flag;
+
+        match (self.dma_start, dma_active){
+            // The dma started before the recording
+            (None, true)=>self.dma_start = Some(self.m_cycles),
+            (Some(start), false)=>{
+                self.push(String::from("OAM DMA"), Track::Dma, Phase::Complete(self.m_cycles - start), start);
+                self.dma_start = None;
+            }
+            _=>{}
+        }
+    }
+
+    // Writes to IF by the program are not interrupt requests from the hardware
+    pub fn sync_interrupt_flag(&mut self, interrupt_flag:u8){
+        self.interrupt_flag = interrupt_flag;
+    }
+
+    pub fn trace_interrupt_dispatch(&mut self, vector:u16){
+        let index = ((vector - 0x40) / 8) as usize;
+        if self.is_recording() && index < INTERRUPT_NAMES.len(){
+            self.push(format!("{} dispatched", INTERRUPT_NAMES[index]), Track::Interrupts, Phase::Instant, self.m_cycles);
+        }
+    }
+
+    pub fn trace_dma_start(&mut self){
+        if self.is_recording(){
+            if let Some(start) = self.dma_start{
+                self.push(String::from("OAM DMA (restarted)"), Track::Dma, Phase::Complete(self.m_cycles - start), start);
+            }
+            self.dma_start = Some(self.m_cycles);
+        }
+    }
+
+    pub fn trace_apu_trigger(&mut self, channel:u8){
+        if self.is_recording(){
+            self.push(format!("CH{} trigger", channel), Track::Apu, Phase::Instant, self.m_cycles);
+        }
+    }
+
+    pub fn trace_rom_bank_switch(&mut self, bank:u16){
+        if self.is_recording(){
+            self.push(format!("ROM bank {}", bank), Track::Mbc, Phase::Instant, self.m_cycles);
+        }
+    }
+
+    pub fn trace_ram_bank_switch(&mut self, bank:u16){
+        if self.is_recording(){
+            self.push(format!("RAM bank {}", bank), Track::Mbc, Phase::Instant, self.m_cycles);
+        }
+    }
+
+    pub fn end_frame(&mut self){
+        if self.is_recording(){
+            self.push(format!("Frame {}", self.current_frame), Track::Frames, Phase::Complete(self.m_cycles - self.frame_start), self.frame_start);
+        }
+        self.current_frame += 1;
+        self.frame_start = self.m_cycles;
+    }
+
+    pub fn write_chrome_trace(&self, writer:&mut impl Write)->std::io::Result<()>{
+        writeln!(writer, "{{\"displayTimeUnit\":\"ns\",\"traceEvents\":[")?;
+        for (index, name) in TRACK_NAMES.iter().enumerate(){
+            writeln!(writer, "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":0,\"tid\":{},\"args\":{{\"name\":\"{}\"}}}},", index, name)?;
+            writeln!(writer, "{{\"name\":\"thread_sort_index\",\"ph\":\"M\",\"pid\":0,\"tid\":{},\"args\":{{\"sort_index\":{}}}}},", index, index)?;
+        }
+        writeln!(writer, "{{\"name\":\"process_name\",\"ph\":\"M\",\"pid\":0,\"args\":{{\"name\":\"GameBoy\"}}}}")?;
+        for event in &self.events{
+            let timestamp = Self::to_microseconds(event.m_cycle);
+            write!(writer, ",{{\"name\":\"{}\",\"pid\":0,\"tid\":{},\"ts\":{:.3},", event.name, event.track as u8, timestamp)?;
+            match event.phase{
+                Phase::Complete(duration)=>write!(writer, "\"ph\":\"X\",\"dur\":{:.3},\"args\":{{\"m_cycle\":{},\"m_cycles\":{}}}",
+                    Self::to_microseconds(duration), event.m_cycle, duration)?,
+                Phase::Instant=>write!(writer, "\"ph\":\"i\",\"s\":\"t\",\"args\":{{\"m_cycle\":{}}}", event.m_cycle)?,
+                Phase::Counter(value)=>write!(writer, "\"ph\":\"C\",\"args\":{{\"{}\":{}}}", event.name, value)?
+            }
+            writeln!(writer, "}}")?;
+        }
+        writeln!(writer, "]}}")?;
+
+        return Ok(());
+    }
+
+    fn push(&mut self, name:String, track:Track, phase:Phase, m_cycle:u64){
+        self.events.push(TraceEvent{name, track, phase, m_cycle});
+    }
+
+    fn to_microseconds(m_cycles:u64)->f64{
+        m_cycles as f64 / M_CYCLES_PER_MICROSECOND
+    }
+
+    fn get_ppu_state_name(state:PpuState)->String{
+        String::from(match state{
+            PpuState::Hblank=>"Mode 0 (HBlank)",
+            PpuState::Vblank=>"Mode 1 (VBlank)",
+            PpuState::OamSearch=>"Mode 2 (OAM search)",
+            PpuState::PixelTransfer=>"Mode 3 (Pixel transfer)"
+        })
+    }
+}
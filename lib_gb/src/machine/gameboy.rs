@@ -1,9 +1,12 @@
 use crate::{
     apu::{audio_device::AudioDevice, gb_apu::GbApu},
     cpu::gb_cpu::GbCpu,
-    mmu::{carts::mbc::Mbc, gb_mmu::{GbMmu, BOOT_ROM_SIZE}, memory::Memory}, 
-    ppu::gfx_device::GfxDevice, keypad::joypad_provider::JoypadProvider
+    mmu::{carts::mbc::Mbc, gb_mmu::{GbMmu, BOOT_ROM_SIZE, HRAM_SIZE}, memory::MemoryBank, io_bus::IO_PORTS_SIZE, code_data_logger::CodeDataLogger}, 
+    ppu::{gfx_device::GfxDevice, gb_ppu::{GbPpu, OAM_MEMORY_SIZE}, colors::DmgPalettes, layer_toggles::LayerToggles, hd_pack::{HdPack, HdTilesDumper, HdTileKey}, post_processing::PostProcessing, screenshot::Screenshot, ppu_register_updater::set_dmg_palettes}, keypad::joypad_provider::JoypadProvider
 };
+use super::profiler::{Profiler, is_call_opcode, is_return_opcode};
+use super::event_tracer::EventTracer;
+use super::mode::Mode;
 use std::boxed::Box;
 
 //CPU frequrncy: 4,194,304 / 59.727~ / 4 == 70224 / 4
@@ -11,31 +14,48 @@ pub const CYCLES_PER_FRAME:u32 = 17556;
 
 pub struct GameBoy<'a, JP: JoypadProvider, AD:AudioDevice, GFX:GfxDevice> {
     cpu: GbCpu,
-    mmu: GbMmu::<'a, AD, GFX, JP>
+    mmu: GbMmu::<'a, AD, GFX, JP>,
+    profiler: Option<Profiler>
 }
 
 impl<'a, JP:JoypadProvider, AD:AudioDevice, GFX:GfxDevice> GameBoy<'a, JP, AD, GFX>{
 
+    // The boot rom is a dmg boot rom so the gameboy always runs in dmg mode
     pub fn new_with_bootrom(mbc:&'a mut Box<dyn Mbc>,joypad_provider:JP, audio_device:AD, gfx_device:GFX, boot_rom:[u8;BOOT_ROM_SIZE])->GameBoy<JP, AD, GFX>{
         GameBoy{
             cpu:GbCpu::default(),
-            mmu:GbMmu::new_with_bootrom(mbc, boot_rom, GbApu::new(audio_device), gfx_device, joypad_provider),
+            mmu:GbMmu::new_with_bootrom(mbc, boot_rom, GbApu::new(audio_device), gfx_device, joypad_provider, Mode::DMG),
+            profiler:None
         }
     }
 
     pub fn new(mbc:&'a mut Box<dyn Mbc>,joypad_provider:JP, audio_device:AD, gfx_device:GFX)->GameBoy<JP, AD, GFX>{
+        let mode = Mode::from_cartridge(&**mbc);
         let mut cpu = GbCpu::default();
         //Values after the bootrom
-        *cpu.af.value() = 0x190;
-        *cpu.bc.value() = 0x13;
-        *cpu.de.value() = 0xD8;
-        *cpu.hl.value() = 0x14D;
+        match mode{
+            Mode::DMG=>{
+                *cpu.af.value() = 0x190;
+                *cpu.bc.value() = 0x13;
+                *cpu.de.value() = 0xD8;
+                *cpu.hl.value() = 0x14D;
+            }
+            // Games detect the cgb by A being 0x11
+            Mode::CGB=>{
+                *cpu.af.value() = 0x1180;
+                *cpu.bc.value() = 0x0;
+                *cpu.de.value() = 0xFF56;
+                *cpu.hl.value() = 0xD;
+                cpu.cgb_mode = true;
+            }
+        }
         cpu.stack_pointer = 0xFFFE;
         cpu.program_counter = 0x100;
 
         GameBoy{
             cpu:cpu,
-            mmu:GbMmu::new(mbc, GbApu::new(audio_device), gfx_device, joypad_provider)
+            mmu:GbMmu::new(mbc, GbApu::new(audio_device), gfx_device, joypad_provider, mode),
+            profiler:None
         }
     }
 
@@ -44,6 +64,11 @@ impl<'a, JP:JoypadProvider, AD:AudioDevice, GFX:GfxDevice> GameBoy<'a, JP, AD, G
             self.mmu.poll_joypad_state();
 
             //CPU
+            let pc = self.cpu.program_counter;
+            let sp = self.cpu.stack_pointer;
+            let m_cycles_before = self.mmu.m_cycle_counter;
+            // Peeking before the execution since the opcode could be overwritten and peek has no side effects
+            let opcode = if self.profiler.is_some() {self.mmu.peek(pc)} else {0};
             let mut cpu_cycles_passed = 1;
             if !self.cpu.halt{
                 cpu_cycles_passed = self.execute_opcode();
@@ -51,16 +76,33 @@ impl<'a, JP:JoypadProvider, AD:AudioDevice, GFX:GfxDevice> GameBoy<'a, JP, AD, G
             if cpu_cycles_passed != 0{
                 self.mmu.cycle(cpu_cycles_passed);
             }
+            if self.cpu.double_speed != self.mmu.is_double_speed(){
+                self.mmu.switch_speed();
+            }
+            if self.profiler.is_some(){
+                self.profile_opcode(pc, sp, opcode, self.mmu.m_cycle_counter - m_cycles_before);
+            }
             
             //interrupts
+            let return_address = self.cpu.program_counter;
+            let m_cycles_before = self.mmu.m_cycle_counter;
             let interrupt_request = self.mmu.handle_interrupts(self.cpu.mie);
             let interrupt_cycles = self.cpu.execute_interrupt_request(&mut self.mmu, interrupt_request);
             if interrupt_cycles != 0{
+                if let Some(tracer) = self.mmu.get_event_tracer(){
+                    tracer.trace_interrupt_dispatch(self.cpu.program_counter);
+                }
                 self.mmu.cycle(interrupt_cycles);
+                if self.profiler.is_some(){
+                    self.profile_interrupt(return_address, self.mmu.m_cycle_counter - m_cycles_before);
+                }
             }
         }
 
         self.mmu.m_cycle_counter = 0;
+        if let Some(tracer) = self.mmu.get_event_tracer(){
+            tracer.end_frame();
+        }
     }
 
     fn execute_opcode(&mut self)->u8{
@@ -72,10 +114,152 @@ impl<'a, JP:JoypadProvider, AD:AudioDevice, GFX:GfxDevice> GameBoy<'a, JP, AD, G
             {*self.cpu.de.high()}, *self.cpu.de.low(),
             {*self.cpu.hl.high()}, *self.cpu.hl.low(),
             self.cpu.stack_pointer, pc,
-            self.mmu.read(pc,0), self.mmu.read(pc+1,0), self.mmu.read(pc+2,0), self.mmu.read(pc+3,0)
+            self.mmu.peek(pc), self.mmu.peek(pc+1), self.mmu.peek(pc+2), self.mmu.peek(pc+3)
         );
     
         self.cpu.run_opcode(&mut self.mmu)
     }
-}
 
+    // Debug memory access which doesnt affect the emulation timing, see GbMmu::peek
+    pub fn peek(&mut self, address:u16)->u8{
+        self.mmu.peek(address)
+    }
+
+    pub fn poke(&mut self, address:u16, value:u8){
+        self.mmu.poke(address, value);
+    }
+
+    pub fn peek_bank(&mut self, bank:MemoryBank, address:u16)->u8{
+        self.mmu.peek_bank(bank, address)
+    }
+
+    pub fn poke_bank(&mut self, bank:MemoryBank, address:u16, value:u8){
+        self.mmu.poke_bank(bank, address, value);
+    }
+
+    pub fn dump_oam(&self)->[u8;OAM_MEMORY_SIZE]{
+        self.mmu.dump_oam()
+    }
+
+    pub fn dump_hram(&self)->[u8;HRAM_SIZE]{
+        self.mmu.dump_hram()
+    }
+
+    pub fn dump_io(&mut self)->[u8;IO_PORTS_SIZE]{
+        self.mmu.dump_io()
+    }
+
+    // AF, BC, DE and HL
+    pub fn dump_registers(&mut self)->[u16;4]{
+        [*self.cpu.af.value(), *self.cpu.bc.value(), *self.cpu.de.value(), *self.cpu.hl.value()]
+    }
+
+    // For the vram debug views, see ppu::debug_views
+    pub fn get_ppu(&self)->&GbPpu<GFX>{
+        &self.mmu.io_bus.ppu
+    }
+
+    pub fn get_layer_toggles(&self)->LayerToggles{
+        self.mmu.io_bus.ppu.layer_toggles
+    }
+
+    pub fn set_layer_toggles(&mut self, toggles:LayerToggles){
+        self.mmu.io_bus.ppu.layer_toggles = toggles;
+    }
+
+    // An enhancement which draws all the sprites on a line without changing the emulated timing
+    pub fn set_unlimited_sprites_per_line(&mut self, unlimited:bool){
+        self.mmu.io_bus.ppu.unlimited_sprites_per_line = unlimited;
+    }
+
+    pub fn is_unlimited_sprites_per_line(&self)->bool{
+        self.mmu.io_bus.ppu.unlimited_sprites_per_line
+    }
+
+    // The filters applied to every frame before it is passed to the GfxDevice, can be changed while the game is running
+    pub fn get_post_processing(&mut self)->&mut PostProcessing{
+        &mut self.mmu.io_bus.ppu.post_processing
+    }
+
+    // The upscaled frames are passed to GfxDevice::swap_hd_buffer
+    pub fn set_hd_pack(&mut self, pack:Option<HdPack>){
+        self.mmu.io_bus.ppu.set_hd_pack(pack);
+    }
+
+    pub fn set_hd_tiles_dumper(&mut self, dumper:Option<HdTilesDumper>){
+        self.mmu.io_bus.ppu.set_hd_tiles_dumper(dumper);
+    }
+
+    // The tiles seen for the first time since the last call, empty without a dumper
+    pub fn take_hd_dumped_tiles(&mut self)->Vec<(HdTileKey, Vec<u8>)>{
+        self.mmu.io_bus.ppu.get_hd_tiles_dumper().map_or(Vec::new(), |dumper|dumper.take_new_tiles())
+    }
+
+    // The screenshot is captured when the current frame is finished
+    pub fn request_screenshot(&mut self){
+        self.mmu.io_bus.ppu.request_screenshot();
+    }
+
+    pub fn take_screenshot(&mut self)->Option<Screenshot>{
+        self.mmu.io_bus.ppu.take_screenshot()
+    }
+
+    // Replaces the colors of the dmg shades, can be called while the game is running (has no effect in cgb mode)
+    pub fn set_dmg_palettes(&mut self, palettes:DmgPalettes){
+        set_dmg_palettes(&mut self.mmu.io_bus.ppu, palettes);
+    }
+
+    pub fn set_code_data_logger(&mut self, logger:CodeDataLogger){
+        self.mmu.set_code_data_logger(logger);
+    }
+
+    pub fn take_code_data_logger(&mut self)->Option<CodeDataLogger>{
+        self.mmu.take_code_data_logger()
+    }
+
+    pub fn set_event_tracer(&mut self, tracer:EventTracer){
+        self.mmu.set_event_tracer(tracer);
+    }
+
+    pub fn take_event_tracer(&mut self)->Option<EventTracer>{
+        self.mmu.take_event_tracer()
+    }
+
+    pub fn set_profiler(&mut self, profiler:Profiler){
+        self.profiler = Some(profiler);
+    }
+
+    pub fn take_profiler(&mut self)->Option<Profiler>{
+        self.profiler.take()
+    }
+
+    fn profile_opcode(&mut self, pc:u16, sp:u16, opcode:u8, m_cycles:u32){
+        let bank = self.get_bank(pc);
+        let next_pc = self.cpu.program_counter;
+        let next_bank = self.get_bank(next_pc);
+        let profiler = self.profiler.as_mut().unwrap();
+        profiler.record(bank, pc, m_cycles);
+        // The stack pointer tells whether a conditional call or return was taken
+        if is_call_opcode(opcode) && self.cpu.stack_pointer == sp.wrapping_sub(2){
+            profiler.enter_routine(bank, pc, next_bank, next_pc);
+        }
+        else if is_return_opcode(opcode) && self.cpu.stack_pointer == sp.wrapping_add(2){
+            profiler.exit_routine();
+        }
+    }
+
+    fn profile_interrupt(&mut self, return_address:u16, m_cycles:u32){
+        let return_bank = self.get_bank(return_address);
+        let vector = self.cpu.program_counter;
+        let profiler = self.profiler.as_mut().unwrap();
+        profiler.enter_routine(return_bank, return_address, 0, vector);
+        profiler.record(0, vector, m_cycles);
+    }
+
+    fn get_bank(&self, address:u16)->u16{
+        return match address{
+            0x4000..=0x7FFF=>self.mmu.get_current_rom_bank(),
+            _=>0
+        };
+    }
+}
\ No newline at end of file
@@ -1,2 +1,5 @@
 pub mod gameboy;
-pub mod mbc_initializer;
\ No newline at end of file
+pub mod mbc_initializer;
+pub mod mode;
+pub mod profiler;
+pub mod event_tracer;
\ No newline at end of file
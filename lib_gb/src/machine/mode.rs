@@ -0,0 +1,17 @@
+use crate::mmu::carts::mbc::Mbc;
+
+const CGB_FLAG_ADDRESS:u16 = 0x143;
+const CGB_FLAG_MASK:u8 = 0x80;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Mode{
+    DMG,
+    CGB
+}
+
+impl Mode{
+    // The cgb flag is set both for cgb only games (0xC0) and for games that support the dmg too (0x80)
+    pub fn from_cartridge(mbc:&dyn Mbc)->Self{
+        return if mbc.read_bank0(CGB_FLAG_ADDRESS) & CGB_FLAG_MASK != 0 {Mode::CGB} else {Mode::DMG};
+    }
+}
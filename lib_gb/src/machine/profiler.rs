@@ -0,0 +1,209 @@
+// Attributes the executed M-cycles to the rom bank:address of each instruction and builds a call tree out of the
+// CALL/RST/RET instructions and the interrupts dispatches.
+// Frames are named by the nearest global label from a .sym file (the rgbds format: "BB:AAAA Label"),
+// local labels are ignored so the cycles are aggregated per routine.
+// The result is written in the folded stacks format used by flamegraph tools ("Main;UpdateSprites;CopyOam 1234").
+
+use std::collections::HashMap;
+use std::io::Write;
+
+const MAX_CALL_DEPTH:usize = 256;
+const ROOT_NODE_INDEX:usize = 0;
+const ROOT_FRAME_NAME:&str = "(root)";
+
+pub struct SymbolTable{
+    names:Vec<String>,
+    // sorted by address for each bank
+    banks:HashMap<u16, Vec<(u16, usize)>>
+}
+
+impl SymbolTable{
+    pub fn empty()->Self{
+        Self{names:Vec::new(), banks:HashMap::new()}
+    }
+
+    pub fn parse(content:&str)->Self{
+        let mut table = Self::empty();
+        for line in content.lines(){
+            let line = match line.find(';'){
+                Some(index)=>&line[..index],
+                None=>line
+            };
+            if line.trim().is_empty(){
+                continue;
+            }
+            let (bank, address, name) = match Self::parse_line(line){
+                Some(symbol)=>symbol,
+                None=>{
+                    log::warn!("bad symbol line: {}", line);
+                    continue;
+                }
+            };
+            if name.contains('.'){
+                continue;
+            }
+            table.banks.entry(bank).or_default().push((address, table.names.len()));
+            table.names.push(String::from(name));
+        }
+        for symbols in table.banks.values_mut(){
+            symbols.sort_by_key(|s|s.0);
+        }
+
+        return table;
+    }
+
+    fn parse_line(line:&str)->Option<(u16, u16, &str)>{
+        let mut parts = line.split_whitespace();
+        let (bank, address) = parts.next()?.split_once(':')?;
+        let name = parts.next()?;
+
+        return Some((u16::from_str_radix(bank, 16).ok()?, u16::from_str_radix(address, 16).ok()?, name));
+    }
+
+    // The nearest label at or below the address in the same memory region (rom0, romx, vram/sram, wram/hram)
+    pub fn find(&self, bank:u16, address:u16)->Option<usize>{
+        let symbols = self.banks.get(&bank)?;
+        let index = symbols.partition_point(|s|s.0 <= address);
+        if index == 0{
+            return None;
+        }
+        let (symbol_address, symbol_index) = symbols[index - 1];
+        if symbol_address >> 14 != address >> 14{
+            return None;
+        }
+
+        return Some(symbol_index);
+    }
+
+    pub fn get_name(&self, index:usize)->&str{
+        &self.names[index]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Frame{
+    Root,
+    Label(usize),
+    Address(u16, u16)
+}
+
+struct CallNode{
+    frame:Frame,
+    parent:usize,
+    children:HashMap<Frame, usize>,
+    m_cycles:u64
+}
+
+pub struct Profiler{
+    symbols:SymbolTable,
+    nodes:Vec<CallNode>,
+    call_stack:Vec<usize>,
+    current_node:usize,
+    address_m_cycles:HashMap<(u16, u16), u64>
+}
+
+impl Profiler{
+    pub fn new(symbols:SymbolTable)->Self{
+        let root = CallNode{frame:Frame::Root, parent:ROOT_NODE_INDEX, children:HashMap::new(), m_cycles:0};
+        Self{symbols, nodes:vec![root], call_stack:Vec::new(), current_node:ROOT_NODE_INDEX, address_m_cycles:HashMap::new()}
+    }
+
+    pub fn record(&mut self, bank:u16, address:u16, m_cycles:u32){
+        *self.address_m_cycles.entry((bank, address)).or_insert(0) += m_cycles as u64;
+        let node = self.get_leaf_node(bank, address);
+        self.nodes[node].m_cycles += m_cycles as u64;
+    }
+
+    // Called for taken CALL and RST instructions and for interrupts dispatches
+    pub fn enter_routine(&mut self, call_bank:u16, call_address:u16, target_bank:u16, target_address:u16){
+        if self.call_stack.len() >= MAX_CALL_DEPTH{
+            // Probably a routine that never returns (manipulates the stack), start over from the root
+            log::warn!("profiler call stack is too deep, resetting it");
+            self.call_stack.clear();
+            self.current_node = ROOT_NODE_INDEX;
+        }
+        let call_site_node = self.get_leaf_node(call_bank, call_address);
+        let frame = match self.symbols.find(target_bank, target_address){
+            Some(index)=>Frame::Label(index),
+            None=>Frame::Address(target_bank, target_address)
+        };
+        self.call_stack.push(self.current_node);
+        self.current_node = self.get_child(call_site_node, frame);
+    }
+
+    // Called for taken RET and RETI instructions
+    pub fn exit_routine(&mut self){
+        // Returning from a routine that was called before the profiling started keeps the frame at the root
+        self.current_node = self.call_stack.pop().unwrap_or(ROOT_NODE_INDEX);
+    }
+
+    pub fn get_address_m_cycles(&self, bank:u16, address:u16)->u64{
+        *self.address_m_cycles.get(&(bank, address)).unwrap_or(&0)
+    }
+
+    // Each line is a call stack and the M-cycles spent in its last frame (exclusive), the tools sum them to the inclusive tree
+    pub fn write_folded_stacks(&self, writer:&mut impl Write)->std::io::Result<()>{
+        let mut lines = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate(){
+            if node.m_cycles != 0{
+                lines.push(format!("{} {}", self.get_stack_name(index), node.m_cycles));
+            }
+        }
+        lines.sort();
+        for line in lines{
+            writeln!(writer, "{}", line)?;
+        }
+
+        return Ok(());
+    }
+
+    fn get_leaf_node(&mut self, bank:u16, address:u16)->usize{
+        return match self.symbols.find(bank, address){
+            Some(index) if self.nodes[self.current_node].frame != Frame::Label(index)=>self.get_child(self.current_node, Frame::Label(index)),
+            // unlabeled code is attributed to the routine that called it
+            _=>self.current_node
+        };
+    }
+
+    fn get_child(&mut self, parent:usize, frame:Frame)->usize{
+        if let Some(child) = self.nodes[parent].children.get(&frame){
+            return *child;
+        }
+        let child = self.nodes.len();
+        self.nodes.push(CallNode{frame, parent, children:HashMap::new(), m_cycles:0});
+        self.nodes[parent].children.insert(frame, child);
+
+        return child;
+    }
+
+    fn get_stack_name(&self, mut index:usize)->String{
+        if index == ROOT_NODE_INDEX{
+            return String::from(ROOT_FRAME_NAME);
+        }
+        let mut frames = Vec::new();
+        while index != ROOT_NODE_INDEX{
+            frames.push(match self.nodes[index].frame{
+                Frame::Label(symbol)=>String::from(self.symbols.get_name(symbol)),
+                Frame::Address(bank, address)=>format!("{:02X}:{:04X}", bank, address),
+                Frame::Root=>String::from(ROOT_FRAME_NAME)
+            });
+            index = self.nodes[index].parent;
+        }
+        frames.reverse();
+
+        return frames.join(";");
+    }
+}
+
+pub fn is_call_opcode(opcode:u8)->bool{
+    match opcode{
+        0xCD | 0xC4 | 0xCC | 0xD4 | 0xDC=>true,
+        // RST
+        0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF=>true,
+        _=>false
+    }
+}
+
+pub fn is_return_opcode(opcode:u8)->bool{
+    matches!(opcode, 0xC9 | 0xD9 | 0xC0 | 0xC8 | 0xD0 | 0xD8)
+}
@@ -32,6 +32,9 @@ pub fn init_ram(ram_reg:u8, external_ram:Option<Vec<u8>>)->Vec<u8>{
 
 pub trait Mbc{
     fn get_ram(&self)->&[u8];
+    fn get_ram_mut(&mut self)->&mut [u8];
+    fn get_rom(&self)->&[u8];
+    fn get_rom_mut(&mut self)->&mut [u8];
     fn has_battery(&self)->bool;
 
     fn read_bank0(&self, address:u16)->u8;
@@ -39,4 +42,6 @@ pub trait Mbc{
     fn write_rom(&mut self, address:u16, value:u8);
     fn read_external_ram(&self, address:u16)->u8;
     fn write_external_ram(&mut self, address:u16, value:u8);
+    fn get_current_rom_bank(&self)->u16;
+    fn get_current_ram_bank(&self)->u16;
 }
\ No newline at end of file
@@ -17,6 +17,18 @@ impl Mbc for Mbc1{
         self.ram.as_slice()
     }
 
+    fn get_ram_mut(&mut self)->&mut [u8]{
+        self.ram.as_mut_slice()
+    }
+
+    fn get_rom(&self)->&[u8]{
+        self.program.as_slice()
+    }
+
+    fn get_rom_mut(&mut self)->&mut [u8]{
+        self.program.as_mut_slice()
+    }
+
     fn has_battery(&self) ->bool {
         self.battery
     }
@@ -26,7 +38,7 @@ impl Mbc for Mbc1{
     }
 
     fn read_current_bank(&self, address:u16)->u8{
-        let bank:u16 = self.get_current_rom_bank() as u16;
+        let bank:u16 = self.get_current_rom_bank();
         return self.program[ROM_BANK_SIZE as usize * bank as usize + address as usize];
     }
 
@@ -44,36 +56,18 @@ impl Mbc for Mbc1{
         if self.ram.is_empty(){
             return 0xFF;
         }
-        let bank:u16 = self.get_current_ram_bank() as u16;
+        let bank:u16 = self.get_current_ram_bank();
         return self.ram[(bank * RAM_BANK_SIZE + address) as usize];
     }
 
     fn write_external_ram(&mut self, address: u16, value: u8){
         if self.ram.len() > 0{
-            let bank:u16 = self.get_current_ram_bank() as u16;
+            let bank:u16 = self.get_current_ram_bank();
             self.ram[(bank * RAM_BANK_SIZE + address) as usize] = value;   
         }
     }
-}
-
-impl Mbc1{
-    pub fn new(v:Vec<u8>, battery:bool, ram:Option<Vec<u8>>)->Self{
-        let mut mbc = Mbc1{
-            program:v,
-            ram:Vec::new(),
-            register0:0,
-            register1:0,
-            register2:0,
-            register3:0,
-            battery:battery
-        };
-
-        mbc.ram = init_ram(mbc.program[MBC_RAM_SIZE_LOCATION], ram);
-
-        return mbc;
-    }
 
-    fn get_current_rom_bank(&self)->u8{
+    fn get_current_rom_bank(&self)->u16{
         let mut bank = self.register1 & 0b11111;
 
         //banks 0x0 0x20 0x40 0x60 are not avaalible through this method
@@ -84,14 +78,32 @@ impl Mbc1{
             bank |= (self.register2 & 0b11)<<5;
         }
 
-        return bank;
+        return bank as u16;
     }
 
-    fn get_current_ram_bank(&self)->u8{
+    fn get_current_ram_bank(&self)->u16{
         if self.register3 == 1{
-            return self.register2 & 0b11;
+            return (self.register2 & 0b11) as u16;
         }
 
         return 0;
     }
+}
+
+impl Mbc1{
+    pub fn new(v:Vec<u8>, battery:bool, ram:Option<Vec<u8>>)->Self{
+        let mut mbc = Mbc1{
+            program:v,
+            ram:Vec::new(),
+            register0:0,
+            register1:0,
+            register2:0,
+            register3:0,
+            battery:battery
+        };
+
+        mbc.ram = init_ram(mbc.program[MBC_RAM_SIZE_LOCATION], ram);
+
+        return mbc;
+    }
 }
\ No newline at end of file
@@ -20,6 +20,18 @@ impl Mbc for Mbc3{
         self.ram.as_slice()
     }
 
+    fn get_ram_mut(&mut self)->&mut [u8]{
+        self.ram.as_mut_slice()
+    }
+
+    fn get_rom(&self)->&[u8]{
+        self.program.as_slice()
+    }
+
+    fn get_rom_mut(&mut self)->&mut [u8]{
+        self.program.as_mut_slice()
+    }
+
     fn has_battery(&self) ->bool {
         self.battery
     }
@@ -29,7 +41,7 @@ impl Mbc for Mbc3{
     }
 
     fn read_current_bank(&self, address: u16)->u8{
-        let current_bank = self.get_current_rom_bank() as u16;
+        let current_bank = self.get_current_rom_bank();
         let internal_address:usize = (ROM_BANK_SIZE as usize* current_bank as usize) + address as usize;
 
         self.program[internal_address]
@@ -72,6 +84,21 @@ impl Mbc for Mbc3{
             }
         }
     }
+
+    fn get_current_rom_bank(&self)->u16{
+        //discard last bit as this register is 7 bits long
+        let mut value = (self.current_bank << 1) >> 1;
+        if value == 0{
+            value += 1;
+        }
+
+        value as u16
+    }
+
+    // When the RTC registers are selected the bank is out of the ram range
+    fn get_current_ram_bank(&self)->u16{
+        self.ram_rtc_select as u16
+    }
 }
 
 impl Mbc3{
@@ -92,14 +119,4 @@ impl Mbc3{
 
         mbc
     }
-
-    fn get_current_rom_bank(&self)->u8{
-        //discard last bit as this register is 7 bits long
-        let mut value = (self.current_bank << 1) >> 1;
-        if value == 0{
-            value += 1;
-        }
-
-        value
-    }
 }
\ No newline at end of file
@@ -14,6 +14,18 @@ impl Mbc for Rom{
         self.external_ram.as_slice()
     }
 
+    fn get_ram_mut(&mut self)->&mut [u8]{
+        self.external_ram.as_mut_slice()
+    }
+
+    fn get_rom(&self)->&[u8]{
+        self.program.as_slice()
+    }
+
+    fn get_rom_mut(&mut self)->&mut [u8]{
+        self.program.as_mut_slice()
+    }
+
     fn has_battery(&self) ->bool {
         self.battery
     }
@@ -38,6 +50,14 @@ impl Mbc for Rom{
         self.external_ram[address as usize] = value
     }
 
+    fn get_current_rom_bank(&self)->u16{
+        return 1;
+    }
+
+    fn get_current_ram_bank(&self)->u16{
+        return 0;
+    }
+
 }
 
 impl Rom{
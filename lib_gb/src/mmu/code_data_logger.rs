@@ -0,0 +1,101 @@
+// Logs how every rom and cartridge ram byte was accessed, the file format is documented at docs/CDL.md
+
+const MAGIC:&[u8;4] = b"GBCD";
+const VERSION:u8 = 1;
+const HEADER_SIZE:usize = 16;
+
+pub const OPCODE_FLAG:u8            = 1 << 0;
+pub const OPERAND_FLAG:u8           = 1 << 1;
+pub const DATA_FLAG:u8              = 1 << 2;
+pub const DMA_SOURCE_FLAG:u8        = 1 << 3;
+pub const FIXED_BANK_FLAG:u8        = 1 << 4;
+pub const SWITCHABLE_BANK_FLAG:u8   = 1 << 5;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MemoryAccessType{
+    Opcode,
+    Operand,
+    Data,
+    DmaSource
+}
+
+impl MemoryAccessType{
+    fn flag(self)->u8{
+        match self{
+            Self::Opcode=>OPCODE_FLAG,
+            Self::Operand=>OPERAND_FLAG,
+            Self::Data=>DATA_FLAG,
+            Self::DmaSource=>DMA_SOURCE_FLAG
+        }
+    }
+}
+
+pub struct CodeDataLogger{
+    rom_flags:Vec<u8>,
+    ram_flags:Vec<u8>
+}
+
+impl CodeDataLogger{
+    pub fn new(rom_size:usize, ram_size:usize)->Self{
+        Self{rom_flags:vec![0;rom_size], ram_flags:vec![0;ram_size]}
+    }
+
+    // Loads a previous session log in order to accumulate on it
+    pub fn from_bytes(data:&[u8])->Result<Self, String>{
+        if data.len() < HEADER_SIZE || &data[0..4] != MAGIC{
+            return Err(String::from("not a CDL file"));
+        }
+        if data[4] != VERSION{
+            return Err(format!("unsupported CDL version: {}", data[4]));
+        }
+        let rom_size = u32::from_le_bytes([data[8], data[9], data[10], data[11]]) as usize;
+        let ram_size = u32::from_le_bytes([data[12], data[13], data[14], data[15]]) as usize;
+        if data.len() != HEADER_SIZE + rom_size + ram_size{
+            return Err(String::from("CDL file size does not match its header"));
+        }
+        let ram_start = HEADER_SIZE + rom_size;
+
+        return Ok(Self{rom_flags:data[HEADER_SIZE..ram_start].to_vec(), ram_flags:data[ram_start..].to_vec()});
+    }
+
+    pub fn to_bytes(&self)->Vec<u8>{
+        let mut data = Vec::with_capacity(HEADER_SIZE + self.rom_flags.len() + self.ram_flags.len());
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&[VERSION, 0, 0, 0]);
+        data.extend_from_slice(&(self.rom_flags.len() as u32).to_le_bytes());
+        data.extend_from_slice(&(self.ram_flags.len() as u32).to_le_bytes());
+        data.extend_from_slice(&self.rom_flags);
+        data.extend_from_slice(&self.ram_flags);
+
+        return data;
+    }
+
+    pub fn get_rom_size(&self)->usize{
+        self.rom_flags.len()
+    }
+
+    pub fn get_ram_size(&self)->usize{
+        self.ram_flags.len()
+    }
+
+    pub fn get_rom_flags(&self)->&[u8]{
+        &self.rom_flags
+    }
+
+    pub fn get_ram_flags(&self)->&[u8]{
+        &self.ram_flags
+    }
+
+    pub fn log_rom(&mut self, offset:usize, access:MemoryAccessType, bank_flag:u8){
+        if let Some(flags) = self.rom_flags.get_mut(offset){
+            *flags |= access.flag() | bank_flag;
+        }
+    }
+
+    // Offsets out of the ram (like the MBC3 RTC registers) are ignored
+    pub fn log_ram(&mut self, offset:usize, access:MemoryAccessType){
+        if let Some(flags) = self.ram_flags.get_mut(offset){
+            *flags |= access.flag();
+        }
+    }
+}
@@ -1,26 +1,49 @@
-use super::{ram::Ram, carts::Mbc};
+use super::{ram::Ram, carts::{Mbc, mbc::{ROM_BANK_SIZE, RAM_BANK_SIZE}}, code_data_logger::*};
 
 pub struct ExternalMemoryBus<'a>{
     ram: Ram,
-    mbc: &'a mut Box<dyn Mbc>
+    mbc: &'a mut Box<dyn Mbc>,
+    code_data_logger:Option<CodeDataLogger>
 }
 
 impl<'a> ExternalMemoryBus<'a> {
     pub fn new(mbc:&'a mut Box<dyn Mbc>)->Self{
         Self{
             mbc,
-            ram:Ram::default()
+            ram:Ram::default(),
+            code_data_logger:None
         }
     }
 
-    pub fn read(&mut self, address:u16)->u8 {
+    pub fn read(&mut self, address:u16, access:MemoryAccessType)->u8 {
+        if let Some(logger) = &mut self.code_data_logger{
+            match address{
+                0x0000..=0x3FFF=>logger.log_rom(address as usize, access, FIXED_BANK_FLAG),
+                0x4000..=0x7FFF=>{
+                    let offset = self.mbc.get_current_rom_bank() as usize * ROM_BANK_SIZE as usize + (address - 0x4000) as usize;
+                    logger.log_rom(offset, access, SWITCHABLE_BANK_FLAG);
+                }
+                0xA000..=0xBFFF=>{
+                    let offset = self.mbc.get_current_ram_bank() as usize * RAM_BANK_SIZE as usize + (address - 0xA000) as usize;
+                    logger.log_ram(offset, access);
+                }
+                _=>{}
+            }
+        }
+
+        return self.peek(address);
+    }
+
+    // Reads without logging the access
+    pub fn peek(&self, address:u16)->u8{
         return match address{
             0x0000..=0x3FFF=>self.mbc.read_bank0(address),
             0x4000..=0x7FFF=>self.mbc.read_current_bank(address - 0x4000),
             0xA000..=0xBFFF=>self.mbc.read_external_ram(address - 0xA000),
             0xC000..=0xCFFF=>self.ram.read_bank0(address - 0xC000),
             0xD000..=0xDFFF=>self.ram.read_current_bank(address - 0xD000),
-            0xE000..=0xFDFF=>self.ram.read_bank0(address - 0xE000),
+            0xE000..=0xEFFF=>self.ram.read_bank0(address - 0xE000),
+            0xF000..=0xFDFF=>self.ram.read_current_bank(address - 0xF000),
             _=>std::panic!("Error: attemp to read invalid external memory bus address: {:#X}", address)
         }
     }
@@ -31,8 +54,41 @@ impl<'a> ExternalMemoryBus<'a> {
             0xA000..=0xBFFF=>self.mbc.write_external_ram(address - 0xA000, value),
             0xC000..=0xCFFF =>self.ram.write_bank0(address - 0xC000, value),
             0xD000..=0xDFFF=>self.ram.write_current_bank(address-0xD000, value),
-            0xE000..=0xFDFF=>self.ram.write_bank0(address - 0xE000, value),
+            0xE000..=0xEFFF=>self.ram.write_bank0(address - 0xE000, value),
+            0xF000..=0xFDFF=>self.ram.write_current_bank(address - 0xF000, value),
             _=>std::panic!("Error: attemp to write invalid external memory bus address: {:#X}", address)
         }
     }
+
+    pub fn get_current_rom_bank(&self)->u16{
+        self.mbc.get_current_rom_bank()
+    }
+
+    pub fn get_current_ram_bank(&self)->u16{
+        self.mbc.get_current_ram_bank()
+    }
+
+    pub fn set_wram_bank(&mut self, bank:u8){
+        self.ram.set_bank(bank);
+    }
+
+    pub fn get_wram_bank(&self)->u8{
+        self.ram.get_bank()
+    }
+
+    pub fn get_mbc_mut(&mut self)->&mut dyn Mbc{
+        &mut **self.mbc
+    }
+
+    pub fn get_ram_mut(&mut self)->&mut Ram{
+        &mut self.ram
+    }
+
+    pub fn set_code_data_logger(&mut self, logger:CodeDataLogger){
+        self.code_data_logger = Some(logger);
+    }
+
+    pub fn take_code_data_logger(&mut self)->Option<CodeDataLogger>{
+        self.code_data_logger.take()
+    }
 }
\ No newline at end of file
@@ -2,17 +2,24 @@ use super::external_memory_bus::ExternalMemoryBus;
 use super::interrupts_handler::InterruptRequest;
 use super::{io_bus::IoBus, memory::*};
 use super::access_bus::AccessBus;
+use super::code_data_logger::{CodeDataLogger, MemoryAccessType};
 use crate::keypad::joypad_provider::JoypadProvider;
 use crate::ppu::gfx_device::GfxDevice;
 use crate::{apu::{audio_device::AudioDevice, gb_apu::GbApu}, utils::memory_registers::BOOT_REGISTER_ADDRESS};
-use super::carts::mbc::Mbc;
+use super::carts::mbc::{Mbc, ROM_BANK_SIZE, RAM_BANK_SIZE};
+use super::{io_bus::IO_PORTS_SIZE, ram::BANK_SIZE as WRAM_BANK_SIZE, vram::VRAM_BANK_SIZE};
+use crate::ppu::{gb_ppu::OAM_MEMORY_SIZE, oam_corruption::OamCorruption};
 use crate::ppu::ppu_state::PpuState;
+use crate::machine::{event_tracer::EventTracer, mode::Mode};
+use crate::utils::memory_registers::*;
 use std::boxed::Box;
 
 pub const BOOT_ROM_SIZE:usize = 0x100;
-const HRAM_SIZE:usize = 0x7F;
+pub const HRAM_SIZE:usize = 0x7F;
 
 const BAD_READ_VALUE:u8 = 0xFF;
+// The cpu is stopped during the speed switch while the ppu and apu keep running
+const SPEED_SWITCH_M_CYCLES:u32 = 2050;
 
 pub struct GbMmu<'a, D:AudioDevice, G:GfxDevice, J:JoypadProvider>{
     pub io_bus: IoBus<D, G, J>,
@@ -21,53 +28,54 @@ pub struct GbMmu<'a, D:AudioDevice, G:GfxDevice, J:JoypadProvider>{
     external_memory_bus:ExternalMemoryBus<'a>,
     oucupied_access_bus:Option<AccessBus>,
     hram: [u8;HRAM_SIZE],
-    interupt_enable_register:u8
+    interupt_enable_register:u8,
+    mode:Mode,
+    // In double speed 2 cpu m_cycles are a single normal speed m_cycle
+    double_speed_cycles_remainder:u32,
+    event_tracer:Option<EventTracer>
 }
 
 
 //DMA only locks the used bus. there 2 possible used buses: extrnal (wram, rom, sram) and video (vram)
 impl<'a, D:AudioDevice, G:GfxDevice, J:JoypadProvider> Memory for GbMmu<'a, D, G, J>{
     fn read(&mut self, address:u16, m_cycles:u8)->u8{
+        self.read_with_access(address, m_cycles, MemoryAccessType::Data, OamCorruption::Read)
+    }
+
+    fn read_opcode(&mut self, address:u16, m_cycles:u8)->u8{
+        self.read_with_access(address, m_cycles, MemoryAccessType::Opcode, OamCorruption::Read)
+    }
+
+    fn read_operand(&mut self, address:u16, m_cycles:u8)->u8{
+        self.read_with_access(address, m_cycles, MemoryAccessType::Operand, OamCorruption::Read)
+    }
+
+    fn read_with_inc_dec(&mut self, address:u16, m_cycles:u8)->u8{
+        self.read_with_access(address, m_cycles, MemoryAccessType::Data, OamCorruption::ReadDuringIncDec)
+    }
+
+    fn inc_dec_access(&mut self, address:u16, m_cycles:u8){
         self.cycle(m_cycles);
-        if let Some (bus) = &self.oucupied_access_bus{
-            return match address{
-                0xFF00..=0xFF7F => self.io_bus.read(address - 0xFF00),
-                0xFEA0..=0xFEFF | 0xFF80..=0xFFFE | 0xFFFF=>self.read_unprotected(address),
-                0x8000..=0x9FFF => if let AccessBus::External = bus {self.read_unprotected(address)} else{Self::bad_dma_read(address)},
-                0..=0x7FFF | 0xA000..=0xFDFF => if let AccessBus::Video = bus {self.read_unprotected(address)} else{Self::bad_dma_read(address)},
-                _=>Self::bad_dma_read(address)
-            };
-        }
-        return match address{
-            0x8000..=0x9FFF=>{
-                if self.is_vram_ready_for_io(){
-                    return self.io_bus.ppu.vram.read_current_bank(address-0x8000);
-                }
-                else{
-                    log::warn!("bad vram read");
-                    return BAD_READ_VALUE;
-                }
-            },
-            0xFE00..=0xFE9F=>{
-                if self.is_oam_ready_for_io(){
-                    return self.io_bus.ppu.oam[(address-0xFE00) as usize];
-                }
-                else{
-                    log::warn!("bad oam read");
-                    return BAD_READ_VALUE;
-                }
-            },
-            0xFF00..=0xFF7F => self.io_bus.read(address - 0xFF00),
-            0xFFFF => self.io_bus.interrupt_handler.interrupt_enable_flag,
-            _=>self.read_unprotected(address)
-        };
+        self.try_corrupt_oam(address, OamCorruption::Write);
     }
 
     fn write(&mut self, address:u16, value:u8, m_cycles:u8){
         self.cycle(m_cycles);
+        self.try_corrupt_oam(address, OamCorruption::Write);
+        if self.event_tracer.is_some(){
+            self.write_traced(address, value);
+        }
+        else{
+            self.write_memory(address, value);
+        }
+    }
+}
+
+impl<'a, D:AudioDevice, G:GfxDevice, J:JoypadProvider> GbMmu<'a, D, G, J>{
+    fn write_memory(&mut self, address:u16, value:u8){
         if let Some(bus) = &self.oucupied_access_bus{
             match address{
-                0xFF00..=0xFF7F => self.io_bus.write(address- 0xFF00, value),
+                0xFF00..=0xFF7F => self.write_io(address, value),
                 0xFF80..=0xFFFE | 0xFFFF=>self.write_unprotected(address, value),
                 0x8000..=0x9FFF => if let AccessBus::External = bus {self.write_unprotected(address, value)} else{Self::bad_dma_write(address)},
                 0..=0x7FFF | 0xA000..=0xFDFF => if let AccessBus::Video = bus {self.write_unprotected(address, value)} else{Self::bad_dma_write(address)},
@@ -92,30 +100,112 @@ impl<'a, D:AudioDevice, G:GfxDevice, J:JoypadProvider> Memory for GbMmu<'a, D, G
                         log::warn!("bad oam write")
                     }
                 },
-                0xFF00..=0xFF7F=>self.io_bus.write(address - 0xFF00, value),
+                0xFF00..=0xFF7F=>self.write_io(address, value),
                 0xFFFF => self.io_bus.interrupt_handler.interrupt_enable_flag = value,
                 _=>self.write_unprotected(address, value)
             }
         }
     }
-}
 
-impl<'a, D:AudioDevice, G:GfxDevice, J:JoypadProvider> GbMmu<'a, D, G, J>{
-    fn read_unprotected(&mut self, address:u16) ->u8 {
+    fn read_with_access(&mut self, address:u16, m_cycles:u8, access:MemoryAccessType, corruption:OamCorruption)->u8{
+        self.cycle(m_cycles);
+        self.try_corrupt_oam(address, corruption);
+        if let Some (bus) = &self.oucupied_access_bus{
+            return match address{
+                0xFF00..=0xFF7F => self.read_io(address),
+                0xFEA0..=0xFEFF | 0xFF80..=0xFFFE | 0xFFFF=>self.read_unprotected(address, access),
+                0x8000..=0x9FFF => if let AccessBus::External = bus {self.read_unprotected(address, access)} else{self.dma_bus_conflict_read(address)},
+                0..=0x7FFF | 0xA000..=0xFDFF => if let AccessBus::Video = bus {self.read_unprotected(address, access)} else{self.dma_bus_conflict_read(address)},
+                _=>Self::bad_dma_read(address)
+            };
+        }
+        return match address{
+            0x8000..=0x9FFF=>{
+                if self.is_vram_ready_for_io(){
+                    return self.io_bus.ppu.vram.read_current_bank(address-0x8000);
+                }
+                else{
+                    log::warn!("bad vram read");
+                    return BAD_READ_VALUE;
+                }
+            },
+            0xFE00..=0xFE9F=>{
+                if self.is_oam_ready_for_io(){
+                    return self.io_bus.ppu.oam[(address-0xFE00) as usize];
+                }
+                else{
+                    log::warn!("bad oam read");
+                    return BAD_READ_VALUE;
+                }
+            },
+            0xFF00..=0xFF7F => self.read_io(address),
+            0xFFFF => self.io_bus.interrupt_handler.interrupt_enable_flag,
+            _=>self.read_unprotected(address, access)
+        };
+    }
+
+    fn write_traced(&mut self, address:u16, value:u8){
+        let rom_bank = self.external_memory_bus.get_current_rom_bank();
+        let ram_bank = self.external_memory_bus.get_current_ram_bank();
+        self.write_memory(address, value);
+
+        let tracer = self.event_tracer.as_mut().unwrap();
+        match address{
+            0..=0x7FFF=>{
+                let new_rom_bank = self.external_memory_bus.get_current_rom_bank();
+                let new_ram_bank = self.external_memory_bus.get_current_ram_bank();
+                if new_rom_bank != rom_bank{
+                    tracer.trace_rom_bank_switch(new_rom_bank);
+                }
+                if new_ram_bank != ram_bank{
+                    tracer.trace_ram_bank_switch(new_ram_bank);
+                }
+            }
+            NR14_REGISTER_ADDRESS | NR24_REGISTER_ADDRESS | NR34_REGISTER_ADDRESS | NR44_REGISTER_ADDRESS if value & 0x80 != 0=>{
+                let channel = match address{
+                    NR14_REGISTER_ADDRESS=>1,
+                    NR24_REGISTER_ADDRESS=>2,
+                    NR34_REGISTER_ADDRESS=>3,
+                    _=>4
+                };
+                tracer.trace_apu_trigger(channel);
+            }
+            DMA_REGISTER_ADDRESS=>tracer.trace_dma_start(),
+            _=>{}
+        }
+        tracer.sync_interrupt_flag(self.io_bus.interrupt_handler.interrupt_flag);
+    }
+
+    // The wram bank register is handled here since the wram is on the external bus
+    fn read_io(&mut self, address:u16)->u8{
+        return match address{
+            SVBK_REGISTER_ADDRESS if self.mode == Mode::CGB=>self.external_memory_bus.get_wram_bank() | 0b1111_1000,
+            _=>self.io_bus.read(address - 0xFF00)
+        };
+    }
+
+    fn write_io(&mut self, address:u16, value:u8){
+        match address{
+            SVBK_REGISTER_ADDRESS if self.mode == Mode::CGB=>self.external_memory_bus.set_wram_bank(value & 0b111),
+            _=>self.io_bus.write(address - 0xFF00, value)
+        }
+    }
+
+    fn read_unprotected(&mut self, address:u16, access:MemoryAccessType) ->u8 {
         return match address{
             0x0..=0xFF=>{
                 if self.io_bus.finished_boot{
-                    return self.external_memory_bus.read(address);
+                    return self.external_memory_bus.read(address, access);
                 }
                 
                 return self.boot_rom[address as usize];
             },
-            0x100..=0x7FFF=>self.external_memory_bus.read(address),
+            0x100..=0x7FFF=>self.external_memory_bus.read(address, access),
             0x8000..=0x9FFF=>self.io_bus.ppu.vram.read_current_bank(address-0x8000),
-            0xA000..=0xFDFF=>self.external_memory_bus.read(address),
+            0xA000..=0xFDFF=>self.external_memory_bus.read(address, access),
             0xFE00..=0xFE9F=>self.io_bus.ppu.oam[(address-0xFE00) as usize],
             0xFEA0..=0xFEFF=>0x0,
-            0xFF00..=0xFF7F=>self.io_bus.read(address - 0xFF00),
+            0xFF00..=0xFF7F=>self.read_io(address),
             0xFF80..=0xFFFE=>self.hram[(address-0xFF80) as usize],
             0xFFFF=>self.interupt_enable_register
         };
@@ -128,7 +218,7 @@ impl<'a, D:AudioDevice, G:GfxDevice, J:JoypadProvider> GbMmu<'a, D, G, J>{
             0xA000..=0xFDFF=>self.external_memory_bus.write(address, value),
             0xFE00..=0xFE9F=>self.io_bus.ppu.oam[(address-0xFE00) as usize] = value,
             0xFEA0..=0xFEFF=>{},
-            0xFF00..=0xFF7F=>self.io_bus.write(address - 0xFF00, value),
+            0xFF00..=0xFF7F=>self.write_io(address, value),
             0xFF80..=0xFFFE=>self.hram[(address-0xFF80) as usize] = value,
             0xFFFF=>self.interupt_enable_register = value
         }
@@ -136,20 +226,23 @@ impl<'a, D:AudioDevice, G:GfxDevice, J:JoypadProvider> GbMmu<'a, D, G, J>{
 }
 
 impl<'a, D:AudioDevice, G:GfxDevice, J:JoypadProvider> GbMmu<'a, D, G, J>{
-    pub fn new_with_bootrom(mbc:&'a mut Box<dyn Mbc>, boot_rom:[u8;BOOT_ROM_SIZE], apu:GbApu<D>, gfx_device:G, joypad_proider:J)->Self{
+    pub fn new_with_bootrom(mbc:&'a mut Box<dyn Mbc>, boot_rom:[u8;BOOT_ROM_SIZE], apu:GbApu<D>, gfx_device:G, joypad_proider:J, mode:Mode)->Self{
         GbMmu{
-            io_bus:IoBus::new(apu, gfx_device, joypad_proider),
+            io_bus:IoBus::new(apu, gfx_device, joypad_proider, mode),
             m_cycle_counter:0,
             external_memory_bus: ExternalMemoryBus::new(mbc),
             oucupied_access_bus:None,
             hram:[0;HRAM_SIZE],
             interupt_enable_register:0,
             boot_rom:boot_rom,
+            mode,
+            double_speed_cycles_remainder:0,
+            event_tracer:None
         }
     }
 
-    pub fn new(mbc:&'a mut Box<dyn Mbc>, apu:GbApu<D>, gfx_device: G, joypad_proider:J)->Self{
-        let mut mmu = GbMmu::new_with_bootrom(mbc, [0;BOOT_ROM_SIZE], apu, gfx_device, joypad_proider);
+    pub fn new(mbc:&'a mut Box<dyn Mbc>, apu:GbApu<D>, gfx_device: G, joypad_proider:J, mode:Mode)->Self{
+        let mut mmu = GbMmu::new_with_bootrom(mbc, [0;BOOT_ROM_SIZE], apu, gfx_device, joypad_proider, mode);
 
         //Setting the bootrom register to be set (the boot sequence has over)
         mmu.write(BOOT_REGISTER_ADDRESS, 1, 0);
@@ -157,20 +250,96 @@ impl<'a, D:AudioDevice, G:GfxDevice, J:JoypadProvider> GbMmu<'a, D, G, J>{
         return mmu;
     }
 
+    // The m_cycles are cpu m_cycles, the m_cycle counter counts normal speed m_cycles
     pub fn cycle(&mut self, m_cycles:u8){
-        self.oucupied_access_bus = self.io_bus.dma_controller.cycle(m_cycles as u32, &mut self.external_memory_bus, &mut self.io_bus.ppu);
-        self.io_bus.cycle(m_cycles as u32);
-        self.m_cycle_counter += m_cycles as u32;
+        let cpu_m_cycles = m_cycles as u32;
+        let m_cycles = if self.io_bus.double_speed{
+            self.double_speed_cycles_remainder += cpu_m_cycles;
+            let normal_speed_m_cycles = self.double_speed_cycles_remainder >> 1;
+            self.double_speed_cycles_remainder &= 1;
+            normal_speed_m_cycles
+        }
+        else{
+            cpu_m_cycles
+        };
+        self.cycle_hardware(cpu_m_cycles, m_cycles);
+
+        // The cpu is stalled while the vram dma transfers, so the hardware keeps running before the cpu continues.
+        // The vram dma runs at the normal speed
+        let stalled_m_cycles = self.io_bus.vram_dma_controller.cycle(&mut self.external_memory_bus, &mut self.io_bus.ppu);
+        if stalled_m_cycles != 0{
+            let stalled_cpu_m_cycles = if self.io_bus.double_speed {stalled_m_cycles << 1} else {stalled_m_cycles};
+            self.cycle_hardware(stalled_cpu_m_cycles, stalled_m_cycles);
+        }
+    }
+
+    pub fn is_double_speed(&self)->bool{
+        return self.io_bus.double_speed;
+    }
+
+    // Called after the cpu executed a stop with a prepared speed switch
+    pub fn switch_speed(&mut self){
+        self.io_bus.double_speed = !self.io_bus.double_speed;
+        self.io_bus.prepare_speed_switch = false;
+        self.double_speed_cycles_remainder = 0;
+
+        // Stop resets the divider which doesnt tick during the switch
+        self.write_io(DIV_REGISTER_ADDRESS, 0);
+        self.cycle_hardware(0, SPEED_SWITCH_M_CYCLES);
+    }
+
+    // The oam dma and the timer run at the cpu rate, everything else runs at the normal speed rate
+    fn cycle_hardware(&mut self, cpu_m_cycles:u32, m_cycles:u32){
+        self.oucupied_access_bus = self.io_bus.dma_controller.cycle(cpu_m_cycles, &mut self.external_memory_bus, &mut self.io_bus.ppu);
+        self.io_bus.cycle(cpu_m_cycles, m_cycles);
+        self.m_cycle_counter += m_cycles;
+        if let Some(tracer) = &mut self.event_tracer{
+            tracer.observe(m_cycles, self.io_bus.ppu.state, self.io_bus.ppu.ly_register, self.io_bus.interrupt_handler.interrupt_flag, self.io_bus.dma_controller.is_active());
+        }
+    }
+
+    pub fn set_event_tracer(&mut self, tracer:EventTracer){
+        self.event_tracer = Some(tracer);
+    }
+
+    pub fn take_event_tracer(&mut self)->Option<EventTracer>{
+        return self.event_tracer.take();
+    }
+
+    pub fn get_event_tracer(&mut self)->Option<&mut EventTracer>{
+        return self.event_tracer.as_mut();
     }
 
     pub fn handle_interrupts(&mut self, master_interrupt_enable:bool)->InterruptRequest{
         return self.io_bus.interrupt_handler.handle_interrupts(master_interrupt_enable, self.io_bus.ppu.stat_register);
     }
 
+    pub fn get_current_rom_bank(&self)->u16{
+        return self.external_memory_bus.get_current_rom_bank();
+    }
+
+    pub fn set_code_data_logger(&mut self, logger:CodeDataLogger){
+        self.external_memory_bus.set_code_data_logger(logger);
+    }
+
+    pub fn take_code_data_logger(&mut self)->Option<CodeDataLogger>{
+        return self.external_memory_bus.take_code_data_logger();
+    }
+
     pub fn poll_joypad_state(&mut self){
         self.io_bus.joypad_handler.poll_joypad_state();
     }
 
+    // On dmg the oam row read by the oam search is corrupted when the cpu puts an oam address on the bus
+    fn try_corrupt_oam(&mut self, address:u16, corruption:OamCorruption){
+        if self.mode != Mode::DMG || !(0xFE00..=0xFEFF).contains(&address) || self.oucupied_access_bus.is_some(){
+            return;
+        }
+        // The ppu is lazily cycled and the corrupted row depends on the current m_cycle
+        self.io_bus.cycle_ppu();
+        self.io_bus.ppu.corrupt_oam(corruption);
+    }
+
     fn is_oam_ready_for_io(&self)->bool{
         return self.io_bus.ppu.state != PpuState::OamSearch && self.io_bus.ppu.state != PpuState::PixelTransfer
     }
@@ -184,7 +353,101 @@ impl<'a, D:AudioDevice, G:GfxDevice, J:JoypadProvider> GbMmu<'a, D, G, J>{
         return BAD_READ_VALUE;
     }
 
+    // The cpu reads the value the dma is transferring on the same bus
+    fn dma_bus_conflict_read(&self, address:u16)->u8{
+        log::trace!("bus conflict read during dma. {:#X}", address);
+        return self.io_bus.dma_controller.get_transferred_value();
+    }
+
     fn bad_dma_write(address:u16){
         log::warn!("bad memory write during dma. {:#X}", address)
     }
+}
+
+// Debug access to the memory for tools, it doesnt cycle the hardware and ignores the ppu and dma locking.
+// Peeking an io register syncs the lazily updated hardware to the current cycle (which doesnt advance the emulation),
+// poking an io register has the same effect as a write by the cpu.
+impl<'a, D:AudioDevice, G:GfxDevice, J:JoypadProvider> GbMmu<'a, D, G, J>{
+    pub fn peek(&mut self, address:u16)->u8{
+        return match address{
+            0x0..=0xFF if !self.io_bus.finished_boot=>self.boot_rom[address as usize],
+            0x0..=0x7FFF | 0xC000..=0xFDFF=>self.external_memory_bus.peek(address),
+            0x8000..=0x9FFF=>self.io_bus.ppu.vram.read_current_bank(address - 0x8000),
+            0xA000..=0xBFFF=>{
+                let bank = self.external_memory_bus.get_current_ram_bank();
+                self.peek_bank(MemoryBank::CartRam(bank), address)
+            }
+            0xFE00..=0xFE9F=>self.io_bus.ppu.oam[(address - 0xFE00) as usize],
+            0xFEA0..=0xFEFF=>0x0,
+            0xFF00..=0xFF7F=>self.read_io(address),
+            0xFF80..=0xFFFE=>self.hram[(address - 0xFF80) as usize],
+            0xFFFF=>self.io_bus.interrupt_handler.interrupt_enable_flag
+        };
+    }
+
+    // Writes to the rom patch it instead of accessing the mbc registers
+    pub fn poke(&mut self, address:u16, value:u8){
+        match address{
+            0x0..=0xFF if !self.io_bus.finished_boot=>self.boot_rom[address as usize] = value,
+            0x0..=0x3FFF=>self.poke_bank(MemoryBank::Rom(0), address, value),
+            0x4000..=0x7FFF=>{
+                let bank = self.external_memory_bus.get_current_rom_bank();
+                self.poke_bank(MemoryBank::Rom(bank), address, value);
+            }
+            0x8000..=0x9FFF=>self.io_bus.ppu.vram.write_current_bank(address - 0x8000, value),
+            0xA000..=0xBFFF=>{
+                let bank = self.external_memory_bus.get_current_ram_bank();
+                self.poke_bank(MemoryBank::CartRam(bank), address, value);
+            }
+            0xC000..=0xFDFF=>self.external_memory_bus.write(address, value),
+            0xFE00..=0xFE9F=>self.io_bus.ppu.oam[(address - 0xFE00) as usize] = value,
+            0xFEA0..=0xFEFF=>{},
+            0xFF00..=0xFF7F=>self.write_io(address, value),
+            0xFF80..=0xFFFE=>self.hram[(address - 0xFF80) as usize] = value,
+            0xFFFF=>self.io_bus.interrupt_handler.interrupt_enable_flag = value
+        }
+    }
+
+    // Only the offset of the address inside the bank is used, banks out of the memory read as 0xFF
+    pub fn peek_bank(&mut self, bank:MemoryBank, address:u16)->u8{
+        return match self.get_banked_byte(bank, address){
+            Some(value)=>*value,
+            None=>BAD_READ_VALUE
+        };
+    }
+
+    // Writes to banks out of the memory are ignored
+    pub fn poke_bank(&mut self, bank:MemoryBank, address:u16, value:u8){
+        if let Some(byte) = self.get_banked_byte(bank, address){
+            *byte = value;
+        }
+    }
+
+    pub fn dump_oam(&self)->[u8;OAM_MEMORY_SIZE]{
+        return self.io_bus.ppu.oam;
+    }
+
+    pub fn dump_hram(&self)->[u8;HRAM_SIZE]{
+        return self.hram;
+    }
+
+    pub fn dump_io(&mut self)->[u8;IO_PORTS_SIZE]{
+        let mut io_ports = [0;IO_PORTS_SIZE];
+        for (address, value) in io_ports.iter_mut().enumerate(){
+            *value = self.read_io(0xFF00 + address as u16);
+        }
+
+        return io_ports;
+    }
+
+    fn get_banked_byte(&mut self, bank:MemoryBank, address:u16)->Option<&mut u8>{
+        let (memory, bank, bank_size) = match bank{
+            MemoryBank::Rom(bank)=>(self.external_memory_bus.get_mbc_mut().get_rom_mut(), bank as usize, ROM_BANK_SIZE as usize),
+            MemoryBank::Vram(bank)=>(self.io_bus.ppu.vram.get_memory_mut(), bank as usize, VRAM_BANK_SIZE),
+            MemoryBank::Wram(bank)=>(self.external_memory_bus.get_ram_mut().get_memory_mut(), bank as usize, WRAM_BANK_SIZE),
+            MemoryBank::CartRam(bank)=>(self.external_memory_bus.get_mbc_mut().get_ram_mut(), bank as usize, RAM_BANK_SIZE as usize)
+        };
+
+        return memory.get_mut(bank * bank_size + address as usize % bank_size);
+    }
 }
\ No newline at end of file
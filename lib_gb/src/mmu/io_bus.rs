@@ -2,9 +2,11 @@ use crate::{
     apu::{*,audio_device::AudioDevice, gb_apu::GbApu}, 
     ppu::{gb_ppu::GbPpu, ppu_register_updater::*, gfx_device::GfxDevice},
     timer::{timer_register_updater::*, gb_timer::GbTimer}, 
-    keypad::{joypad_provider::JoypadProvider, joypad_handler::JoypadHandler}
+    keypad::{joypad_provider::JoypadProvider, joypad_handler::JoypadHandler},
+    machine::mode::Mode,
+    utils::bit_masks::{BIT_0_MASK, BIT_7_MASK}
 };
-use super::{interrupts_handler::*, io_ports::*, oam_dma_controller::OamDmaController};
+use super::{interrupts_handler::*, io_ports::*, oam_dma_controller::OamDmaController, vram_dma_controller::VramDmaController};
 
 pub const IO_PORTS_SIZE:usize = 0x80;
 const WAVE_RAM_START_INDEX:u16 = 0x30;
@@ -15,9 +17,13 @@ pub struct IoBus<AD:AudioDevice, GFX:GfxDevice, JP:JoypadProvider>{
     pub timer: GbTimer,
     pub ppu:GbPpu<GFX>,
     pub dma_controller:OamDmaController,
+    pub vram_dma_controller:VramDmaController,
     pub interrupt_handler:InterruptsHandler,
     pub joypad_handler: JoypadHandler<JP>,
     pub finished_boot:bool,
+    pub mode:Mode,
+    pub double_speed:bool,
+    pub prepare_speed_switch:bool,
 
     apu_cycles_counter:u32,
     ppu_cycles:u32,
@@ -37,7 +43,7 @@ impl<AD:AudioDevice, GFX:GfxDevice, JP:JoypadProvider> IoBus<AD, GFX, JP>{
         match address{
             TAC_REGISTER_INDEX | DIV_REGISTER_INDEX | TIMA_REGISTER_INDEX=> self.cycle_timer(),
             NR10_REGISTER_INDEX..=WAVE_RAM_END_INDEX => self.cycle_apu(),
-            LCDC_REGISTER_INDEX..=WX_REGISTER_INDEX => self.cycle_ppu(),
+            LCDC_REGISTER_INDEX..=WX_REGISTER_INDEX | BCPD_REGISTER_INDEX | OCPD_REGISTER_INDEX => self.cycle_ppu(),
             _=>{}
         }
 
@@ -91,7 +97,7 @@ impl<AD:AudioDevice, GFX:GfxDevice, JP:JoypadProvider> IoBus<AD, GFX, JP>{
             BOOT_REGISTER_INDEX=> self.finished_boot as u8,
             //Joypad
             JOYP_REGISTER_INDEX => self.joypad_handler.register,
-            _=>0xFF
+            _=>if self.mode == Mode::CGB {self.read_cgb_register(address)} else {0xFF}
         };
     }
 
@@ -99,7 +105,7 @@ impl<AD:AudioDevice, GFX:GfxDevice, JP:JoypadProvider> IoBus<AD, GFX, JP>{
         match address{
             DIV_REGISTER_INDEX | TIMA_REGISTER_INDEX | TMA_REGISTER_INDEX | TAC_REGISTER_INDEX => self.cycle_timer(),
             NR10_REGISTER_INDEX..=WAVE_RAM_END_INDEX => self.cycle_apu(),
-            LCDC_REGISTER_INDEX..=WX_REGISTER_INDEX => self.cycle_ppu(),
+            LCDC_REGISTER_INDEX..=WX_REGISTER_INDEX | BCPD_REGISTER_INDEX | OCPD_REGISTER_INDEX => self.cycle_ppu(),
             _=>{}
         }
         match address{
@@ -135,35 +141,71 @@ impl<AD:AudioDevice, GFX:GfxDevice, JP:JoypadProvider> IoBus<AD, GFX, JP>{
             WAVE_RAM_START_INDEX..=WAVE_RAM_END_INDEX => set_wave_ram(&mut self.apu.wave_channel, address, value), 
             //PPU
             LCDC_REGISTER_INDEX=> handle_lcdcontrol_register(value, &mut self.ppu),
-            STAT_REGISTER_INDEX=> update_stat_register(value, &mut self.ppu),
+            STAT_REGISTER_INDEX=> update_stat_register(value, &mut self.ppu, &mut self.interrupt_handler.interrupt_flag),
             SCY_REGISTER_INDEX=> set_scy(&mut self.ppu, value),
             SCX_REGISTER_INDEX=> set_scx(&mut self.ppu, value),
             // LY is readonly
             LYC_REGISTER_INDEX=> set_lyc(&mut self.ppu, value),
             DMA_REGISTER_INDEX=>self.dma_controller.set_dma_register(value),
-            BGP_REGISTER_INDEX=> handle_bg_pallet_register(value,&mut self.ppu.bg_color_mapping, &mut self.ppu.bg_palette_register),
-            OBP0_REGISTER_INDEX=> handle_obp_pallet_register(value,&mut self.ppu.obj_color_mapping0, &mut self.ppu.obj_pallete_0_register),
-            OBP1_REGISTER_INDEX=> handle_obp_pallet_register(value,&mut self.ppu.obj_color_mapping1, &mut self.ppu.obj_pallete_1_register),
+            BGP_REGISTER_INDEX=> handle_bg_pallet_register(value, &self.ppu.dmg_palettes.bg, &mut self.ppu.bg_color_mapping, &mut self.ppu.bg_palette_register),
+            OBP0_REGISTER_INDEX=> handle_obp_pallet_register(value, &self.ppu.dmg_palettes.obj0, &mut self.ppu.obj_color_mapping0, &mut self.ppu.obj_pallete_0_register),
+            OBP1_REGISTER_INDEX=> handle_obp_pallet_register(value, &self.ppu.dmg_palettes.obj1, &mut self.ppu.obj_color_mapping1, &mut self.ppu.obj_pallete_1_register),
             WY_REGISTER_INDEX=> handle_wy_register(value, &mut self.ppu),
             WX_REGISTER_INDEX=> handle_wx_register(value, &mut self.ppu),
             BOOT_REGISTER_INDEX=> self.finished_boot = value != 0,
             JOYP_REGISTER_INDEX => self.joypad_handler.set_register(value),
-            // TODO: handle gbc registers (expecailly ram and vram)
+            _=>if self.mode == Mode::CGB {self.write_cgb_register(address, value)}
+        }
+    }
+
+    // The wram bank register (SVBK) is handled by the mmu since the wram is not on this bus
+    fn read_cgb_register(&self, address:u16)->u8{
+        return match address{
+            KEY1_REGISTER_INDEX=> ((self.double_speed as u8) << 7) | 0b0111_1110 | self.prepare_speed_switch as u8,
+            VBK_REGISTER_INDEX=> get_vbk(&self.ppu),
+            BCPS_REGISTER_INDEX=> self.ppu.bg_color_ram.get_index_register(),
+            BCPD_REGISTER_INDEX=> get_bcpd(&self.ppu),
+            OCPS_REGISTER_INDEX=> self.ppu.obj_color_ram.get_index_register(),
+            OCPD_REGISTER_INDEX=> get_ocpd(&self.ppu),
+            OPRI_REGISTER_INDEX=> get_opri(&self.ppu),
+            HDMA5_REGISTER_INDEX=> self.vram_dma_controller.get_hdma5(),
+            _=>0xFF
+        };
+    }
+
+    fn write_cgb_register(&mut self, address:u16, value:u8){
+        match address{
+            KEY1_REGISTER_INDEX=> self.prepare_speed_switch = value & BIT_0_MASK != 0,
+            VBK_REGISTER_INDEX=> set_vbk(&mut self.ppu, value),
+            BCPS_REGISTER_INDEX=> self.ppu.bg_color_ram.set_index_register(value),
+            BCPD_REGISTER_INDEX=> set_bcpd(&mut self.ppu, value),
+            OCPS_REGISTER_INDEX=> self.ppu.obj_color_ram.set_index_register(value),
+            OCPD_REGISTER_INDEX=> set_ocpd(&mut self.ppu, value),
+            OPRI_REGISTER_INDEX=> set_opri(&mut self.ppu, value),
+            HDMA1_REGISTER_INDEX=> self.vram_dma_controller.set_hdma1(value),
+            HDMA2_REGISTER_INDEX=> self.vram_dma_controller.set_hdma2(value),
+            HDMA3_REGISTER_INDEX=> self.vram_dma_controller.set_hdma3(value),
+            HDMA4_REGISTER_INDEX=> self.vram_dma_controller.set_hdma4(value),
+            HDMA5_REGISTER_INDEX=> self.vram_dma_controller.set_hdma5(value, self.ppu.lcd_control & BIT_7_MASK != 0),
             _=>{}
         }
     }
 }
 
 impl<AD:AudioDevice, GFX:GfxDevice, JP:JoypadProvider> IoBus<AD, GFX, JP>{
-    pub fn new(apu:GbApu<AD>, gfx_device:GFX, joypad_provider:JP)->Self{
+    pub fn new(apu:GbApu<AD>, gfx_device:GFX, joypad_provider:JP, mode:Mode)->Self{
         Self{
             apu,
             timer:GbTimer::default(),
-            ppu:GbPpu::new(gfx_device),
+            ppu:GbPpu::new(gfx_device, mode),
             dma_controller: OamDmaController::new(),
+            vram_dma_controller: VramDmaController::new(),
             interrupt_handler: InterruptsHandler::default(),
             joypad_handler: JoypadHandler::new(joypad_provider),
             finished_boot:false,
+            mode,
+            double_speed:false,
+            prepare_speed_switch:false,
             apu_cycles_counter:0,
             ppu_cycles:0,
             timer_cycles:0,
@@ -173,9 +215,10 @@ impl<AD:AudioDevice, GFX:GfxDevice, JP:JoypadProvider> IoBus<AD, GFX, JP>{
         }
     }
 
-    pub fn cycle(&mut self, cycles:u32){
+    // In double speed the timer runs at the cpu rate while the ppu and apu keep the normal rate
+    pub fn cycle(&mut self, cpu_cycles:u32, cycles:u32){
         self.apu_cycles_counter += cycles;
-        self.timer_cycles += cycles;
+        self.timer_cycles += cpu_cycles;
         
         if !self.ppu_event.is_none(){
             self.ppu_cycles += cycles;
@@ -199,7 +242,7 @@ impl<AD:AudioDevice, GFX:GfxDevice, JP:JoypadProvider> IoBus<AD, GFX, JP>{
         }
     }
 
-    fn cycle_ppu(&mut self){
+    pub fn cycle_ppu(&mut self){
         self.ppu_event = self.ppu.cycle(self.ppu_cycles, &mut self.interrupt_handler.interrupt_flag);
         self.ppu_cycles = 0;
     }
@@ -29,6 +29,18 @@ pub_io_port_index!(BGP_REGISTER_INDEX, BGP_REGISTER_ADDRESS);
 pub_io_port_index!(OBP0_REGISTER_INDEX, OBP0_REGISTER_ADDRESS);
 pub_io_port_index!(OBP1_REGISTER_INDEX, OBP1_REGISTER_ADDRESS);
 pub_io_port_index!(IF_REGISTER_INDEX, IF_REGISTER_ADDRESS);
+pub_io_port_index!(KEY1_REGISTER_INDEX, KEY1_REGISTER_ADDRESS);
+pub_io_port_index!(VBK_REGISTER_INDEX, VBK_REGISTER_ADDRESS);
+pub_io_port_index!(BCPS_REGISTER_INDEX, BCPS_REGISTER_ADDRESS);
+pub_io_port_index!(BCPD_REGISTER_INDEX, BCPD_REGISTER_ADDRESS);
+pub_io_port_index!(OCPS_REGISTER_INDEX, OCPS_REGISTER_ADDRESS);
+pub_io_port_index!(OCPD_REGISTER_INDEX, OCPD_REGISTER_ADDRESS);
+pub_io_port_index!(OPRI_REGISTER_INDEX, OPRI_REGISTER_ADDRESS);
+pub_io_port_index!(HDMA1_REGISTER_INDEX, HDMA1_REGISTER_ADDRESS);
+pub_io_port_index!(HDMA2_REGISTER_INDEX, HDMA2_REGISTER_ADDRESS);
+pub_io_port_index!(HDMA3_REGISTER_INDEX, HDMA3_REGISTER_ADDRESS);
+pub_io_port_index!(HDMA4_REGISTER_INDEX, HDMA4_REGISTER_ADDRESS);
+pub_io_port_index!(HDMA5_REGISTER_INDEX, HDMA5_REGISTER_ADDRESS);
 
 pub_io_port_index!(JOYP_REGISTER_INDEX, JOYP_REGISTER_ADDRESS);
 pub_io_port_index!(NR10_REGISTER_INDEX, NR10_REGISTER_ADDRESS);
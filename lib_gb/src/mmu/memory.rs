@@ -1,5 +1,31 @@
-
 pub trait Memory{
     fn read(&mut self, address:u16, m_cycles:u8)->u8;
     fn write(&mut self, address:u16, value:u8, m_cycles:u8);
+
+    // Same as read but lets the implementation know the byte is fetched as an opcode (used for code/data logging)
+    fn read_opcode(&mut self, address:u16, m_cycles:u8)->u8{
+        self.read(address, m_cycles)
+    }
+
+    fn read_operand(&mut self, address:u16, m_cycles:u8)->u8{
+        self.read(address, m_cycles)
+    }
+
+    // A read while the increment/decrement unit updates the register holding the address (used for the dmg oam corruption)
+    fn read_with_inc_dec(&mut self, address:u16, m_cycles:u8)->u8{
+        self.read(address, m_cycles)
+    }
+
+    // The increment/decrement unit puts the register value on the address bus without accessing the memory,
+    // the implementation must still advance the m_cycles
+    fn inc_dec_access(&mut self, address:u16, m_cycles:u8);
+}
+
+// A specific bank of a banked memory region, regardless of the bank currently mapped by the hardware
+#[derive(Clone, Copy)]
+pub enum MemoryBank{
+    Rom(u16),
+    Vram(u8),
+    Wram(u8),
+    CartRam(u16)
 }
\ No newline at end of file
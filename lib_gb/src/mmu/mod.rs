@@ -8,4 +8,6 @@ pub mod access_bus;
 pub mod io_bus;
 pub mod interrupts_handler;
 pub mod external_memory_bus;
-pub mod oam_dma_controller;
\ No newline at end of file
+pub mod oam_dma_controller;
+pub mod vram_dma_controller;
+pub mod code_data_logger;
\ No newline at end of file
@@ -1,58 +1,98 @@
 use crate::ppu::{gb_ppu::GbPpu, gfx_device::GfxDevice};
-use super::{external_memory_bus::ExternalMemoryBus, access_bus::AccessBus};
+use super::{external_memory_bus::ExternalMemoryBus, access_bus::AccessBus, code_data_logger::MemoryAccessType};
 
 const DMA_SIZE:u16 = 0xA0;
 const VRAM_BASE_ADDRESS:u16 = 0x8000;
+// Sources above the wram are read from the wram echo (the address bus ignores bit 13)
+const ECHO_SOURCE_BASE_ADDRESS:u16 = 0xE000;
+const ECHO_SOURCE_OFFSET:u16 = 0x2000;
+// The transfer starts an m_cycle after the register write
+const DMA_START_DELAY_M_CYCLES:u8 = 1;
+
+#[derive(Clone, Copy)]
+struct DmaTransfer{
+    source_address:u16,
+    bus:AccessBus,
+    index:u16
+}
 
 pub struct OamDmaController{
-    soure_address:u16,
-    enable:Option<AccessBus>,
-    dma_cycle_counter:u16
+    dma_register:u8,
+    transfer:Option<DmaTransfer>,
+    // A restarted transfer keeps the running one going until the new one starts
+    pending_transfer:Option<DmaTransfer>,
+    start_delay_counter:u8,
+    occupied_bus:Option<AccessBus>,
+    transferred_value:u8
 }
 
 impl OamDmaController{
     pub fn new()->Self{
-        Self{dma_cycle_counter:0, enable:None, soure_address:0}
+        Self{dma_register:0, transfer:None, pending_transfer:None, start_delay_counter:0, occupied_bus:None, transferred_value:0}
     }
+
+    // Transfers a byte every m_cycle, the bus is occupied during the m_cycles a byte is transferred
     pub fn cycle<G:GfxDevice>(&mut self, m_cycles:u32, external_bus: &mut ExternalMemoryBus, ppu:&mut GbPpu<G>)->Option<AccessBus>{
-        if let Some(bus) = self.enable{
-            let cycles_to_run = std::cmp::min(self.dma_cycle_counter + m_cycles as u16, DMA_SIZE);
-            match bus{
-                AccessBus::External=>{
-                    for i in self.dma_cycle_counter..cycles_to_run as u16{
-                        let source_value = external_bus.read(self.soure_address + i);
-                        ppu.oam[i as usize] = source_value;
-                    }
+        for _ in 0..m_cycles{
+            if self.transfer.is_none() && self.pending_transfer.is_none(){
+                self.occupied_bus = None;
+                break;
+            }
+
+            if self.pending_transfer.is_some(){
+                if self.start_delay_counter == 0{
+                    self.transfer = self.pending_transfer.take();
                 }
-                AccessBus::Video=>{
-                    let base_source_address = self.soure_address - VRAM_BASE_ADDRESS;
-                    for i in self.dma_cycle_counter..cycles_to_run as u16{
-                        let source_value = ppu.vram.read_current_bank(base_source_address + i);
-                        ppu.oam[i as usize] = source_value;
-                    }
+                else{
+                    self.start_delay_counter -= 1;
                 }
             }
 
-            self.dma_cycle_counter += m_cycles as u16;
-            if self.dma_cycle_counter >= DMA_SIZE{
-                self.dma_cycle_counter = 0;
-                self.enable = Option::None;
+            self.occupied_bus = None;
+            if let Some(transfer) = &mut self.transfer{
+                let source_address = transfer.source_address + transfer.index;
+                self.transferred_value = match transfer.bus{
+                    AccessBus::External=>external_bus.read(source_address, MemoryAccessType::DmaSource),
+                    AccessBus::Video=>ppu.vram.read_current_bank(source_address - VRAM_BASE_ADDRESS)
+                };
+                ppu.oam[transfer.index as usize] = self.transferred_value;
+                self.occupied_bus = Some(transfer.bus);
+
+                transfer.index += 1;
+                if transfer.index == DMA_SIZE{
+                    self.transfer = None;
+                }
             }
         }
 
-        return self.enable;
+        return self.occupied_bus;
+    }
+
+    // Includes the start delay of a pending transfer
+    pub fn is_active(&self)->bool{
+        self.occupied_bus.is_some() || self.pending_transfer.is_some()
+    }
+
+    // The value on the occupied bus, this is what the cpu reads when accessing it
+    pub fn get_transferred_value(&self)->u8{
+        self.transferred_value
     }
 
     pub fn get_dma_register(&self)->u8{
-        (self.soure_address >> 8) as u8
+        self.dma_register
     }
 
     pub fn set_dma_register(&mut self, value:u8){
-        let address = (value as u16) << 8;
-        self.soure_address = address;
-        self.enable = match value{
-            0..=0x7F | 0xA0..=0xFF=> Some(AccessBus::External),
-            0x80..=0x9F=> Some(AccessBus::Video),
+        self.dma_register = value;
+        let mut source_address = (value as u16) << 8;
+        if source_address >= ECHO_SOURCE_BASE_ADDRESS{
+            source_address -= ECHO_SOURCE_OFFSET;
         }
+        let bus = match value{
+            0x80..=0x9F=> AccessBus::Video,
+            _=> AccessBus::External,
+        };
+        self.pending_transfer = Some(DmaTransfer{source_address, bus, index:0});
+        self.start_delay_counter = DMA_START_DELAY_M_CYCLES;
     }
-}
\ No newline at end of file
+}
@@ -1,6 +1,6 @@
 
 const RAM_SZIE:usize = 0x8000;
-const BANK_SIZE:usize = 0x1000;
+pub const BANK_SIZE:usize = 0x1000;
 
 pub struct Ram{
     memory: [u8;RAM_SZIE],
@@ -24,6 +24,10 @@ impl Ram{
         self.memory[self.get_valid_address(address)] = value;
     }
 
+    pub fn get_memory_mut(&mut self)->&mut [u8]{
+        &mut self.memory
+    }
+
     pub fn set_bank(&mut self, mut bank:u8){
         if bank == 0{
             bank = 1;
@@ -32,6 +36,10 @@ impl Ram{
         self.ram_bank_register = bank;
     }
 
+    pub fn get_bank(&self)->u8{
+        self.ram_bank_register
+    }
+
     fn get_valid_address(&self, address:u16)->usize{
         return BANK_SIZE*(self.ram_bank_register as usize) + (address as usize);
     }
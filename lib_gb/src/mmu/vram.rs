@@ -1,5 +1,5 @@
 const VRAM_SIZE:usize = 0x4000;
-const VRAM_BANK_SIZE:usize = 0x2000;
+pub const VRAM_BANK_SIZE:usize = 0x2000;
 pub struct VRam{
     memory:[u8;VRAM_SIZE],
     current_bank_register:u8
@@ -10,6 +10,15 @@ impl VRam{
         self.current_bank_register = bank;
     }
 
+    pub fn get_bank(&self)->u8{
+        self.current_bank_register
+    }
+
+    // Used by the ppu which accesses the banks regardless of the bank register
+    pub fn read_bank(&self, bank:u8, address:u16)->u8{
+        return self.memory[(address as usize) + ((bank as usize) * VRAM_BANK_SIZE)];
+    }
+
     pub fn read_current_bank(&self, address:u16)->u8{
         return self.memory[self.get_valid_address(address)];
     }
@@ -18,6 +27,10 @@ impl VRam{
         self.memory[self.get_valid_address(address)] = value;
     }
 
+    pub fn get_memory_mut(&mut self)->&mut [u8]{
+        &mut self.memory
+    }
+
     fn get_valid_address(&self, address:u16)->usize{
         return (address as usize) + ((self.current_bank_register as usize)*VRAM_BANK_SIZE);
     }
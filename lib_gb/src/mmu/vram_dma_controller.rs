@@ -0,0 +1,127 @@
+use crate::ppu::{gb_ppu::{GbPpu, SCREEN_HEIGHT}, gfx_device::GfxDevice, ppu_state::PpuState};
+use crate::utils::bit_masks::BIT_7_MASK;
+use super::{external_memory_bus::ExternalMemoryBus, code_data_logger::MemoryAccessType};
+
+const BLOCK_SIZE:u16 = 0x10;
+// The transfer copies 2 bytes every m_cycle and the cpu is stalled meanwhile
+const BLOCK_M_CYCLES:u32 = 8;
+const VRAM_SIZE:u16 = 0x2000;
+const BLOCKS_COUNT_MASK:u8 = 0x7F;
+
+#[derive(Clone, Copy, PartialEq)]
+enum TransferMode{
+    General,
+    HBlank
+}
+
+// The cgb vram dma (HDMA1-HDMA5), copies from the external bus to the current vram bank in blocks of 16 bytes.
+// A general purpose transfer copies everything at once and an hblank transfer copies a block at the start of every hblank.
+pub struct VramDmaController{
+    source_address:u16,
+    destination_address:u16,
+    remaining_blocks:u8,
+    transfer:Option<TransferMode>,
+    // Set when an hblank transfer is started while the lcd is off, in this case a block is copied immediately
+    pending_block:bool,
+    last_ppu_state:PpuState
+}
+
+impl VramDmaController{
+    pub fn new()->Self{
+        Self{source_address:0, destination_address:0, remaining_blocks:0, transfer:None, pending_block:false, last_ppu_state:PpuState::Hblank}
+    }
+
+    // Returns the m_cycles the cpu is stalled for the transferred blocks
+    pub fn cycle<G:GfxDevice>(&mut self, external_bus:&mut ExternalMemoryBus, ppu:&mut GbPpu<G>)->u32{
+        let entered_hblank = ppu.lcd_control & BIT_7_MASK != 0 && ppu.state == PpuState::Hblank &&
+            self.last_ppu_state != PpuState::Hblank && ppu.ly_register < SCREEN_HEIGHT as u8;
+        self.last_ppu_state = ppu.state;
+
+        let blocks_to_transfer = match self.transfer{
+            Some(TransferMode::General)=>self.remaining_blocks,
+            Some(TransferMode::HBlank) if entered_hblank || self.pending_block=>1,
+            _=>return 0
+        };
+        self.pending_block = false;
+
+        let mut transferred_blocks = 0;
+        while transferred_blocks < blocks_to_transfer && self.transfer.is_some(){
+            self.transfer_block(external_bus, ppu);
+            transferred_blocks += 1;
+        }
+
+        return transferred_blocks as u32 * BLOCK_M_CYCLES;
+    }
+
+    pub fn set_hdma1(&mut self, value:u8){
+        self.source_address = (self.source_address & 0xFF) | ((value as u16) << 8);
+    }
+
+    // The lower 4 bits of the addresses are ignored
+    pub fn set_hdma2(&mut self, value:u8){
+        self.source_address = (self.source_address & 0xFF00) | (value & 0xF0) as u16;
+    }
+
+    // The destination is always in vram
+    pub fn set_hdma3(&mut self, value:u8){
+        self.destination_address = (self.destination_address & 0xFF) | (((value & 0x1F) as u16) << 8);
+    }
+
+    pub fn set_hdma4(&mut self, value:u8){
+        self.destination_address = (self.destination_address & 0xFF00) | (value & 0xF0) as u16;
+    }
+
+    // Bit 7 is cleared while a transfer is active, the lower bits are the remaining blocks minus 1 (0xFF after a completed transfer)
+    pub fn get_hdma5(&self)->u8{
+        return ((self.transfer.is_none() as u8) << 7) | (self.remaining_blocks.wrapping_sub(1) & BLOCKS_COUNT_MASK);
+    }
+
+    pub fn set_hdma5(&mut self, value:u8, lcd_enabled:bool){
+        // Writing with bit 7 cleared during an hblank transfer cancels it
+        if self.transfer == Some(TransferMode::HBlank) && value & BIT_7_MASK == 0{
+            self.transfer = None;
+            self.pending_block = false;
+            return;
+        }
+
+        self.remaining_blocks = (value & BLOCKS_COUNT_MASK) + 1;
+        if value & BIT_7_MASK == 0{
+            self.transfer = Some(TransferMode::General);
+        }
+        else{
+            self.transfer = Some(TransferMode::HBlank);
+            self.pending_block = !lcd_enabled;
+        }
+    }
+
+    fn transfer_block<G:GfxDevice>(&mut self, external_bus:&mut ExternalMemoryBus, ppu:&mut GbPpu<G>){
+        for _ in 0..BLOCK_SIZE{
+            let value = match self.source_address{
+                // Vram cant be the source since the destination is on the same bus
+                0x8000..=0x9FFF=>0xFF,
+                // The upper addresses are mirrors of the external ram
+                0xE000..=0xFFFF=>external_bus.read(self.source_address - 0x4000, MemoryAccessType::DmaSource),
+                _=>external_bus.read(self.source_address, MemoryAccessType::DmaSource)
+            };
+            ppu.vram.write_current_bank(self.destination_address, value);
+            self.source_address = self.source_address.wrapping_add(1);
+            self.destination_address += 1;
+        }
+
+        self.remaining_blocks -= 1;
+        // The transfer stops when the destination overflows the vram
+        if self.destination_address == VRAM_SIZE{
+            self.destination_address = 0;
+            self.remaining_blocks = 0;
+        }
+        if self.remaining_blocks == 0{
+            self.transfer = None;
+        }
+    }
+}
+
+impl Default for VramDmaController{
+    fn default()->Self{
+        Self::new()
+    }
+}
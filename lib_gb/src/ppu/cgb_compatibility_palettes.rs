@@ -0,0 +1,242 @@
+// The palettes the cgb boot rom colorizes dmg games with. The palettes are selected by a checksum of the cartridge title
+// (only for games licensed by Nintendo) or by a key combination the user holds during the boot logo.
+
+use crate::mmu::carts::mbc::Mbc;
+use super::{color::Color, colors::DmgPalettes};
+
+const OLD_LICENSEE_CODE_ADDRESS:u16 = 0x14B;
+const NEW_LICENSEE_CODE_ADDRESS:u16 = 0x144;
+const NEW_LICENSEE_CODE_MARKER:u8 = 0x33;
+const NINTENDO_OLD_LICENSEE_CODE:u8 = 0x01;
+const NINTENDO_NEW_LICENSEE_CODE:[u8;2] = *b"01";
+const TITLE_ADDRESS:u16 = 0x134;
+const TITLE_SIZE:u16 = 0x10;
+const TITLE_FOURTH_LETTER_ADDRESS:u16 = TITLE_ADDRESS + 3;
+
+const DEFAULT_COMBINATION_INDEX:u8 = 0;
+const COLORS_PER_PALETTE:usize = 4;
+
+const TITLE_CHECKSUMS:[u8;79] = [
+    0x00, 0x88, 0x16, 0x36, 0xD1, 0xDB, 0xF2, 0x3C, 0x8C, 0x92, 0x3D, 0x5C, 0x58, 0xC9, 0x3E, 0x70,
+    0x1D, 0x59, 0x69, 0x19, 0x35, 0xA8, 0x14, 0xAA, 0x75, 0x95, 0x99, 0x34, 0x6F, 0x15, 0xFF, 0x97,
+    0x4B, 0x90, 0x17, 0x10, 0x39, 0xF7, 0xF6, 0xA2, 0x49, 0x4E, 0x43, 0x68, 0xE0, 0x8B, 0xF0, 0xCE,
+    0x0C, 0x29, 0xE8, 0xB7, 0x86, 0x9A, 0x52, 0x01, 0x9D, 0x71, 0x9C, 0xBD, 0x5D, 0x6D, 0x67, 0x3F,
+    0x6B,
+    // These checksums are shared by several games which are told apart by the 4th letter of the title
+    0xB3, 0x46, 0x28, 0xA5, 0xC6, 0xD3, 0x27, 0x61, 0x18, 0x66, 0x6A, 0xBF, 0x0D, 0xF4
+];
+const FIRST_DUPLICATE_CHECKSUM_INDEX:usize = 65;
+const DUPLICATE_CHECKSUMS_COUNT:usize = TITLE_CHECKSUMS.len() - FIRST_DUPLICATE_CHECKSUM_INDEX;
+
+// The candidates for each duplicate checksum are every DUPLICATE_CHECKSUMS_COUNT letters
+const DUPLICATES_FOURTH_LETTERS:[u8;29] = *b"BEFAARBEKEK R-URAR INAILICE R";
+
+// The palettes combination of every title checksum followed by the combination of every duplicates letter
+const COMBINATION_INDEXES:[u8;FIRST_DUPLICATE_CHECKSUM_INDEX + DUPLICATES_FOURTH_LETTERS.len()] = [
+    0, 4, 5, 35, 34, 3, 31, 15, 10, 5, 19, 36, 7, 37, 30, 44,
+    21, 32, 31, 20, 5, 33, 13, 14, 5, 29, 5, 18, 9, 3, 2, 26,
+    25, 25, 41, 42, 26, 45, 42, 45, 36, 38, 26, 42, 30, 41, 34, 34,
+    5, 42, 6, 5, 33, 25, 42, 42, 40, 2, 16, 25, 42, 42, 5, 0,
+    39,
+    36, 22, 25, 6, 32, 12, 36, 11, 39, 18, 39, 24, 31, 50, 17, 46,
+    6, 27, 0, 47, 41, 41, 0, 0, 19, 34, 23, 18, 29
+];
+
+const fn palette(index:usize)->usize{
+    index * COLORS_PER_PALETTE
+}
+
+// The offset of the first color in PALETTES for the objects 0, objects 1 and background palettes.
+// Most of the combinations are aligned to a palette but some of them start in the middle of one
+const COMBINATIONS:[[usize;3];51] = [
+    [palette(4), palette(4), palette(29)],
+    [palette(18), palette(18), palette(18)],
+    [palette(20), palette(20), palette(20)],
+    [palette(24), palette(24), palette(24)],
+    [palette(9), palette(9), palette(9)],
+    [palette(0), palette(0), palette(0)],
+    [palette(27), palette(27), palette(27)],
+    [palette(5), palette(5), palette(5)],
+    [palette(12), palette(12), palette(12)],
+    [palette(26), palette(26), palette(26)],
+    [palette(16), palette(8), palette(8)],
+    [palette(4), palette(28), palette(28)],
+    [palette(4), palette(2), palette(2)],
+    [palette(3), palette(4), palette(4)],
+    [palette(4), palette(29), palette(29)],
+    [palette(28), palette(4), palette(28)],
+    [palette(2), palette(17), palette(2)],
+    [palette(16), palette(16), palette(8)],
+    [palette(4), palette(4), palette(7)],
+    [palette(4), palette(4), palette(18)],
+    [palette(4), palette(4), palette(20)],
+    [palette(19), palette(19), palette(9)],
+    [palette(4) - 1, palette(4) - 1, palette(11)],
+    [palette(17), palette(17), palette(2)],
+    [palette(4), palette(4), palette(2)],
+    [palette(4), palette(4), palette(3)],
+    [palette(28), palette(28), palette(0)],
+    [palette(3), palette(3), palette(0)],
+    [palette(0), palette(0), palette(1)],
+    [palette(18), palette(22), palette(18)],
+    [palette(20), palette(22), palette(20)],
+    [palette(24), palette(22), palette(24)],
+    [palette(16), palette(22), palette(8)],
+    [palette(17), palette(4), palette(13)],
+    [palette(28) - 1, palette(0), palette(14)],
+    [palette(28) - 1, palette(4), palette(15)],
+    [palette(19), palette(22), palette(9)],
+    [palette(16), palette(28), palette(10)],
+    [palette(4), palette(23), palette(28)],
+    [palette(17), palette(22), palette(2)],
+    [palette(4), palette(0), palette(2)],
+    [palette(4), palette(28), palette(3)],
+    [palette(28), palette(3), palette(0)],
+    [palette(3), palette(28), palette(4)],
+    [palette(21), palette(28), palette(4)],
+    [palette(3), palette(28), palette(0)],
+    [palette(25), palette(3), palette(28)],
+    [palette(0), palette(28), palette(8)],
+    [palette(4), palette(3), palette(28)],
+    [palette(28), palette(3), palette(6)],
+    [palette(4), palette(28), palette(29)]
+];
+
+// RGB555 colors, 4 for each palette
+const PALETTES:[u16;30 * COLORS_PER_PALETTE] = [
+    0x7FFF, 0x32BF, 0x00D0, 0x0000,
+    0x639F, 0x4279, 0x15B0, 0x04CB,
+    0x7FFF, 0x6E31, 0x454A, 0x0000,
+    0x7FFF, 0x1BEF, 0x0200, 0x0000,
+    0x7FFF, 0x421F, 0x1CF2, 0x0000,
+    0x7FFF, 0x5294, 0x294A, 0x0000,
+    0x7FFF, 0x03FF, 0x012F, 0x0000,
+    0x7FFF, 0x03EF, 0x01D6, 0x0000,
+    0x7FFF, 0x42B5, 0x3DC8, 0x0000,
+    0x7E74, 0x03FF, 0x0180, 0x0000,
+    0x67FF, 0x77AC, 0x1A13, 0x2D6B,
+    0x7ED6, 0x4BFF, 0x2175, 0x0000,
+    0x53FF, 0x4A5F, 0x7E52, 0x0000,
+    0x4FFF, 0x7ED2, 0x3A4C, 0x1CE0,
+    0x03ED, 0x7FFF, 0x255F, 0x0000,
+    0x036A, 0x021F, 0x03FF, 0x7FFF,
+    0x7FFF, 0x01DF, 0x0112, 0x0000,
+    0x231F, 0x035F, 0x00F2, 0x0009,
+    0x7FFF, 0x03EA, 0x011F, 0x0000,
+    0x299F, 0x001A, 0x000C, 0x0000,
+    0x7FFF, 0x027F, 0x001F, 0x0000,
+    0x7FFF, 0x03E0, 0x0206, 0x0120,
+    0x7FFF, 0x7EEB, 0x001F, 0x7C00,
+    0x7FFF, 0x3FFF, 0x7E00, 0x001F,
+    0x7FFF, 0x03FF, 0x001F, 0x0000,
+    0x03FF, 0x001F, 0x000C, 0x0000,
+    0x7FFF, 0x033F, 0x0193, 0x0000,
+    0x0000, 0x4200, 0x037F, 0x7FFF,
+    0x7FFF, 0x7E8C, 0x7C00, 0x0000,
+    0x7FFF, 0x1BEF, 0x6180, 0x0000
+];
+
+// The key combinations the boot rom accepts, the names are the colors the cgb manual lists for them
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PaletteKeyCombination{
+    Up,         // Brown
+    UpA,        // Red
+    UpB,        // Dark brown
+    Left,       // Blue
+    LeftA,      // Dark blue
+    LeftB,      // Grayscale
+    Down,       // Pale yellow
+    DownA,      // Orange
+    DownB,      // Yellow
+    Right,      // Green
+    RightA,     // Dark green
+    RightB      // Inverted
+}
+
+impl PaletteKeyCombination{
+    pub const ALL:[PaletteKeyCombination;12] = [
+        Self::Up, Self::UpA, Self::UpB, Self::Left, Self::LeftA, Self::LeftB,
+        Self::Down, Self::DownA, Self::DownB, Self::Right, Self::RightA, Self::RightB
+    ];
+
+    pub fn get_palettes(self)->DmgPalettes{
+        let combination_index = match self{
+            Self::Up=>5,
+            Self::UpA=>43,
+            Self::UpB=>28,
+            Self::Left=>48,
+            Self::LeftA=>40,
+            Self::LeftB=>7,
+            Self::Down=>8,
+            Self::DownA=>3,
+            Self::DownB=>49,
+            Self::Right=>1,
+            Self::RightA=>0,
+            Self::RightB=>6
+        };
+
+        return get_combination_palettes(combination_index);
+    }
+
+    pub fn get_name(self)->&'static str{
+        match self{
+            Self::Up=>"Up",
+            Self::UpA=>"Up + A",
+            Self::UpB=>"Up + B",
+            Self::Left=>"Left",
+            Self::LeftA=>"Left + A",
+            Self::LeftB=>"Left + B",
+            Self::Down=>"Down",
+            Self::DownA=>"Down + A",
+            Self::DownB=>"Down + B",
+            Self::Right=>"Right",
+            Self::RightA=>"Right + A",
+            Self::RightB=>"Right + B"
+        }
+    }
+}
+
+// The palettes the boot rom chooses for the cartridge when no key combination is held
+pub fn get_compatibility_palettes(mbc:&dyn Mbc)->DmgPalettes{
+    return get_combination_palettes(get_combination_index(mbc));
+}
+
+fn get_combination_index(mbc:&dyn Mbc)->u8{
+    let licensed_by_nintendo = match mbc.read_bank0(OLD_LICENSEE_CODE_ADDRESS){
+        NEW_LICENSEE_CODE_MARKER=>[mbc.read_bank0(NEW_LICENSEE_CODE_ADDRESS), mbc.read_bank0(NEW_LICENSEE_CODE_ADDRESS + 1)] == NINTENDO_NEW_LICENSEE_CODE,
+        code=>code == NINTENDO_OLD_LICENSEE_CODE
+    };
+    if !licensed_by_nintendo{
+        return DEFAULT_COMBINATION_INDEX;
+    }
+
+    let checksum = (TITLE_ADDRESS..TITLE_ADDRESS + TITLE_SIZE).fold(0u8, |sum, address|sum.wrapping_add(mbc.read_bank0(address)));
+    return match TITLE_CHECKSUMS.iter().position(|c|*c == checksum){
+        Some(index) if index < FIRST_DUPLICATE_CHECKSUM_INDEX=>COMBINATION_INDEXES[index],
+        Some(index)=>{
+            let fourth_letter = mbc.read_bank0(TITLE_FOURTH_LETTER_ADDRESS);
+            let letter_index = (index - FIRST_DUPLICATE_CHECKSUM_INDEX..DUPLICATES_FOURTH_LETTERS.len())
+                .step_by(DUPLICATE_CHECKSUMS_COUNT)
+                .find(|i|DUPLICATES_FOURTH_LETTERS[*i] == fourth_letter);
+            match letter_index{
+                Some(letter_index)=>COMBINATION_INDEXES[FIRST_DUPLICATE_CHECKSUM_INDEX + letter_index],
+                None=>DEFAULT_COMBINATION_INDEX
+            }
+        }
+        None=>DEFAULT_COMBINATION_INDEX
+    };
+}
+
+fn get_combination_palettes(combination_index:u8)->DmgPalettes{
+    let [obj0, obj1, bg] = COMBINATIONS[combination_index as usize];
+    return DmgPalettes{bg:get_palette(bg), obj0:get_palette(obj0), obj1:get_palette(obj1)};
+}
+
+fn get_palette(first_color:usize)->[Color;COLORS_PER_PALETTE]{
+    let mut palette = [Color::default();COLORS_PER_PALETTE];
+    for (i, color) in palette.iter_mut().enumerate(){
+        *color = Color::from_rgb555(PALETTES[first_color + i]);
+    }
+
+    return palette;
+}
@@ -0,0 +1,70 @@
+use crate::utils::bit_masks::BIT_7_MASK;
+use super::color::Color;
+
+const PALETTES_COUNT:usize = 8;
+const COLORS_PER_PALETTE:usize = 4;
+const PALETTE_RAM_SIZE:usize = PALETTES_COUNT * COLORS_PER_PALETTE * 2;
+const INDEX_MASK:u8 = 0b11_1111;
+// Bit 6 of the index register is unused and always reads as set
+const INDEX_REGISTER_UNUSED_BITS:u8 = 0b0100_0000;
+
+// The cgb palette ram (background or objects), accessed through an index register (BCPS/OCPS) and a data register (BCPD/OCPD).
+// Each color is stored as little endian RGB555.
+pub struct CgbPaletteRam{
+    ram:[u8;PALETTE_RAM_SIZE],
+    colors:[[Color;COLORS_PER_PALETTE];PALETTES_COUNT],
+    index:u8,
+    auto_increment:bool
+}
+
+impl CgbPaletteRam{
+    pub fn get_index_register(&self)->u8{
+        return ((self.auto_increment as u8) << 7) | INDEX_REGISTER_UNUSED_BITS | self.index;
+    }
+
+    pub fn set_index_register(&mut self, value:u8){
+        self.index = value & INDEX_MASK;
+        self.auto_increment = value & BIT_7_MASK != 0;
+    }
+
+    pub fn read_data(&self)->u8{
+        return self.ram[self.index as usize];
+    }
+
+    // The index is incremented even when the ram is locked (during pixel transfer) and the write is ignored
+    pub fn write_data(&mut self, value:u8, locked:bool){
+        if !locked{
+            self.ram[self.index as usize] = value;
+            self.update_color(self.index as usize / 2);
+        }
+        if self.auto_increment{
+            self.index = (self.index + 1) & INDEX_MASK;
+        }
+    }
+
+    pub fn get_color(&self, palette:u8, color_number:u8)->Color{
+        return self.colors[palette as usize][color_number as usize];
+    }
+
+    fn update_color(&mut self, color_index:usize){
+        let rgb555 = u16::from_le_bytes([self.ram[color_index * 2], self.ram[(color_index * 2) + 1]]);
+        self.colors[color_index / COLORS_PER_PALETTE][color_index % COLORS_PER_PALETTE] = Color::from_rgb555(rgb555);
+    }
+}
+
+impl Default for CgbPaletteRam{
+    // Initialized to white like the cgb boot rom does
+    fn default()->Self{
+        let mut palette_ram = CgbPaletteRam{
+            ram:[0xFF;PALETTE_RAM_SIZE],
+            colors:[[Color::default();COLORS_PER_PALETTE];PALETTES_COUNT],
+            index:0,
+            auto_increment:false
+        };
+        for color_index in 0..PALETTES_COUNT * COLORS_PER_PALETTE{
+            palette_ram.update_color(color_index);
+        }
+
+        return palette_ram;
+    }
+}
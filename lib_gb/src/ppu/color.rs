@@ -1,11 +1,24 @@
 use super::gfx_device::Pixel;
 
+#[derive(Debug)]
 pub struct Color{
     pub r:u8,
     pub g:u8,
     pub b:u8
 }
 
+impl Color{
+    // Converts a cgb little endian RGB555 color, each 5 bit channel is scaled to 8 bits (so 0x1F is mapped to 0xFF)
+    pub fn from_rgb555(value:u16)->Color{
+        let expand_channel = |channel:u16|((channel << 3) | (channel >> 2)) as u8;
+        Color{
+            r:expand_channel(value & 0x1F),
+            g:expand_channel((value >> 5) & 0x1F),
+            b:expand_channel((value >> 10) & 0x1F)
+        }
+    }
+}
+
 impl Default for Color{
     fn default()->Color{
         Color{
@@ -48,4 +61,22 @@ impl From<Color> for Pixel{
             (((color.r >> 3) as u16) << 11) | (((color.g >> 2) as u16) << 5) | ((color.b >> 3) as u16)
         }
     }
+}
+
+impl From<Pixel> for Color{
+    #[inline]
+    fn from(pixel: Pixel) -> Self {
+        #[cfg(not(feature = "u16pixel"))]
+        {
+            Color{r:(pixel >> 16) as u8, g:(pixel >> 8) as u8, b:pixel as u8}
+        }
+        #[cfg(feature = "u16pixel")]
+        {
+            // Scales the RGB565 channels to 8 bits like Color::from_rgb555
+            let r = ((pixel >> 11) & 0x1F) as u8;
+            let g = ((pixel >> 5) & 0x3F) as u8;
+            let b = (pixel & 0x1F) as u8;
+            Color{r:(r << 3) | (r >> 2), g:(g << 2) | (g >> 4), b:(b << 3) | (b >> 2)}
+        }
+    }
 }
\ No newline at end of file
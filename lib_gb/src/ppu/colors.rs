@@ -3,4 +3,69 @@ use super::color::Color;
 pub const WHITE:Color = Color {r: 255,g: 255,b: 255};
 pub const LIGHT_GRAY:Color = Color {r: 160,g: 160,b: 160};
 pub const DARK_GRAY:Color = Color {r: 64,g: 64,b: 64};
-pub const BLACK:Color = Color {r: 0,g: 0,b: 0};
\ No newline at end of file
+pub const BLACK:Color = Color {r: 0,g: 0,b: 0};
+
+// The colors the 4 dmg shades are displayed with, separate for the background and each objects palette
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DmgPalettes{
+    pub bg:[Color;4],
+    pub obj0:[Color;4],
+    pub obj1:[Color;4]
+}
+
+impl DmgPalettes{
+    // The same shades for the background and the objects
+    pub const fn from_shades(shades:[Color;4])->Self{
+        DmgPalettes{bg:shades, obj0:shades, obj1:shades}
+    }
+}
+
+impl Default for DmgPalettes{
+    fn default()->Self{
+        DmgPalettesPreset::Grayscale.get_palettes()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DmgPalettesPreset{
+    Grayscale,
+    // The green tinted lcd of the original gameboy
+    DmgGreen,
+    Pocket,
+    // The gameboy light with the backlight on
+    Light,
+    // Evenly spaced shades from white to black
+    HighContrast,
+    // Shades that differ both in brightness and in an orange/blue hue, distinguishable with the common color vision deficiencies
+    ColorblindSafe
+}
+
+impl DmgPalettesPreset{
+    pub const ALL:[DmgPalettesPreset;6] = [
+        Self::Grayscale, Self::DmgGreen, Self::Pocket, Self::Light, Self::HighContrast, Self::ColorblindSafe
+    ];
+
+    pub fn get_palettes(self)->DmgPalettes{
+        let shades = match self{
+            Self::Grayscale=>[WHITE, LIGHT_GRAY, DARK_GRAY, BLACK],
+            Self::DmgGreen=>[Color{r:0x9B, g:0xBC, b:0x0F}, Color{r:0x8B, g:0xAC, b:0x0F}, Color{r:0x30, g:0x62, b:0x30}, Color{r:0x0F, g:0x38, b:0x0F}],
+            Self::Pocket=>[Color{r:0xC4, g:0xCF, b:0xA1}, Color{r:0x8B, g:0x95, b:0x6D}, Color{r:0x4D, g:0x53, b:0x3C}, Color{r:0x1F, g:0x1F, b:0x1F}],
+            Self::Light=>[Color{r:0x00, g:0xB5, b:0x81}, Color{r:0x00, g:0x9A, b:0x71}, Color{r:0x00, g:0x69, b:0x4A}, Color{r:0x00, g:0x4F, b:0x3B}],
+            Self::HighContrast=>[WHITE, Color{r:0xAA, g:0xAA, b:0xAA}, Color{r:0x55, g:0x55, b:0x55}, BLACK],
+            Self::ColorblindSafe=>[WHITE, Color{r:0xE6, g:0x9F, b:0x00}, Color{r:0x00, g:0x72, b:0xB2}, BLACK]
+        };
+
+        return DmgPalettes::from_shades(shades);
+    }
+
+    pub fn get_name(self)->&'static str{
+        match self{
+            Self::Grayscale=>"Grayscale",
+            Self::DmgGreen=>"DMG green",
+            Self::Pocket=>"Pocket",
+            Self::Light=>"Light",
+            Self::HighContrast=>"High contrast",
+            Self::ColorblindSafe=>"Colorblind safe"
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{machine::mode::Mode, utils::bit_masks::*};
+use super::{gb_ppu::{GbPpu, SCREEN_HEIGHT, SCREEN_WIDTH, OAM_MEMORY_SIZE}, gfx_device::GfxDevice, color::Color, sprite_attribute::SpriteAttribute};
+
+pub const TILE_SIZE:usize = 8;
+const TILE_BYTES_SIZE:u16 = 16;
+const TILES_PER_BANK:usize = 384;
+const VRAM_BANKS_COUNT:usize = 2;
+const TILES_VIEW_TILES_PER_ROW:usize = 16;
+pub const TILES_VIEW_WIDTH:usize = TILES_VIEW_TILES_PER_ROW * TILE_SIZE * VRAM_BANKS_COUNT;
+pub const TILES_VIEW_HEIGHT:usize = (TILES_PER_BANK / TILES_VIEW_TILES_PER_ROW) * TILE_SIZE;
+
+const TILE_MAP_TILES_PER_ROW:usize = 32;
+pub const TILE_MAP_VIEW_SIZE:usize = TILE_MAP_TILES_PER_ROW * TILE_SIZE;
+// The cgb tile attributes are stored in the second vram bank at the tile map address
+const TILE_ATTRIBUTES_VRAM_BANK:u8 = 1;
+
+const OAM_ENTRY_SIZE:usize = 4;
+pub const SPRITES_COUNT:usize = OAM_MEMORY_SIZE / OAM_ENTRY_SIZE;
+const SPRITES_VIEW_SPRITES_PER_ROW:usize = 8;
+const SPRITE_CELL_HEIGHT:usize = 16;
+pub const SPRITES_VIEW_WIDTH:usize = SPRITES_VIEW_SPRITES_PER_ROW * TILE_SIZE;
+pub const SPRITES_VIEW_HEIGHT:usize = (SPRITES_COUNT / SPRITES_VIEW_SPRITES_PER_ROW) * SPRITE_CELL_HEIGHT;
+
+const VIEWPORT_OVERLAY_COLOR:Color = Color{r:0xFF, g:0, b:0};
+const WINDOW_OVERLAY_COLOR:Color = Color{r:0, g:0, b:0xFF};
+// Drawn for the transparent sprite pixels
+const TRANSPARENT_COLOR:Color = Color{r:0xFF, g:0, b:0xFF};
+
+// A plain RGB888 buffer, row by row
+pub struct RgbImage{
+    pub width:usize,
+    pub height:usize,
+    pub buffer:Vec<u8>
+}
+
+impl RgbImage{
+    pub fn new(width:usize, height:usize)->Self{
+        Self{width, height, buffer:vec![0;width * height * 3]}
+    }
+
+    pub fn get_pixel(&self, x:usize, y:usize)->Color{
+        let index = ((y * self.width) + x) * 3;
+        return Color{r:self.buffer[index], g:self.buffer[index + 1], b:self.buffer[index + 2]};
+    }
+
+    pub fn set_pixel(&mut self, x:usize, y:usize, color:Color){
+        let index = ((y * self.width) + x) * 3;
+        self.buffer[index..index + 3].copy_from_slice(&[color.r, color.g, color.b]);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TileMap{
+    Map9800,
+    Map9C00
+}
+
+impl TileMap{
+    fn get_vram_address(&self)->u16{
+        match self{
+            TileMap::Map9800=>0x1800,
+            TileMap::Map9C00=>0x1C00
+        }
+    }
+
+    fn from_lcd_control_bit(lcd_control:u8, mask:u8)->TileMap{
+        if lcd_control & mask == 0 {TileMap::Map9800} else {TileMap::Map9C00}
+    }
+}
+
+// Debug views of the vram for tools and frontends, rendering them does not affect the emulation
+impl<GFX:GfxDevice> GbPpu<GFX>{
+    // All the tiles of both vram banks side by side, colored with the first bg palette
+    pub fn render_tiles(&self)->RgbImage{
+        let mut image = RgbImage::new(TILES_VIEW_WIDTH, TILES_VIEW_HEIGHT);
+        let bank_width = TILES_VIEW_TILES_PER_ROW * TILE_SIZE;
+        for bank in 0..VRAM_BANKS_COUNT{
+            for tile in 0..TILES_PER_BANK{
+                let tile_x = (bank * bank_width) + ((tile % TILES_VIEW_TILES_PER_ROW) * TILE_SIZE);
+                let tile_y = (tile / TILES_VIEW_TILES_PER_ROW) * TILE_SIZE;
+                for y in 0..TILE_SIZE{
+                    for x in 0..TILE_SIZE{
+                        let color_number = self.get_tile_color_number(bank as u8, tile as u16 * TILE_BYTES_SIZE, x, y);
+                        image.set_pixel(tile_x + x, tile_y + y, self.get_bg_color(0, color_number));
+                    }
+                }
+            }
+        }
+
+        return image;
+    }
+
+    // The viewport of the background (SCX/SCY) and the visible part of the window are outlined on the maps they use
+    pub fn render_tile_map(&self, tile_map:TileMap)->RgbImage{
+        let mut image = RgbImage::new(TILE_MAP_VIEW_SIZE, TILE_MAP_VIEW_SIZE);
+        let map_address = tile_map.get_vram_address();
+        for tile_index in 0..TILE_MAP_TILES_PER_ROW * TILE_MAP_TILES_PER_ROW{
+            let tile_number = self.vram.read_bank(0, map_address + tile_index as u16);
+            let attributes = if self.mode == Mode::CGB {self.vram.read_bank(TILE_ATTRIBUTES_VRAM_BANK, map_address + tile_index as u16)} else {0};
+            let tile_address = self.get_bg_tile_address(tile_number);
+            let bank = (attributes & BIT_3_MASK) >> 3;
+            let tile_x = (tile_index % TILE_MAP_TILES_PER_ROW) * TILE_SIZE;
+            let tile_y = (tile_index / TILE_MAP_TILES_PER_ROW) * TILE_SIZE;
+            for y in 0..TILE_SIZE{
+                for x in 0..TILE_SIZE{
+                    let tile_pixel_x = if attributes & BIT_5_MASK != 0 {TILE_SIZE - 1 - x} else {x};
+                    let tile_pixel_y = if attributes & BIT_6_MASK != 0 {TILE_SIZE - 1 - y} else {y};
+                    let color_number = self.get_tile_color_number(bank, tile_address, tile_pixel_x, tile_pixel_y);
+                    image.set_pixel(tile_x + x, tile_y + y, self.get_bg_color(attributes & 0b111, color_number));
+                }
+            }
+        }
+
+        if TileMap::from_lcd_control_bit(self.lcd_control, BIT_3_MASK) == tile_map{
+            draw_rectangle(&mut image, self.bg_pos.x as usize, self.bg_pos.y as usize, SCREEN_WIDTH, SCREEN_HEIGHT, VIEWPORT_OVERLAY_COLOR);
+        }
+        let window_visible = self.lcd_control & BIT_5_MASK != 0 && (self.window_pos.x as usize) < SCREEN_WIDTH && (self.window_pos.y as usize) < SCREEN_HEIGHT;
+        if window_visible && TileMap::from_lcd_control_bit(self.lcd_control, BIT_6_MASK) == tile_map{
+            let width = SCREEN_WIDTH - self.window_pos.x as usize;
+            let height = SCREEN_HEIGHT - self.window_pos.y as usize;
+            draw_rectangle(&mut image, 0, 0, width, height, WINDOW_OVERLAY_COLOR);
+        }
+
+        return image;
+    }
+
+    // The 40 oam sprites by their oam index, each in a 8x16 cell (8x8 sprites use only the top half)
+    pub fn render_sprites(&self)->RgbImage{
+        let mut image = RgbImage::new(SPRITES_VIEW_WIDTH, SPRITES_VIEW_HEIGHT);
+        let sprite_height = if self.lcd_control & BIT_2_MASK == 0 {TILE_SIZE} else {SPRITE_CELL_HEIGHT};
+        for (index, sprite) in self.get_sprites_attributes().iter().enumerate(){
+            let cell_x = (index % SPRITES_VIEW_SPRITES_PER_ROW) * TILE_SIZE;
+            let cell_y = (index / SPRITES_VIEW_SPRITES_PER_ROW) * SPRITE_CELL_HEIGHT;
+            let bank = if self.mode == Mode::CGB {sprite.vram_bank} else {0};
+            let tile_number = if sprite_height == SPRITE_CELL_HEIGHT {sprite.tile_number & !BIT_0_MASK} else {sprite.tile_number};
+            for y in 0..SPRITE_CELL_HEIGHT{
+                for x in 0..TILE_SIZE{
+                    if y >= sprite_height{
+                        image.set_pixel(cell_x + x, cell_y + y, TRANSPARENT_COLOR);
+                        continue;
+                    }
+                    let sprite_x = if sprite.flip_x {TILE_SIZE - 1 - x} else {x};
+                    let sprite_y = if sprite.flip_y {sprite_height - 1 - y} else {y};
+                    let tile_address = (tile_number as u16 + (sprite_y / TILE_SIZE) as u16) * TILE_BYTES_SIZE;
+                    let color_number = self.get_tile_color_number(bank, tile_address, sprite_x, sprite_y % TILE_SIZE);
+                    image.set_pixel(cell_x + x, cell_y + y, self.get_sprite_color(sprite, color_number));
+                }
+            }
+        }
+
+        return image;
+    }
+
+    pub fn get_sprites_attributes(&self)->Vec<SpriteAttribute>{
+        return self.oam.chunks_exact(OAM_ENTRY_SIZE).enumerate()
+            .map(|(index, entry)|SpriteAttribute::new(entry[0], entry[1], entry[2], entry[3], index as u8))
+            .collect();
+    }
+
+    fn get_tile_color_number(&self, bank:u8, tile_address:u16, x:usize, y:usize)->u8{
+        let line_address = tile_address + (y as u16 * 2);
+        let low_data = self.vram.read_bank(bank, line_address);
+        let high_data = self.vram.read_bank(bank, line_address + 1);
+        let bit = TILE_SIZE - 1 - x;
+        return ((low_data >> bit) & 1) | (((high_data >> bit) & 1) << 1);
+    }
+
+    pub(crate) fn get_bg_tile_address(&self, tile_number:u8)->u16{
+        let base_address = if self.lcd_control & BIT_4_MASK == 0 && tile_number & BIT_7_MASK == 0 {0x1000} else {0};
+        return base_address + (tile_number as u16 * TILE_BYTES_SIZE);
+    }
+
+    fn get_bg_color(&self, cgb_palette:u8, color_number:u8)->Color{
+        return match self.mode{
+            Mode::DMG=>self.bg_color_mapping[color_number as usize],
+            Mode::CGB=>self.bg_color_ram.get_color(cgb_palette, color_number)
+        };
+    }
+
+    fn get_sprite_color(&self, sprite:&SpriteAttribute, color_number:u8)->Color{
+        if color_number == 0{
+            return TRANSPARENT_COLOR;
+        }
+        return match self.mode{
+            Mode::DMG=>{
+                let mapping = if sprite.palette_number {&self.obj_color_mapping1} else {&self.obj_color_mapping0};
+                mapping[color_number as usize].unwrap_or(TRANSPARENT_COLOR)
+            }
+            Mode::CGB=>self.obj_color_ram.get_color(sprite.cgb_palette_number, color_number)
+        };
+    }
+}
+
+// The rectangle wraps around the edges of the image like the background scrolling
+fn draw_rectangle(image:&mut RgbImage, x:usize, y:usize, width:usize, height:usize, color:Color){
+    for i in 0..width{
+        image.set_pixel((x + i) % image.width, y % image.height, color);
+        image.set_pixel((x + i) % image.width, (y + height - 1) % image.height, color);
+    }
+    for i in 0..height{
+        image.set_pixel(x % image.width, (y + i) % image.height, color);
+        image.set_pixel((x + width - 1) % image.width, (y + i) % image.height, color);
+    }
+}
@@ -1,19 +1,19 @@
 use super::fetching_state::*;
 
-pub struct FetcherStateMachine{
+pub struct FetcherStateMachine<const SIZE:usize>{
     pub data:FetchingStateData,
     state:usize,
-    state_machine:[FetchingState;8]
+    state_machine:[FetchingState;SIZE]
 }
 
-impl FetcherStateMachine{
+impl<const SIZE:usize> FetcherStateMachine<SIZE>{
     pub fn advance(&mut self){
-        self.state = (self.state + 1) % 8;
+        self.state = (self.state + 1) % SIZE;
     }
 
-    pub fn new(state_machine:[FetchingState;8])->Self{
+    pub fn new(state_machine:[FetchingState;SIZE])->Self{
         Self{
-            data:FetchingStateData{high_tile_data:0, low_tile_data:0, tile_data:0},
+            data:FetchingStateData{high_tile_data:0, low_tile_data:0, tile_data:0, tile_attributes:0},
             state:0,
             state_machine
         }
@@ -27,4 +27,4 @@ impl FetcherStateMachine{
     pub fn current_state(&self)->&FetchingState{
         &self.state_machine[self.state]
     }
-}
\ No newline at end of file
+}
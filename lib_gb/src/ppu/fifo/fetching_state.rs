@@ -1,4 +1,4 @@
-// Since each operation takes 2 t_cycles I pad them with sleep for my implementation
+// Since each operation takes 2 t_cycles I pad them with sleep for my implementation, the operation happens on the first t_cycle
 pub enum FetchingState{
     FetchTileNumber,
     FetchLowTile,
@@ -11,6 +11,7 @@ pub enum FetchingState{
 
 pub struct FetchingStateData{
     pub tile_data:u8,
+    pub tile_attributes:u8,
     pub low_tile_data:u8,
     pub high_tile_data:u8,
 }
@@ -20,5 +21,6 @@ impl FetchingStateData{
         self.high_tile_data = 0;
         self.low_tile_data = 0;
         self.tile_data = 0;
+        self.tile_attributes = 0;
     }
 }
\ No newline at end of file
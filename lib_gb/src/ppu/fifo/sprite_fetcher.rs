@@ -1,121 +1,153 @@
-use crate::{mmu::vram::VRam, ppu::sprite_attribute::SpriteAttribute, utils::{self, bit_masks::{BIT_0_MASK, BIT_2_MASK}, fixed_size_queue::FixedSizeQueue}};
+use crate::{machine::mode::Mode, mmu::vram::VRam, ppu::{sprite_attribute::SpriteAttribute, layer_toggles::LayerToggles, hd_pack::HdTile}, utils::{self, bit_masks::{BIT_0_MASK, BIT_2_MASK}, fixed_size_queue::FixedSizeQueue}};
 use super::{FIFO_SIZE, SPRITE_WIDTH, fetcher_state_machine::FetcherStateMachine, fetching_state::*};
 
 pub const NORMAL_SPRITE_HIGHT:u8 = 8;
 pub const EXTENDED_SPRITE_HIGHT:u8 = 16;
 pub const MAX_SPRITES_PER_LINE:usize = 10;
+// Without the sprites per line limit all the oam entries can be on the same line
+pub const MAX_OAM_ENTRIES_PER_LINE:usize = 40;
 
 pub struct SpriteFetcher{
     pub fifo:FixedSizeQueue<(u8, u8), FIFO_SIZE>,
-    pub oam_entries:[SpriteAttribute; 10],
+    pub oam_entries:[SpriteAttribute; MAX_OAM_ENTRIES_PER_LINE],
     pub oam_entries_len:u8,
+    // The sprites after it are above the hardware sprites per line limit
+    pub last_timed_oam_index:u8,
     pub rendering:bool,
+    // The tile of every fetched oam entry, only set when hd rendering
+    pub(crate) hd_tiles:[Option<HdTile>; MAX_OAM_ENTRIES_PER_LINE],
+    pub hd_enabled:bool,
 
-    fetcher_state_machine:FetcherStateMachine,
+    fetcher_state_machine:FetcherStateMachine<6>,
     current_oam_entry:u8,
+    skip_x:u8,
+    mode:Mode
 }
 
 impl SpriteFetcher{
-    pub fn new()->Self{
-        let oam_entries:[SpriteAttribute; MAX_SPRITES_PER_LINE] = utils::create_array(|| SpriteAttribute::new(0,0,0,0));
-        let state_machine:[FetchingState;8] = [FetchingState::FetchTileNumber, FetchingState::Sleep, FetchingState::Sleep, FetchingState::FetchLowTile, FetchingState::Sleep, FetchingState::FetchHighTile, FetchingState::Sleep, FetchingState::Push];
+    pub fn new(mode:Mode)->Self{
+        let oam_entries:[SpriteAttribute; MAX_OAM_ENTRIES_PER_LINE] = utils::create_array(|| SpriteAttribute::new(0,0,0,0,0));
+        let state_machine:[FetchingState;6] = [FetchingState::FetchTileNumber, FetchingState::Sleep, FetchingState::FetchLowTile, FetchingState::Sleep, FetchingState::FetchHighTile, FetchingState::Push];
         
         SpriteFetcher{
             fetcher_state_machine:FetcherStateMachine::new(state_machine),
             current_oam_entry:0,
             oam_entries_len:0,
+            last_timed_oam_index:u8::MAX,
             oam_entries,
             fifo:FixedSizeQueue::<(u8,u8), 8>::new(),
             rendering:false,
+            hd_tiles:[None; MAX_OAM_ENTRIES_PER_LINE],
+            hd_enabled:false,
+            skip_x:0,
+            mode
         }
     }
 
     pub fn reset(&mut self){
         self.current_oam_entry = 0;
         self.oam_entries_len = 0;
+        self.last_timed_oam_index = u8::MAX;
         self.fetcher_state_machine.reset();
         self.fifo.clear();
         self.rendering = false;
+        self.skip_x = 0;
+        if self.hd_enabled{
+            self.hd_tiles = [None; MAX_OAM_ENTRIES_PER_LINE];
+        }
     }
 
-    pub fn fetch_pixels(&mut self, vram:&VRam, lcd_control:u8, ly_register:u8, current_x_pos:u8){
+    // current_x_pos is the position of the next pixel out of the fifo in oam coordinates (the screen x plus 8).
+    // A sprite starting at this position stalls the pixel transfer until it is fetched, except for the sprites
+    // above the hardware limit (last_timed_oam_index) which are fetched at once.
+    pub fn try_start_fetch(&mut self, vram:&VRam, lcd_control:u8, ly_register:u8, current_x_pos:u8, priority_by_x:bool, layer_toggles:&LayerToggles){
+        while self.oam_entries_len > self.current_oam_entry && self.oam_entries[self.current_oam_entry as usize].x <= current_x_pos{
+            let oam_entry = &self.oam_entries[self.current_oam_entry as usize];
+            let mut tile_number = oam_entry.tile_number;
+            if lcd_control & BIT_2_MASK != 0{
+                tile_number &= !BIT_0_MASK
+            }
+            // Sprites with x=0 stall the pixel transfer like the rest but all their pixels are discarded
+            self.skip_x = current_x_pos - oam_entry.x;
+            let timed = oam_entry.oam_index <= self.last_timed_oam_index;
+            self.rendering = true;
+            self.fetcher_state_machine.reset();
+            self.fetcher_state_machine.data.tile_data = tile_number;
+            if timed{
+                return;
+            }
+            while self.rendering{
+                self.fetch_pixels(vram, lcd_control, ly_register, priority_by_x, layer_toggles);
+            }
+        }
+    }
+
+    // Cycles the fetch of the current sprite for a single t_cycle
+    // When the priority is not by x (cgb mode) overlapping sprites are prioritized by their oam index,
+    // hidden sprites are pushed as transparent so the sprites below them are still drawn
+    pub fn fetch_pixels(&mut self, vram:&VRam, lcd_control:u8, ly_register:u8, priority_by_x:bool, layer_toggles:&LayerToggles){
         let sprite_size = if lcd_control & BIT_2_MASK == 0 {NORMAL_SPRITE_HIGHT} else{EXTENDED_SPRITE_HIGHT};
 
         match self.fetcher_state_machine.current_state(){
-            FetchingState::FetchTileNumber=>{
-                self.try_fetch_tile_number(current_x_pos, lcd_control);
-            }
+            // The tile number is read from the oam entry when the fetch starts
+            FetchingState::FetchTileNumber | FetchingState::Sleep=>{}
             FetchingState::FetchLowTile=>{
                 let tile_num = self.fetcher_state_machine.data.tile_data;
                 let oam_attribute = &self.oam_entries[self.current_oam_entry as usize];
                 let current_tile_data_address = Self::get_current_tile_data_address(ly_register, oam_attribute, sprite_size, tile_num);
-                let low_data = vram.read_current_bank(current_tile_data_address);
+                let low_data = vram.read_bank(self.get_vram_bank(oam_attribute), current_tile_data_address);
                 self.fetcher_state_machine.data.low_tile_data = low_data;
-                self.fetcher_state_machine.advance();
             }
             FetchingState::FetchHighTile=>{
                 let tile_num= self.fetcher_state_machine.data.tile_data;
                 let oam_attribute = &self.oam_entries[self.current_oam_entry as usize];
                 let current_tile_data_address = Self::get_current_tile_data_address(ly_register, oam_attribute, sprite_size, tile_num);
-                let high_data = vram.read_current_bank(current_tile_data_address + 1);
+                let high_data = vram.read_bank(self.get_vram_bank(oam_attribute), current_tile_data_address + 1);
                 self.fetcher_state_machine.data.high_tile_data = high_data;
-                self.fetcher_state_machine.advance();
             }
             FetchingState::Push=>{
                 let low_data = self.fetcher_state_machine.data.low_tile_data;
                 let high_data = self.fetcher_state_machine.data.high_tile_data;
-                let oam_attribute = &self.oam_entries[self.current_oam_entry as usize];
-                let start_x = self.fifo.len();
-                let skip_x = 8 - (oam_attribute.x - current_x_pos) as usize;
+                let oam_entry = &self.oam_entries[self.current_oam_entry as usize];
+                let flip_x = oam_entry.flip_x;
+                let hidden = layer_toggles.is_sprite_hidden(oam_entry.oam_index);
 
-                if oam_attribute.flip_x{
-                    for i in (0 + skip_x)..SPRITE_WIDTH as usize{
-                        let pixel = Self::get_decoded_pixel(i, low_data, high_data);
-                        if i >= start_x {
-                            self.fifo.push((pixel, self.current_oam_entry));
-                        }
-                        else if self.fifo[i].0 == 0{
-                            self.fifo[i] = (pixel, self.current_oam_entry);
-                        }
+                // The columns left to the current position are already out of the fifo
+                for column in self.skip_x as usize..SPRITE_WIDTH as usize{
+                    let bit = if flip_x {column} else {SPRITE_WIDTH as usize - 1 - column};
+                    let pixel = if hidden {0} else {Self::get_decoded_pixel(bit, low_data, high_data)};
+                    let fifo_index = column - self.skip_x as usize;
+                    if fifo_index >= self.fifo.len(){
+                        self.fifo.push((pixel, self.current_oam_entry));
                     }
-                }
-                else{
-                    let fifo_max_index = FIFO_SIZE as usize - 1;
-                    for i in (0..(SPRITE_WIDTH as usize - skip_x)).rev(){
-                        let pixel = Self::get_decoded_pixel(i, low_data, high_data);
-                        if fifo_max_index - skip_x - i >= start_x {
-                            self.fifo.push((pixel, self.current_oam_entry));
-                        }
-                        else if self.fifo[fifo_max_index - skip_x - i].0 == 0{
-                            self.fifo[fifo_max_index - skip_x - i] = (pixel, self.current_oam_entry);
-                        }
+                    else if self.should_replace_pixel(self.fifo[fifo_index], pixel, priority_by_x){
+                        self.fifo[fifo_index] = (pixel, self.current_oam_entry);
                     }
                 }
 
+                if self.hd_enabled{
+                    let oam_entry = &self.oam_entries[self.current_oam_entry as usize];
+                    let address = Self::get_current_tile_data_address(ly_register, oam_entry, sprite_size, self.fetcher_state_machine.data.tile_data);
+                    self.hd_tiles[self.current_oam_entry as usize] = Some(HdTile::new(self.get_vram_bank(oam_entry), address, flip_x, oam_entry.flip_y));
+                }
+
                 self.current_oam_entry += 1;
-                self.fetcher_state_machine.advance();
+                self.rendering = false;
             }
-            FetchingState::Sleep=>self.fetcher_state_machine.advance()
         }
+        self.fetcher_state_machine.advance();
     }
 
-    //This is a function on order to abort if rendering
-    fn try_fetch_tile_number(&mut self, current_x_pos: u8, lcd_control: u8) {
-        if self.oam_entries_len > self.current_oam_entry{
-            let oam_entry = &self.oam_entries[self.current_oam_entry as usize];
-            if oam_entry.x <= current_x_pos + SPRITE_WIDTH && current_x_pos < oam_entry.x{
-                let mut tile_number = oam_entry.tile_number;
-                if lcd_control & BIT_2_MASK != 0{
-                    tile_number &= !BIT_0_MASK
-                }
-                self.rendering = true;
-                self.fetcher_state_machine.data.reset();
-                self.fetcher_state_machine.data.tile_data = tile_number;
-                self.fetcher_state_machine.advance();
-                return;
-            }
+    fn should_replace_pixel(&self, fifo_pixel:(u8, u8), pixel:u8, priority_by_x:bool)->bool{
+        if fifo_pixel.0 == 0{
+            return true;
         }
-        self.rendering = false;
+        let fifo_pixel_oam_index = self.oam_entries[fifo_pixel.1 as usize].oam_index;
+        return !priority_by_x && pixel != 0 && self.oam_entries[self.current_oam_entry as usize].oam_index < fifo_pixel_oam_index;
+    }
+
+    fn get_vram_bank(&self, sprite_attrib:&SpriteAttribute)->u8{
+        return if self.mode == Mode::CGB {sprite_attrib.vram_bank} else {0};
     }
 
     // Receiving the tile_num since in case of extended sprite this could change (the first bit is reset)
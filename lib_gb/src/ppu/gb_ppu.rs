@@ -1,8 +1,9 @@
 use crate::mmu::vram::VRam;
+use crate::machine::mode::Mode;
 use crate::utils::{vec2::Vec2, bit_masks::*};
-use crate::ppu::{gfx_device::GfxDevice, ppu_state::PpuState, sprite_attribute::SpriteAttribute, colors::*, color::*};
+use crate::ppu::{gfx_device::GfxDevice, ppu_state::PpuState, sprite_attribute::SpriteAttribute, colors::*, color::*, cgb_palette_ram::CgbPaletteRam, oam_corruption::*, layer_toggles::LayerToggles, hd_pack::*, post_processing::PostProcessing, debug_views::TILE_SIZE, screenshot::*};
 
-use super::fifo::{FIFO_SIZE, sprite_fetcher::*, background_fetcher::BackgroundFetcher};
+use super::fifo::{FIFO_SIZE, SPRITE_WIDTH, sprite_fetcher::*, background_fetcher::BackgroundFetcher};
 use super::gfx_device::Pixel;
 
 pub const SCREEN_HEIGHT: usize = 144;
@@ -10,11 +11,13 @@ pub const SCREEN_WIDTH: usize = 160;
 pub const BUFFERS_NUMBER:usize = 2;
 
 const OAM_ENTRY_SIZE:u16 = 4;
-const OAM_MEMORY_SIZE:usize = 0xA0;
+pub const OAM_MEMORY_SIZE:usize = 0xA0;
 
 const OAM_SEARCH_M_CYCLES_LENGTH: u16 = 80 / 4;
 const HBLANK_M_CYCLES_LENGTH: u16 = 456 / 4;
 const VBLANK_M_CYCLES_LENGTH: u16 = 4560 / 4;
+const LAST_LINE:u8 = 153;
+
 
 pub struct GbPpu<GFX: GfxDevice>{
     pub vram: VRam,
@@ -32,6 +35,15 @@ pub struct GbPpu<GFX: GfxDevice>{
     pub obj_color_mapping0: [Option<Color>;4],
     pub obj_pallete_1_register:u8,
     pub obj_color_mapping1: [Option<Color>;4],
+    pub dmg_palettes:DmgPalettes,
+    pub bg_color_ram:CgbPaletteRam,
+    pub obj_color_ram:CgbPaletteRam,
+    // Dmg style sprites priority, on cgb it is controlled by the OPRI register
+    pub sprite_priority_by_x:bool,
+    pub layer_toggles:LayerToggles,
+    // Draws all the sprites on the line (reduces flicker), the pixel transfer timing still counts only the first 10
+    pub unlimited_sprites_per_line:bool,
+    pub post_processing:PostProcessing,
 
     //interrupts
     pub v_blank_interrupt_request:bool,
@@ -40,12 +52,19 @@ pub struct GbPpu<GFX: GfxDevice>{
     pub coincidence_interrupt_request:bool,
 
     gfx_device: GFX,
+    pub(crate) mode:Mode,
     m_cycles_passed:u16,
     screen_buffers: [[Pixel; SCREEN_HEIGHT * SCREEN_WIDTH];BUFFERS_NUMBER],
     current_screen_buffer_index:usize,
     screen_buffer_index:usize,
     pixel_x_pos:u8,
-    scanline_started:bool,
+    // The first pixels of the scanline are discarded according to SCX
+    pixels_to_discard:u8,
+    hd_renderer:HdRenderer,
+    screenshot_capture:ScreenshotCapture,
+    // The first line after the lcd is turned on has no oam search and the first frame is not displayed
+    first_line_after_lcd_on:bool,
+    hide_frame:bool,
     bg_fetcher:BackgroundFetcher,
     sprite_fetcher:SpriteFetcher,
     stat_triggered:bool,
@@ -54,9 +73,11 @@ pub struct GbPpu<GFX: GfxDevice>{
 }
 
 impl<GFX:GfxDevice> GbPpu<GFX>{
-    pub fn new(device:GFX) -> Self {
+    pub fn new(device:GFX, mode:Mode) -> Self {
+        let dmg_palettes = DmgPalettes::default();
         Self{
             gfx_device: device,
+            mode,
             vram: VRam::default(),
             oam: [0;OAM_MEMORY_SIZE],
             stat_register: 0,
@@ -67,11 +88,18 @@ impl<GFX:GfxDevice> GbPpu<GFX>{
             screen_buffers:[[0;SCREEN_HEIGHT * SCREEN_WIDTH];BUFFERS_NUMBER],
             current_screen_buffer_index:0,
             bg_palette_register:0,
-            bg_color_mapping:[WHITE, LIGHT_GRAY, DARK_GRAY, BLACK],
+            bg_color_mapping:dmg_palettes.bg,
             obj_pallete_0_register:0,
-            obj_color_mapping0: [None, Some(LIGHT_GRAY), Some(DARK_GRAY), Some(BLACK)],
+            obj_color_mapping0: [None, Some(dmg_palettes.obj0[1]), Some(dmg_palettes.obj0[2]), Some(dmg_palettes.obj0[3])],
             obj_pallete_1_register:0,
-            obj_color_mapping1: [None, Some(LIGHT_GRAY), Some(DARK_GRAY), Some(BLACK)],
+            obj_color_mapping1: [None, Some(dmg_palettes.obj1[1]), Some(dmg_palettes.obj1[2]), Some(dmg_palettes.obj1[3])],
+            dmg_palettes,
+            bg_color_ram:CgbPaletteRam::default(),
+            obj_color_ram:CgbPaletteRam::default(),
+            sprite_priority_by_x: mode == Mode::DMG,
+            layer_toggles:LayerToggles::default(),
+            unlimited_sprites_per_line:false,
+            post_processing:PostProcessing::default(),
             ly_register:0,
             state: PpuState::Hblank,
             //interrupts
@@ -83,18 +111,21 @@ impl<GFX:GfxDevice> GbPpu<GFX>{
             m_cycles_passed:0,
             stat_triggered:false,
             trigger_stat_interrupt:false,
-            bg_fetcher:BackgroundFetcher::new(),
-            sprite_fetcher:SpriteFetcher::new(),
+            bg_fetcher:BackgroundFetcher::new(mode),
+            sprite_fetcher:SpriteFetcher::new(mode),
             pixel_x_pos:0,
-            scanline_started:false,
+            pixels_to_discard:0,
+            hd_renderer:HdRenderer::default(),
+            screenshot_capture:ScreenshotCapture::default(),
+            first_line_after_lcd_on:false,
+            hide_frame:false,
             next_state:PpuState::OamSearch
         }
     }
 
     pub fn turn_off(&mut self){
         self.m_cycles_passed = 0;
-        //This is an expensive operation!
-        unsafe{std::ptr::write_bytes(self.screen_buffers[self.current_screen_buffer_index].as_mut_ptr(), 0xFF, SCREEN_HEIGHT * SCREEN_WIDTH)};
+        self.clear_screen_buffer();
         self.swap_buffer();
         self.state = PpuState::Hblank;
         self.ly_register = 0;
@@ -105,10 +136,32 @@ impl<GFX:GfxDevice> GbPpu<GFX>{
         self.bg_fetcher.reset();
         self.sprite_fetcher.reset();
         self.pixel_x_pos = 0;
+        self.pixels_to_discard = 0;
     }
 
+    // The first line starts with mode 0 instead of the oam search
     pub fn turn_on(&mut self){
-        self.state = PpuState::OamSearch;
+        self.state = PpuState::Hblank;
+        self.next_state = PpuState::OamSearch;
+        self.first_line_after_lcd_on = true;
+        self.hide_frame = true;
+    }
+
+    // The row currently read by the oam search is corrupted by the cpu accesses to the oam range
+    pub fn corrupt_oam(&mut self, corruption:OamCorruption){
+        if self.state == PpuState::OamSearch{
+            corrupt_oam(&mut self.oam, self.m_cycles_passed as usize, corruption);
+        }
+    }
+
+    // On dmg writing to STAT enables all the STAT interrupt sources for a single m_cycle
+    pub fn handle_stat_write_quirk(&mut self, if_register:&mut u8){
+        if self.mode != Mode::DMG || self.lcd_control & BIT_7_MASK == 0 || self.stat_triggered{
+            return;
+        }
+        if self.state == PpuState::Hblank || self.state == PpuState::Vblank || self.ly_register == self.lyc_register{
+            *if_register |= BIT_1_MASK;
+        }
     }
 
     pub fn cycle(&mut self, m_cycles:u32, if_register:&mut u8)->Option<u32>{
@@ -125,7 +178,66 @@ impl<GFX:GfxDevice> GbPpu<GFX>{
         return Some(cycles);
     }
 
+    // Replaces the tiles with the pack images and outputs an upscaled frame in addition to the original one
+    pub fn set_hd_pack(&mut self, pack:Option<HdPack>){
+        self.hd_renderer.set_pack(pack);
+        self.update_hd_fetching();
+    }
+
+    pub fn get_hd_pack(&self)->Option<&HdPack>{
+        self.hd_renderer.pack.as_ref()
+    }
+
+    // Records every unique tile drawn on the screen, see HdTilesDumper::take_new_tiles
+    pub fn set_hd_tiles_dumper(&mut self, dumper:Option<HdTilesDumper>){
+        self.hd_renderer.dumper = dumper;
+        self.update_hd_fetching();
+    }
+
+    // The fetchers keep the tiles of the pixels they push only for the hd rendering
+    fn update_hd_fetching(&mut self){
+        let active = self.hd_renderer.is_active();
+        self.bg_fetcher.hd_enabled = active;
+        self.sprite_fetcher.hd_enabled = active;
+    }
+
+    pub fn get_hd_tiles_dumper(&mut self)->Option<&mut HdTilesDumper>{
+        self.hd_renderer.dumper.as_mut()
+    }
+
+    pub fn request_screenshot(&mut self){
+        self.screenshot_capture.request();
+    }
+
+    pub fn take_screenshot(&mut self)->Option<Screenshot>{
+        self.screenshot_capture.take()
+    }
+
     fn swap_buffer(&mut self){
+        let buffer_index = self.current_screen_buffer_index;
+        let capture_screenshot = self.screenshot_capture.is_capturing();
+        if capture_screenshot{
+            self.screenshot_capture.capture_frame(&self.screen_buffers[buffer_index]);
+        }
+        self.post_processing.process(&mut self.screen_buffers[buffer_index][..], 1);
+        let processed_frame = if let Some((buffer, scale)) = self.hd_renderer.get_buffer_mut(buffer_index){
+            self.post_processing.process(buffer, scale);
+            self.gfx_device.swap_hd_buffer(buffer, scale);
+            Some((&*buffer, scale))
+        }
+        else if let Some((buffer, scale)) = self.post_processing.upscale(&self.screen_buffers[buffer_index][..]){
+            self.gfx_device.swap_hd_buffer(buffer, scale);
+            Some((buffer, scale))
+        }
+        else if !self.post_processing.get_filters().is_empty(){
+            Some((&self.screen_buffers[buffer_index][..], 1))
+        }
+        else{
+            None
+        };
+        if capture_screenshot{
+            self.screenshot_capture.capture_processed_frame(processed_frame);
+        }
         self.gfx_device.swap_buffer(&self.screen_buffers[self.current_screen_buffer_index]);
         self.screen_buffer_index = 0;
         self.current_screen_buffer_index = (self.current_screen_buffer_index + 1) % BUFFERS_NUMBER;
@@ -173,22 +285,26 @@ impl<GFX:GfxDevice> GbPpu<GFX>{
         while m_cycles_counter < m_cycles{
             match self.next_state{
                 PpuState::OamSearch=>{
-                    self.state = PpuState::OamSearch;
+                    self.state = if self.first_line_after_lcd_on {PpuState::Hblank} else {PpuState::OamSearch};
                     // first iteration
-                    if self.m_cycles_passed == 0{
+                    if self.m_cycles_passed == 0 && !self.first_line_after_lcd_on{
                         let sprite_height = if (self.lcd_control & BIT_2_MASK) != 0 {EXTENDED_SPRITE_HIGHT} else {NORMAL_SPRITE_HIGHT};
                         for oam_index in 0..(OAM_MEMORY_SIZE as u16 / OAM_ENTRY_SIZE){
                             let oam_entry_address = (oam_index * OAM_ENTRY_SIZE) as usize;
                             let end_y = self.oam[oam_entry_address];
                             let end_x = self.oam[oam_entry_address + 1];
 
-                            if end_x > 0 && self.ly_register + 16 >= end_y && self.ly_register + 16 < end_y + sprite_height {
+                            // Sprites with x=0 are hidden but still count for the sprites per line limit and stall the pixel transfer
+                            if self.ly_register + 16 >= end_y && self.ly_register + 16 < end_y + sprite_height {
                                 let tile_number = self.oam[oam_entry_address + 2];
                                 let attributes = self.oam[oam_entry_address + 3];
-                                self.sprite_fetcher.oam_entries[self.sprite_fetcher.oam_entries_len as usize] = SpriteAttribute::new(end_y, end_x, tile_number, attributes);
+                                self.sprite_fetcher.oam_entries[self.sprite_fetcher.oam_entries_len as usize] = SpriteAttribute::new(end_y, end_x, tile_number, attributes, oam_index as u8);
                                 self.sprite_fetcher.oam_entries_len += 1;
                                 if self.sprite_fetcher.oam_entries_len == MAX_SPRITES_PER_LINE as u8{
-                                    break;
+                                    self.sprite_fetcher.last_timed_oam_index = oam_index as u8;
+                                    if !self.unlimited_sprites_per_line{
+                                        break;
+                                    }
                                 }
                             }
                         }
@@ -197,13 +313,14 @@ impl<GFX:GfxDevice> GbPpu<GFX>{
                             .sort_by(|s1:&SpriteAttribute, s2:&SpriteAttribute| s1.x.cmp(&s2.x));
                     }
                     
-                    let scope_m_cycles_passed = std::cmp::min(m_cycles as u16, OAM_SEARCH_M_CYCLES_LENGTH - self.m_cycles_passed);
+                    let scope_m_cycles_passed = std::cmp::min((m_cycles - m_cycles_counter) as u16, OAM_SEARCH_M_CYCLES_LENGTH - self.m_cycles_passed);
                     self.m_cycles_passed += scope_m_cycles_passed;
                     m_cycles_counter += scope_m_cycles_passed as u32;
                     
                     if self.m_cycles_passed == OAM_SEARCH_M_CYCLES_LENGTH{
                         self.next_state = PpuState::PixelTransfer;
-                        self.scanline_started = false;
+                        self.pixels_to_discard = self.bg_pos.x % SPRITE_WIDTH;
+                        self.first_line_after_lcd_on = false;
                     }
                 }
                 PpuState::Hblank=>{
@@ -251,43 +368,35 @@ impl<GFX:GfxDevice> GbPpu<GFX>{
                     }
                     else{
                         //VBlank is technically 10 HBlank combined
-                        self.ly_register = SCREEN_HEIGHT as u8 + (self.m_cycles_passed / HBLANK_M_CYCLES_LENGTH) as u8;
+                        let line = SCREEN_HEIGHT as u8 + (self.m_cycles_passed / HBLANK_M_CYCLES_LENGTH) as u8;
+                        // LY reads 0 after the first m_cycle of the last line
+                        self.ly_register = if line == LAST_LINE && self.m_cycles_passed % HBLANK_M_CYCLES_LENGTH != 0 {0} else {line};
                     }
                     
                 }
                 PpuState::PixelTransfer=>{
                     self.state = PpuState::PixelTransfer;
+                    // The length of the pixel transfer comes out of the fetchers, it ends when the last pixel is pushed to the lcd
                     while m_cycles_counter < m_cycles && self.pixel_x_pos < SCREEN_WIDTH as u8{
                         for _ in 0..4{
-                            if self.lcd_control & BIT_1_MASK != 0{
-                                self.sprite_fetcher.fetch_pixels(&self.vram, self.lcd_control, self.ly_register, self.pixel_x_pos);
-                            }
-                            if self.sprite_fetcher.rendering{
-                                self.bg_fetcher.pause();
-                            }
-                            else{
-                                self.bg_fetcher.fetch_pixels(&self.vram, self.lcd_control, self.ly_register, &self.window_pos, &self.bg_pos);
-                                self.try_push_to_lcd();
-                                if self.pixel_x_pos == SCREEN_WIDTH as u8{
-                                    self.next_state = PpuState::Hblank;
-                                    if self.h_blank_interrupt_request{
-                                        self.trigger_stat_interrupt = true;
-                                    }
-                                    self.bg_fetcher.try_increment_window_counter(self.ly_register, self.window_pos.y);
-                                    self.bg_fetcher.reset();
-                                    self.sprite_fetcher.reset();
-                                
-                                    // If im on the first iteration and finished the 160 pixels break;
-                                    // In this case the number of t_cycles should be eneven but it will break
-                                    // my code way too much for now so Im leaving this as it is... (maybe in the future)
-                                    break;
-                                }
+                            if self.pixel_x_pos < SCREEN_WIDTH as u8{
+                                self.cycle_pixel_transfer();
                             }
                         }
 
                         self.m_cycles_passed += 1;
                         m_cycles_counter += 1;
                     }
+
+                    if self.pixel_x_pos == SCREEN_WIDTH as u8{
+                        self.next_state = PpuState::Hblank;
+                        if self.h_blank_interrupt_request{
+                            self.trigger_stat_interrupt = true;
+                        }
+                        self.bg_fetcher.try_increment_window_counter(self.ly_register, self.window_pos.y);
+                        self.bg_fetcher.reset();
+                        self.sprite_fetcher.reset();
+                    }
                 }
             }
         }
@@ -298,44 +407,85 @@ impl<GFX:GfxDevice> GbPpu<GFX>{
             return 1;
         }
         let m_cycles_for_state = match self.next_state{
+            PpuState::Vblank if self.ly_register == LAST_LINE => self.m_cycles_passed + 1,
             PpuState::Vblank => ((self.m_cycles_passed / HBLANK_M_CYCLES_LENGTH)+1) * HBLANK_M_CYCLES_LENGTH,
             PpuState::Hblank => HBLANK_M_CYCLES_LENGTH,
             PpuState::OamSearch => OAM_SEARCH_M_CYCLES_LENGTH,
-            
-            // taking the pixels that left to draw and divide by 4 (usually pushing 4 pixels per m_cycle) 
-            // to try and calculate how much cycles left for the pixel transfer state
-            PpuState::PixelTransfer => self.m_cycles_passed + ((SCREEN_WIDTH - self.pixel_x_pos as usize) as u16 >> 2) 
+            // The end is unknown ahead, every pixel takes at least a t_cycle
+            PpuState::PixelTransfer => self.m_cycles_passed + std::cmp::max(1, (SCREEN_WIDTH as u16 - self.pixel_x_pos as u16) / 4)
         };
 
         return m_cycles_for_state - self.m_cycles_passed;
     }
 
-    fn try_push_to_lcd(&mut self){
-        if self.bg_fetcher.fifo.len() == 0{
+    // Cycles the fetchers and the lcd for a single t_cycle
+    fn cycle_pixel_transfer(&mut self){
+        // The bg fetcher finishes its current tile before a sprite fetch starts
+        if self.sprite_fetcher.rendering && self.bg_fetcher.is_waiting_to_push(){
+            self.sprite_fetcher.fetch_pixels(&self.vram, self.lcd_control, self.ly_register, self.sprite_priority_by_x, &self.layer_toggles);
+        }
+        else{
+            self.bg_fetcher.fetch_pixels(&self.vram, self.lcd_control, self.ly_register, &self.window_pos, &self.bg_pos);
+        }
+        if self.bg_fetcher.fifo.len() == 0 || self.sprite_fetcher.rendering{
             return;
         }
-        if !self.scanline_started{
-            // discard the next pixel in the bg fifo
-            // the bg fifo should start with 8 pixels and not push more untill its empty again
-            if FIFO_SIZE as usize - self.bg_fetcher.fifo.len() >= self.bg_pos.x as usize % FIFO_SIZE as usize{
-                self.scanline_started = true;
-            }
-            else{
-                self.bg_fetcher.fifo.remove();
+
+        if self.pixels_to_discard == 0 && self.should_start_window(){
+            self.bg_fetcher.start_window(&self.vram, self.lcd_control, self.ly_register, &self.window_pos, &self.bg_pos);
+            return;
+        }
+        if self.lcd_control & BIT_1_MASK != 0{
+            let x_pos = self.pixel_x_pos + SPRITE_WIDTH - self.pixels_to_discard;
+            self.sprite_fetcher.try_start_fetch(&self.vram, self.lcd_control, self.ly_register, x_pos, self.sprite_priority_by_x, &self.layer_toggles);
+            if self.sprite_fetcher.rendering{
                 return;
             }
         }
 
-        let bg_pixel_color_num = self.bg_fetcher.fifo.remove();
-        let bg_pixel = self.bg_color_mapping[bg_pixel_color_num as usize];
+        self.try_push_to_lcd();
+    }
+
+    fn should_start_window(&self)->bool{
+        return !self.bg_fetcher.is_rendering_window() && self.lcd_control & BIT_5_MASK != 0 &&
+            self.bg_fetcher.has_wy_reached_ly && self.pixel_x_pos == self.window_pos.x;
+    }
+
+    fn try_push_to_lcd(&mut self){
+        if self.pixels_to_discard != 0{
+            self.bg_fetcher.fifo.remove();
+            if self.sprite_fetcher.fifo.len() != 0{
+                self.sprite_fetcher.fifo.remove();
+            }
+            self.pixels_to_discard -= 1;
+            return;
+        }
+
+        // The column of the pixel in the fetched tile line
+        let bg_tile_column = FIFO_SIZE - self.bg_fetcher.fifo.len();
+        let (mut bg_pixel_color_num, mut bg_pixel_attributes, is_window_pixel) = self.bg_fetcher.fifo.remove();
+        if (is_window_pixel && self.layer_toggles.hide_window) || (!is_window_pixel && self.layer_toggles.hide_background){
+            bg_pixel_color_num = 0;
+            bg_pixel_attributes = 0;
+        }
+        let bg_pixel = match self.mode{
+            Mode::DMG=>self.bg_color_mapping[bg_pixel_color_num as usize],
+            Mode::CGB=>self.bg_color_ram.get_color(bg_pixel_attributes & 0b111, bg_pixel_color_num)
+        };
+        let mut drawn_sprite_entry = None;
         let pixel = if !(self.sprite_fetcher.fifo.len() == 0){
             let sprite_color_num = self.sprite_fetcher.fifo.remove();
             let pixel_oam_attribute = &self.sprite_fetcher.oam_entries[sprite_color_num.1 as usize];
 
-            if sprite_color_num.0 == 0 || (pixel_oam_attribute.is_bg_priority && bg_pixel_color_num != 0){
+            if sprite_color_num.0 == 0 || self.is_bg_over_sprite(bg_pixel_color_num, bg_pixel_attributes, pixel_oam_attribute){
                 bg_pixel
             }
+            else if self.mode == Mode::CGB{
+                drawn_sprite_entry = Some(sprite_color_num.1);
+                self.obj_color_ram.get_color(pixel_oam_attribute.cgb_palette_number, sprite_color_num.0)
+            }
             else{
+                drawn_sprite_entry = Some(sprite_color_num.1);
                 let sprite_pixel = if pixel_oam_attribute.palette_number{
                     self.obj_color_mapping1[sprite_color_num.0 as usize]
                 }
@@ -350,15 +500,97 @@ impl<GFX:GfxDevice> GbPpu<GFX>{
             bg_pixel
         };
 
+        if self.hd_renderer.is_active(){
+            self.render_hd_pixel(drawn_sprite_entry, is_window_pixel, bg_pixel_attributes, bg_tile_column, pixel);
+        }
+
         self.push_pixel(Color::into(pixel));
         self.pixel_x_pos += 1;
     }
 
+    fn render_hd_pixel(&mut self, drawn_sprite_entry:Option<u8>, is_window_pixel:bool, bg_attributes:u8, bg_tile_column:usize, pixel:Color){
+        let (tile, x) = match drawn_sprite_entry{
+            Some(entry)=>{
+                let sprite = &self.sprite_fetcher.oam_entries[entry as usize];
+                let column = (self.pixel_x_pos + SPRITE_WIDTH - sprite.x) as usize;
+                let x = if sprite.flip_x {TILE_SIZE - 1 - column} else {column};
+                let palette = self.get_sprite_hd_palette(sprite);
+                let tile = self.sprite_fetcher.hd_tiles[entry as usize].as_mut();
+                (Self::resolve_hd_tile(&mut self.hd_renderer, &self.vram, tile, &palette), x)
+            }
+            None=>{
+                let bg_hidden = (is_window_pixel && self.layer_toggles.hide_window) || (!is_window_pixel && self.layer_toggles.hide_background);
+                let palette = self.get_bg_hd_palette(bg_attributes);
+                let tile = if bg_hidden {None} else {self.bg_fetcher.hd_tile.as_mut()};
+                let tile = Self::resolve_hd_tile(&mut self.hd_renderer, &self.vram, tile, &palette);
+                let x = match tile{
+                    Some(tile) if tile.flip_x=>TILE_SIZE - 1 - bg_tile_column,
+                    _=>bg_tile_column
+                };
+                (tile, x)
+            }
+        };
+        self.hd_renderer.render_pixel(tile.as_ref(), x, pixel, self.current_screen_buffer_index, self.screen_buffer_index);
+    }
+
+    // The key of the tile is calculated once, on its first drawn pixel
+    fn resolve_hd_tile(hd_renderer:&mut HdRenderer, vram:&VRam, tile:Option<&mut HdTile>, palette:&[Option<Color>;4])->Option<HdTile>{
+        let tile = tile?;
+        if !tile.is_resolved(){
+            hd_renderer.resolve_tile(vram, tile, palette);
+        }
+        return Some(*tile);
+    }
+
+    fn get_bg_hd_palette(&self, attributes:u8)->[Option<Color>;4]{
+        let mut palette = [None;4];
+        for (color_number, color) in palette.iter_mut().enumerate(){
+            *color = Some(match self.mode{
+                Mode::DMG=>self.bg_color_mapping[color_number],
+                Mode::CGB=>self.bg_color_ram.get_color(attributes & 0b111, color_number as u8)
+            });
+        }
+        return palette;
+    }
+
+    fn get_sprite_hd_palette(&self, sprite:&SpriteAttribute)->[Option<Color>;4]{
+        let mut palette = [None;4];
+        for (color_number, color) in palette.iter_mut().enumerate().skip(1){
+            *color = match self.mode{
+                Mode::DMG=>if sprite.palette_number {self.obj_color_mapping1[color_number]} else {self.obj_color_mapping0[color_number]},
+                Mode::CGB=>Some(self.obj_color_ram.get_color(sprite.cgb_palette_number, color_number as u8))
+            };
+        }
+        return palette;
+    }
+
+    // Only called for non transparent sprite pixels
+    fn is_bg_over_sprite(&self, bg_pixel_color_num:u8, bg_pixel_attributes:u8, sprite_attribute:&SpriteAttribute)->bool{
+        if bg_pixel_color_num == 0{
+            return false;
+        }
+        return match self.mode{
+            Mode::DMG=>sprite_attribute.is_bg_priority,
+            // LCDC bit 0 is the bg master priority, when cleared the sprites are always on top
+            Mode::CGB=>self.lcd_control & BIT_0_MASK != 0 && (sprite_attribute.is_bg_priority || bg_pixel_attributes & BIT_7_MASK != 0)
+        };
+    }
+
     fn push_pixel(&mut self, pixel: Pixel) {
         self.screen_buffers[self.current_screen_buffer_index][self.screen_buffer_index] = pixel;
         self.screen_buffer_index += 1;
         if self.screen_buffer_index == SCREEN_WIDTH * SCREEN_HEIGHT{
-           self.swap_buffer();
+            if self.hide_frame{
+                self.hide_frame = false;
+                self.clear_screen_buffer();
+            }
+            self.swap_buffer();
         }
     }
+
+    fn clear_screen_buffer(&mut self){
+        //This is an expensive operation!
+        unsafe{std::ptr::write_bytes(self.screen_buffers[self.current_screen_buffer_index].as_mut_ptr(), 0xFF, SCREEN_HEIGHT * SCREEN_WIDTH)};
+        self.hd_renderer.clear_buffer(self.current_screen_buffer_index);
+    }
 }
\ No newline at end of file
@@ -8,4 +8,7 @@ pub type Pixel = u16;
 
 pub trait GfxDevice{
     fn swap_buffer(&mut self, buffer:&[Pixel; SCREEN_HEIGHT * SCREEN_WIDTH]);
+
+    // Called before swap_buffer with the upscaled frame when an hd pack is loaded or a post processing filter needs one (SCREEN_WIDTH * scale by SCREEN_HEIGHT * scale)
+    fn swap_hd_buffer(&mut self, _buffer:&[Pixel], _scale:usize){}
 }
\ No newline at end of file
@@ -0,0 +1,250 @@
+use std::collections::{HashMap, HashSet};
+use crate::mmu::vram::VRam;
+use super::{gb_ppu::{SCREEN_HEIGHT, SCREEN_WIDTH, BUFFERS_NUMBER}, gfx_device::Pixel, color::Color, debug_views::TILE_SIZE};
+
+const TILE_DATA_SIZE:u16 = 16;
+const RGBA_CHANNELS:usize = 4;
+// The palette colors in the key are RGB888, the transparent sprite color is not a valid RGB888 color
+const TRANSPARENT_KEY_COLOR:u32 = u32::MAX;
+const TRANSPARENT_FILE_NAME_COLOR:&str = "none";
+const FNV_OFFSET_BASIS:u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME:u64 = 0x100_0000_01B3;
+
+// Identifies a tile by its data and the colors it is drawn with, so the same tile can be replaced differently for every palette
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct HdTileKey{
+    pub tile_hash:u64,
+    pub palette:[u32;4]
+}
+
+impl HdTileKey{
+    pub fn new(tile_data:&[u8;TILE_DATA_SIZE as usize], palette:&[Option<Color>;4])->Self{
+        // FNV-1a, stable between runs so it can be used in the pack files names
+        let tile_hash = tile_data.iter().fold(FNV_OFFSET_BASIS, |hash, byte|(hash ^ *byte as u64).wrapping_mul(FNV_PRIME));
+        let mut key_palette = [TRANSPARENT_KEY_COLOR;4];
+        for (key_color, color) in key_palette.iter_mut().zip(palette.iter()){
+            if let Some(color) = color{
+                *key_color = ((color.r as u32) << 16) | ((color.g as u32) << 8) | color.b as u32;
+            }
+        }
+        return Self{tile_hash, palette:key_palette};
+    }
+
+    // The file name of the tile in the pack without the extension, the tile hash followed by the palette colors (00ff00 for example)
+    pub fn get_file_name(&self)->String{
+        let mut name = format!("{:016x}", self.tile_hash);
+        for color in self.palette.iter(){
+            if *color == TRANSPARENT_KEY_COLOR{
+                name.push_str(&format!("_{}", TRANSPARENT_FILE_NAME_COLOR));
+            }
+            else{
+                name.push_str(&format!("_{:06x}", color));
+            }
+        }
+        return name;
+    }
+
+    pub fn from_file_name(name:&str)->Option<Self>{
+        let mut parts = name.split('_');
+        let tile_hash = u64::from_str_radix(parts.next()?, 16).ok()?;
+        let mut palette = [TRANSPARENT_KEY_COLOR;4];
+        for color in palette.iter_mut(){
+            let part = parts.next()?;
+            if part != TRANSPARENT_FILE_NAME_COLOR{
+                *color = u32::from_str_radix(part, 16).ok().filter(|color|*color <= 0xFF_FFFF)?;
+            }
+        }
+        if parts.next().is_some(){
+            return None;
+        }
+        return Some(Self{tile_hash, palette});
+    }
+}
+
+// Replacement images for tiles, every image is a RGBA8 square of 8 * scale pixels
+pub struct HdPack{
+    scale:usize,
+    // The key lookup is done once per fetched tile, the pixels access the image by its index
+    indexes:HashMap<HdTileKey, usize>,
+    tiles:Vec<Vec<u8>>
+}
+
+impl HdPack{
+    pub fn new(scale:usize)->Self{
+        if scale == 0{
+            std::panic!("Error: the hd pack scale must be at least 1");
+        }
+        Self{scale, indexes:HashMap::new(), tiles:Vec::new()}
+    }
+
+    pub fn get_scale(&self)->usize{
+        self.scale
+    }
+
+    pub fn len(&self)->usize{
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self)->bool{
+        self.tiles.is_empty()
+    }
+
+    pub fn add_tile(&mut self, key:HdTileKey, rgba:Vec<u8>)->Result<(), String>{
+        let size = TILE_SIZE * self.scale;
+        if rgba.len() != size * size * RGBA_CHANNELS{
+            return Err(format!("the tile {} must be {}x{} pixels", key.get_file_name(), size, size));
+        }
+        match self.indexes.get(&key){
+            Some(index)=>self.tiles[*index] = rgba,
+            None=>{
+                self.indexes.insert(key, self.tiles.len());
+                self.tiles.push(rgba);
+            }
+        }
+        return Ok(());
+    }
+}
+
+// Collects every unique tile drawn on the screen in its original size, for bootstrapping packs
+#[derive(Default)]
+pub struct HdTilesDumper{
+    seen_tiles:HashSet<HdTileKey>,
+    new_tiles:Vec<(HdTileKey, Vec<u8>)>
+}
+
+impl HdTilesDumper{
+    pub fn new()->Self{
+        Self::default()
+    }
+
+    // The tiles seen for the first time since the last call as 8x8 RGBA8 images
+    pub fn take_new_tiles(&mut self)->Vec<(HdTileKey, Vec<u8>)>{
+        std::mem::take(&mut self.new_tiles)
+    }
+
+    fn record(&mut self, key:HdTileKey, tile_data:&[u8;TILE_DATA_SIZE as usize], palette:&[Option<Color>;4]){
+        if !self.seen_tiles.insert(key){
+            return;
+        }
+        let mut rgba = Vec::with_capacity(TILE_SIZE * TILE_SIZE * RGBA_CHANNELS);
+        for y in 0..TILE_SIZE{
+            for x in 0..TILE_SIZE{
+                match palette[get_color_number(tile_data, x, y) as usize]{
+                    Some(color)=>rgba.extend_from_slice(&[color.r, color.g, color.b, 0xFF]),
+                    None=>rgba.extend_from_slice(&[0, 0, 0, 0])
+                }
+            }
+        }
+        self.new_tiles.push((key, rgba));
+    }
+}
+
+// A tile line pushed to a fifo, its key and replacement are resolved once for all of its pixels
+#[derive(Clone, Copy)]
+pub(crate) struct HdTile{
+    pub bank:u8,
+    pub tile_address:u16,
+    // The line in the unflipped tile
+    pub y:usize,
+    pub flip_x:bool,
+    pub flip_y:bool,
+    resolved:bool,
+    replacement:Option<usize>
+}
+
+impl HdTile{
+    // The address of the line data as read by the fetcher
+    pub fn new(bank:u8, line_address:u16, flip_x:bool, flip_y:bool)->Self{
+        Self{
+            bank,
+            tile_address:line_address & !(TILE_DATA_SIZE - 1),
+            y:(line_address % TILE_DATA_SIZE) as usize / 2,
+            flip_x, flip_y,
+            resolved:false,
+            replacement:None
+        }
+    }
+
+    pub fn is_resolved(&self)->bool{
+        self.resolved
+    }
+}
+
+// Renders the upscaled frame alongside the original one
+#[derive(Default)]
+pub(crate) struct HdRenderer{
+    pub pack:Option<HdPack>,
+    pub dumper:Option<HdTilesDumper>,
+    buffers:[Vec<Pixel>;BUFFERS_NUMBER]
+}
+
+impl HdRenderer{
+    pub fn is_active(&self)->bool{
+        self.pack.is_some() || self.dumper.is_some()
+    }
+
+    pub fn set_pack(&mut self, pack:Option<HdPack>){
+        let size = pack.as_ref().map_or(0, |pack|SCREEN_WIDTH * SCREEN_HEIGHT * pack.scale * pack.scale);
+        for buffer in self.buffers.iter_mut(){
+            *buffer = vec![0;size];
+        }
+        self.pack = pack;
+    }
+
+    pub fn get_buffer_mut(&mut self, index:usize)->Option<(&mut [Pixel], usize)>{
+        let scale = self.pack.as_ref()?.scale;
+        return Some((self.buffers[index].as_mut_slice(), scale));
+    }
+
+    pub fn clear_buffer(&mut self, index:usize){
+        self.buffers[index].iter_mut().for_each(|pixel|*pixel = Pixel::MAX);
+    }
+
+    // Called on the first drawn pixel of the tile with the colors it is drawn with
+    pub fn resolve_tile(&mut self, vram:&VRam, tile:&mut HdTile, palette:&[Option<Color>;4]){
+        let mut tile_data = [0;TILE_DATA_SIZE as usize];
+        for (i, byte) in tile_data.iter_mut().enumerate(){
+            *byte = vram.read_bank(tile.bank, tile.tile_address + i as u16);
+        }
+        let key = HdTileKey::new(&tile_data, palette);
+        if let Some(dumper) = &mut self.dumper{
+            dumper.record(key, &tile_data, palette);
+        }
+        tile.replacement = self.pack.as_ref().and_then(|pack|pack.indexes.get(&key).copied());
+        tile.resolved = true;
+    }
+
+    // Without a resolved tile or a replacement the original pixel is scaled, x is in the unflipped tile
+    pub fn render_pixel(&mut self, tile:Option<&HdTile>, x:usize, pixel:Color, buffer_index:usize, screen_index:usize){
+        let pack = match &self.pack{
+            Some(pack)=>pack,
+            None=>return
+        };
+        let replacement = tile.and_then(|tile|tile.replacement.map(|index|(&pack.tiles[index], tile)));
+        let scale = pack.scale;
+        let hd_width = SCREEN_WIDTH * scale;
+        let screen_x = (screen_index % SCREEN_WIDTH) * scale;
+        let screen_y = (screen_index / SCREEN_WIDTH) * scale;
+        let original_pixel:Pixel = pixel.into();
+        for sub_y in 0..scale{
+            for sub_x in 0..scale{
+                let mut hd_pixel = original_pixel;
+                if let Some((rgba, tile)) = &replacement{
+                    let tile_x = (x * scale) + if tile.flip_x {scale - 1 - sub_x} else {sub_x};
+                    let tile_y = (tile.y * scale) + if tile.flip_y {scale - 1 - sub_y} else {sub_y};
+                    let index = ((tile_y * TILE_SIZE * scale) + tile_x) * RGBA_CHANNELS;
+                    // Transparent replacement pixels fall back to the original pixel
+                    if rgba[index + 3] != 0{
+                        hd_pixel = Color{r:rgba[index], g:rgba[index + 1], b:rgba[index + 2]}.into();
+                    }
+                }
+                self.buffers[buffer_index][((screen_y + sub_y) * hd_width) + screen_x + sub_x] = hd_pixel;
+            }
+        }
+    }
+}
+
+fn get_color_number(tile_data:&[u8;TILE_DATA_SIZE as usize], x:usize, y:usize)->u8{
+    let bit = TILE_SIZE - 1 - x;
+    return ((tile_data[y * 2] >> bit) & 1) | (((tile_data[(y * 2) + 1] >> bit) & 1) << 1);
+}
@@ -0,0 +1,36 @@
+use super::debug_views::SPRITES_COUNT;
+
+// Debug switches that hide layers when mixing the pixels, they dont affect the emulated timing.
+// A hidden background or window is drawn as color 0 and hidden sprites are transparent.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct LayerToggles{
+    pub hide_background:bool,
+    pub hide_window:bool,
+    // A bit for every oam index
+    hidden_sprites:u64
+}
+
+const ALL_SPRITES_MASK:u64 = (1 << SPRITES_COUNT) - 1;
+
+impl LayerToggles{
+    pub fn is_sprite_hidden(&self, oam_index:u8)->bool{
+        self.hidden_sprites & (1 << oam_index) != 0
+    }
+
+    pub fn set_sprite_hidden(&mut self, oam_index:u8, hidden:bool){
+        if hidden{
+            self.hidden_sprites |= 1 << oam_index;
+        }
+        else{
+            self.hidden_sprites &= !(1 << oam_index);
+        }
+    }
+
+    pub fn set_all_sprites_hidden(&mut self, hidden:bool){
+        self.hidden_sprites = if hidden {ALL_SPRITES_MASK} else {0};
+    }
+
+    pub fn are_all_sprites_hidden(&self)->bool{
+        self.hidden_sprites == ALL_SPRITES_MASK
+    }
+}
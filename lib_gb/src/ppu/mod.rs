@@ -5,4 +5,12 @@ pub mod colors;
 pub mod ppu_register_updater;
 pub mod fifo;
 pub mod gfx_device;
-mod sprite_attribute;
\ No newline at end of file
+pub mod cgb_palette_ram;
+pub mod cgb_compatibility_palettes;
+pub mod oam_corruption;
+pub mod sprite_attribute;
+pub mod debug_views;
+pub mod layer_toggles;
+pub mod hd_pack;
+pub mod post_processing;
+pub mod screenshot;
\ No newline at end of file
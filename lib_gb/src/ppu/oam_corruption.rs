@@ -0,0 +1,66 @@
+use super::gb_ppu::OAM_MEMORY_SIZE;
+
+// The oam search reads a row of 2 entries every m_cycle
+pub const OAM_ROW_SIZE:usize = 8;
+pub const OAM_ROWS_COUNT:usize = OAM_MEMORY_SIZE / OAM_ROW_SIZE;
+// The read during inc/dec corruption does not happen in the first 4 rows and in the last row
+const FIRST_INC_DEC_CORRUPTED_ROW:usize = 4;
+
+// The dmg cpu accesses that corrupt the oam when the address bus is in the oam range during the oam search
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OamCorruption{
+    Read,
+    Write,
+    // A read combined with the increment/decrement unit (ld a, [hl+] or pop for example)
+    ReadDuringIncDec
+}
+
+// The row is the one currently read by the oam search, the first row is never corrupted
+pub fn corrupt_oam(oam:&mut [u8;OAM_MEMORY_SIZE], row:usize, corruption:OamCorruption){
+    if row == 0 || row >= OAM_ROWS_COUNT{
+        return;
+    }
+
+    match corruption{
+        OamCorruption::Write=>{
+            let (a, b, c) = (get_word(oam, row, 0), get_word(oam, row - 1, 0), get_word(oam, row - 1, 2));
+            set_word(oam, row, 0, ((a ^ c) & (b ^ c)) ^ c);
+            copy_row_tail(oam, row - 1, row);
+        }
+        OamCorruption::Read=>read_corruption(oam, row),
+        OamCorruption::ReadDuringIncDec=>{
+            if (FIRST_INC_DEC_CORRUPTED_ROW..OAM_ROWS_COUNT - 1).contains(&row){
+                let a = get_word(oam, row - 2, 0);
+                let b = get_word(oam, row - 1, 0);
+                let c = get_word(oam, row, 0);
+                let d = get_word(oam, row - 1, 2);
+                set_word(oam, row - 1, 0, (b & (a | c | d)) | (a & c & d));
+                let preceding_row = row - 1;
+                oam.copy_within(preceding_row * OAM_ROW_SIZE..row * OAM_ROW_SIZE, row * OAM_ROW_SIZE);
+                oam.copy_within(preceding_row * OAM_ROW_SIZE..row * OAM_ROW_SIZE, (row - 2) * OAM_ROW_SIZE);
+            }
+            read_corruption(oam, row);
+        }
+    }
+}
+
+fn read_corruption(oam:&mut [u8;OAM_MEMORY_SIZE], row:usize){
+    let (a, b, c) = (get_word(oam, row, 0), get_word(oam, row - 1, 0), get_word(oam, row - 1, 2));
+    set_word(oam, row, 0, b | (a & c));
+    copy_row_tail(oam, row - 1, row);
+}
+
+// Copies the last 3 words of the row
+fn copy_row_tail(oam:&mut [u8;OAM_MEMORY_SIZE], src_row:usize, dest_row:usize){
+    oam.copy_within((src_row * OAM_ROW_SIZE) + 2..(src_row + 1) * OAM_ROW_SIZE, (dest_row * OAM_ROW_SIZE) + 2);
+}
+
+fn get_word(oam:&[u8;OAM_MEMORY_SIZE], row:usize, word:usize)->u16{
+    let index = (row * OAM_ROW_SIZE) + (word * 2);
+    return u16::from_le_bytes([oam[index], oam[index + 1]]);
+}
+
+fn set_word(oam:&mut [u8;OAM_MEMORY_SIZE], row:usize, word:usize, value:u16){
+    let index = (row * OAM_ROW_SIZE) + (word * 2);
+    oam[index..index + 2].copy_from_slice(&value.to_le_bytes());
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use super::{gb_ppu::{SCREEN_HEIGHT, SCREEN_WIDTH}, gfx_device::Pixel, color::Color};
+
+pub const DEFAULT_GHOSTING_WEIGHT:u8 = 50;
+pub const DEFAULT_DOT_MATRIX_INTENSITY:u8 = 40;
+pub const DEFAULT_SCANLINES_INTENSITY:u8 = 30;
+pub const DEFAULT_SCALE:usize = 3;
+const MAX_PERCENTS:u16 = 100;
+
+// The weights of every channel in 16ths for the output red, green and blue
+type ColorMatrix = [[u16;3];3];
+// Approximates the washed out colors of the cgb lcd
+const CGB_COLOR_MATRIX:ColorMatrix = [[13, 2, 1], [0, 12, 4], [3, 2, 11]];
+// The backlit ags-101 screen is brighter and less washed out than the cgb lcd
+const AGS_COLOR_MATRIX:ColorMatrix = [[14, 1, 1], [1, 14, 1], [1, 2, 13]];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PostProcessingFilter{
+    // Blends every frame with the previous output like the slow response of the dmg lcd, some games rely on it for transparency.
+    // The value is the weight of the previous frame in percents
+    LcdGhosting(u8),
+    CgbColorCorrection,
+    AgsColorCorrection,
+    // The filters below darken parts of every pixel by the intensity in percents so they apply only to upscaled frames
+    DotMatrix(u8),
+    Scanlines(u8)
+}
+
+impl PostProcessingFilter{
+    pub const ALL:[PostProcessingFilter;5] = [
+        Self::LcdGhosting(DEFAULT_GHOSTING_WEIGHT), Self::CgbColorCorrection, Self::AgsColorCorrection,
+        Self::DotMatrix(DEFAULT_DOT_MATRIX_INTENSITY), Self::Scanlines(DEFAULT_SCANLINES_INTENSITY)
+    ];
+
+    pub fn get_name(self)->&'static str{
+        match self{
+            Self::LcdGhosting(_)=>"LCD ghosting",
+            Self::CgbColorCorrection=>"CGB color correction",
+            Self::AgsColorCorrection=>"AGS color correction",
+            Self::DotMatrix(_)=>"Dot matrix",
+            Self::Scanlines(_)=>"Scanlines"
+        }
+    }
+
+    // Filters of the same kind with different values
+    pub fn is_same_kind(self, other:PostProcessingFilter)->bool{
+        std::mem::discriminant(&self) == std::mem::discriminant(&other)
+    }
+
+    fn is_scaled(self)->bool{
+        matches!(self, Self::DotMatrix(_) | Self::Scanlines(_))
+    }
+}
+
+// A chain of filters applied in order to every frame before it is passed to the GfxDevice
+pub struct PostProcessing{
+    filters:Vec<PostProcessingFilter>,
+    // The scale of the frame the dot matrix and the scanlines are drawn on when there is no hd pack
+    scale:usize,
+    // The previous output of every ghosting filter by its index and the frame scale
+    previous_frames:HashMap<(usize, usize), Vec<Pixel>>,
+    scaled_frame:Vec<Pixel>
+}
+
+impl Default for PostProcessing{
+    fn default()->Self{
+        Self::new(Vec::new(), DEFAULT_SCALE)
+    }
+}
+
+impl PostProcessing{
+    pub fn new(filters:Vec<PostProcessingFilter>, scale:usize)->Self{
+        if scale == 0{
+            std::panic!("Error: the post processing scale must be at least 1");
+        }
+        Self{filters, scale, previous_frames:HashMap::new(), scaled_frame:Vec::new()}
+    }
+
+    pub fn get_filters(&self)->&[PostProcessingFilter]{
+        &self.filters
+    }
+
+    pub fn set_filters(&mut self, filters:Vec<PostProcessingFilter>){
+        self.filters = filters;
+        self.previous_frames.clear();
+    }
+
+    // Removes the filters of the same kind or appends the filter to the end of the chain
+    pub fn toggle_filter(&mut self, filter:PostProcessingFilter){
+        let mut filters = self.filters.clone();
+        if filters.iter().any(|f|f.is_same_kind(filter)){
+            filters.retain(|f|!f.is_same_kind(filter));
+        }
+        else{
+            filters.push(filter);
+        }
+        self.set_filters(filters);
+    }
+
+    pub fn get_scale(&self)->usize{
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale:usize){
+        *self = Self::new(std::mem::take(&mut self.filters), scale);
+    }
+
+    // Applies the filters to a frame of SCREEN_WIDTH * scale by SCREEN_HEIGHT * scale pixels
+    pub(crate) fn process(&mut self, buffer:&mut [Pixel], scale:usize){
+        for index in 0..self.filters.len(){
+            self.apply_filter(index, buffer, scale);
+        }
+    }
+
+    // Upscales the (already processed) frame for the scaled filters, returns None when there are none of them
+    pub(crate) fn upscale(&mut self, buffer:&[Pixel])->Option<(&[Pixel], usize)>{
+        let scale = self.scale;
+        if scale == 1 || !self.filters.iter().any(|filter|filter.is_scaled()){
+            return None;
+        }
+        let mut scaled_frame = std::mem::take(&mut self.scaled_frame);
+        scaled_frame.resize(SCREEN_WIDTH * SCREEN_HEIGHT * scale * scale, 0);
+        for (index, pixel) in scaled_frame.iter_mut().enumerate(){
+            let x = (index % (SCREEN_WIDTH * scale)) / scale;
+            let y = index / (SCREEN_WIDTH * scale * scale);
+            *pixel = buffer[(y * SCREEN_WIDTH) + x];
+        }
+        for index in 0..self.filters.len(){
+            if self.filters[index].is_scaled(){
+                self.apply_filter(index, &mut scaled_frame, scale);
+            }
+        }
+        self.scaled_frame = scaled_frame;
+
+        return Some((&self.scaled_frame, scale));
+    }
+
+    fn apply_filter(&mut self, index:usize, buffer:&mut [Pixel], scale:usize){
+        match self.filters[index]{
+            PostProcessingFilter::LcdGhosting(weight)=>{
+                let previous_frame = self.previous_frames.entry((index, scale)).or_insert_with(||buffer.to_vec());
+                for (pixel, previous) in buffer.iter_mut().zip(previous_frame.iter_mut()){
+                    *pixel = blend(Color::from(*previous), Color::from(*pixel), weight.min(MAX_PERCENTS as u8) as u16).into();
+                    *previous = *pixel;
+                }
+            }
+            PostProcessingFilter::CgbColorCorrection=>buffer.iter_mut().for_each(|pixel|*pixel = apply_color_matrix(Color::from(*pixel), &CGB_COLOR_MATRIX).into()),
+            PostProcessingFilter::AgsColorCorrection=>buffer.iter_mut().for_each(|pixel|*pixel = apply_color_matrix(Color::from(*pixel), &AGS_COLOR_MATRIX).into()),
+            // The last row and column of every pixel are the gaps between the lcd dots
+            PostProcessingFilter::DotMatrix(intensity)=>darken_sub_pixels(buffer, scale, intensity, |x, y|x == scale - 1 || y == scale - 1),
+            PostProcessingFilter::Scanlines(intensity)=>darken_sub_pixels(buffer, scale, intensity, |_, y|y == scale - 1)
+        }
+    }
+}
+
+// Weight is the percents of the first color
+fn blend(first:Color, second:Color, weight:u16)->Color{
+    let blend_channel = |a:u8, b:u8|(((a as u16 * weight) + (b as u16 * (MAX_PERCENTS - weight))) / MAX_PERCENTS) as u8;
+    return Color{r:blend_channel(first.r, second.r), g:blend_channel(first.g, second.g), b:blend_channel(first.b, second.b)};
+}
+
+fn apply_color_matrix(color:Color, matrix:&ColorMatrix)->Color{
+    let apply_row = |row:&[u16;3]|(((color.r as u16 * row[0]) + (color.g as u16 * row[1]) + (color.b as u16 * row[2])) >> 4) as u8;
+    return Color{r:apply_row(&matrix[0]), g:apply_row(&matrix[1]), b:apply_row(&matrix[2])};
+}
+
+// Darkens the sub pixels (x and y inside the scaled pixel) the predicate selects, does nothing for unscaled frames
+fn darken_sub_pixels(buffer:&mut [Pixel], scale:usize, intensity:u8, predicate:impl Fn(usize, usize)->bool){
+    if scale == 1{
+        return;
+    }
+    let black = Color::default();
+    let width = SCREEN_WIDTH * scale;
+    for (index, pixel) in buffer.iter_mut().enumerate(){
+        if predicate((index % width) % scale, (index / width) % scale){
+            *pixel = blend(black, Color::from(*pixel), intensity.min(MAX_PERCENTS as u8) as u16).into();
+        }
+    }
+}
\ No newline at end of file
@@ -1,5 +1,5 @@
 use crate::utils::bit_masks::*;
-use super::{color::*, colors::*, gb_ppu::GbPpu, gfx_device::GfxDevice};
+use super::{color::*, colors::*, gb_ppu::GbPpu, gfx_device::GfxDevice, ppu_state::PpuState};
 
 const WX_OFFSET:u8 = 7;
 
@@ -14,7 +14,8 @@ pub fn handle_lcdcontrol_register<GFX:GfxDevice>( register:u8, ppu:&mut GbPpu<GF
     ppu.lcd_control = register;
 }
 
-pub fn update_stat_register<GFX:GfxDevice>(register:u8, ppu: &mut GbPpu<GFX>){
+pub fn update_stat_register<GFX:GfxDevice>(register:u8, ppu: &mut GbPpu<GFX>, if_register:&mut u8){
+    ppu.handle_stat_write_quirk(if_register);
     ppu.h_blank_interrupt_request = register & BIT_3_MASK != 0;
     ppu.v_blank_interrupt_request = register & BIT_4_MASK != 0;
     ppu.oam_search_interrupt_request = register & BIT_5_MASK != 0;
@@ -32,30 +33,32 @@ pub fn set_scy<GFX:GfxDevice>(ppu:&mut GbPpu<GFX>, value:u8){
     ppu.bg_pos.y = value;
 }
 
-pub fn handle_bg_pallet_register(register:u8, pallet:&mut [Color;4], palette_register:&mut u8){
-    pallet[0] = get_matching_color(register&0b00000011);
-    pallet[1] = get_matching_color((register&0b00001100)>>2);
-    pallet[2] = get_matching_color((register&0b00110000)>>4);
-    pallet[3] = get_matching_color((register&0b11000000)>>6);
+pub fn handle_bg_pallet_register(register:u8, shades:&[Color;4], pallet:&mut [Color;4], palette_register:&mut u8){
+    pallet[0] = get_matching_color(register&0b00000011, shades);
+    pallet[1] = get_matching_color((register&0b00001100)>>2, shades);
+    pallet[2] = get_matching_color((register&0b00110000)>>4, shades);
+    pallet[3] = get_matching_color((register&0b11000000)>>6, shades);
     *palette_register = register;
 }
 
-pub fn handle_obp_pallet_register(register:u8, pallet:&mut [Option<Color>;4], palette_register:&mut u8){
+pub fn handle_obp_pallet_register(register:u8, shades:&[Color;4], pallet:&mut [Option<Color>;4], palette_register:&mut u8){
     pallet[0] = None;
-    pallet[1] = Some(get_matching_color((register&0b00001100)>>2));
-    pallet[2] = Some(get_matching_color((register&0b00110000)>>4));
-    pallet[3] = Some(get_matching_color((register&0b11000000)>>6));
+    pallet[1] = Some(get_matching_color((register&0b00001100)>>2, shades));
+    pallet[2] = Some(get_matching_color((register&0b00110000)>>4, shades));
+    pallet[3] = Some(get_matching_color((register&0b11000000)>>6, shades));
     *palette_register = register;
 }
 
-fn get_matching_color(number:u8)->Color{
-    return match number{
-        0b00=>WHITE,
-        0b01=>LIGHT_GRAY,
-        0b10=>DARK_GRAY,
-        0b11=>BLACK,
-        _=>std::panic!("no macthing color for color number: {}", number)
-    };
+// Remaps the current palette registers to the new shades, so the palettes can be replaced while a game is running
+pub fn set_dmg_palettes<GFX:GfxDevice>(ppu:&mut GbPpu<GFX>, palettes:DmgPalettes){
+    ppu.dmg_palettes = palettes;
+    handle_bg_pallet_register(ppu.bg_palette_register, &palettes.bg, &mut ppu.bg_color_mapping, &mut ppu.bg_palette_register);
+    handle_obp_pallet_register(ppu.obj_pallete_0_register, &palettes.obj0, &mut ppu.obj_color_mapping0, &mut ppu.obj_pallete_0_register);
+    handle_obp_pallet_register(ppu.obj_pallete_1_register, &palettes.obj1, &mut ppu.obj_color_mapping1, &mut ppu.obj_pallete_1_register);
+}
+
+fn get_matching_color(number:u8, shades:&[Color;4])->Color{
+    return shades[number as usize];
 }
 
 pub fn handle_wy_register<GFX:GfxDevice>(register:u8, ppu:&mut GbPpu<GFX>){
@@ -82,4 +85,39 @@ pub fn get_stat<GFX:GfxDevice>(ppu:&GbPpu<GFX>)->u8{
 
 pub fn set_lyc<GFX:GfxDevice>(ppu:&mut GbPpu<GFX>, value:u8){
     ppu.lyc_register = value;
+}
+
+pub fn set_vbk<GFX:GfxDevice>(ppu:&mut GbPpu<GFX>, value:u8){
+    ppu.vram.set_bank(value & BIT_0_MASK);
+}
+
+pub fn get_vbk<GFX:GfxDevice>(ppu:&GbPpu<GFX>)->u8{
+    // Only bit 0 is used, the rest read as set
+    return ppu.vram.get_bank() | 0b1111_1110;
+}
+
+pub fn set_bcpd<GFX:GfxDevice>(ppu:&mut GbPpu<GFX>, value:u8){
+    let locked = ppu.state == PpuState::PixelTransfer;
+    ppu.bg_color_ram.write_data(value, locked);
+}
+
+pub fn get_bcpd<GFX:GfxDevice>(ppu:&GbPpu<GFX>)->u8{
+    return if ppu.state == PpuState::PixelTransfer {0xFF} else {ppu.bg_color_ram.read_data()};
+}
+
+pub fn set_ocpd<GFX:GfxDevice>(ppu:&mut GbPpu<GFX>, value:u8){
+    let locked = ppu.state == PpuState::PixelTransfer;
+    ppu.obj_color_ram.write_data(value, locked);
+}
+
+pub fn get_ocpd<GFX:GfxDevice>(ppu:&GbPpu<GFX>)->u8{
+    return if ppu.state == PpuState::PixelTransfer {0xFF} else {ppu.obj_color_ram.read_data()};
+}
+
+pub fn set_opri<GFX:GfxDevice>(ppu:&mut GbPpu<GFX>, value:u8){
+    ppu.sprite_priority_by_x = value & BIT_0_MASK != 0;
+}
+
+pub fn get_opri<GFX:GfxDevice>(ppu:&GbPpu<GFX>)->u8{
+    return ppu.sprite_priority_by_x as u8 | 0b1111_1110;
 }
\ No newline at end of file
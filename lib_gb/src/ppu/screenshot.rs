@@ -0,0 +1,52 @@
+use super::{gb_ppu::{SCREEN_HEIGHT, SCREEN_WIDTH}, gfx_device::Pixel, color::Color, debug_views::RgbImage};
+
+// The frames of the next buffer swap after a screenshot is requested
+pub struct Screenshot{
+    // The frame as the ppu rendered it, before the post processing
+    pub frame:RgbImage,
+    // The frame passed to the GfxDevice when it is post processed or rendered with an hd pack (can be scaled)
+    pub processed_frame:Option<RgbImage>
+}
+
+// Captures the frames of the next buffer swap when requested
+#[derive(Default)]
+pub(crate) struct ScreenshotCapture{
+    requested:bool,
+    frame:Option<RgbImage>,
+    screenshot:Option<Screenshot>
+}
+
+impl ScreenshotCapture{
+    pub fn request(&mut self){
+        self.requested = true;
+    }
+
+    pub fn is_capturing(&self)->bool{
+        self.requested
+    }
+
+    pub fn capture_frame(&mut self, buffer:&[Pixel]){
+        self.frame = Some(to_rgb_image(buffer, 1));
+    }
+
+    // Completes the screenshot, the processed frame is None when the frame passed to the GfxDevice is the raw one
+    pub fn capture_processed_frame(&mut self, buffer:Option<(&[Pixel], usize)>){
+        if let Some(frame) = self.frame.take(){
+            self.screenshot = Some(Screenshot{frame, processed_frame:buffer.map(|(buffer, scale)|to_rgb_image(buffer, scale))});
+        }
+        self.requested = false;
+    }
+
+    pub fn take(&mut self)->Option<Screenshot>{
+        self.screenshot.take()
+    }
+}
+
+fn to_rgb_image(buffer:&[Pixel], scale:usize)->RgbImage{
+    let mut image = RgbImage::new(SCREEN_WIDTH * scale, SCREEN_HEIGHT * scale);
+    for (rgb, pixel) in image.buffer.chunks_exact_mut(3).zip(buffer.iter()){
+        let color = Color::from(*pixel);
+        rgb.copy_from_slice(&[color.r, color.g, color.b]);
+    }
+    return image;
+}
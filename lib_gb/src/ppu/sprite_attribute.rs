@@ -7,11 +7,15 @@ pub struct SpriteAttribute{
     pub is_bg_priority:bool,
     pub flip_y:bool,
     pub flip_x:bool,
-    pub palette_number:bool
+    pub palette_number:bool,
+    // Cgb only attributes
+    pub vram_bank:u8,
+    pub cgb_palette_number:u8,
+    pub oam_index:u8
 }
 
 impl SpriteAttribute{
-    pub fn new(y:u8, x:u8, tile_number:u8, attributes:u8)->Self{
+    pub fn new(y:u8, x:u8, tile_number:u8, attributes:u8, oam_index:u8)->Self{
         SpriteAttribute{
             y:y,
             x:x,
@@ -19,7 +23,10 @@ impl SpriteAttribute{
             is_bg_priority: attributes & BIT_7_MASK != 0,
             flip_y: attributes & BIT_6_MASK != 0,
             flip_x: attributes & BIT_5_MASK != 0,
-            palette_number: attributes & BIT_4_MASK != 0
+            palette_number: attributes & BIT_4_MASK != 0,
+            vram_bank: (attributes & BIT_3_MASK) >> 3,
+            cgb_palette_number: attributes & 0b111,
+            oam_index
         }
     }
-}
\ No newline at end of file
+}
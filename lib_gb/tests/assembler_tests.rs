@@ -0,0 +1,179 @@
+mod memory_stub;
+
+use lib_gb::assembler::{assemble, assemble_program, assemble_rom};
+use lib_gb::cpu::gb_cpu::GbCpu;
+use crate::memory_stub::MemoryStub;
+
+macro_rules! encoding_test{
+    ($name:ident, $source:expr, $expected:expr) => {
+        #[test]
+        fn $name(){
+            let bytes = assemble($source).unwrap();
+            assert_eq!(bytes, $expected);
+        }
+    };
+}
+
+encoding_test!(nop_test, "nop", vec![0x00]);
+encoding_test!(ld_r_r_test, "ld b, a", vec![0x47]);
+encoding_test!(ld_r_indirect_hl_test, "ld a, [hl]", vec![0x7E]);
+encoding_test!(ld_indirect_hl_n_test, "ld [hl], $12", vec![0x36, 0x12]);
+encoding_test!(ld_rr_nn_test, "ld hl, $C000", vec![0x21, 0x00, 0xC0]);
+encoding_test!(ld_hl_increment_test, "ld a, [hl+]\nld [hld], a", vec![0x2A, 0x32]);
+encoding_test!(ld_indirect_nn_test, "ld [$C000], a\nld a, [$C000]", vec![0xEA, 0x00, 0xC0, 0xFA, 0x00, 0xC0]);
+encoding_test!(ld_indirect_c_test, "ld [c], a\nldh a, [c]", vec![0xE2, 0xF2]);
+encoding_test!(ldh_test, "ldh [$FF40], a\nldh a, [$44]", vec![0xE0, 0x40, 0xF0, 0x44]);
+encoding_test!(ld_sp_test, "ld [$C000], sp\nld sp, hl\nld hl, sp-2", vec![0x08, 0x00, 0xC0, 0xF9, 0xF8, 0xFE]);
+encoding_test!(alu_test, "add a, b\nsub [hl]\nxor a\ncp $10", vec![0x80, 0x96, 0xAF, 0xFE, 0x10]);
+encoding_test!(add_16bit_test, "add hl, de\nadd sp, -1", vec![0x19, 0xE8, 0xFF]);
+encoding_test!(inc_dec_test, "inc a\ndec [hl]\ninc sp\ndec bc", vec![0x3C, 0x35, 0x33, 0x0B]);
+encoding_test!(cb_test, "rlc b\nswap a\nbit 7, h\nres 0, [hl]\nset 3, c", vec![0xCB, 0x00, 0xCB, 0x37, 0xCB, 0x7C, 0xCB, 0x86, 0xCB, 0xD9]);
+encoding_test!(stack_test, "push af\npop bc", vec![0xF5, 0xC1]);
+encoding_test!(jump_test, "jp $150\njp nz, $150\njp hl\ncall c, $4000\nret z\nreti\nrst $38", vec![0xC3, 0x50, 0x01, 0xC2, 0x50, 0x01, 0xE9, 0xDC, 0x00, 0x40, 0xC8, 0xD9, 0xFF]);
+encoding_test!(control_test, "di\nei\nhalt\nstop\ndaa\ncpl\nscf\nccf", vec![0xF3, 0xFB, 0x76, 0x10, 0x00, 0x27, 0x2F, 0x37, 0x3F]);
+
+#[test]
+fn labels_and_relative_jumps_test(){
+    let source = "
+        SECTION \"main\", ROM0[$0150]
+    Start:
+        ld a, 3         ; 0150
+    .loop:
+        dec a           ; 0152
+        jr nz, .loop    ; 0153
+        jp End          ; 0155
+    End:
+        jr Start        ; 0158
+    ";
+    let bytes = assemble(source).unwrap();
+    assert_eq!(bytes, vec![0x3E, 0x03, 0x3D, 0x20, 0xFD, 0xC3, 0x58, 0x01, 0x18, 0xF6]);
+}
+
+#[test]
+fn constants_and_data_test(){
+    let source = "
+        DEF LCDC EQU $FF40
+        COUNT EQU 2 * 3
+        org $C000
+        db COUNT, LOW(LCDC), HIGH(LCDC), \"AB\"
+        dw LCDC, Table
+    Table:
+        ds 2, $FF
+    ";
+    let bytes = assemble(source).unwrap();
+    assert_eq!(bytes, vec![6, 0x40, 0xFF, b'A', b'B', 0x40, 0xFF, 0x09, 0xC0, 0xFF, 0xFF]);
+}
+
+#[test]
+fn errors_report_line_test(){
+    let error = assemble("nop\nld a, Missing").unwrap_err();
+    assert_eq!(error.line, 2);
+    assert!(assemble("jr $1000").is_err());
+    assert!(assemble("ld [hl], [hl]").is_err());
+    assert!(assemble("foo a").is_err());
+}
+
+#[test]
+fn rom_header_test(){
+    let rom = assemble_rom("SECTION \"main\", ROM0[$150]\nhalt", "TEST").unwrap();
+    assert_eq!(rom.len(), 0x8000);
+    // default entry point: nop; jp $0150
+    assert_eq!(&rom[0x100..0x104], &[0x00, 0xC3, 0x50, 0x01]);
+    assert_eq!(rom[0x104], 0xCE);
+    assert_eq!(&rom[0x134..0x138], b"TEST");
+    assert_eq!(rom[0x147], 0);
+    assert_eq!(rom[0x148], 0);
+    assert_eq!(rom[0x150], 0x76);
+
+    let mut header_checksum:u8 = 0;
+    for byte in &rom[0x134..0x14D]{
+        header_checksum = header_checksum.wrapping_sub(*byte).wrapping_sub(1);
+    }
+    assert_eq!(rom[0x14D], header_checksum);
+
+    let global_checksum = rom.iter().enumerate()
+        .filter(|(i, _)|*i != 0x14E && *i != 0x14F)
+        .fold(0u16, |sum, (_, b)|sum.wrapping_add(*b as u16));
+    assert_eq!(((rom[0x14E] as u16) << 8) | rom[0x14F] as u16, global_checksum);
+}
+
+#[test]
+fn rom_banked_sections_test(){
+    let source = "
+        SECTION \"bank 2\", ROMX[$4000], BANK[2]
+        db $AA
+        SECTION \"bank 3\", ROMX[$4000], BANK[3]
+        db $BB
+    ";
+    let rom = assemble_rom(source, "BANKS").unwrap();
+    assert_eq!(rom.len(), 0x10000);
+    assert_eq!(rom[0x8000], 0xAA);
+    assert_eq!(rom[0xC000], 0xBB);
+    assert_eq!(rom[0x147], 1);
+    assert_eq!(rom[0x148], 1);
+}
+
+#[test]
+fn ram_sections_define_labels_test(){
+    let source = "
+        SECTION \"main\", ROM0[$150]
+        ld a, [Counter]
+        SECTION \"vars\", WRAM0[$C000]
+    Buffer:
+        ds 16
+    Counter:
+        ds 1
+        SECTION \"hram vars\", HRAM
+    Flag:
+        ds 1
+        SECTION \"more code\", ROM0
+        ld [Flag], a
+    ";
+    let program = assemble_program(source).unwrap();
+    assert_eq!(program.symbols["Buffer"], 0xC000);
+    assert_eq!(program.symbols["Counter"], 0xC010);
+    assert_eq!(program.symbols["Flag"], 0xFF80);
+    // Only the rom sections are emitted and the rom layout continues after the ram sections
+    assert_eq!(program.to_bytes(), vec![0xFA, 0x10, 0xC0, 0xEA, 0x80, 0xFF]);
+
+    let rom = assemble_rom(source, "RAM").unwrap();
+    assert_eq!(rom.len(), 0x8000);
+    assert_eq!(&rom[0x150..0x156], &[0xFA, 0x10, 0xC0, 0xEA, 0x80, 0xFF]);
+
+    // Ram sections can not hold data
+    assert!(assemble("SECTION \"vars\", WRAM0[$C000]\ndb 1").is_err());
+    assert!(assemble("SECTION \"vars\", WRAM0[$C000]\ndw 1").is_err());
+    assert!(assemble("SECTION \"vars\", HRAM[$FF80]\nnop").is_err());
+    assert!(assemble("SECTION \"vars\", WRAM0[$C000]\nds 2, $FF").is_err());
+}
+
+#[test]
+fn rom_header_overlap_test(){
+    assert!(assemble_rom("org $140\nnop", "BAD").is_err());
+}
+
+#[test]
+fn assembled_program_runs_on_cpu_test(){
+    let source = "
+        org $100
+        ld b, 0
+        ld a, 5
+    .loop:
+        inc b
+        dec a
+        jr nz, .loop
+        ld hl, $C000
+        ld [hl], b
+        halt
+    ";
+    let bytes = assemble(source).unwrap();
+    let mut memory = MemoryStub{data:[0;0xFFFF]};
+    memory.data[0x100..0x100 + bytes.len()].copy_from_slice(&bytes);
+    let mut cpu = GbCpu{program_counter:0x100, ..Default::default()};
+
+    while !cpu.halt{
+        cpu.run_opcode(&mut memory);
+    }
+
+    assert_eq!(memory.data[0xC000], 5);
+}
@@ -0,0 +1,194 @@
+mod gameboy_stub;
+
+use lib_gb::assembler::assemble_rom;
+use lib_gb::mmu::memory::MemoryBank;
+use lib_gb::ppu::{color::Color, gb_ppu::SCREEN_WIDTH, gfx_device::Pixel};
+use gameboy_stub::*;
+
+// Draws the first line of the screen while the lcd is off and stores the registers values at $C000
+const PROGRAM:&str = "
+    SECTION \"main\", ROM0[$150]
+Main:
+    ld [$C000], a       ; a after the boot
+    ld a, 3
+    ldh [$70], a        ; wram bank 3
+    ld a, $33
+    ld [$D000], a
+    ldh a, [$70]
+    ld [$C001], a
+    xor a
+    ldh [$70], a        ; selects bank 1
+    ldh a, [$70]
+    ld [$C002], a
+
+    ld a, 1
+    ldh [$4F], a        ; vram bank 1
+    ldh a, [$4F]
+    ld [$C003], a
+    ld hl, $8010        ; tile 1 in bank 1, right half color 3
+    ld b, 8
+.bank1_tile:
+    ld a, $0F
+    ld [hl+], a
+    ld [hl+], a
+    dec b
+    jr nz, .bank1_tile
+    ld hl, $9801        ; tiles attributes
+    ld a, $20           ; x flip
+    ld [hl+], a
+    ld a, $08           ; tile data from bank 1
+    ld [hl+], a
+    ld a, $02           ; palette 2
+    ld [hl+], a
+    ld hl, $9806
+    ld a, $80           ; bg to obj priority
+    ld [hl], a
+    xor a
+    ldh [$4F], a
+
+    ld hl, $8010        ; tile 1, left half color 1
+    ld b, 8
+.tile1:
+    ld a, $F0
+    ld [hl+], a
+    xor a
+    ld [hl+], a
+    dec b
+    jr nz, .tile1
+    ld b, 8             ; tile 2, color 1
+.tile2:
+    ld a, $FF
+    ld [hl+], a
+    xor a
+    ld [hl+], a
+    dec b
+    jr nz, .tile2
+    ld hl, $9800
+    ld a, 1
+    ld [hl+], a
+    ld [hl+], a
+    ld [hl+], a
+    ld [hl+], a
+    ld hl, $9806
+    ld [hl], a
+
+    ld a, $80           ; auto increment from palette 0
+    ldh [$68], a
+    ld hl, BgPalettes
+    ld b, BgPalettesEnd - BgPalettes
+.bg_palettes:
+    ld a, [hl+]
+    ldh [$69], a
+    dec b
+    jr nz, .bg_palettes
+    ldh a, [$68]
+    ld [$C004], a
+    ld a, 2
+    ldh [$68], a
+    ldh a, [$69]
+    ld [$C005], a
+    ld a, $98           ; auto increment from palette 3
+    ldh [$6A], a
+    ld hl, ObjPalettes
+    ld b, ObjPalettesEnd - ObjPalettes
+.obj_palettes:
+    ld a, [hl+]
+    ldh [$6B], a
+    dec b
+    jr nz, .obj_palettes
+
+    ld hl, $FE00
+    ld de, Sprites
+    ld b, SpritesEnd - Sprites
+.sprites:
+    ld a, [de]
+    inc de
+    ld [hl+], a
+    dec b
+    jr nz, .sprites
+
+    ld a, $93           ; lcd on, objects on, tiles at $8000
+    ldh [$40], a
+.wait:
+    jr .wait
+
+BgPalettes:
+    db $FF, $7F, $1F, $00, $00, $00, $00, $7C     ; white, red, black, blue
+    db $FF, $7F, $FF, $7F, $FF, $7F, $FF, $7F
+    db $FF, $7F, $E0, $03                         ; white, green
+BgPalettesEnd:
+ObjPalettes:
+    db $00, $00, $1F, $7C, $00, $00, $00, $00     ; palette 3 - magenta
+    db $00, $00, $FF, $03                         ; palette 4 - yellow
+ObjPalettesEnd:
+Sprites:
+    db 16, 48, 1, $03
+    db 16, 56, 2, $03   ; under a bg tile with priority
+    db 16, 88, 2, $04
+    db 16, 84, 2, $03   ; overlaps the previous sprite which has a lower oam index
+SpritesEnd:
+";
+
+const CGB_FLAG_ADDRESS:usize = 0x143;
+
+const WHITE:Color = Color{r:0xFF, g:0xFF, b:0xFF};
+const RED:Color = Color{r:0xFF, g:0, b:0};
+const GREEN:Color = Color{r:0, g:0xFF, b:0};
+const BLUE:Color = Color{r:0, g:0, b:0xFF};
+const MAGENTA:Color = Color{r:0xFF, g:0, b:0xFF};
+const YELLOW:Color = Color{r:0xFF, g:0xFF, b:0};
+
+// Returns the results and the last frame
+fn run_program(cgb_flag:u8)->([u8;6], Vec<Pixel>){
+    let mut rom = assemble_rom(PROGRAM, "CGB").unwrap();
+    rom[CGB_FLAG_ADDRESS] = cgb_flag;
+    let gfx_device = FramesGfxDevice::new();
+    let frames = gfx_device.frames.clone();
+    let results = run_rom(rom, gfx_device, |gameboy|{
+        for _ in 0..3{
+            gameboy.cycle_frame();
+        }
+
+        let mut results = [0;6];
+        for (i, result) in results.iter_mut().enumerate(){
+            *result = gameboy.peek(0xC000 + i as u16);
+        }
+        assert_eq!(gameboy.peek_bank(MemoryBank::Wram(if cgb_flag != 0 {3} else {1}), 0xD000), 0x33);
+        results
+    });
+
+    let frame = frames.borrow().last().unwrap().clone();
+    return (results, frame);
+}
+
+#[test]
+fn test_cgb_registers(){
+    let (results, _) = run_program(0x80);
+
+    assert_eq!(results, [0x11, 0xFB, 0xF9, 0xFF, 0xD4, 0x1F]);
+}
+
+#[test]
+fn test_cgb_registers_ignored_in_dmg_mode(){
+    let (results, _) = run_program(0);
+
+    assert_eq!(results, [0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+}
+
+#[test]
+fn test_cgb_rendering(){
+    let (_, frame) = run_program(0xC0);
+
+    let mut expected = vec![Pixel::from(WHITE);SCREEN_WIDTH];
+    let mut fill = |start:usize, end:usize, color:Color|expected[start..end].fill(Pixel::from(color));
+    fill(0, 4, RED);
+    fill(12, 16, RED);          // x flip
+    fill(20, 24, BLUE);         // bank 1 tile data
+    fill(24, 28, GREEN);        // palette 2
+    fill(40, 44, MAGENTA);
+    fill(48, 52, RED);          // bg priority over the sprite
+    fill(52, 56, MAGENTA);
+    fill(76, 80, MAGENTA);
+    fill(80, 88, YELLOW);       // the lower oam index is on top
+    assert_eq!(&frame[0..SCREEN_WIDTH], expected.as_slice());
+}
@@ -0,0 +1,97 @@
+mod gameboy_stub;
+
+use lib_gb::assembler::{assemble_program, build_rom};
+use lib_gb::mmu::code_data_logger::*;
+use gameboy_stub::*;
+
+// Copies a routine to hram and starts an OAM DMA from the switchable bank
+const PROGRAM:&str = "
+    SECTION \"main\", ROM0[$150]
+Main:
+    ld hl, Table
+    ld a, [hl]
+    ld c, $80
+    ld hl, DmaRoutine
+    ld b, DmaRoutineEnd - DmaRoutine
+.copy:
+    ld a, [hl+]
+    ld [c], a
+    inc c
+    dec b
+    jr nz, .copy
+    call $FF80
+.wait:
+    jr .wait
+DmaRoutine:
+    ld a, $40
+    ldh [$46], a
+    ld a, 40
+.delay:
+    dec a
+    jr nz, .delay
+    ret
+DmaRoutineEnd:
+Table:
+    db $12, $34
+
+    SECTION \"oam\", ROMX[$4000], BANK[1]
+    ds $A0, $55
+";
+
+fn run_program()->(CodeDataLogger, std::collections::HashMap<String, i64>){
+    let program = assemble_program(PROGRAM).unwrap();
+    let rom = build_rom(&program, "CDL").unwrap();
+    let rom_size = rom.len();
+    let logger = run_rom(rom, StubGfxDevice{}, |gameboy|{
+        gameboy.set_code_data_logger(CodeDataLogger::new(rom_size, 0));
+        gameboy.cycle_frame();
+        gameboy.take_code_data_logger().unwrap()
+    });
+
+    return (logger, program.symbols);
+}
+
+#[test]
+fn test_code_data_logger_flags(){
+    let (logger, symbols) = run_program();
+    let flags = logger.get_rom_flags();
+    let main = symbols["Main"] as usize;
+    let table = symbols["Table"] as usize;
+    let dma_routine = symbols["DmaRoutine"] as usize;
+
+    // ld hl, Table
+    assert_eq!(flags[main], OPCODE_FLAG | FIXED_BANK_FLAG);
+    assert_eq!(flags[main + 1], OPERAND_FLAG | FIXED_BANK_FLAG);
+    assert_eq!(flags[main + 2], OPERAND_FLAG | FIXED_BANK_FLAG);
+    assert_eq!(flags[table], DATA_FLAG | FIXED_BANK_FLAG);
+    assert_eq!(flags[table + 1], 0);
+    // the routine is executed from hram so in the rom it is only data
+    assert_eq!(flags[dma_routine], DATA_FLAG | FIXED_BANK_FLAG);
+    assert!(flags[0x4000..0x40A0].iter().all(|f|*f == DMA_SOURCE_FLAG | SWITCHABLE_BANK_FLAG));
+    assert_eq!(flags[0x40A0], 0);
+    // the header is never read by the program
+    assert_eq!(flags[0x134], 0);
+}
+
+#[test]
+fn test_code_data_logger_file_accumulates(){
+    let mut previous = CodeDataLogger::new(0x8000, 0x2000);
+    previous.log_rom(0x7FFF, MemoryAccessType::Data, SWITCHABLE_BANK_FLAG);
+    previous.log_rom(0x150, MemoryAccessType::Operand, FIXED_BANK_FLAG);
+    previous.log_ram(0x10, MemoryAccessType::Data);
+
+    let mut logger = CodeDataLogger::from_bytes(&previous.to_bytes()).unwrap();
+    assert_eq!(logger.get_rom_size(), 0x8000);
+    assert_eq!(logger.get_ram_size(), 0x2000);
+    logger.log_rom(0x150, MemoryAccessType::Opcode, FIXED_BANK_FLAG);
+
+    let data = logger.to_bytes();
+    assert_eq!(&data[0..5], b"GBCD\x01");
+    assert_eq!(data.len(), 16 + 0x8000 + 0x2000);
+    let logger = CodeDataLogger::from_bytes(&data).unwrap();
+    assert_eq!(logger.get_rom_flags()[0x150], OPCODE_FLAG | OPERAND_FLAG | FIXED_BANK_FLAG);
+    assert_eq!(logger.get_rom_flags()[0x7FFF], DATA_FLAG | SWITCHABLE_BANK_FLAG);
+    assert_eq!(logger.get_ram_flags()[0x10], DATA_FLAG);
+
+    assert!(CodeDataLogger::from_bytes(b"not a cdl file at all").is_err());
+}
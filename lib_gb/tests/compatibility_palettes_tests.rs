@@ -0,0 +1,156 @@
+mod gameboy_stub;
+
+use lib_gb::assembler::assemble_rom;
+use lib_gb::machine::mbc_initializer::initialize_mbc;
+use lib_gb::ppu::{color::Color, colors::*, cgb_compatibility_palettes::*, gfx_device::Pixel};
+use gameboy_stub::*;
+
+// Fills the screen with color 3 of the background palette
+const PROGRAM:&str = "
+    SECTION \"main\", ROM0[$150]
+Main:
+    ld a, $FF
+    ldh [$47], a
+    ld a, $91
+    ldh [$40], a
+.loop:
+    jr .loop
+";
+
+// Draws a color 3 sprite with OBP0 at the top left corner and one with OBP1 right after it over a color 0 background
+const SPRITES_PROGRAM:&str = "
+    SECTION \"main\", ROM0[$150]
+Main:
+    ld hl, $8010        ; tile 1, color 3
+    ld b, 16
+    ld a, $FF
+.tile:
+    ld [hl+], a
+    dec b
+    jr nz, .tile
+
+    ld hl, $FE00
+    ld de, Sprites
+    ld b, SpritesEnd - Sprites
+.sprites:
+    ld a, [de]
+    inc de
+    ld [hl+], a
+    dec b
+    jr nz, .sprites
+
+    xor a
+    ldh [$47], a
+    ld a, $FF
+    ldh [$48], a
+    ldh [$49], a
+    ld a, $93           ; lcd on, objects on, tiles at $8000
+    ldh [$40], a
+.loop:
+    jr .loop
+
+Sprites:
+    db 16, 8, 1, $00
+    db 16, 16, 1, $10   ; OBP1
+SpritesEnd:
+";
+
+const OLD_LICENSEE_CODE_ADDRESS:usize = 0x14B;
+const NEW_LICENSEE_CODE_ADDRESS:usize = 0x144;
+
+const RED:Color = Color{r:0xFF, g:0, b:0};
+const YELLOW:Color = Color{r:0xFF, g:0xFF, b:0};
+const BLUE:Color = Color{r:0, g:0, b:0xFF};
+
+fn get_palettes(title:&str, old_licensee_code:u8)->DmgPalettes{
+    let mut rom = assemble_rom(PROGRAM, title).unwrap();
+    rom[OLD_LICENSEE_CODE_ADDRESS] = old_licensee_code;
+    rom[NEW_LICENSEE_CODE_ADDRESS..NEW_LICENSEE_CODE_ADDRESS + 2].copy_from_slice(b"01");
+    let mbc = initialize_mbc(rom, None);
+    return get_compatibility_palettes(&*mbc);
+}
+
+#[test]
+fn test_title_checksum(){
+    let palettes = get_palettes("TETRIS", 0x01);
+    assert_eq!(palettes.bg, [WHITE, YELLOW, RED, BLACK]);
+    assert_eq!(palettes.obj0, palettes.bg);
+    assert_eq!(palettes.obj1, palettes.bg);
+
+    // The new licensee code is used instead when the old one is 0x33
+    assert_eq!(get_palettes("TETRIS", 0x33), palettes);
+    assert_eq!(get_palettes("TETRIS", 0x02), PaletteKeyCombination::RightA.get_palettes());
+    assert_eq!(get_palettes("UNKNOWN", 0x01), PaletteKeyCombination::RightA.get_palettes());
+}
+
+#[test]
+fn test_duplicate_title_checksum(){
+    // Both titles have the checksum 0x61 and are told apart by the 4th letter
+    let pokemon_blue = get_palettes("POKEMON BLUE", 0x01);
+    assert_eq!(pokemon_blue.bg[2], BLUE);
+    assert_eq!(pokemon_blue.obj0[3], BLACK);
+    let vegas_stakes = get_palettes("VEGAS STAKES", 0x01);
+    assert_eq!(vegas_stakes.bg[2], Color{r:0, g:0x84, b:0});
+
+    // A title with the same checksum and an unknown 4th letter gets the default palettes
+    assert_eq!(get_palettes("POKFMON BLUD", 0x01), PaletteKeyCombination::RightA.get_palettes());
+}
+
+#[test]
+fn test_set_dmg_palettes_while_running(){
+    let gfx_device = FramesGfxDevice::new();
+    let frames = gfx_device.frames.clone();
+    run_rom(assemble_rom(PROGRAM, "PALETTES").unwrap(), gfx_device, |gameboy|{
+        for _ in 0..3{
+            gameboy.cycle_frame();
+        }
+        assert!(frames.borrow().last().unwrap().iter().all(|pixel|*pixel == Pixel::from(BLACK)));
+
+        // The inverted palette maps color 3 to white
+        gameboy.set_dmg_palettes(PaletteKeyCombination::RightB.get_palettes());
+        for _ in 0..2{
+            gameboy.cycle_frame();
+        }
+        assert!(frames.borrow().last().unwrap().iter().all(|pixel|*pixel == Pixel::from(WHITE)));
+    });
+}
+
+#[test]
+fn test_dmg_palettes_presets(){
+    let gfx_device = FramesGfxDevice::new();
+    let frames = gfx_device.frames.clone();
+    assert_eq!(DmgPalettes::default(), DmgPalettesPreset::Grayscale.get_palettes());
+    run_rom(assemble_rom(PROGRAM, "PALETTES").unwrap(), gfx_device, |gameboy|{
+        for preset in DmgPalettesPreset::ALL{
+            let palettes = preset.get_palettes();
+            assert_eq!(palettes.obj0, palettes.bg, "{}", preset.get_name());
+            gameboy.set_dmg_palettes(palettes);
+            for _ in 0..2{
+                gameboy.cycle_frame();
+            }
+            assert!(frames.borrow().last().unwrap().iter().all(|pixel|*pixel == Pixel::from(palettes.bg[3])), "{}", preset.get_name());
+        }
+    });
+}
+
+#[test]
+fn test_dmg_palettes_per_layer(){
+    let gfx_device = FramesGfxDevice::new();
+    let frames = gfx_device.frames.clone();
+    // Each layer can have its own shades
+    let mut palettes = DmgPalettesPreset::Pocket.get_palettes();
+    palettes.obj0 = DmgPalettesPreset::DmgGreen.get_palettes().bg;
+    palettes.obj1 = DmgPalettesPreset::HighContrast.get_palettes().bg;
+    assert!(palettes.obj0[3] != palettes.bg[3] && palettes.obj1[3] != palettes.bg[3] && palettes.obj0[3] != palettes.obj1[3]);
+    run_rom(assemble_rom(SPRITES_PROGRAM, "PALETTES").unwrap(), gfx_device, |gameboy|{
+        gameboy.set_dmg_palettes(palettes);
+        for _ in 0..3{
+            gameboy.cycle_frame();
+        }
+        let frames = frames.borrow();
+        let frame = frames.last().unwrap();
+        assert_eq!(frame[0], Pixel::from(palettes.obj0[3]));
+        assert_eq!(frame[8], Pixel::from(palettes.obj1[3]));
+        assert_eq!(frame[16], Pixel::from(palettes.bg[0]));
+    });
+}
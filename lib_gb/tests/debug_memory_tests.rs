@@ -0,0 +1,135 @@
+mod gameboy_stub;
+
+use lib_gb::assembler::assemble_rom;
+use lib_gb::mmu::memory::MemoryBank;
+use gameboy_stub::*;
+
+// Samples LY into wram in a loop, so any change in the emulation timing changes the wram content
+const PROGRAM:&str = "
+    SECTION \"main\", ROM0[$150]
+Main:
+    ld a, $91
+    ldh [$40], a
+.loop:
+    ld hl, $C000
+.sample:
+    ldh a, [$44]
+    ld [hl+], a
+    ld a, h
+    cp $C4
+    jr nz, .sample
+    jr .loop
+
+    SECTION \"bank1\", ROMX[$4000], BANK[1]
+    db $11
+    SECTION \"bank2\", ROMX[$4000], BANK[2]
+    db $22
+    SECTION \"bank3\", ROMX[$4000], BANK[3]
+    db $33
+";
+
+const CART_RAM_SIZE_ADDRESS:usize = 0x149;
+const CART_RAM_32KB:u8 = 3;
+
+fn build_test_rom()->Vec<u8>{
+    let mut rom = assemble_rom(PROGRAM, "PEEK").unwrap();
+    rom[CART_RAM_SIZE_ADDRESS] = CART_RAM_32KB;
+    return rom;
+}
+
+#[test]
+fn test_peek_does_not_affect_emulation(){
+    run_rom(build_test_rom(), StubGfxDevice{}, |gameboy|{
+        run_rom(build_test_rom(), StubGfxDevice{}, |other_gameboy|{
+            for _ in 0..3{
+                gameboy.cycle_frame();
+                other_gameboy.cycle_frame();
+                for address in 0..=0xFFFF{
+                    gameboy.peek(address);
+                }
+                gameboy.dump_io();
+            }
+
+            for address in 0xC000..0xC400{
+                assert_eq!(gameboy.peek(address), other_gameboy.peek(address), "{:#X}", address);
+            }
+            assert_eq!(gameboy.dump_io(), other_gameboy.dump_io());
+        });
+    });
+}
+
+#[test]
+fn test_peek_ignores_ppu_locking(){
+    run_rom(build_test_rom(), StubGfxDevice{}, |gameboy|{
+        gameboy.cycle_frame();
+        gameboy.poke(0x8000, 0x5A);
+        gameboy.poke(0xFE00, 0xA5);
+
+        // The lcd is on so the program samples every ppu mode including the ones that lock vram and oam
+        gameboy.cycle_frame();
+        assert_eq!(gameboy.peek(0x8000), 0x5A);
+        assert_eq!(gameboy.peek(0xFE00), 0xA5);
+        assert_eq!(gameboy.dump_oam()[0], 0xA5);
+    });
+}
+
+#[test]
+fn test_rom_banks(){
+    run_rom(build_test_rom(), StubGfxDevice{}, |gameboy|{
+        assert_eq!(gameboy.peek(0x4000), 0x11);
+        assert_eq!(gameboy.peek_bank(MemoryBank::Rom(2), 0x4000), 0x22);
+        assert_eq!(gameboy.peek_bank(MemoryBank::Rom(3), 0x0000), 0x33);
+        assert_eq!(gameboy.peek_bank(MemoryBank::Rom(0), 0x150), 0x3E);
+        assert_eq!(gameboy.peek_bank(MemoryBank::Rom(100), 0x4000), 0xFF);
+
+        // Poking the rom patches it and does not switch banks
+        gameboy.poke(0x2000, 3);
+        assert_eq!(gameboy.peek(0x2000), 3);
+        assert_eq!(gameboy.peek(0x4000), 0x11);
+        gameboy.poke_bank(MemoryBank::Rom(2), 0x4001, 0x44);
+        assert_eq!(gameboy.peek_bank(MemoryBank::Rom(2), 0x4001), 0x44);
+    });
+}
+
+#[test]
+fn test_ram_banks(){
+    run_rom(build_test_rom(), StubGfxDevice{}, |gameboy|{
+        gameboy.poke_bank(MemoryBank::Wram(3), 0xD010, 0x42);
+        assert_eq!(gameboy.peek(0xD010), 0);
+        assert_eq!(gameboy.peek_bank(MemoryBank::Wram(3), 0x0010), 0x42);
+        gameboy.poke(0xC010, 0x24);
+        assert_eq!(gameboy.peek_bank(MemoryBank::Wram(0), 0xC010), 0x24);
+
+        gameboy.poke_bank(MemoryBank::Vram(1), 0x8000, 0x77);
+        assert_eq!(gameboy.peek(0x8000), 0);
+        assert_eq!(gameboy.peek_bank(MemoryBank::Vram(1), 0x8000), 0x77);
+
+        // The cart ram is accessible even when it is disabled by the mbc
+        gameboy.poke(0xA000, 0x66);
+        gameboy.poke_bank(MemoryBank::CartRam(2), 0xA000, 0x55);
+        assert_eq!(gameboy.peek(0xA000), 0x66);
+        assert_eq!(gameboy.peek_bank(MemoryBank::CartRam(0), 0xA000), 0x66);
+        assert_eq!(gameboy.peek_bank(MemoryBank::CartRam(2), 0xA000), 0x55);
+        assert_eq!(gameboy.peek_bank(MemoryBank::CartRam(4), 0xA000), 0xFF);
+    });
+}
+
+#[test]
+fn test_dumps(){
+    run_rom(build_test_rom(), StubGfxDevice{}, |gameboy|{
+        gameboy.poke(0xFF80, 0x12);
+        gameboy.poke(0xFFFE, 0x34);
+        gameboy.poke(0xFF42, 0x56);
+        gameboy.poke(0xFFFF, 0x1F);
+
+        let hram = gameboy.dump_hram();
+        assert_eq!(hram.len(), 0x7F);
+        assert_eq!(hram[0], 0x12);
+        assert_eq!(hram[0x7E], 0x34);
+        let io = gameboy.dump_io();
+        assert_eq!(io.len(), 0x80);
+        assert_eq!(io[0x42], 0x56);
+        assert_eq!(io[0x50], 1);
+        assert_eq!(gameboy.peek(0xFFFF), 0x1F);
+    });
+}
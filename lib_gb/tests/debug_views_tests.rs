@@ -0,0 +1,85 @@
+mod gameboy_stub;
+
+use lib_gb::ppu::{gb_ppu::GbPpu, color::Color, debug_views::*, ppu_register_updater::*};
+use gameboy_stub::*;
+
+const LCDC_UNSIGNED_TILES:u8 = 0x10;
+const LCDC_WINDOW_ON_9C00:u8 = 0x60;
+const LCDC_BIG_SPRITES:u8 = 0x04;
+const RED:Color = Color{r:0xFF, g:0, b:0};
+const BLUE:Color = Color{r:0, g:0, b:0xFF};
+const MAGENTA:Color = Color{r:0xFF, g:0, b:0xFF};
+
+// Tile 1 has its first line in color 1 on the left half and color 3 on the right half
+fn create_tiles_ppu()->GbPpu<FramesGfxDevice>{
+    let (mut ppu, _, _) = create_ppu();
+    ppu.vram.write_current_bank(0x10, 0xFF);
+    ppu.vram.write_current_bank(0x11, 0x0F);
+    return ppu;
+}
+
+#[test]
+fn test_render_tiles(){
+    let ppu = create_tiles_ppu();
+    let image = ppu.render_tiles();
+
+    assert_eq!((image.width, image.height), (TILES_VIEW_WIDTH, TILES_VIEW_HEIGHT));
+    assert_eq!(image.buffer.len(), TILES_VIEW_WIDTH * TILES_VIEW_HEIGHT * 3);
+    assert_eq!(image.get_pixel(0, 0), ppu.bg_color_mapping[0]);
+    assert_eq!(image.get_pixel(8, 0), ppu.bg_color_mapping[1]);
+    assert_eq!(image.get_pixel(15, 0), ppu.bg_color_mapping[3]);
+    assert_eq!(image.get_pixel(8, 1), ppu.bg_color_mapping[0]);
+}
+
+#[test]
+fn test_render_tile_map_with_overlays(){
+    let mut ppu = create_tiles_ppu();
+    ppu.lcd_control = LCDC_UNSIGNED_TILES | LCDC_WINDOW_ON_9C00;
+    ppu.vram.write_current_bank(0x1800, 1);
+    ppu.vram.write_current_bank(0x1C00, 1);
+    set_scx(&mut ppu, 200);
+    set_scy(&mut ppu, 20);
+    handle_wx_register(107, &mut ppu);
+    handle_wy_register(44, &mut ppu);
+
+    let image = ppu.render_tile_map(TileMap::Map9800);
+    assert_eq!((image.width, image.height), (TILE_MAP_VIEW_SIZE, TILE_MAP_VIEW_SIZE));
+    assert_eq!(image.get_pixel(0, 0), ppu.bg_color_mapping[1]);
+    // The viewport wraps around the right edge
+    assert_eq!(image.get_pixel(200, 20), RED);
+    assert_eq!(image.get_pixel(255, 163), RED);
+    assert_eq!(image.get_pixel((200 + 159) % 256, 100), RED);
+    assert_eq!(image.get_pixel(201, 21), ppu.bg_color_mapping[0]);
+    assert_ne!(image.get_pixel(0, 0), BLUE);
+
+    let image = ppu.render_tile_map(TileMap::Map9C00);
+    assert_eq!(image.get_pixel(0, 0), BLUE);
+    assert_eq!(image.get_pixel(59, 99), BLUE);
+    assert_eq!(image.get_pixel(1, 1), ppu.bg_color_mapping[0]);
+    assert_ne!(image.get_pixel(200, 20), RED);
+}
+
+#[test]
+fn test_render_sprites(){
+    let mut ppu = create_tiles_ppu();
+    // Sprite 1 uses tile 1 flipped on the x axis
+    ppu.oam[4..8].copy_from_slice(&[16, 8, 1, 0x20]);
+
+    let image = ppu.render_sprites();
+    assert_eq!((image.width, image.height), (SPRITES_VIEW_WIDTH, SPRITES_VIEW_HEIGHT));
+    assert_eq!(image.get_pixel(8, 0), ppu.obj_color_mapping0[3].unwrap());
+    assert_eq!(image.get_pixel(15, 0), ppu.obj_color_mapping0[1].unwrap());
+    assert_eq!(image.get_pixel(8, 1), MAGENTA);
+    // 8x8 sprites use only the top half of the cell
+    assert_eq!(image.get_pixel(8, 8), MAGENTA);
+
+    // With 8x16 sprites the tile number ignores its first bit
+    ppu.lcd_control = LCDC_BIG_SPRITES;
+    let image = ppu.render_sprites();
+    assert_eq!(image.get_pixel(8, 0), MAGENTA);
+    assert_eq!(image.get_pixel(8, 8), ppu.obj_color_mapping0[3].unwrap());
+
+    let attributes = ppu.get_sprites_attributes();
+    assert_eq!(attributes.len(), SPRITES_COUNT);
+    assert_eq!((attributes[1].y, attributes[1].x, attributes[1].tile_number, attributes[1].flip_x), (16, 8, 1, true));
+}
@@ -0,0 +1,72 @@
+mod gameboy_stub;
+
+use lib_gb::assembler::assemble_rom;
+use gameboy_stub::*;
+
+// Measures the lines the ppu draws during a fixed length loop before and after switching to double speed,
+// the results are stored at $C000
+const PROGRAM:&str = "
+    SECTION \"main\", ROM0[$150]
+Main:
+    ld a, $91
+    ldh [$40], a
+    call MeasureLines
+    ld [$C000], a
+
+    ldh a, [$4D]
+    ld [$C001], a
+    ld a, 1             ; prepare the speed switch
+    ldh [$4D], a
+    ldh a, [$4D]
+    ld [$C002], a
+    ld a, $80
+    ldh [$04], a
+    stop
+    ldh a, [$04]        ; the divider is reset and stopped during the switch
+    ld [$C003], a
+    ldh a, [$4D]
+    ld [$C004], a
+
+    call MeasureLines
+    ld [$C005], a
+.loop:
+    jr .loop
+
+; Waits for line $10 and returns the lines passed after 1024 cpu m_cycles
+MeasureLines:
+    ldh a, [$44]
+    cp $10
+    jr nz, MeasureLines
+    ld b, 0
+.delay:
+    dec b
+    jr nz, .delay
+    ldh a, [$44]
+    sub $10
+    ret
+";
+
+const CGB_FLAG_ADDRESS:usize = 0x143;
+
+#[test]
+fn test_double_speed(){
+    let mut rom = assemble_rom(PROGRAM, "KEY1").unwrap();
+    rom[CGB_FLAG_ADDRESS] = 0x80;
+    let gfx_device = FramesGfxDevice::new();
+    let frames = gfx_device.frames.clone();
+    run_rom(rom, gfx_device, |gameboy|{
+        for _ in 0..5{
+            gameboy.cycle_frame();
+        }
+
+        let results:Vec<u8> = (0xC000..0xC006).map(|address|gameboy.peek(address)).collect();
+        assert_eq!(results, [9, 0x7E, 0x7F, 0, 0xFE, 4]);
+
+        // The frame length is in real time so there is still a single frame every cycle_frame
+        let frames_before = frames.borrow().len();
+        for _ in 0..10{
+            gameboy.cycle_frame();
+        }
+        assert_eq!(frames.borrow().len() - frames_before, 10);
+    });
+}
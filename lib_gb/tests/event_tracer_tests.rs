@@ -0,0 +1,111 @@
+mod gameboy_stub;
+
+use lib_gb::assembler::assemble_rom;
+use lib_gb::machine::event_tracer::EventTracer;
+use gameboy_stub::*;
+
+const PROGRAM:&str = "
+    SECTION \"vblank\", ROM0[$40]
+    reti
+    SECTION \"timer\", ROM0[$50]
+    reti
+
+    SECTION \"main\", ROM0[$150]
+Main:
+    ld a, $91           ; lcd on
+    ldh [$40], a
+    ld a, %101          ; vblank and timer interrupts
+    ldh [$FF], a
+    xor a
+    ldh [$0F], a
+    ld a, %101          ; timer enabled at 262144Hz
+    ldh [$07], a
+    ld a, $80
+    ldh [$26], a        ; apu on
+    ldh [$14], a        ; channel 1 trigger
+    ld a, 2
+    ld [$2000], a       ; switch to rom bank 2
+    ld c, $80
+    ld hl, DmaRoutine
+    ld b, DmaRoutineEnd - DmaRoutine
+.copy:
+    ld a, [hl+]
+    ld [c], a
+    inc c
+    dec b
+    jr nz, .copy
+    call $FF80
+    ei
+.wait:
+    halt
+    jr .wait
+DmaRoutine:
+    ld a, $C0
+    ldh [$46], a
+    ld a, 40
+.delay:
+    dec a
+    jr nz, .delay
+    ret
+DmaRoutineEnd:
+
+    SECTION \"bank 3\", ROMX[$4000], BANK[3]
+    db 0
+";
+
+fn run_trace()->String{
+    let rom = assemble_rom(PROGRAM, "TRACE").unwrap();
+    let tracer = run_rom(rom, StubGfxDevice{}, |gameboy|{
+        gameboy.set_event_tracer(EventTracer::new(0, 1));
+        for _ in 0..3{
+            gameboy.cycle_frame();
+        }
+        gameboy.take_event_tracer().unwrap()
+    });
+    assert!(tracer.is_finished());
+    let mut output = Vec::new();
+    tracer.write_chrome_trace(&mut output).unwrap();
+
+    return String::from_utf8(output).unwrap();
+}
+
+#[test]
+fn test_event_tracer_records_hardware_events(){
+    let trace = run_trace();
+    let json:serde_json::Value = serde_json::from_str(&trace).unwrap();
+    let events = json["traceEvents"].as_array().unwrap();
+    let names:Vec<&str> = events.iter().map(|e|e["name"].as_str().unwrap()).collect();
+
+    for expected in ["Mode 2 (OAM search)", "Mode 3 (Pixel transfer)", "Mode 0 (HBlank)", "Mode 1 (VBlank)", "LY",
+        "VBlank requested", "VBlank dispatched", "Timer requested", "Timer dispatched", "TIMA overflow",
+        "OAM DMA", "CH1 trigger", "ROM bank 2", "Frame 0", "Frame 1"].iter(){
+        assert!(names.contains(expected), "missing event: {}", expected);
+    }
+    // Out of the recorded range
+    assert!(!names.contains(&"Frame 2"));
+
+    for event in events.iter().filter(|e|e["ph"] != "M"){
+        assert!(event["ts"].as_f64().unwrap() >= 0.0);
+        assert!(event["tid"].as_u64().unwrap() < 8);
+    }
+    let dma = events.iter().find(|e|e["name"] == "OAM DMA").unwrap();
+    assert_eq!(dma["ph"], "X");
+    // 160 bytes transferred, one each M-cycle
+    let dma_m_cycles = dma["args"]["m_cycles"].as_u64().unwrap();
+    assert!((160..=164).contains(&dma_m_cycles), "{}", dma_m_cycles);
+    let frame = events.iter().find(|e|e["name"] == "Frame 1").unwrap();
+    assert_eq!(frame["args"]["m_cycles"].as_u64().unwrap(), 17556);
+}
+
+#[test]
+fn test_event_tracer_frame_range(){
+    let mut tracer = EventTracer::new(2, 3);
+    assert!(!tracer.is_recording());
+    tracer.end_frame();
+    tracer.end_frame();
+    assert!(tracer.is_recording());
+    tracer.end_frame();
+    tracer.end_frame();
+    assert!(!tracer.is_recording());
+    assert!(tracer.is_finished());
+}
@@ -0,0 +1,82 @@
+// Shared by the tests that run a GameBoy or a GbPpu, not every test uses all of it
+#![allow(dead_code)]
+
+use std::{cell::RefCell, rc::Rc};
+use lib_gb::apu::audio_device::{AudioDevice, StereoSample, BUFFER_SIZE};
+use lib_gb::keypad::{joypad::Joypad, joypad_provider::JoypadProvider};
+use lib_gb::machine::{gameboy::GameBoy, mbc_initializer::initialize_mbc, mode::Mode};
+use lib_gb::ppu::{gb_ppu::{GbPpu, SCREEN_HEIGHT, SCREEN_WIDTH}, gfx_device::{GfxDevice, Pixel}, ppu_register_updater::*};
+
+pub type Frames = Rc<RefCell<Vec<Vec<Pixel>>>>;
+// The hd or upscaled frames with their scale
+pub type HdFrames = Rc<RefCell<Vec<(Vec<Pixel>, usize)>>>;
+
+pub const IDENTITY_PALETTE:u8 = 0b11_10_01_00;
+
+pub struct StubGfxDevice;
+impl GfxDevice for StubGfxDevice{
+    fn swap_buffer(&mut self, _buffer:&[Pixel; SCREEN_HEIGHT * SCREEN_WIDTH]) {}
+}
+
+// Keeps every frame
+pub struct FramesGfxDevice{
+    pub frames:Frames,
+    pub hd_frames:HdFrames
+}
+impl FramesGfxDevice{
+    pub fn new()->Self{
+        Self{frames:Rc::new(RefCell::new(Vec::new())), hd_frames:Rc::new(RefCell::new(Vec::new()))}
+    }
+}
+impl Default for FramesGfxDevice{
+    fn default()->Self{
+        Self::new()
+    }
+}
+impl GfxDevice for FramesGfxDevice{
+    fn swap_buffer(&mut self, buffer:&[Pixel; SCREEN_HEIGHT * SCREEN_WIDTH]) {
+        self.frames.borrow_mut().push(buffer.to_vec());
+    }
+
+    fn swap_hd_buffer(&mut self, buffer:&[Pixel], scale:usize){
+        self.hd_frames.borrow_mut().push((buffer.to_vec(), scale));
+    }
+}
+
+pub struct StubAudioDevice;
+impl AudioDevice for StubAudioDevice{
+    fn push_buffer(&mut self, _buffer:&[StereoSample; BUFFER_SIZE]) {}
+}
+
+pub struct StubJoypadProvider;
+impl JoypadProvider for StubJoypadProvider{
+    fn provide(&mut self, _joypad:&mut Joypad) {}
+}
+
+pub type TestGameBoy<'a, GFX> = GameBoy<'a, StubJoypadProvider, StubAudioDevice, GFX>;
+
+// The GameBoy borrows the cartridge so it is available only inside the closure
+pub fn run_rom<GFX:GfxDevice, T>(rom:Vec<u8>, gfx_device:GFX, run:impl FnOnce(&mut TestGameBoy<GFX>)->T)->T{
+    let mut mbc = initialize_mbc(rom, None);
+    let mut gameboy = GameBoy::new(&mut mbc, StubJoypadProvider{}, StubAudioDevice{}, gfx_device);
+    return run(&mut gameboy);
+}
+
+// A dmg ppu with the identity bg and obj0 palettes, the lcd is still off
+pub fn create_ppu()->(GbPpu<FramesGfxDevice>, Frames, HdFrames){
+    let gfx_device = FramesGfxDevice::new();
+    let (frames, hd_frames) = (gfx_device.frames.clone(), gfx_device.hd_frames.clone());
+    let mut ppu = GbPpu::new(gfx_device, Mode::DMG);
+    handle_bg_pallet_register(IDENTITY_PALETTE, &ppu.dmg_palettes.bg.clone(), &mut ppu.bg_color_mapping, &mut ppu.bg_palette_register);
+    handle_obp_pallet_register(IDENTITY_PALETTE, &ppu.dmg_palettes.obj0.clone(), &mut ppu.obj_color_mapping0, &mut ppu.obj_pallete_0_register);
+    return (ppu, frames, hd_frames);
+}
+
+// Cycles the ppu until it renders the count of frames
+pub fn render_frames(ppu:&mut GbPpu<FramesGfxDevice>, frames:&Frames, count:usize){
+    let mut if_register = 0;
+    let target = frames.borrow().len() + count;
+    while frames.borrow().len() < target{
+        ppu.cycle(1, &mut if_register);
+    }
+}
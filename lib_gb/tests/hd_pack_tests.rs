@@ -0,0 +1,127 @@
+mod gameboy_stub;
+
+use lib_gb::ppu::{gb_ppu::{GbPpu, SCREEN_HEIGHT, SCREEN_WIDTH}, gfx_device::{GfxDevice, Pixel}, color::Color, hd_pack::*, ppu_register_updater::*};
+use gameboy_stub::*;
+
+const LCDC_ON:u8 = 0x91;
+const SCALE:usize = 2;
+const REPLACEMENT_COLOR:Color = Color{r:0x12, g:0x34, b:0x56};
+// Tile 1 is drawn with color 3 on the first background tile and tile 0 (color 0) everywhere else
+const TILE_DATA:[u8;16] = [0xFF;16];
+
+fn create_hd_ppu()->(GbPpu<FramesGfxDevice>, Frames, HdFrames){
+    let (mut ppu, frames, hd_frames) = create_ppu();
+    for (i, byte) in TILE_DATA.iter().enumerate(){
+        ppu.vram.write_current_bank(0x10 + i as u16, *byte);
+    }
+    ppu.vram.write_current_bank(0x1800, 1);
+    handle_lcdcontrol_register(LCDC_ON, &mut ppu);
+    return (ppu, frames, hd_frames);
+}
+
+fn get_bg_palette<GFX:GfxDevice>(ppu:&GbPpu<GFX>)->[Option<Color>;4]{
+    let mut palette = [None;4];
+    for (color, mapping) in palette.iter_mut().zip(ppu.bg_color_mapping.iter()){
+        *color = Some(*mapping);
+    }
+    return palette;
+}
+
+// Renders until the second frame since the first one after turning the lcd on is not displayed
+const RENDERED_FRAMES:usize = 2;
+
+#[test]
+fn test_tile_key_file_name(){
+    let palette = [None, Some(Color{r:0xFF, g:0, b:0}), Some(REPLACEMENT_COLOR), Some(Color{r:0, g:0, b:0})];
+    let key = HdTileKey::new(&TILE_DATA, &palette);
+    let name = key.get_file_name();
+    assert!(name.ends_with("_none_ff0000_123456_000000"));
+    assert_eq!(HdTileKey::from_file_name(&name), Some(key));
+    assert_eq!(HdTileKey::from_file_name("not_a_key"), None);
+    assert_ne!(HdTileKey::new(&[0;16], &palette), key);
+}
+
+#[test]
+fn test_pack_tile_size_validation(){
+    let key = HdTileKey::new(&TILE_DATA, &[None;4]);
+    let mut pack = HdPack::new(SCALE);
+    assert!(pack.add_tile(key, vec![0;8 * 8 * 4]).is_err());
+    assert!(pack.add_tile(key, vec![0;16 * 16 * 4]).is_ok());
+    assert_eq!(pack.len(), 1);
+}
+
+#[test]
+fn test_dumper_records_unique_tiles(){
+    let (mut ppu, frames, _) = create_hd_ppu();
+    ppu.set_hd_tiles_dumper(Some(HdTilesDumper::new()));
+    render_frames(&mut ppu, &frames, RENDERED_FRAMES);
+
+    let tiles = ppu.get_hd_tiles_dumper().unwrap().take_new_tiles();
+    assert_eq!(tiles.len(), 2);
+    let palette = get_bg_palette(&ppu);
+    let (_, rgba) = tiles.iter().find(|(key, _)|*key == HdTileKey::new(&TILE_DATA, &palette)).unwrap();
+    assert_eq!(rgba[0..4], [palette[3].unwrap().r, palette[3].unwrap().g, palette[3].unwrap().b, 0xFF]);
+
+    // Seen tiles are not recorded again
+    frames.borrow_mut().clear();
+    render_frames(&mut ppu, &frames, RENDERED_FRAMES);
+    assert!(ppu.get_hd_tiles_dumper().unwrap().take_new_tiles().is_empty());
+}
+
+#[test]
+fn test_pack_replaces_tiles_in_hd_frame(){
+    let (mut ppu, frames, hd_frames) = create_hd_ppu();
+    let mut pack = HdPack::new(SCALE);
+    let replacement = [REPLACEMENT_COLOR.r, REPLACEMENT_COLOR.g, REPLACEMENT_COLOR.b, 0xFF].repeat(16 * 16);
+    pack.add_tile(HdTileKey::new(&TILE_DATA, &get_bg_palette(&ppu)), replacement).unwrap();
+    ppu.set_hd_pack(Some(pack));
+    render_frames(&mut ppu, &frames, RENDERED_FRAMES);
+
+    let (hd_frame, scale) = &hd_frames.borrow()[1];
+    assert_eq!(*scale, SCALE);
+    assert_eq!(hd_frame.len(), SCREEN_WIDTH * SCREEN_HEIGHT * SCALE * SCALE);
+    let hd_width = SCREEN_WIDTH * SCALE;
+    let replacement_pixel:Pixel = REPLACEMENT_COLOR.into();
+    assert_eq!(hd_frame[0], replacement_pixel);
+    assert_eq!(hd_frame[(15 * hd_width) + 15], replacement_pixel);
+    // Tiles without a replacement are scaled from the original frame
+    let original_pixel = frames.borrow()[1][8];
+    assert_eq!(hd_frame[16], original_pixel);
+    assert_eq!(hd_frame[hd_width + 17], original_pixel);
+}
+#[test]
+fn test_pack_replaces_flipped_sprites_in_hd_frame(){
+    // The left half of tile 2 is color 3 and the right half is transparent, the sprite is x flipped
+    const SPRITE_TILE_DATA:[u8;16] = [0xF0;16];
+    const SPRITE_X:usize = 8;
+    let (mut ppu, frames, hd_frames) = create_hd_ppu();
+    for (i, byte) in SPRITE_TILE_DATA.iter().enumerate(){
+        ppu.vram.write_current_bank(0x20 + i as u16, *byte);
+    }
+    ppu.oam[0..4].copy_from_slice(&[16, (SPRITE_X + 8) as u8, 2, 0x20]);
+    handle_lcdcontrol_register(LCDC_ON | 0b10, &mut ppu);
+
+    // The left half of the replacement is red and the right half blue
+    let red = Color{r:0xFF, g:0, b:0};
+    let blue = Color{r:0, g:0, b:0xFF};
+    let mut replacement = Vec::new();
+    for _ in 0..16{
+        for x in 0..16{
+            let color = if x < 8 {red} else {blue};
+            replacement.extend_from_slice(&[color.r, color.g, color.b, 0xFF]);
+        }
+    }
+    let mut palette = ppu.obj_color_mapping0;
+    palette[0] = None;
+    let mut pack = HdPack::new(SCALE);
+    pack.add_tile(HdTileKey::new(&SPRITE_TILE_DATA, &palette), replacement).unwrap();
+    ppu.set_hd_pack(Some(pack));
+    render_frames(&mut ppu, &frames, RENDERED_FRAMES);
+
+    let (hd_frame, _) = &hd_frames.borrow()[1];
+    // The flipped left half of the tile is on the right half of the sprite
+    assert_eq!(hd_frame[(SPRITE_X + 4) * SCALE], Pixel::from(red));
+    assert_eq!(hd_frame[((SPRITE_X + 8) * SCALE) - 1], Pixel::from(red));
+    // The transparent sprite pixels are scaled from the original frame
+    assert_eq!(hd_frame[SPRITE_X * SCALE], frames.borrow()[1][SPRITE_X]);
+}
@@ -1,12 +1,13 @@
+mod gameboy_stub;
+
 use std::collections::hash_map::DefaultHasher;
 use std::convert::TryInto;
 use std::hash::{Hash, Hasher};
 use std::io::Read;
-use lib_gb::mmu::gb_mmu::BOOT_ROM_SIZE;
-use lib_gb::ppu::{gb_ppu::{SCREEN_HEIGHT, SCREEN_WIDTH}, gfx_device::{Pixel, GfxDevice}};
-use lib_gb::apu::audio_device::{BUFFER_SIZE, AudioDevice};
-use lib_gb::keypad::joypad_provider::JoypadProvider;
+use lib_gb::mmu::{gb_mmu::BOOT_ROM_SIZE, memory::MemoryBank};
+use lib_gb::ppu::{gb_ppu::{SCREEN_HEIGHT, SCREEN_WIDTH}, gfx_device::{Pixel, GfxDevice}, color::Color, colors::DmgPalettes};
 use lib_gb::machine::{gameboy::GameBoy, mbc_initializer::initialize_mbc};
+use gameboy_stub::*;
 
 struct CheckHashGfxDevice{
     hash:u64,
@@ -28,16 +29,6 @@ impl GfxDevice for CheckHashGfxDevice{
     }
 }
 
-struct StubAudioDevice;
-impl AudioDevice for StubAudioDevice{
-    fn push_buffer(&mut self, _buffer:&[lib_gb::apu::audio_device::StereoSample; BUFFER_SIZE]) {}
-}
-
-struct StubJoypadProvider;
-impl JoypadProvider for StubJoypadProvider{
-    fn provide(&mut self, _joypad:&mut lib_gb::keypad::joypad::Joypad) {}
-}
-
 #[test]
 fn test_cpu_instrs(){
     let file_url = "https://raw.githubusercontent.com/retrio/gb-test-roms/master/cpu_instrs/cpu_instrs.gb";
@@ -56,6 +47,46 @@ fn test_dmg_acid(){
     run_integration_test_from_url(file_url, 60, 1690571533691915665);
 }
 
+#[test]
+fn test_blargg_oam_bug_lcd_sync(){
+    run_blargg_memory_test("oam_bug/rom_singles/1-lcd_sync.gb");
+}
+
+#[test]
+fn test_blargg_oam_bug_causes(){
+    run_blargg_memory_test("oam_bug/rom_singles/2-causes.gb");
+}
+
+#[test]
+fn test_blargg_oam_bug_non_causes(){
+    run_blargg_memory_test("oam_bug/rom_singles/3-non_causes.gb");
+}
+
+#[test]
+fn test_blargg_oam_bug_scanline_timing(){
+    run_blargg_memory_test("oam_bug/rom_singles/4-scanline_timing.gb");
+}
+
+#[test]
+fn test_blargg_oam_bug_timing_bug(){
+    run_blargg_memory_test("oam_bug/rom_singles/5-timing_bug.gb");
+}
+
+#[test]
+fn test_blargg_oam_bug_timing_no_bug(){
+    run_blargg_memory_test("oam_bug/rom_singles/6-timing_no_bug.gb");
+}
+
+#[test]
+fn test_blargg_oam_bug_timing_effect(){
+    run_blargg_memory_test("oam_bug/rom_singles/7-timing_effect.gb");
+}
+
+#[test]
+fn test_blargg_oam_bug_instr_effect(){
+    run_blargg_memory_test("oam_bug/rom_singles/8-instr_effect.gb");
+}
+
 #[test]
 fn test_turtle_window_y_trigger(){
     run_turtle_integration_test("window_y_trigger.gb", 15511617103807079362);
@@ -86,6 +117,91 @@ fn test_mooneye_acceptance_ppu_intr_2_oam_ok_timing(){
     run_mooneye_test_suite_test("acceptance/ppu/intr_2_oam_ok_timing.gb", 1784377789505089325);
 }
 
+#[test]
+fn test_mooneye_acceptance_ppu_intr_1_2_timing(){
+    run_mooneye_test_suite_registers_test("acceptance/ppu/intr_1_2_timing-GS.gb");
+}
+
+#[test]
+fn test_mooneye_acceptance_ppu_hblank_ly_scx_timing(){
+    run_mooneye_test_suite_registers_test("acceptance/ppu/hblank_ly_scx_timing-GS.gb");
+}
+
+#[test]
+fn test_mooneye_acceptance_ppu_lcdon_timing(){
+    run_mooneye_test_suite_registers_test("acceptance/ppu/lcdon_timing-GS.gb");
+}
+
+#[test]
+fn test_mooneye_acceptance_ppu_stat_lyc_onoff(){
+    run_mooneye_test_suite_registers_test("acceptance/ppu/stat_lyc_onoff.gb");
+}
+
+#[test]
+fn test_mooneye_acceptance_ppu_vblank_stat_intr(){
+    run_mooneye_test_suite_registers_test("acceptance/ppu/vblank_stat_intr-GS.gb");
+}
+
+#[test]
+fn test_mooneye_acceptance_ppu_stat_irq_blocking(){
+    run_mooneye_test_suite_registers_test("acceptance/ppu/stat_irq_blocking.gb");
+}
+
+#[test]
+fn test_mooneye_acceptance_ppu_lcdon_write_timing(){
+    run_mooneye_test_suite_registers_test("acceptance/ppu/lcdon_write_timing-GS.gb");
+}
+
+#[test]
+fn test_mooneye_acceptance_oam_dma_start(){
+    run_mooneye_test_suite_registers_test("acceptance/oam_dma_start.gb");
+}
+
+#[test]
+fn test_mooneye_acceptance_oam_dma_restart(){
+    run_mooneye_test_suite_registers_test("acceptance/oam_dma_restart.gb");
+}
+
+#[test]
+fn test_mooneye_acceptance_oam_dma_timing(){
+    run_mooneye_test_suite_registers_test("acceptance/oam_dma_timing.gb");
+}
+
+#[test]
+fn test_mooneye_acceptance_oam_dma_sources(){
+    run_mooneye_test_suite_registers_test("acceptance/oam_dma/sources-GS.gb");
+}
+
+#[test]
+fn test_mealybug_tearoom_m2_win_en_toggle(){
+    run_mealybug_tearoom_test("m2_win_en_toggle");
+}
+
+#[test]
+fn test_mealybug_tearoom_m3_bgp_change(){
+    run_mealybug_tearoom_test("m3_bgp_change");
+}
+
+// The newer blargg roms report the result in the cartridge ram, $A000 is the status (0 on success) once the signature is written
+fn run_blargg_memory_test(program_path:&str){
+    const SIGNATURE:[u8;3] = [0xDE, 0xB0, 0x61];
+    const RUNNING_STATUS:u8 = 0x80;
+    let program_url = format!("https://raw.githubusercontent.com/retrio/gb-test-roms/master/{}", program_path);
+    let program = reqwest::blocking::get(program_url.as_str()).unwrap().bytes().unwrap().to_vec();
+    let status = run_rom(program, StubGfxDevice{}, |gameboy|{
+        for _ in 0..1000{
+            gameboy.cycle_frame();
+            let signature = [1, 2, 3].map(|address|gameboy.peek_bank(MemoryBank::CartRam(0), address));
+            let status = gameboy.peek_bank(MemoryBank::CartRam(0), 0);
+            if signature == SIGNATURE && status != RUNNING_STATUS{
+                return Some(status);
+            }
+        }
+        return None;
+    });
+    assert_eq!(status, Some(0), "The program: {} has failed", program_url);
+}
+
 fn run_turtle_integration_test(program_name:&str, hash:u64){
     let zip_url = "https://github.com/Powerlated/TurtleTests/releases/download/v1.0/release.zip";
     let program = get_ziped_program(zip_url, program_name);
@@ -93,12 +209,53 @@ fn run_turtle_integration_test(program_name:&str, hash:u64){
 }
 
 fn run_mooneye_test_suite_test(program_name:&str, hash:u64){
+    let (program, boot_rom, program_zip_path) = get_mooneye_test_suite_program(program_name);
+    run_integration_test(program, Some(boot_rom), 300, hash, format!("The program: {} has failed", program_zip_path));
+}
+
+// The mooneye roms report the result in the registers, the fibonacci numbers on success
+fn run_mooneye_test_suite_registers_test(program_name:&str){
+    const SUCCESS_REGISTERS:[u16;3] = [0x0305, 0x080D, 0x1522];
+    let (program, boot_rom, program_zip_path) = get_mooneye_test_suite_program(program_name);
+    let mut mbc = initialize_mbc(program, None);
+    let mut gameboy = GameBoy::new_with_bootrom(&mut mbc, StubJoypadProvider{}, StubAudioDevice{}, StubGfxDevice{}, boot_rom);
+    for _ in 0..300{
+        gameboy.cycle_frame();
+        if gameboy.dump_registers()[1..] == SUCCESS_REGISTERS{
+            return;
+        }
+    }
+    assert!(false, "The program: {} has failed", program_zip_path);
+}
+
+// The mealybug tearoom roms are compared to a screenshot of a dmg, so the shades are set to the screenshot shades
+fn run_mealybug_tearoom_test(program_name:&str){
+    let zip_url = "https://github.com/mattcurrie/mealybug-tearoom-tests/raw/master/mealybug-tearoom-tests.zip";
+    let program = get_ziped_program(zip_url, format!("ppu/{}.gb", program_name).as_str());
+    let expected_image = get_ziped_program(zip_url, format!("expected/DMG-blob/{}.png", program_name).as_str());
+    let expected_image = image::load_from_memory(&expected_image).unwrap().to_rgb8();
+    let expected_frame = expected_image.pixels().map(|pixel|Pixel::from(Color{r:pixel[0], g:pixel[1], b:pixel[2]})).collect::<Vec<Pixel>>();
+
+    let shades = [0xFF, 0xAA, 0x55, 0].map(|shade|Color{r:shade, g:shade, b:shade});
+    let gfx_device = FramesGfxDevice::new();
+    let frames = gfx_device.frames.clone();
+    run_rom(program, gfx_device, |gameboy|{
+        gameboy.set_dmg_palettes(DmgPalettes{bg:shades, obj0:shades, obj1:shades});
+        // The roms finish drawing in a few frames and then loop forever
+        for _ in 0..60{
+            gameboy.cycle_frame();
+        }
+    });
+    assert!(*frames.borrow().last().unwrap() == expected_frame, "The program: {} has failed", program_name);
+}
+
+fn get_mooneye_test_suite_program(program_name:&str)->(Vec<u8>, [u8;BOOT_ROM_SIZE], String){
     let zip_url = "https://gekkio.fi/files/mooneye-test-suite/mts-20220522-1522-55c535c/mts-20220522-1522-55c535c.zip";
     let boot_rom_url = "https://github.com/alloncm/MagenBoot/releases/download/0.1.1/dmg_boot.bin";
     let program_zip_path = format!("{}/{program_name}", "mts-20220522-1522-55c535c");
     let program = get_ziped_program(zip_url, program_zip_path.as_str());
     let boot_rom = reqwest::blocking::get(boot_rom_url).unwrap().bytes().unwrap().to_vec();
-    run_integration_test(program, Some(boot_rom.try_into().unwrap()), 300, hash, format!("The program: {} has failed", program_zip_path));
+    return (program, boot_rom.try_into().unwrap(), program_zip_path);
 }
 
 fn get_ziped_program(zip_url:&str, program_zip_path:&str)->Vec<u8>{
@@ -0,0 +1,116 @@
+mod gameboy_stub;
+
+use lib_gb::ppu::{gb_ppu::{GbPpu, SCREEN_HEIGHT, SCREEN_WIDTH}, gfx_device::Pixel, layer_toggles::LayerToggles, ppu_register_updater::*};
+use gameboy_stub::*;
+
+// Lcd on, window on the 9C00 map, unsigned tiles addressing, sprites and background on
+const LCDC:u8 = 0xF3;
+const WINDOW_X:usize = 80;
+const WINDOW_Y:usize = 72;
+const SPRITE_X:usize = 16;
+const SPRITE_Y:usize = 8;
+
+// The background is color 1, the window is color 2 and the sprites are color 3,
+// a color 2 sprite with a lower priority is below the first sprite
+fn create_layers_ppu()->(GbPpu<FramesGfxDevice>, Frames){
+    let (mut ppu, frames, _) = create_ppu();
+    for tile in 1..4_u16{
+        for line in 0..8_u16{
+            ppu.vram.write_current_bank((tile * 16) + (line * 2), if tile & 1 != 0 {0xFF} else {0});
+            ppu.vram.write_current_bank((tile * 16) + (line * 2) + 1, if tile & 2 != 0 {0xFF} else {0});
+        }
+    }
+    for i in 0..0x400{
+        ppu.vram.write_current_bank(0x1800 + i, 1);
+        ppu.vram.write_current_bank(0x1C00 + i, 2);
+    }
+    for (sprite, x, tile) in [(0, SPRITE_X, 3), (1, SPRITE_X + 8, 3), (2, SPRITE_X, 2)]{
+        ppu.oam[(sprite * 4)..(sprite * 4) + 4].copy_from_slice(&[(SPRITE_Y + 16) as u8, (x + 8) as u8, tile, 0]);
+    }
+    handle_wx_register((WINDOW_X + 7) as u8, &mut ppu);
+    handle_wy_register(WINDOW_Y as u8, &mut ppu);
+    handle_lcdcontrol_register(LCDC, &mut ppu);
+    return (ppu, frames);
+}
+
+// Renders the second frame since the first one after turning the lcd on is not displayed,
+// returns the frame and the m_cycles it took
+fn render_frame(toggles:LayerToggles)->(Vec<Pixel>, u32){
+    let (mut ppu, frames) = create_layers_ppu();
+    ppu.layer_toggles = toggles;
+    let mut if_register = 0;
+    let mut m_cycles = 0;
+    while frames.borrow().len() < 2{
+        ppu.cycle(1, &mut if_register);
+        m_cycles += 1;
+    }
+
+    let frame = frames.borrow()[1].clone();
+    return (frame, m_cycles);
+}
+
+fn get_pixel(frame:&[Pixel], x:usize, y:usize)->Pixel{
+    frame[(y * SCREEN_WIDTH) + x]
+}
+
+fn get_color(color_number:usize)->Pixel{
+    let (ppu, _) = create_layers_ppu();
+    return ppu.bg_color_mapping[color_number].into();
+}
+
+#[test]
+fn test_all_layers_visible(){
+    let (frame, _) = render_frame(LayerToggles::default());
+    assert_eq!(get_pixel(&frame, 0, 0), get_color(1));
+    assert_eq!(get_pixel(&frame, WINDOW_X, WINDOW_Y), get_color(2));
+    assert_eq!(get_pixel(&frame, SPRITE_X, SPRITE_Y), get_color(3));
+    assert_eq!(get_pixel(&frame, SPRITE_X + 8, SPRITE_Y), get_color(3));
+}
+
+#[test]
+fn test_hide_layers(){
+    let (_, visible_m_cycles) = render_frame(LayerToggles::default());
+
+    let mut toggles = LayerToggles::default();
+    toggles.hide_background = true;
+    let (frame, m_cycles) = render_frame(toggles);
+    assert_eq!(get_pixel(&frame, 0, 0), get_color(0));
+    assert_eq!(get_pixel(&frame, WINDOW_X, WINDOW_Y), get_color(2));
+    assert_eq!(get_pixel(&frame, SPRITE_X, SPRITE_Y), get_color(3));
+    // The timing is not affected
+    assert_eq!(m_cycles, visible_m_cycles);
+
+    let mut toggles = LayerToggles::default();
+    toggles.hide_window = true;
+    let (frame, _) = render_frame(toggles);
+    assert_eq!(get_pixel(&frame, 0, 0), get_color(1));
+    assert_eq!(get_pixel(&frame, WINDOW_X, WINDOW_Y), get_color(0));
+    assert_eq!(get_pixel(&frame, SCREEN_WIDTH - 1, SCREEN_HEIGHT - 1), get_color(0));
+}
+
+#[test]
+fn test_hide_sprites_by_oam_index(){
+    let mut toggles = LayerToggles::default();
+    toggles.set_sprite_hidden(1, true);
+    assert!(toggles.is_sprite_hidden(1) && !toggles.is_sprite_hidden(0));
+    let (frame, _) = render_frame(toggles);
+    assert_eq!(get_pixel(&frame, SPRITE_X, SPRITE_Y), get_color(3));
+    assert_eq!(get_pixel(&frame, SPRITE_X + 8, SPRITE_Y), get_color(1));
+
+    // The sprite below a hidden sprite is drawn
+    let mut toggles = LayerToggles::default();
+    toggles.set_sprite_hidden(0, true);
+    let (frame, _) = render_frame(toggles);
+    assert_eq!(get_pixel(&frame, SPRITE_X, SPRITE_Y), get_color(2));
+    assert_eq!(get_pixel(&frame, SPRITE_X + 8, SPRITE_Y), get_color(3));
+
+    toggles.set_all_sprites_hidden(true);
+    assert!(toggles.are_all_sprites_hidden());
+    let (frame, _) = render_frame(toggles);
+    assert_eq!(get_pixel(&frame, SPRITE_X, SPRITE_Y), get_color(1));
+
+    toggles.set_sprite_hidden(0, false);
+    assert!(!toggles.are_all_sprites_hidden());
+    let (frame, _) = render_frame(toggles);
+    assert_eq!(get_pixel(&frame, SPRITE_X, SPRITE_Y), get_color(3));
+}
@@ -0,0 +1,120 @@
+mod gameboy_stub;
+
+use lib_gb::assembler::assemble_rom;
+use lib_gb::ppu::{gb_ppu::GbPpu, ppu_state::PpuState, ppu_register_updater::*};
+use gameboy_stub::*;
+
+// Writes STAT during vblank with all the interrupts disabled and stores IF at $C000
+const STAT_WRITE_PROGRAM:&str = "
+    SECTION \"main\", ROM0[$150]
+Main:
+    ld a, $91
+    ldh [$40], a
+.wait_vblank:
+    ldh a, [$44]
+    cp 145
+    jr nz, .wait_vblank
+    xor a
+    ldh [$0F], a
+    ldh [$41], a
+    ldh a, [$0F]
+    ld [$C000], a
+.loop:
+    jr .loop
+";
+
+const CGB_FLAG_ADDRESS:usize = 0x143;
+const STAT_INTERRUPT_MASK:u8 = 0b10;
+const LCDC_ON:u8 = 0x91;
+const BLACK_BGP:u8 = 0xFF;
+const LINE_M_CYCLES:u32 = 114;
+
+fn run_stat_write_program(cgb_flag:u8)->u8{
+    let mut rom = assemble_rom(STAT_WRITE_PROGRAM, "STAT").unwrap();
+    rom[CGB_FLAG_ADDRESS] = cgb_flag;
+    return run_rom(rom, StubGfxDevice{}, |gameboy|{
+        for _ in 0..2{
+            gameboy.cycle_frame();
+        }
+        gameboy.peek(0xC000)
+    });
+}
+
+fn create_black_ppu()->(GbPpu<FramesGfxDevice>, Frames){
+    let (mut ppu, frames, _) = create_ppu();
+    handle_bg_pallet_register(BLACK_BGP, &ppu.dmg_palettes.bg.clone(), &mut ppu.bg_color_mapping, &mut ppu.bg_palette_register);
+    return (ppu, frames);
+}
+
+#[test]
+fn test_dmg_stat_write_triggers_interrupt(){
+    assert_eq!(run_stat_write_program(0) & STAT_INTERRUPT_MASK, STAT_INTERRUPT_MASK);
+}
+
+#[test]
+fn test_cgb_stat_write_does_not_trigger_interrupt(){
+    assert_eq!(run_stat_write_program(0x80) & STAT_INTERRUPT_MASK, 0);
+}
+
+#[test]
+fn test_ly_is_0_during_last_line(){
+    let (mut ppu, _) = create_black_ppu();
+    update_stat_register(0x40, &mut ppu, &mut 0);    // LYC interrupt
+    handle_lcdcontrol_register(LCDC_ON, &mut ppu);
+
+    let mut if_register = 0;
+    while ppu.ly_register != 153{
+        ppu.cycle(1, &mut if_register);
+    }
+    if_register = 0;
+    ppu.cycle(1, &mut if_register);
+    assert_eq!(ppu.ly_register, 0);
+    assert!(ppu.state == PpuState::Vblank);
+    // LYC=0 is matched while still in the last line
+    assert_eq!(if_register & STAT_INTERRUPT_MASK, STAT_INTERRUPT_MASK);
+
+    // The m_cycle with LY=153 and the current one
+    let mut m_cycles = 2;
+    loop{
+        ppu.cycle(1, &mut if_register);
+        if ppu.state != PpuState::Vblank{
+            break;
+        }
+        assert_eq!(ppu.ly_register, 0);
+        m_cycles += 1;
+    }
+    // LY stays 0 for the last line and for the first line
+    while ppu.ly_register == 0{
+        ppu.cycle(1, &mut if_register);
+        m_cycles += 1;
+    }
+    assert_eq!(m_cycles, LINE_M_CYCLES * 2);
+}
+
+#[test]
+fn test_lcd_power_on(){
+    let (mut ppu, frames) = create_black_ppu();
+    handle_lcdcontrol_register(LCDC_ON, &mut ppu);
+
+    // The first line starts with mode 0 instead of the oam search
+    let mut if_register = 0;
+    let mut hblank_m_cycles = 0;
+    loop{
+        ppu.cycle(1, &mut if_register);
+        if ppu.state != PpuState::Hblank{
+            break;
+        }
+        hblank_m_cycles += 1;
+    }
+    assert_eq!(hblank_m_cycles, 20);
+    assert!(ppu.state == PpuState::PixelTransfer);
+
+    // The first frame is not displayed
+    while frames.borrow().len() < 2{
+        ppu.cycle(1, &mut if_register);
+    }
+    let frames = frames.borrow();
+    assert!(frames[0].iter().all(|pixel|*pixel == frames[0][0]));
+    assert_ne!(frames[0][0], 0);
+    assert!(frames[1].iter().all(|pixel|*pixel == 0));
+}
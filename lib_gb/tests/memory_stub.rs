@@ -12,4 +12,6 @@ impl Memory for MemoryStub{
     fn write(&mut self, address:u16, value:u8, _m_cycles:u8){
         self.data[address as usize] = value;
     }
+
+    fn inc_dec_access(&mut self, _address:u16, _m_cycles:u8){}
 }
\ No newline at end of file
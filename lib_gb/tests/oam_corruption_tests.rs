@@ -0,0 +1,122 @@
+mod gameboy_stub;
+
+use lib_gb::assembler::assemble_rom;
+use lib_gb::ppu::{gb_ppu::OAM_MEMORY_SIZE, oam_corruption::*};
+use gameboy_stub::*;
+
+// Fills the oam with its indexes during vblank and then increments hl while pointing to the oam during the oam search
+const PROGRAM:&str = "
+    SECTION \"main\", ROM0[$150]
+Main:
+    ld a, $91
+    ldh [$40], a
+.wait_vblank:
+    ldh a, [$44]
+    cp 144
+    jr nz, .wait_vblank
+    ld hl, $FE00
+    ld b, $A0
+    xor a
+.fill:
+    ld [hl+], a
+    inc a
+    dec b
+    jr nz, .fill
+.wait_oam_search:
+    ldh a, [$41]
+    and 3
+    cp 2
+    jr nz, .wait_oam_search
+    ld hl, $FE00
+    inc hl
+    inc hl
+.loop:
+    jr .loop
+";
+
+const CGB_FLAG_ADDRESS:usize = 0x143;
+
+fn run_program(cgb_flag:u8)->[u8;OAM_MEMORY_SIZE]{
+    let mut rom = assemble_rom(PROGRAM, "OAMBUG").unwrap();
+    rom[CGB_FLAG_ADDRESS] = cgb_flag;
+    return run_rom(rom, StubGfxDevice{}, |gameboy|{
+        for _ in 0..3{
+            gameboy.cycle_frame();
+        }
+        gameboy.dump_oam()
+    });
+}
+
+fn create_oam()->[u8;OAM_MEMORY_SIZE]{
+    let mut oam = [0;OAM_MEMORY_SIZE];
+    for (i, value) in oam.iter_mut().enumerate(){
+        *value = i as u8;
+    }
+    return oam;
+}
+
+fn get_row(oam:&[u8;OAM_MEMORY_SIZE], row:usize)->&[u8]{
+    return &oam[row * OAM_ROW_SIZE..(row + 1) * OAM_ROW_SIZE];
+}
+
+#[test]
+fn test_dmg_inc_dec_corrupts_oam(){
+    let oam = run_program(0);
+    assert_ne!(oam, create_oam());
+    // The first row is never corrupted
+    assert_eq!(get_row(&oam, 0), get_row(&create_oam(), 0));
+}
+
+#[test]
+fn test_cgb_oam_is_not_corrupted(){
+    assert_eq!(run_program(0x80), create_oam());
+}
+
+#[test]
+fn test_write_corruption(){
+    let mut oam = create_oam();
+    corrupt_oam(&mut oam, 3, OamCorruption::Write);
+
+    // a=0x1918, b=0x1110, c=0x1514
+    assert_eq!(get_row(&oam, 3), [0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17]);
+    // The bits of a are taken where b and c are different
+    oam = create_oam();
+    oam[0x18] = 0x04;
+    corrupt_oam(&mut oam, 3, OamCorruption::Write);
+    assert_eq!(&get_row(&oam, 3)[0..2], [0x14, 0x11]);
+    assert_eq!(get_row(&oam, 2), get_row(&create_oam(), 2));
+}
+
+#[test]
+fn test_read_corruption(){
+    let mut oam = create_oam();
+    oam[0x18] = 0x0F;
+    corrupt_oam(&mut oam, 3, OamCorruption::Read);
+
+    // b | (a & c)
+    assert_eq!(get_row(&oam, 3), [0x14, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17]);
+}
+
+#[test]
+fn test_read_during_inc_dec_corruption(){
+    let mut oam = create_oam();
+    corrupt_oam(&mut oam, 5, OamCorruption::ReadDuringIncDec);
+
+    // The preceding row is corrupted and copied to the current row and 2 rows before, then the read corruption is applied
+    // a=0x1918, b=0x2120, c=0x2928, d=0x2524
+    let preceding_row = [0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27];
+    assert_eq!(get_row(&oam, 3), preceding_row);
+    assert_eq!(get_row(&oam, 4), preceding_row);
+    assert_eq!(get_row(&oam, 5), preceding_row);
+
+    // Only the read corruption in the first rows
+    let mut oam = create_oam();
+    corrupt_oam(&mut oam, 2, OamCorruption::ReadDuringIncDec);
+    let mut expected = create_oam();
+    corrupt_oam(&mut expected, 2, OamCorruption::Read);
+    assert_eq!(oam, expected);
+
+    let mut oam = create_oam();
+    corrupt_oam(&mut oam, 0, OamCorruption::ReadDuringIncDec);
+    assert_eq!(oam, create_oam());
+}
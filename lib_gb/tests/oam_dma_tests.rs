@@ -0,0 +1,142 @@
+mod gameboy_stub;
+
+use lib_gb::assembler::assemble_rom;
+use lib_gb::machine::{mbc_initializer::initialize_mbc, mode::Mode};
+use lib_gb::mmu::{access_bus::AccessBus, external_memory_bus::ExternalMemoryBus, oam_dma_controller::OamDmaController};
+use lib_gb::ppu::gb_ppu::{GbPpu, OAM_MEMORY_SIZE};
+use gameboy_stub::*;
+
+// Starts a dma from $C000 in a routine running from hram and reads the rom and the oam during the transfer,
+// the results are stored at $FFF0 and $FFF1
+const CONFLICT_PROGRAM:&str = "
+    SECTION \"main\", ROM0[$150]
+Main:
+    ld hl, $C000
+    ld b, $A0
+    ld a, $10
+.fill:
+    ld [hl+], a
+    inc a
+    dec b
+    jr nz, .fill
+    ld hl, Routine
+    ld c, $80
+    ld b, RoutineEnd - Routine
+.copy:
+    ld a, [hl+]
+    ldh [c], a
+    inc c
+    dec b
+    jr nz, .copy
+    call $FF80
+.loop:
+    jr .loop
+Routine:
+    ld hl, $0000
+    ld a, $C0
+    ldh [$46], a
+    ld b, [hl]          ; the first transferred byte is on the bus
+    ld a, [$FE00]       ; the oam is inaccessible
+    ld c, a
+    ld a, 40
+.wait:
+    dec a
+    jr nz, .wait
+    ld a, b
+    ldh [$F0], a
+    ld a, c
+    ldh [$F1], a
+    ret
+RoutineEnd:
+";
+
+const DMA_M_CYCLES:u32 = 160;
+
+fn create_rom()->Vec<u8>{
+    return assemble_rom("SECTION \"main\", ROM0[$150]\nMain:\n    jr Main\n", "DMA").unwrap();
+}
+
+// Fills each wram page with its high byte plus the index in the page
+fn fill_wram(external_bus:&mut ExternalMemoryBus){
+    for address in 0xC000..=0xDFFF_u16{
+        external_bus.write(address, ((address >> 8) as u8).wrapping_add(address as u8));
+    }
+}
+
+fn expected_oam(source_address:u16)->[u8;OAM_MEMORY_SIZE]{
+    let mut oam = [0;OAM_MEMORY_SIZE];
+    for (i, value) in oam.iter_mut().enumerate(){
+        *value = ((source_address >> 8) as u8).wrapping_add(i as u8);
+    }
+    return oam;
+}
+
+#[test]
+fn test_dma_start_delay_and_length(){
+    let mut mbc = initialize_mbc(create_rom(), None);
+    let mut external_bus = ExternalMemoryBus::new(&mut mbc);
+    fill_wram(&mut external_bus);
+    let mut ppu = GbPpu::new(StubGfxDevice{}, Mode::DMG);
+    let mut controller = OamDmaController::new();
+
+    controller.set_dma_register(0xC0);
+    assert_eq!(controller.get_dma_register(), 0xC0);
+    // The transfer starts after an m_cycle
+    assert!(controller.cycle(1, &mut external_bus, &mut ppu).is_none());
+    for i in 0..DMA_M_CYCLES{
+        assert!(matches!(controller.cycle(1, &mut external_bus, &mut ppu), Some(AccessBus::External)));
+        assert_eq!(controller.get_transferred_value(), 0xC0_u8.wrapping_add(i as u8));
+    }
+    assert!(controller.cycle(1, &mut external_bus, &mut ppu).is_none());
+    assert_eq!(ppu.oam, expected_oam(0xC000));
+}
+
+#[test]
+fn test_dma_restart(){
+    let mut mbc = initialize_mbc(create_rom(), None);
+    let mut external_bus = ExternalMemoryBus::new(&mut mbc);
+    fill_wram(&mut external_bus);
+    let mut ppu = GbPpu::new(StubGfxDevice{}, Mode::DMG);
+    let mut controller = OamDmaController::new();
+
+    controller.set_dma_register(0xC0);
+    controller.cycle(11, &mut external_bus, &mut ppu);
+    controller.set_dma_register(0xD0);
+    // The running transfer continues during the start delay of the new one
+    assert!(controller.cycle(1, &mut external_bus, &mut ppu).is_some());
+    assert_eq!(ppu.oam[10], 0xC0 + 10);
+    for _ in 0..DMA_M_CYCLES{
+        assert!(controller.cycle(1, &mut external_bus, &mut ppu).is_some());
+    }
+    assert!(controller.cycle(1, &mut external_bus, &mut ppu).is_none());
+    assert_eq!(ppu.oam, expected_oam(0xD000));
+}
+
+#[test]
+fn test_dma_sources_above_wram(){
+    let mut mbc = initialize_mbc(create_rom(), None);
+    let mut external_bus = ExternalMemoryBus::new(&mut mbc);
+    fill_wram(&mut external_bus);
+    let mut ppu = GbPpu::new(StubGfxDevice{}, Mode::DMG);
+    let mut controller = OamDmaController::new();
+
+    for (value, source_address) in [(0xE0, 0xC000), (0xF1, 0xD100), (0xFE, 0xDE00), (0xFF, 0xDF00)]{
+        controller.set_dma_register(value);
+        controller.cycle(DMA_M_CYCLES + 1, &mut external_bus, &mut ppu);
+        assert_eq!(controller.get_dma_register(), value);
+        assert_eq!(ppu.oam, expected_oam(source_address), "source: {:#X}", value);
+    }
+}
+
+#[test]
+fn test_dma_bus_conflict_reads(){
+    run_rom(assemble_rom(CONFLICT_PROGRAM, "DMA").unwrap(), StubGfxDevice{}, |gameboy|{
+        gameboy.cycle_frame();
+
+        assert_eq!(gameboy.peek(0xFFF0), 0x10);
+        assert_eq!(gameboy.peek(0xFFF1), 0xFF);
+        let oam = gameboy.dump_oam();
+        assert_eq!(oam[0], 0x10);
+        assert_eq!(oam[OAM_MEMORY_SIZE - 1], 0x10 + OAM_MEMORY_SIZE as u8 - 1);
+    });
+}
@@ -0,0 +1,83 @@
+mod gameboy_stub;
+
+use lib_gb::ppu::{color::Color, gfx_device::Pixel, gb_ppu::{GbPpu, SCREEN_WIDTH}, post_processing::*, ppu_register_updater::*};
+use gameboy_stub::*;
+
+const LCDC_ON:u8 = 0x91;
+const WHITE:Color = Color{r:0xFF, g:0xFF, b:0xFF};
+
+// The whole background is drawn with tile 1, color 3 when the tile is filled and color 0 otherwise
+fn render_tile_frames(ppu:&mut GbPpu<FramesGfxDevice>, frames:&Frames, tile_filled:bool, count:usize){
+    for i in 0..16{
+        ppu.vram.write_current_bank(0x10 + i, if tile_filled {0xFF} else {0});
+    }
+    render_frames(ppu, frames, count);
+}
+
+// The upscaled frames are in the hd frames
+fn create_filtered_ppu(filters:Vec<PostProcessingFilter>)->(GbPpu<FramesGfxDevice>, Frames, HdFrames){
+    let (mut ppu, frames, scaled_frames) = create_ppu();
+    ppu.post_processing = PostProcessing::new(filters, 2);
+    for i in 0..0x400{
+        ppu.vram.write_current_bank(0x1800 + i, 1);
+    }
+    handle_lcdcontrol_register(LCDC_ON, &mut ppu);
+    return (ppu, frames, scaled_frames);
+}
+
+// The u16pixel build stores the colors as RGB565
+fn to_pixel_precision(color:Color)->Color{
+    Color::from(Pixel::from(color))
+}
+
+#[test]
+fn test_lcd_ghosting_blends_frames(){
+    let (mut ppu, frames, scaled_frames) = create_filtered_ppu(vec![PostProcessingFilter::LcdGhosting(50)]);
+    render_tile_frames(&mut ppu, &frames, false, 2);
+    let white:Pixel = WHITE.into();
+    assert_eq!(frames.borrow()[1][0], white);
+
+    // Switching to black shows half of the previous frame
+    render_tile_frames(&mut ppu, &frames, true, 1);
+    let black = Color::from(Pixel::from(ppu.bg_color_mapping[3]));
+    let blended = Color::from(frames.borrow()[2][0]);
+    let half_red = ((WHITE.r as u16 + black.r as u16) / 2) as u8;
+    assert_eq!(blended.r, to_pixel_precision(Color{r:half_red, g:0, b:0}).r);
+    render_tile_frames(&mut ppu, &frames, true, 8);
+    let faded = Color::from(*frames.borrow().last().unwrap().first().unwrap());
+    assert!(faded.r < blended.r);
+    // Ghosting does not need an upscaled frame
+    assert!(scaled_frames.borrow().is_empty());
+}
+
+#[test]
+fn test_color_correction(){
+    let (mut ppu, frames, _) = create_filtered_ppu(vec![PostProcessingFilter::CgbColorCorrection]);
+    render_tile_frames(&mut ppu, &frames, false, 2);
+    // White stays white since every row of the matrix sums to 1
+    let white:Pixel = WHITE.into();
+    assert_eq!(frames.borrow()[1][0], white);
+
+    let mut post_processing = PostProcessing::new(vec![PostProcessingFilter::CgbColorCorrection], 1);
+    post_processing.toggle_filter(PostProcessingFilter::AgsColorCorrection);
+    post_processing.toggle_filter(PostProcessingFilter::CgbColorCorrection);
+    assert_eq!(post_processing.get_filters(), &[PostProcessingFilter::AgsColorCorrection]);
+}
+
+#[test]
+fn test_scaled_filters(){
+    let (mut ppu, frames, scaled_frames) = create_filtered_ppu(vec![PostProcessingFilter::Scanlines(100), PostProcessingFilter::DotMatrix(50)]);
+    render_tile_frames(&mut ppu, &frames, false, 2);
+    let white:Pixel = WHITE.into();
+    // The original frame is not affected
+    assert!(frames.borrow()[1].iter().all(|pixel|*pixel == white));
+
+    let (frame, scale) = &scaled_frames.borrow()[1];
+    assert_eq!(*scale, 2);
+    let width = SCREEN_WIDTH * 2;
+    assert_eq!(frame[0], white);
+    // The right column of the pixel is darkened by the dot matrix and the bottom row by the scanlines
+    assert_eq!(Color::from(frame[1]).r, to_pixel_precision(Color{r:0x7F, g:0, b:0}).r);
+    assert_eq!(frame[width], Pixel::from(Color::default()));
+    assert_eq!(frame[(width * 2) + 2], white);
+}
\ No newline at end of file
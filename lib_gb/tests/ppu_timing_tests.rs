@@ -0,0 +1,91 @@
+mod gameboy_stub;
+
+use lib_gb::machine::mode::Mode;
+use lib_gb::ppu::{gb_ppu::GbPpu, ppu_state::PpuState, ppu_register_updater::*};
+use gameboy_stub::*;
+
+const MEASURED_LINE:u8 = 5;
+const LINE_M_CYCLES:u32 = 114;
+const LCDC_ON_WITH_OBJECTS:u8 = 0x93;
+const LCDC_WINDOW_ENABLE:u8 = 0x20;
+
+// Returns the number of m_cycles the measured line spent in the pixel transfer state
+fn measure_pixel_transfer(scx:u8, sprites_x:&[u8], wx:Option<u8>)->u32{
+    let lcd_control = if wx.is_some() {LCDC_ON_WITH_OBJECTS | LCDC_WINDOW_ENABLE} else {LCDC_ON_WITH_OBJECTS};
+    return measure_pixel_transfer_with_write(scx, sprites_x, wx.unwrap_or(0), lcd_control, None);
+}
+
+// The write is applied to the lcd control after the given m_cycles of the measured line pixel transfer
+fn measure_pixel_transfer_with_write(scx:u8, sprites_x:&[u8], wx:u8, lcd_control:u8, lcd_control_write:Option<(u32, u8)>)->u32{
+    let mut ppu = GbPpu::new(StubGfxDevice{}, Mode::DMG);
+    for (i, x) in sprites_x.iter().enumerate(){
+        ppu.oam[i * 4] = MEASURED_LINE + 16;
+        ppu.oam[(i * 4) + 1] = *x;
+    }
+    set_scx(&mut ppu, scx);
+    handle_wx_register(wx, &mut ppu);
+    handle_lcdcontrol_register(lcd_control, &mut ppu);
+
+    let mut if_register = 0;
+    let mut pixel_transfer_m_cycles = 0;
+    let mut line_m_cycles = 0;
+    while ppu.ly_register <= MEASURED_LINE{
+        ppu.cycle(1, &mut if_register);
+        if ppu.ly_register == MEASURED_LINE{
+            line_m_cycles += 1;
+            if ppu.state == PpuState::PixelTransfer{
+                pixel_transfer_m_cycles += 1;
+                if let Some((m_cycles, value)) = lcd_control_write{
+                    if m_cycles == pixel_transfer_m_cycles{
+                        handle_lcdcontrol_register(value, &mut ppu);
+                    }
+                }
+            }
+        }
+    }
+    assert_eq!(line_m_cycles, LINE_M_CYCLES);
+
+    return pixel_transfer_m_cycles;
+}
+
+#[test]
+fn test_scx_fine_scroll_penalty(){
+    let expected = [43, 44, 44, 44, 44, 45, 45, 45];
+    for scx in 0..8{
+        assert_eq!(measure_pixel_transfer(scx, &[], None), expected[scx as usize], "scx: {}", scx);
+    }
+    assert_eq!(measure_pixel_transfer(8, &[], None), 43);
+}
+
+#[test]
+fn test_window_start_penalty(){
+    assert_eq!(measure_pixel_transfer(0, &[], Some(7)), 45);
+    assert_eq!(measure_pixel_transfer(0, &[], Some(100)), 45);
+    // The window is offscreen
+    assert_eq!(measure_pixel_transfer(0, &[], Some(167)), 43);
+}
+
+#[test]
+fn test_sprite_penalties(){
+    // 6 t_cycles for the fetch and up to 5 more depending on the alignment with the bg tiles
+    assert_eq!(measure_pixel_transfer(0, &[8], None), 46);
+    assert_eq!(measure_pixel_transfer(0, &[12], None), 45);
+    assert_eq!(measure_pixel_transfer(0, &[13], None), 45);
+    assert_eq!(measure_pixel_transfer(3, &[13], None), 47);
+    // Sprites with x=0 are hidden but still stall for 11 t_cycles
+    assert_eq!(measure_pixel_transfer(0, &[0], None), 46);
+    assert_eq!(measure_pixel_transfer(0, &[168], None), 43);
+    // Only the first sprite on a tile pays the alignment penalty
+    assert_eq!(measure_pixel_transfer(0, &[8, 8], None), 48);
+    assert_eq!(measure_pixel_transfer(0, &[8; 10], None), 60);
+}
+
+#[test]
+fn test_registers_writes_during_pixel_transfer(){
+    // The window is enabled before the pixel transfer reaches WX
+    assert_eq!(measure_pixel_transfer_with_write(0, &[], 107, LCDC_ON_WITH_OBJECTS, Some((10, LCDC_ON_WITH_OBJECTS | LCDC_WINDOW_ENABLE))), 45);
+    // The window is enabled after WX has passed
+    assert_eq!(measure_pixel_transfer_with_write(0, &[], 27, LCDC_ON_WITH_OBJECTS, Some((30, LCDC_ON_WITH_OBJECTS | LCDC_WINDOW_ENABLE))), 43);
+    // The sprites are disabled before reaching the sprite
+    assert_eq!(measure_pixel_transfer_with_write(0, &[100], 0, LCDC_ON_WITH_OBJECTS, Some((10, LCDC_ON_WITH_OBJECTS & !0x2))), 43);
+}
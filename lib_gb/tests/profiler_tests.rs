@@ -0,0 +1,62 @@
+mod gameboy_stub;
+
+use lib_gb::assembler::assemble_rom;
+use lib_gb::machine::profiler::{Profiler, SymbolTable};
+use gameboy_stub::*;
+
+const PROGRAM:&str = "
+    SECTION \"main\", ROM0[$150]
+Main:
+    call Work
+    call Work
+.wait:
+    jr .wait
+Work:
+    ld b, 10
+.loop:
+    dec b
+    jr nz, .loop
+    ret
+";
+
+const SYMBOLS:&str = "
+; File generated by rgblink
+00:0150 Main
+00:0156 Main.wait
+00:0158 Work
+00:015A Work.loop
+";
+
+#[test]
+fn test_symbol_table_find_nearest_global_label(){
+    let symbols = SymbolTable::parse(SYMBOLS);
+    assert_eq!(symbols.get_name(symbols.find(0, 0x150).unwrap()), "Main");
+    assert_eq!(symbols.get_name(symbols.find(0, 0x157).unwrap()), "Main");
+    assert_eq!(symbols.get_name(symbols.find(0, 0x15B).unwrap()), "Work");
+    assert!(symbols.find(0, 0x100).is_none());
+    assert!(symbols.find(1, 0x4000).is_none());
+    // different memory region
+    assert!(symbols.find(0, 0xC000).is_none());
+}
+
+#[test]
+fn test_profiler_call_tree(){
+    let rom = assemble_rom(PROGRAM, "PROFILER").unwrap();
+    let profiler = run_rom(rom, StubGfxDevice{}, |gameboy|{
+        gameboy.set_profiler(Profiler::new(SymbolTable::parse(SYMBOLS)));
+        gameboy.cycle_frame();
+        gameboy.take_profiler().unwrap()
+    });
+    let mut output = Vec::new();
+    profiler.write_folded_stacks(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let lines:Vec<&str> = output.lines().collect();
+
+    // ld b (2) + 10 * dec b (1) + 9 * taken jr (3) + not taken jr (2) + ret (4), called twice
+    assert!(lines.contains(&"Main;Work 90"), "{}", output);
+    assert!(lines.iter().any(|l|l.starts_with("Main ")));
+    // the entry point (nop; jp) has no label
+    assert!(lines.contains(&"(root) 5"), "{}", output);
+    // call nn takes 6 m-cycles
+    assert_eq!(profiler.get_address_m_cycles(0, 0x150), 6);
+}
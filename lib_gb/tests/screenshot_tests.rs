@@ -0,0 +1,67 @@
+mod gameboy_stub;
+
+use lib_gb::ppu::{color::Color, gfx_device::Pixel, gb_ppu::{GbPpu, SCREEN_HEIGHT, SCREEN_WIDTH}, post_processing::*, ppu_register_updater::*};
+use gameboy_stub::*;
+
+const LCDC_ON:u8 = 0x91;
+
+fn create_background_ppu()->(GbPpu<FramesGfxDevice>, Frames){
+    let (mut ppu, frames, _) = create_ppu();
+    // The whole background is drawn with the filled tile 1
+    for i in 0..16{
+        ppu.vram.write_current_bank(0x10 + i, 0xFF);
+    }
+    for i in 0..0x400{
+        ppu.vram.write_current_bank(0x1800 + i, 1);
+    }
+    handle_lcdcontrol_register(LCDC_ON, &mut ppu);
+    return (ppu, frames);
+}
+
+#[test]
+fn test_screenshot_is_captured_once_per_request(){
+    let (mut ppu, frames) = create_background_ppu();
+    render_frames(&mut ppu, &frames, 2);
+    assert!(ppu.take_screenshot().is_none());
+
+    ppu.request_screenshot();
+    render_frames(&mut ppu, &frames, 1);
+    let screenshot = ppu.take_screenshot().unwrap();
+    assert_eq!((screenshot.frame.width, screenshot.frame.height), (SCREEN_WIDTH, SCREEN_HEIGHT));
+    let black = Color::from(Pixel::from(ppu.bg_color_mapping[3]));
+    assert_eq!(screenshot.frame.get_pixel(0, 0), black);
+    assert_eq!(screenshot.frame.get_pixel(SCREEN_WIDTH - 1, SCREEN_HEIGHT - 1), black);
+    // Without filters the gfx device gets the raw frame
+    assert!(screenshot.processed_frame.is_none());
+
+    render_frames(&mut ppu, &frames, 1);
+    assert!(ppu.take_screenshot().is_none());
+}
+
+#[test]
+fn test_screenshot_captures_the_frame_before_the_filters(){
+    let (mut ppu, frames) = create_background_ppu();
+    ppu.post_processing = PostProcessing::new(vec![PostProcessingFilter::LcdGhosting(50), PostProcessingFilter::Scanlines(100)], 2);
+    // Ghosting the white tile into the black frame
+    for i in 0..16{
+        ppu.vram.write_current_bank(0x10 + i, 0);
+    }
+    render_frames(&mut ppu, &frames, 2);
+    for i in 0..16{
+        ppu.vram.write_current_bank(0x10 + i, 0xFF);
+    }
+    ppu.request_screenshot();
+    render_frames(&mut ppu, &frames, 1);
+    let screenshot = ppu.take_screenshot().unwrap();
+
+    let black = Color::from(Pixel::from(ppu.bg_color_mapping[3]));
+    let ghosted = Color::from(frames.borrow().last().unwrap()[0]);
+    assert_ne!(ghosted, black);
+    assert_eq!(screenshot.frame.get_pixel(0, 0), black);
+
+    // The processed frame is the upscaled one with the scanlines
+    let processed = screenshot.processed_frame.unwrap();
+    assert_eq!((processed.width, processed.height), (SCREEN_WIDTH * 2, SCREEN_HEIGHT * 2));
+    assert_eq!(processed.get_pixel(0, 0), ghosted);
+    assert_eq!(processed.get_pixel(0, 1), Color::default());
+}
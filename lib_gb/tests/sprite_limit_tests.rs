@@ -0,0 +1,59 @@
+mod gameboy_stub;
+
+use lib_gb::ppu::{gb_ppu::{GbPpu, SCREEN_HEIGHT, SCREEN_WIDTH}, gfx_device::Pixel, ppu_state::PpuState, ppu_register_updater::*};
+use gameboy_stub::*;
+
+const LCDC_ON_WITH_OBJECTS:u8 = 0x93;
+const SPRITES_LINE:usize = 5;
+const SPRITES_COUNT:usize = 12;
+
+// 12 sprites of color 3 next to each other on the same line, the last 2 are above the hardware limit
+fn create_sprites_ppu(unlimited_sprites_per_line:bool)->(GbPpu<FramesGfxDevice>, Frames){
+    let (mut ppu, frames, _) = create_ppu();
+    ppu.unlimited_sprites_per_line = unlimited_sprites_per_line;
+    for i in 0..16{
+        ppu.vram.write_current_bank(0x10 + i, 0xFF);
+    }
+    for i in 0..SPRITES_COUNT{
+        ppu.oam[(i * 4)..(i * 4) + 4].copy_from_slice(&[(SPRITES_LINE + 16) as u8, ((i * 8) + 8) as u8, 1, 0]);
+    }
+    handle_lcdcontrol_register(LCDC_ON_WITH_OBJECTS, &mut ppu);
+    return (ppu, frames);
+}
+
+// Returns the pixel transfer length of every line in the second frame and the frame
+fn render_frame(unlimited_sprites_per_line:bool)->(Vec<u32>, Vec<Pixel>){
+    let (mut ppu, frames) = create_sprites_ppu(unlimited_sprites_per_line);
+    let mut if_register = 0;
+    let mut pixel_transfer_lengths = vec![0;SCREEN_HEIGHT];
+    while frames.borrow().len() < 2{
+        ppu.cycle(1, &mut if_register);
+        if frames.borrow().len() == 1 && ppu.state == PpuState::PixelTransfer{
+            pixel_transfer_lengths[ppu.ly_register as usize] += 1;
+        }
+    }
+
+    let frame = frames.borrow()[1].clone();
+    return (pixel_transfer_lengths, frame);
+}
+
+#[test]
+fn test_sprites_limit(){
+    let (_, frame) = render_frame(false);
+    let sprite_color:Pixel = create_sprites_ppu(false).0.obj_color_mapping0[3].unwrap().into();
+    let line = &frame[SPRITES_LINE * SCREEN_WIDTH..(SPRITES_LINE + 1) * SCREEN_WIDTH];
+    assert!(line[0..80].iter().all(|pixel|*pixel == sprite_color));
+    assert!(line[80..96].iter().all(|pixel|*pixel != sprite_color));
+}
+
+#[test]
+fn test_unlimited_sprites_keeps_the_timing(){
+    let (accurate_lengths, _) = render_frame(false);
+    let (unlimited_lengths, frame) = render_frame(true);
+    assert_eq!(accurate_lengths, unlimited_lengths);
+
+    let sprite_color:Pixel = create_sprites_ppu(true).0.obj_color_mapping0[3].unwrap().into();
+    let line = &frame[SPRITES_LINE * SCREEN_WIDTH..(SPRITES_LINE + 1) * SCREEN_WIDTH];
+    assert!(line[0..96].iter().all(|pixel|*pixel == sprite_color));
+    assert!(line[96..].iter().all(|pixel|*pixel != sprite_color));
+}
@@ -0,0 +1,108 @@
+mod gameboy_stub;
+
+use lib_gb::assembler::assemble_rom;
+use lib_gb::mmu::memory::MemoryBank;
+use gameboy_stub::*;
+
+// Runs a general purpose transfer with the lcd off and 2 hblank transfers (the second is canceled),
+// the results are stored at $C000
+const PROGRAM:&str = "
+    SECTION \"main\", ROM0[$150]
+Main:
+    ld a, $10
+    ldh [$51], a
+    ld a, $0F           ; the lower bits are ignored, source is $1000
+    ldh [$52], a
+    ld a, $E0           ; the upper bits are ignored, destination is $8000
+    ldh [$53], a
+    ld a, $0F
+    ldh [$54], a
+    xor a
+    ldh [$04], a        ; reset div
+    ld a, $3F           ; 64 blocks, the cpu is stalled for 512 m_cycles (8 div ticks)
+    ldh [$55], a
+    ldh a, [$04]
+    ld [$C000], a
+    ldh a, [$55]
+    ld [$C001], a
+
+    ld a, $91
+    ldh [$40], a
+    ld a, $10
+    ldh [$51], a
+    xor a
+    ldh [$52], a
+    ld a, $10           ; destination is $9000
+    ldh [$53], a
+    xor a
+    ldh [$54], a
+    ld a, $82           ; 3 blocks on hblank
+    ldh [$55], a
+    ldh a, [$55]
+    ld [$C002], a
+.wait:
+    ldh a, [$55]
+    cp $FF
+    jr nz, .wait
+
+    ld a, $11           ; destination is $9100, the source continues from the last transfer
+    ldh [$53], a
+    xor a
+    ldh [$54], a
+    ld a, $83           ; 4 blocks on hblank
+    ldh [$55], a
+.wait_block:
+    ldh a, [$55]
+    cp $03
+    jr z, .wait_block
+    xor a               ; cancels the transfer
+    ldh [$55], a
+    ldh a, [$55]
+    ld [$C003], a
+.loop:
+    jr .loop
+
+    SECTION \"data\", ROM0[$1000]
+    db $01, $02, $03, $04, $05, $06, $07, $08, $09, $0A, $0B, $0C, $0D, $0E, $0F, $10
+    db $11, $12, $13, $14, $15, $16, $17, $18, $19, $1A, $1B, $1C, $1D, $1E, $1F, $20
+    db $21, $22, $23, $24, $25, $26, $27, $28, $29, $2A, $2B, $2C, $2D, $2E, $2F, $30
+    db $31, $32, $33, $34, $35, $36, $37, $38, $39, $3A, $3B, $3C, $3D, $3E, $3F, $40
+";
+
+const CGB_FLAG_ADDRESS:usize = 0x143;
+const DATA_SIZE:u16 = 0x40;
+
+fn run_program(cgb_flag:u8)->(Vec<u8>, Vec<u8>){
+    let mut rom = assemble_rom(PROGRAM, "HDMA").unwrap();
+    rom[CGB_FLAG_ADDRESS] = cgb_flag;
+    return run_rom(rom, StubGfxDevice{}, |gameboy|{
+        for _ in 0..3{
+            gameboy.cycle_frame();
+        }
+
+        let results = (0xC000..0xC004).map(|address|gameboy.peek(address)).collect();
+        let vram = (0..0x2000).map(|address|gameboy.peek_bank(MemoryBank::Vram(0), address)).collect();
+        (results, vram)
+    });
+}
+
+#[test]
+fn test_vram_dma(){
+    let (results, vram) = run_program(0x80);
+    let data:Vec<u8> = (1..=DATA_SIZE as u8).collect();
+
+    assert_eq!(results, [8, 0xFF, 0x02, 0x82]);
+    assert_eq!(&vram[0..0x40], data.as_slice());
+    assert_eq!(&vram[0x1000..0x1030], &data[0..0x30]);
+    // Only a single block was transferred before the cancel
+    assert_eq!(&vram[0x1100..0x1110], &data[0x30..0x40]);
+    assert_eq!(vram[0x1110], 0);
+}
+
+#[test]
+fn test_vram_dma_ignored_in_dmg_mode(){
+    let (results, vram) = run_program(0);
+
+    assert_eq!(results[0..2], [0, 0xFF]);
+    assert!(vram.iter().all(|value|*value == 0));
+}